@@ -29,8 +29,8 @@ const APP: () = {
 
         let p0 = hal::gpio::p0::Parts::new(ctx.device.P0);
         let led1 = p0.p0_13.into_push_pull_output(Level::High).degrade();
-        let in_pin = p0.p0_30.into_floating_input();
-        let ref_pin = p0.p0_31.into_floating_input();
+        let in_pin = p0.p0_30.into_analog();
+        let ref_pin = p0.p0_31.into_analog();
 
         let comp = Comp::new(ctx.device.COMP, &in_pin);
         comp.differential(&ref_pin)
@@ -34,8 +34,8 @@ const APP: () = {
         let p0 = hal::gpio::p0::Parts::new(ctx.device.P0);
         let btn1 = p0.p0_11.into_pullup_input().degrade();
         let mut led1 = p0.p0_13.into_push_pull_output(Level::High).degrade();
-        let in_pin = p0.p0_04.into_floating_input();
-        let ref_pin = p0.p0_03.into_floating_input();
+        let in_pin = p0.p0_04.into_analog();
+        let ref_pin = p0.p0_03.into_analog();
 
         let lpcomp = LpComp::new(ctx.device.LPCOMP, &in_pin);
         lpcomp
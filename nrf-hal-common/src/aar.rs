@@ -0,0 +1,132 @@
+//! HAL blocking interface to the accelerated address resolver.
+//!
+//! The accelerated address resolver (AAR) resolves Bluetooth Low Energy resolvable private
+//! addresses, i.e. finds the identity resolving key (IRK) that generated an address, among a list
+//! of up to 16 IRKs.
+//!
+//! ```ignore
+//! let mut aar = Aar::init(p.AAR, p.CCM);
+//! match aar.resolve(&address, &irks)? {
+//!     Some(index) => { /* The address belongs to the peer of `irks[index]` */ }
+//!     None => { /* Unknown peer */ }
+//! }
+//! ```
+//!
+//! The addresses and IRKs are in the order used over the air, i.e. least significant byte first.
+
+use crate::{
+    pac::{AAR, CCM},
+    slice_in_ram,
+};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Maximum number of IRKs the AAR can resolve an address against.
+pub const MAX_IRKS: usize = 16;
+
+const HEADER_SIZE: usize = 3;
+const ADDRESS_SIZE: usize = 6;
+const IRK_SIZE: usize = 16;
+
+/// AAR error.
+#[derive(Debug, PartialEq)]
+pub enum AarError {
+    /// The IRK list passed to the AAR is not in RAM.
+    BufferNotInRAM,
+    /// More than `MAX_IRKS` IRKs were passed to the AAR.
+    TooManyIrks,
+}
+
+/// A safe, blocking wrapper around the AAR peripheral.
+pub struct Aar {
+    regs: AAR,
+    ccm: CCM,
+}
+
+impl Aar {
+    /// Inits the AAR peripheral. This method also demands ownership of the CCM peripheral,
+    /// because it shares registers with the AAR.
+    pub fn init(regs: AAR, ccm: CCM) -> Self {
+        ccm.enable.write(|w| w.enable().disabled());
+
+        // Disable all interrupts
+        regs.intenclr
+            .write(|w| w.end().clear().resolved().clear().notresolved().clear());
+
+        // NOTE(unsafe) 1 is a valid pattern to write to this register
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        regs.enable.write(|w| w.enable().enabled());
+
+        Self { regs, ccm }
+    }
+
+    /// Resolves `address` against the list of IRKs.
+    ///
+    /// Returns the index of the IRK that generated the address, or `None` if none of them did.
+    /// The IRKs must reside in RAM. The resolution takes around 6 µs per IRK.
+    pub fn resolve(
+        &mut self,
+        address: &[u8; ADDRESS_SIZE],
+        irks: &[[u8; IRK_SIZE]],
+    ) -> Result<Option<usize>, AarError> {
+        if irks.len() > MAX_IRKS {
+            return Err(AarError::TooManyIrks);
+        }
+        // NOTE(unsafe) The IRKs are plain bytes without padding
+        let irk_bytes = unsafe {
+            core::slice::from_raw_parts(irks.as_ptr() as *const u8, irks.len() * IRK_SIZE)
+        };
+        if !slice_in_ram(irk_bytes) {
+            return Err(AarError::BufferNotInRAM);
+        }
+        if irks.is_empty() {
+            return Ok(None);
+        }
+
+        // We allocate the DMA'd buffers on the stack, which means that we must not panic or
+        // return before the resolution is finished. The address is read after the S0, length and
+        // S1 fields of a packet header.
+        let mut packet = [0; HEADER_SIZE + ADDRESS_SIZE];
+        packet[HEADER_SIZE..].copy_from_slice(address);
+        let mut scratch = [0u8; 3];
+
+        // NOTE(unsafe) These addreses are in RAM, checked above
+        unsafe {
+            self.regs.addrptr.write(|w| w.bits(packet.as_ptr() as u32));
+            self.regs
+                .irkptr
+                .write(|w| w.bits(irk_bytes.as_ptr() as u32));
+            self.regs
+                .scratchptr
+                .write(|w| w.bits(scratch.as_mut_ptr() as u32));
+            self.regs.nirk.write(|w| w.nirk().bits(irks.len() as u8));
+        }
+
+        // Clear events
+        self.regs.events_end.reset();
+        self.regs.events_resolved.reset();
+        self.regs.events_notresolved.reset();
+
+        // "Preceding reads and writes cannot be moved past subsequent writes."
+        compiler_fence(Ordering::Release);
+
+        // NOTE(unsafe) 1 is a valid pattern to write to this register
+        self.regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        while self.regs.events_end.read().bits() == 0 {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        compiler_fence(Ordering::Acquire);
+
+        if self.regs.events_resolved.read().bits() == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.regs.status.read().status().bits() as usize))
+    }
+
+    /// Disables the AAR, and returns the AAR and CCM peripherals.
+    pub fn free(self) -> (AAR, CCM) {
+        self.regs.enable.write(|w| w.enable().disabled());
+        (self.regs, self.ccm)
+    }
+}
@@ -0,0 +1,121 @@
+//! HAL interface to the Access Control List (ACL) peripheral.
+//!
+//! The ACL restricts the access to regions of the internal flash, e.g. to let a bootloader make
+//! itself read-only, or unreadable, before starting the application:
+//!
+//! ```ignore
+//! let mut acl = Acl::new(p.ACL);
+//! let permissions = Permissions { read: true, write: false };
+//! acl.configure(0, 0x0000_0000, 0x8000, permissions)?;
+//! ```
+//!
+//! A region can only be configured once, and stays configured until the next reset. Accessing a
+//! region against its permissions causes a hard fault.
+
+use crate::pac::{acl, ACL};
+
+/// Number of ACL regions.
+pub const REGIONS: usize = 8;
+
+/// Alignment of the address and size of an ACL region.
+pub const PAGE_SIZE: u32 = 4096;
+
+/// ACL error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The region index is not below `REGIONS`.
+    InvalidRegion,
+    /// The address or size of the region is zero or not aligned to `PAGE_SIZE`.
+    Unaligned,
+    /// The region has already been configured since the last reset.
+    RegionInUse,
+}
+
+/// Access allowed to an ACL region.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Permissions {
+    /// The region can be read, or executed from.
+    pub read: bool,
+    /// The region can be written and erased.
+    pub write: bool,
+}
+
+/// Interface to the ACL peripheral.
+pub struct Acl(ACL);
+
+impl Acl {
+    /// Takes ownership of the ACL peripheral.
+    pub fn new(acl: ACL) -> Self {
+        Self(acl)
+    }
+
+    /// Restricts the access to the `size` bytes of flash from `address` to `permissions`, using
+    /// the ACL region `region`, until the next reset.
+    pub fn configure(
+        &mut self,
+        region: usize,
+        address: u32,
+        size: u32,
+        permissions: Permissions,
+    ) -> Result<(), Error> {
+        if size == 0 || (address | size) & (PAGE_SIZE - 1) != 0 {
+            return Err(Error::Unaligned);
+        }
+        let acl = self.region(region)?;
+        if acl.size.read().bits() != 0 {
+            return Err(Error::RegionInUse);
+        }
+
+        acl.addr.write(|w| unsafe { w.addr().bits(address) });
+        acl.perm.write(|w| {
+            w.read()
+                .bit(!permissions.read)
+                .write()
+                .bit(!permissions.write)
+        });
+        // The region takes effect once its size is written.
+        acl.size.write(|w| unsafe { w.size().bits(size) });
+        Ok(())
+    }
+
+    /// Returns the address, size and permissions of `region`, or `None` if it has not been
+    /// configured.
+    pub fn get(&self, region: usize) -> Result<Option<(u32, u32, Permissions)>, Error> {
+        let acl = self.region(region)?;
+        let size = acl.size.read().bits();
+        if size == 0 {
+            return Ok(None);
+        }
+        let perm = acl.perm.read();
+        let permissions = Permissions {
+            read: perm.read().is_enable(),
+            write: perm.write().is_enable(),
+        };
+        Ok(Some((acl.addr.read().bits(), size, permissions)))
+    }
+
+    /// Releases the ACL peripheral.
+    pub fn free(self) -> ACL {
+        self.0
+    }
+
+    #[cfg(feature = "52840")]
+    fn region(&self, region: usize) -> Result<&acl::ACL, Error> {
+        self.0.acl.get(region).ok_or(Error::InvalidRegion)
+    }
+
+    #[cfg(feature = "52833")]
+    fn region(&self, region: usize) -> Result<&acl::ACL, Error> {
+        match region {
+            0 => Ok(&self.0.acl0),
+            1 => Ok(&self.0.acl1),
+            2 => Ok(&self.0.acl2),
+            3 => Ok(&self.0.acl3),
+            4 => Ok(&self.0.acl4),
+            5 => Ok(&self.0.acl5),
+            6 => Ok(&self.0.acl6),
+            7 => Ok(&self.0.acl7),
+            _ => Err(Error::InvalidRegion),
+        }
+    }
+}
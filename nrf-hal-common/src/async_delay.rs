@@ -0,0 +1,264 @@
+//! Asynchronous delays.
+//!
+//! `AsyncTimerDelay` and `AsyncRtcDelay` implement the `embedded-hal-async` `DelayNs` trait.
+//! Instead of busy-waiting, they wait for a compare interrupt, so the executor can run other
+//! tasks or put the CPU to sleep in the meantime.
+//!
+//! The interrupt of the peripheral must be unmasked in the NVIC, and its handler must call the
+//! `on_interrupt` function of the delay type:
+//!
+//! ```ignore
+//! let mut delay = AsyncTimerDelay::new(p.TIMER1);
+//! unsafe { NVIC::unmask(Interrupt::TIMER1) };
+//!
+//! #[interrupt]
+//! fn TIMER1() {
+//!     AsyncTimerDelay::<TIMER1>::on_interrupt();
+//! }
+//!
+//! delay.delay_ms(10).await;
+//! ```
+
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::{Poll, Waker};
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_hal_async::delay::DelayNs;
+
+use crate::pac::Interrupt;
+use crate::{rtc, timer};
+
+type WakerSlot = Mutex<RefCell<Option<Waker>>>;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: WakerSlot = Mutex::new(RefCell::new(None));
+
+/// Wakers of the pending delays, indexed by `waker_index`.
+static WAKERS: [WakerSlot; 8] = [NO_WAKER; 8];
+
+/// Returns the index of the waker slot of the TIMER or RTC instance with the given interrupt.
+fn waker_index(interrupt: Interrupt) -> usize {
+    match interrupt {
+        Interrupt::TIMER0 => 0,
+        Interrupt::TIMER1 => 1,
+        Interrupt::TIMER2 => 2,
+        #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+        Interrupt::TIMER3 => 3,
+        #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+        Interrupt::TIMER4 => 4,
+        Interrupt::RTC0 => 5,
+        Interrupt::RTC1 => 6,
+        #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+        Interrupt::RTC2 => 7,
+        _ => unreachable!(),
+    }
+}
+
+fn register_waker(interrupt: Interrupt, waker: &Waker) {
+    interrupt::free(|cs| {
+        *WAKERS[waker_index(interrupt)].borrow(cs).borrow_mut() = Some(waker.clone());
+    });
+}
+
+fn wake(interrupt: Interrupt) {
+    if let Some(waker) = interrupt::free(|cs| {
+        WAKERS[waker_index(interrupt)]
+            .borrow(cs)
+            .borrow_mut()
+            .take()
+    }) {
+        waker.wake();
+    }
+}
+
+/// Stops the TIMER if the delay future is dropped before completion.
+struct TimerGuard<T: timer::Instance>(PhantomData<T>);
+
+impl<T: timer::Instance> Drop for TimerGuard<T> {
+    fn drop(&mut self) {
+        let regs = unsafe { &*T::ptr() };
+        regs.intenclr.write(|w| w.compare0().clear());
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+    }
+}
+
+/// An asynchronous delay provider using a TIMER at 1 MHz.
+pub struct AsyncTimerDelay<T> {
+    timer: T,
+}
+
+impl<T> AsyncTimerDelay<T>
+where
+    T: timer::Instance,
+{
+    /// Configures the TIMER as a 1 MHz, 32-bit one-shot timer.
+    pub fn new(timer: T) -> Self {
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        regs.shorts
+            .write(|w| w.compare0_clear().enabled().compare0_stop().enabled());
+        regs.mode.write(|w| w.mode().timer());
+        regs.prescaler.write(
+            |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
+        );
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+
+        Self { timer }
+    }
+
+    /// Handles the TIMER interrupt. Must be called from the interrupt handler of the TIMER.
+    pub fn on_interrupt() {
+        let regs = unsafe { &*T::ptr() };
+        regs.intenclr.write(|w| w.compare0().clear());
+        wake(T::INTERRUPT);
+    }
+
+    /// Releases the TIMER.
+    pub fn free(self) -> T {
+        self.timer
+    }
+
+    async fn wait_us(&mut self, us: u32) {
+        let regs = self.timer.as_timer0();
+        regs.events_compare[0].reset();
+        regs.cc[0].write(|w| unsafe { w.bits(us.max(1)) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        regs.intenset.write(|w| w.compare0().set());
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        let guard = TimerGuard::<T>(PhantomData);
+        poll_fn(|cx| {
+            register_waker(T::INTERRUPT, cx.waker());
+            if regs.events_compare[0].read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                regs.intenset.write(|w| w.compare0().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        drop(guard);
+        regs.events_compare[0].reset();
+    }
+}
+
+impl<T> DelayNs for AsyncTimerDelay<T>
+where
+    T: timer::Instance,
+{
+    async fn delay_ns(&mut self, ns: u32) {
+        self.wait_us(ns.div_ceil(1_000)).await;
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.wait_us(us).await;
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        // Split the delay so that the number of cycles doesn't overflow.
+        for _ in 0..ms / 1_000_000 {
+            self.wait_us(1_000_000_000).await;
+        }
+        self.wait_us((ms % 1_000_000) * 1_000).await;
+    }
+}
+
+/// Disables the RTC compare interrupt if the delay future is dropped before completion.
+struct RtcGuard<T: rtc::Instance>(PhantomData<T>);
+
+impl<T: rtc::Instance> Drop for RtcGuard<T> {
+    fn drop(&mut self) {
+        let regs = unsafe { &*T::ptr() };
+        regs.intenclr.write(|w| w.compare0().clear());
+    }
+}
+
+/// An asynchronous delay provider using CC[0] of a running RTC.
+///
+/// The resolution is limited by the RTC frequency, which is 32.768 kHz at most, but the RTC
+/// keeps running in System ON idle without the high frequency clock.
+pub struct AsyncRtcDelay<T> {
+    rtc: T,
+}
+
+impl<T> AsyncRtcDelay<T>
+where
+    T: rtc::Instance,
+{
+    /// Uses CC[0] of an RTC for delays. The RTC counter must already be running, e.g. with
+    /// `Rtc::enable_counter`, and the low frequency clock must be started.
+    pub fn new(rtc: T) -> Self {
+        rtc.intenclr.write(|w| w.compare0().clear());
+        Self { rtc }
+    }
+
+    /// Handles the RTC interrupt. Must be called from the interrupt handler of the RTC.
+    pub fn on_interrupt() {
+        let regs = unsafe { &*T::ptr() };
+        regs.intenclr.write(|w| w.compare0().clear());
+        wake(T::INTERRUPT);
+    }
+
+    /// Releases the RTC.
+    pub fn free(self) -> T {
+        self.rtc
+    }
+
+    async fn wait_ticks(&mut self, mut ticks: u64) {
+        // Wait in steps that stay well within the 24-bit counter range.
+        const MAX_STEP: u64 = 1 << 23;
+
+        while ticks > 0 {
+            let step = ticks.min(MAX_STEP) as u32;
+            ticks -= u64::from(step);
+
+            // The RTC may miss a compare value less than 2 ticks ahead of the counter.
+            let target = (self.rtc.counter.read().bits() + step.max(2)) & 0x00FF_FFFF;
+            self.rtc.events_compare[0].reset();
+            self.rtc.cc[0].write(|w| unsafe { w.bits(target) });
+            self.rtc.intenset.write(|w| w.compare0().set());
+
+            let guard = RtcGuard::<T>(PhantomData);
+            let rtc = &self.rtc;
+            poll_fn(|cx| {
+                register_waker(T::INTERRUPT, cx.waker());
+                if rtc.events_compare[0].read().bits() != 0 {
+                    Poll::Ready(())
+                } else {
+                    rtc.intenset.write(|w| w.compare0().set());
+                    Poll::Pending
+                }
+            })
+            .await;
+            drop(guard);
+            self.rtc.events_compare[0].reset();
+        }
+    }
+
+    fn frequency(&self) -> u64 {
+        u64::from(crate::clocks::LFCLK_FREQ / (self.rtc.prescaler.read().bits() + 1))
+    }
+}
+
+impl<T> DelayNs for AsyncRtcDelay<T>
+where
+    T: rtc::Instance,
+{
+    async fn delay_ns(&mut self, ns: u32) {
+        let ticks = (u64::from(ns) * self.frequency()).div_ceil(1_000_000_000);
+        self.wait_ticks(ticks).await;
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        let ticks = (u64::from(us) * self.frequency()).div_ceil(1_000_000);
+        self.wait_ticks(ticks).await;
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        let ticks = (u64::from(ms) * self.frequency()).div_ceil(1_000);
+        self.wait_ticks(ticks).await;
+    }
+}
@@ -0,0 +1,194 @@
+//! Battery voltage monitoring.
+//!
+//! `BatteryMonitor` periodically samples the supply voltage, VDDH, or a battery connected to an
+//! analog pin through a resistor divider, and smooths the samples with an exponential moving
+//! average.
+//! An RTC schedules the measurements, so they keep running in System ON idle, and the SAADC only
+//! runs for the few microseconds a measurement takes.
+//!
+//! ```ignore
+//! let input = Input::internal(&InternalVddhDiv5);
+//! let mut battery = BatteryMonitor::new(saadc, p.RTC1, input, 60_000.millis());
+//! battery.set_low_threshold(2_200, 100);
+//! battery.start()?;
+//!
+//! // After unmasking RTC1 in the NVIC, in the `RTC1` interrupt handler:
+//! match battery.on_interrupt() {
+//!     Some(BatteryEvent::Low) => { /* ... */ }
+//!     Some(BatteryEvent::Recovered) => { /* ... */ }
+//!     None => {}
+//! }
+//!
+//! // Anywhere else:
+//! let voltage_mv = battery.voltage_mv();
+//! ```
+//!
+//! The low frequency clock has to be started for the RTC to run.
+
+use fugit::MillisDurationU32;
+
+use crate::rtc::{Instance, PeriodicRtc};
+use crate::saadc::{Error, Gain, Input, Oversample, Reference, Saadc, Scale};
+
+/// A change of the battery state reported by `BatteryMonitor`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BatteryEvent {
+    /// The average voltage dropped below the low threshold.
+    Low,
+    /// The average voltage rose above the low threshold plus the hysteresis.
+    Recovered,
+}
+
+/// Periodic battery voltage measurement with smoothing and low battery detection.
+pub struct BatteryMonitor<T> {
+    saadc: Saadc,
+    rtc: PeriodicRtc<T>,
+    input: Input,
+    scale: Scale,
+    divider: (u32, u32),
+    smoothing: u8,
+    // Average voltage in millivolts, in fixed point with `smoothing` fractional bits.
+    average: Option<u32>,
+    low_mv: u32,
+    hysteresis_mv: u32,
+    low: bool,
+}
+
+impl<T> BatteryMonitor<T>
+where
+    T: Instance,
+{
+    /// Sets up the measurement of `input` every `period`, rounded to 125 ms.
+    ///
+    /// The input is sampled with the internal 0.6 V reference and a gain of 1/6, i.e. an input
+    /// range of 0 to 3.6 V, with 8x oversampling. Measurements start with `start`.
+    ///
+    /// The divider of internal inputs selected with `Input::internal`, e.g. 5 for
+    /// `InternalVddhDiv5`, is applied to the measurements.
+    pub fn new(mut saadc: Saadc, rtc: T, input: Input, period: MillisDurationU32) -> Self {
+        let input = input
+            .gain(Gain::GAIN1_6)
+            .reference(Reference::INTERNAL)
+            .burst(true);
+        saadc.set_oversample(Oversample::OVER8X);
+        let scale = saadc.scale(&input, 0);
+
+        Self {
+            saadc,
+            rtc: PeriodicRtc::new(rtc, period),
+            input,
+            scale,
+            divider: (1, 1),
+            smoothing: 3,
+            average: None,
+            low_mv: 0,
+            hysteresis_mv: 0,
+            low: false,
+        }
+    }
+
+    /// Sets the ratio between the battery voltage and the sampled voltage, for batteries
+    /// connected to an analog pin through an external resistor divider.
+    ///
+    /// This comes on top of the divider of internal inputs, which doesn't need to be set.
+    ///
+    /// For example, a divider made of a 1 MΩ resistor to the battery and a 1 MΩ resistor to
+    /// ground halves the voltage, which is set with `set_divider(2, 1)`.
+    pub fn set_divider(&mut self, numerator: u32, denominator: u32) {
+        self.divider = (numerator, denominator.max(1));
+        self.average = None;
+    }
+
+    /// Sets the weight of new measurements in the average to 1/2^`smoothing`.
+    ///
+    /// The default of 3 averages roughly over the last 8 measurements. 0 disables smoothing.
+    pub fn set_smoothing(&mut self, smoothing: u8) {
+        self.smoothing = smoothing.min(16);
+        self.average = None;
+    }
+
+    /// Sets the voltage below which the battery is considered low, and the hysteresis above it
+    /// for the battery to be considered recovered, both in millivolts.
+    pub fn set_low_threshold(&mut self, low_mv: u32, hysteresis_mv: u32) {
+        self.low_mv = low_mv;
+        self.hysteresis_mv = hysteresis_mv;
+    }
+
+    /// Takes a first measurement, and starts the periodic measurements.
+    ///
+    /// The RTC interrupt must be unmasked in the NVIC, and its handler must call `on_interrupt`.
+    pub fn start(&mut self) -> Result<Option<BatteryEvent>, Error> {
+        let event = self.measure()?;
+
+        self.rtc.enable_interrupt();
+        self.rtc.start();
+
+        Ok(event)
+    }
+
+    /// Stops the periodic measurements.
+    pub fn stop(&mut self) {
+        self.rtc.disable_interrupt();
+        self.rtc.stop();
+    }
+
+    /// Handles the RTC interrupt, taking a measurement if one is due. Must be called from the
+    /// RTC interrupt handler.
+    ///
+    /// Returns an event if the battery became low or recovered.
+    pub fn on_interrupt(&mut self) -> Option<BatteryEvent> {
+        if !self.rtc.is_due() {
+            return None;
+        }
+        self.rtc.schedule_next();
+
+        self.measure().ok().flatten()
+    }
+
+    /// Takes a measurement immediately, and adds it to the average.
+    ///
+    /// Returns an event if the battery became low or recovered.
+    pub fn measure(&mut self) -> Result<Option<BatteryEvent>, Error> {
+        let result = self.saadc.read_input(self.input)?;
+        let sampled_mv = self.scale.to_millivolts(result).max(0) as u32;
+        let voltage_mv = sampled_mv * self.divider.0 / self.divider.1;
+
+        let average = match self.average {
+            Some(average) => average - (average >> self.smoothing) + voltage_mv,
+            None => voltage_mv << self.smoothing,
+        };
+        self.average = Some(average);
+
+        Ok(self.update_state())
+    }
+
+    fn update_state(&mut self) -> Option<BatteryEvent> {
+        let voltage_mv = self.voltage_mv()?;
+        if !self.low && voltage_mv < self.low_mv {
+            self.low = true;
+            Some(BatteryEvent::Low)
+        } else if self.low && voltage_mv >= self.low_mv + self.hysteresis_mv {
+            self.low = false;
+            Some(BatteryEvent::Recovered)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the average battery voltage in millivolts, or `None` before the first
+    /// measurement.
+    pub fn voltage_mv(&self) -> Option<u32> {
+        self.average.map(|average| average >> self.smoothing)
+    }
+
+    /// Returns `true` if the battery is low.
+    pub fn is_low(&self) -> bool {
+        self.low
+    }
+
+    /// Stops the measurements, and releases the SAADC and the RTC.
+    pub fn free(mut self) -> (Saadc, T) {
+        self.stop();
+        (self.saadc, self.rtc.free())
+    }
+}
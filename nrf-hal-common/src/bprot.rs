@@ -0,0 +1,113 @@
+//! HAL interface to the Block Protection (BPROT) peripheral.
+//!
+//! BPROT protects 4 kB regions of the internal flash against writes and erases, e.g. to let a
+//! bootloader lock itself before starting the application:
+//!
+//! ```ignore
+//! let mut bprot = Bprot::new(p.BPROT);
+//! bprot.protect(0x0000_0000, 0x0000_8000)?;
+//! ```
+//!
+//! A protected region stays protected until the next reset. Writing or erasing it causes a
+//! hard fault.
+
+use crate::pac::BPROT;
+
+/// Size of a protected region.
+pub const REGION_SIZE: u32 = 4096;
+
+/// Number of protected regions, covering the flash from address 0.
+#[cfg(feature = "52832")]
+pub const REGIONS: usize = 128;
+#[cfg(any(feature = "52810", feature = "52811"))]
+pub const REGIONS: usize = 64;
+
+/// Block protection error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The address range is not aligned to `REGION_SIZE`.
+    Unaligned,
+    /// The address range extends past the last protected region.
+    OutOfBounds,
+}
+
+/// Interface to the BPROT peripheral.
+pub struct Bprot(BPROT);
+
+impl Bprot {
+    /// Takes ownership of the BPROT peripheral.
+    pub fn new(bprot: BPROT) -> Self {
+        Self(bprot)
+    }
+
+    /// Protects the flash from address `from` up to `to` against writes and erases, until the
+    /// next reset.
+    pub fn protect(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        if from > to || to > REGIONS as u32 * REGION_SIZE {
+            return Err(Error::OutOfBounds);
+        }
+        if (from | to) & (REGION_SIZE - 1) != 0 {
+            return Err(Error::Unaligned);
+        }
+
+        let mut config = [0; REGIONS / 32];
+        for region in (from / REGION_SIZE)..(to / REGION_SIZE) {
+            config[region as usize / 32] |= 1 << (region % 32);
+        }
+        // Writing 0 to a region has no effect, so the other regions are left as they are.
+        for (index, bits) in config.iter().enumerate() {
+            if *bits != 0 {
+                self.write_config(index, *bits);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the region containing `address` is protected.
+    pub fn is_protected(&self, address: u32) -> bool {
+        let region = (address / REGION_SIZE) as usize;
+        region < REGIONS && self.read_config(region / 32) & (1 << (region % 32)) != 0
+    }
+
+    /// Keeps the protection enabled while a debugger is connected, if `enabled` is `true`.
+    ///
+    /// By default, the protection is disabled in debug interface mode.
+    pub fn set_protect_in_debug(&mut self, enabled: bool) {
+        self.0.disableindebug.write(|w| {
+            if enabled {
+                w.disableindebug().enabled()
+            } else {
+                w.disableindebug().disabled()
+            }
+        });
+    }
+
+    /// Releases the BPROT peripheral.
+    pub fn free(self) -> BPROT {
+        self.0
+    }
+
+    fn read_config(&self, index: usize) -> u32 {
+        match index {
+            0 => self.0.config0.read().bits(),
+            1 => self.0.config1.read().bits(),
+            #[cfg(feature = "52832")]
+            2 => self.0.config2.read().bits(),
+            #[cfg(feature = "52832")]
+            3 => self.0.config3.read().bits(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_config(&mut self, index: usize, bits: u32) {
+        match index {
+            0 => self.0.config0.write(|w| unsafe { w.bits(bits) }),
+            1 => self.0.config1.write(|w| unsafe { w.bits(bits) }),
+            #[cfg(feature = "52832")]
+            2 => self.0.config2.write(|w| unsafe { w.bits(bits) }),
+            #[cfg(feature = "52832")]
+            3 => self.0.config3.write(|w| unsafe { w.bits(bits) }),
+            _ => unreachable!(),
+        }
+    }
+}
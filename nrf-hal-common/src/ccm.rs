@@ -44,6 +44,13 @@
 //! The peripheral also needs an area in RAM to store temporary values used during
 //! encryption/decryption. The scratch slice must have a minimum length of 43 bytes, or
 //! (16 + `Packet Length`) bytes, whatever is largest.
+//!
+//! # On-the-fly Decryption
+//!
+//! `Ccm::decrypt_on_the_fly` decrypts a packet while it is received by the RADIO: the key stream
+//! is generated beforehand, and the fixed PPI channel 25 starts the decryption on the RADIO
+//! `ADDRESS` event. The RADIO must receive the packet in the cipher packet buffer, in the format
+//! above, and the channel must be enabled, e.g. with `ppi_channels.ppi25.enable()`.
 
 use crate::{
     pac::{AAR, CCM},
@@ -167,6 +174,19 @@ impl CcmData {
     }
 }
 
+/// The buffers of an on-the-fly decryption, all of which must reside in RAM.
+pub struct CcmBuffers {
+    /// Key, counter, direction and initialization vector.
+    pub ccm_data: &'static mut CcmData,
+    /// The packet received by the RADIO.
+    pub cipher_packet: &'static mut [u8],
+    /// The decrypted packet, which needs 4 bytes less than the cipher packet.
+    pub clear_packet: &'static mut [u8],
+    /// The scratch area, which needs 16 bytes more than the cipher packet payload, and at least
+    /// 43 bytes.
+    pub scratch: &'static mut [u8],
+}
+
 /// A safe, blocking wrapper around the AES-CCM peripheral.
 pub struct Ccm {
     regs: CCM,
@@ -455,4 +475,158 @@ impl Ccm {
         ccm_data.increment_counter();
         Ok(())
     }
+
+    /// Prepares the decryption of the next packet received by the RADIO, and returns without
+    /// waiting for it.
+    ///
+    /// The length of the cipher packet buffer is the largest packet that can be received. See the
+    /// module level documentation for the setup of the RADIO and PPI.
+    pub fn decrypt_on_the_fly(
+        self,
+        buffers: CcmBuffers,
+    ) -> Result<CcmTransfer, (CcmError, Ccm, CcmBuffers)> {
+        if !(slice_in_ram(buffers.cipher_packet)
+            && slice_in_ram(buffers.clear_packet)
+            && slice_in_ram(buffers.scratch))
+        {
+            return Err((CcmError::BufferNotInRAM, self, buffers));
+        }
+
+        let max_payload_len = buffers.cipher_packet.len().saturating_sub(HEADER_SIZE);
+        if max_payload_len < MIC_SIZE
+            || buffers.clear_packet.len() < max_payload_len + HEADER_SIZE - MIC_SIZE
+        {
+            return Err((CcmError::WrongPacketLength, self, buffers));
+        }
+        let max_payload_len = max_payload_len.min(u8::MAX as usize);
+
+        if buffers.scratch.len() < (max_payload_len + 16).max(MINIMUM_SCRATCH_AREA_SIZE) {
+            return Err((CcmError::InsufficientScratchArea, self, buffers));
+        }
+
+        #[cfg(feature = "51")]
+        self.regs.mode.write(|w| w.mode().decryption());
+
+        #[cfg(not(feature = "51"))]
+        {
+            let length_variant = if max_payload_len <= MAXIMUM_LENGTH_5BITS {
+                LENGTH_A::DEFAULT
+            } else {
+                #[cfg(any(
+                    feature = "52840",
+                    feature = "52833",
+                    feature = "52811",
+                    feature = "52810"
+                ))]
+                // NOTE(unsafe) Any 8bits pattern is safe to write to this register
+                self.regs
+                    .maxpacketsize
+                    .write(|w| unsafe { w.maxpacketsize().bits(max_payload_len as u8) });
+
+                LENGTH_A::EXTENDED
+            };
+            self.regs
+                .mode
+                .modify(|_, w| w.mode().decryption().length().variant(length_variant));
+        }
+
+        // Setup the pointers
+        // NOTE(unsafe) These addreses are in RAM, checked above
+        unsafe {
+            self.regs
+                .cnfptr
+                .write(|w| w.bits(&*buffers.ccm_data as *const _ as u32));
+            self.regs
+                .inptr
+                .write(|w| w.bits(buffers.cipher_packet.as_ptr() as u32));
+            self.regs
+                .outptr
+                .write(|w| w.bits(buffers.clear_packet.as_mut_ptr() as u32));
+            self.regs
+                .scratchptr
+                .write(|w| w.bits(buffers.scratch.as_mut_ptr() as u32));
+        }
+
+        // Clear events
+        self.regs.events_endcrypt.reset();
+        self.regs.events_error.reset();
+        self.regs.events_endksgen.reset();
+
+        // "Preceding reads and writes cannot be moved past subsequent writes."
+        compiler_fence(Ordering::Release);
+
+        // Generate the key stream now, the decryption is started by the RADIO through PPI
+        // NOTE(unsafe) 1 is a valid pattern to write to this register
+        self.regs.tasks_ksgen.write(|w| unsafe { w.bits(1) });
+
+        Ok(CcmTransfer {
+            inner: Some((self, buffers)),
+        })
+    }
+}
+
+/// An on-the-fly decryption, started with `Ccm::decrypt_on_the_fly`.
+///
+/// Dropping it stops the CCM.
+pub struct CcmTransfer {
+    // FIXME: Always `Some`, only using `Option` here to allow moving fields out of `inner`.
+    inner: Option<(Ccm, CcmBuffers)>,
+}
+
+impl CcmTransfer {
+    /// Checks if the decryption has ended or failed.
+    pub fn is_done(&self) -> bool {
+        let (ccm, _) = self
+            .inner
+            .as_ref()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        ccm.regs.events_endcrypt.read().bits() != 0 || ccm.regs.events_error.read().bits() != 0
+    }
+
+    /// Blocks until the decryption is done, and returns its result with the CCM and the buffers.
+    ///
+    /// The MIC is checked like with `Ccm::decrypt_packet`, and the counter is incremented on
+    /// success.
+    pub fn wait(mut self) -> (Result<(), CcmError>, Ccm, CcmBuffers) {
+        while !self.is_done() {}
+        compiler_fence(Ordering::Acquire);
+
+        let (ccm, buffers) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        let result = if ccm.regs.events_error.read().bits() != 0 {
+            Err(CcmError::EasyDMAError)
+        } else if buffers.cipher_packet[LENGTH_HEADER_INDEX] == 0 {
+            Ok(())
+        } else if ccm.regs.micstatus.read().micstatus().is_check_failed() {
+            Err(CcmError::InvalidMIC)
+        } else {
+            buffers.ccm_data.increment_counter();
+            Ok(())
+        };
+        (result, ccm, buffers)
+    }
+
+    /// Stops the CCM, and returns it with the buffers.
+    pub fn abort(mut self) -> (Ccm, CcmBuffers) {
+        let (ccm, buffers) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        // NOTE(unsafe) 1 is a valid pattern to write to this register
+        ccm.regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        compiler_fence(Ordering::Acquire);
+        (ccm, buffers)
+    }
+}
+
+impl Drop for CcmTransfer {
+    fn drop(&mut self) {
+        if let Some((ccm, _)) = self.inner.as_ref() {
+            // NOTE(unsafe) 1 is a valid pattern to write to this register
+            ccm.regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+            compiler_fence(Ordering::Acquire);
+        }
+    }
 }
@@ -3,9 +3,20 @@
 //! The comparator (COMP) compares an input voltage (Vin) against a second input voltage (Vref).
 //! Vin can be derived from an analog input pin (AIN0-AIN7).
 //! Vref can be derived from multiple sources depending on the operation mode of the comparator.
+//!
+//! In single ended mode, Vref is one of the internal references, VDD or an external reference
+//! pin, and the thresholds set with `thresholds` provide the hysteresis. In differential mode, Vin
+//! is compared against an external reference pin, with an optional 50 mV hysteresis.
+//!
+//! ```ignore
+//! let comp = Comp::new(p.COMP, &port0.p0_04.into_analog());
+//! // Up above 2/3 VDD, down below 1/3 VDD.
+//! comp.vref(VRef::Vdd).thresholds(20, 42).enable_interrupt(Transition::Cross);
+//! comp.enable();
+//! ```
 
 use {
-    crate::gpio::{p0::*, Floating, Input},
+    crate::gpio::{p0::*, Analog},
     crate::pac::{
         comp::{extrefsel::EXTREFSEL_A, psel::PSEL_A, _EVENTS_CROSS, _EVENTS_DOWN, _EVENTS_UP},
         generic::Reg,
@@ -22,8 +33,7 @@ impl Comp {
     /// Takes ownership of the `COMP` peripheral, returning a safe wrapper.
     pub fn new<P: CompInputPin>(comp: COMP, input_pin: &P) -> Self {
         comp.psel.write(|w| w.psel().variant(input_pin.ain()));
-        comp.mode.write(|w| w.sp().normal());
-        comp.mode.write(|w| w.main().se());
+        comp.mode.write(|w| w.sp().normal().main().se());
         comp.refsel.write(|w| w.refsel().int1v2());
         Self { comp }
     }
@@ -32,9 +42,9 @@ impl Comp {
     #[inline(always)]
     pub fn power_mode(&self, mode: PowerMode) -> &Self {
         match mode {
-            PowerMode::LowPower => self.comp.mode.write(|w| w.sp().low()),
-            PowerMode::Normal => self.comp.mode.write(|w| w.sp().normal()),
-            PowerMode::HighSpeed => self.comp.mode.write(|w| w.sp().high()),
+            PowerMode::LowPower => self.comp.mode.modify(|_r, w| w.sp().low()),
+            PowerMode::Normal => self.comp.mode.modify(|_r, w| w.sp().normal()),
+            PowerMode::HighSpeed => self.comp.mode.modify(|_r, w| w.sp().high()),
         }
         self
     }
@@ -67,17 +77,33 @@ impl Comp {
     /// Sets comparator mode to differential with external Vref pin.
     #[inline(always)]
     pub fn differential<P: CompRefPin>(&self, ref_pin: &P) -> &Self {
-        self.comp.mode.write(|w| w.main().diff());
+        self.comp.mode.modify(|_r, w| w.main().diff());
         self.aref_pin(ref_pin);
         self
     }
 
+    /// Sets comparator mode to single ended, comparing against the Vref set with `vref`.
+    #[inline(always)]
+    pub fn single_ended(&self) -> &Self {
+        self.comp.mode.modify(|_r, w| w.main().se());
+        self
+    }
+
+    /// Returns the current operation mode.
+    #[inline(always)]
+    pub fn operation_mode(&self) -> OperationMode {
+        match self.comp.mode.read().main().is_diff() {
+            true => OperationMode::Differential,
+            false => OperationMode::SingleEnded,
+        }
+    }
+
     /// Upward hysteresis threshold in single ended mode `Vup = (value+1)/64*Vref`.
     #[inline(always)]
     pub fn hysteresis_threshold_up(&self, value: u8) -> &Self {
         self.comp
             .th
-            .write(|w| unsafe { w.thup().bits(value.min(63)) });
+            .modify(|_r, w| unsafe { w.thup().bits(value.min(63)) });
         self
     }
 
@@ -86,7 +112,21 @@ impl Comp {
     pub fn hysteresis_threshold_down(&self, value: u8) -> &Self {
         self.comp
             .th
-            .write(|w| unsafe { w.thdown().bits(value.min(63)) });
+            .modify(|_r, w| unsafe { w.thdown().bits(value.min(63)) });
+        self
+    }
+
+    /// Sets both hysteresis thresholds in single ended mode, `Vdown = (down+1)/64*Vref` and
+    /// `Vup = (up+1)/64*Vref`.
+    ///
+    /// The output goes up when Vin rises above Vup, and down when it falls below Vdown, so `down`
+    /// must not be greater than `up`.
+    #[inline(always)]
+    pub fn thresholds(&self, down: u8, up: u8) -> &Self {
+        self.comp.th.write(|w| unsafe {
+            w.thdown().bits(down.min(63));
+            w.thup().bits(up.min(63))
+        });
         self
     }
 
@@ -208,6 +248,7 @@ impl Comp {
     }
 }
 
+/// Operation mode of the comparator.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum OperationMode {
     Differential,
@@ -283,35 +324,80 @@ macro_rules! comp_ref_pins {
 }
 
 comp_ref_pins! {
-    P0_02<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE0,
-    P0_03<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE1,
-    P0_04<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE2,
-    P0_05<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE3,
-    P0_28<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE4,
-    P0_29<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE5,
-    P0_30<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE6,
-    P0_31<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE7,
+    P0_02<Analog> => EXTREFSEL_A::ANALOGREFERENCE0,
+    P0_03<Analog> => EXTREFSEL_A::ANALOGREFERENCE1,
+    P0_04<Analog> => EXTREFSEL_A::ANALOGREFERENCE2,
+    P0_05<Analog> => EXTREFSEL_A::ANALOGREFERENCE3,
+    P0_28<Analog> => EXTREFSEL_A::ANALOGREFERENCE4,
+    P0_29<Analog> => EXTREFSEL_A::ANALOGREFERENCE5,
+    P0_30<Analog> => EXTREFSEL_A::ANALOGREFERENCE6,
+    P0_31<Analog> => EXTREFSEL_A::ANALOGREFERENCE7,
 }
 
 #[cfg(not(any(feature = "52811", feature = "52810")))]
 comp_input_pins! {
-    P0_02<Input<Floating>> => PSEL_A::ANALOGINPUT0,
-    P0_03<Input<Floating>> => PSEL_A::ANALOGINPUT1,
-    P0_04<Input<Floating>> => PSEL_A::ANALOGINPUT2,
-    P0_05<Input<Floating>> => PSEL_A::ANALOGINPUT3,
-    P0_28<Input<Floating>> => PSEL_A::ANALOGINPUT4,
-    P0_29<Input<Floating>> => PSEL_A::ANALOGINPUT5,
-    P0_30<Input<Floating>> => PSEL_A::ANALOGINPUT6,
-    P0_31<Input<Floating>> => PSEL_A::ANALOGINPUT7,
+    P0_02<Analog> => PSEL_A::ANALOGINPUT0,
+    P0_03<Analog> => PSEL_A::ANALOGINPUT1,
+    P0_04<Analog> => PSEL_A::ANALOGINPUT2,
+    P0_05<Analog> => PSEL_A::ANALOGINPUT3,
+    P0_28<Analog> => PSEL_A::ANALOGINPUT4,
+    P0_29<Analog> => PSEL_A::ANALOGINPUT5,
+    P0_30<Analog> => PSEL_A::ANALOGINPUT6,
+    P0_31<Analog> => PSEL_A::ANALOGINPUT7,
 }
 
 #[cfg(any(feature = "52811", feature = "52810"))]
 comp_input_pins! {
-    P0_02<Input<Floating>> => PSEL_A::ANALOGINPUT0,
-    P0_03<Input<Floating>> => PSEL_A::ANALOGINPUT1,
-    P0_04<Input<Floating>> => PSEL_A::ANALOGINPUT2,
-    P0_05<Input<Floating>> => PSEL_A::ANALOGINPUT3,
-    P0_28<Input<Floating>> => PSEL_A::ANALOGINPUT4,
-    P0_29<Input<Floating>> => PSEL_A::ANALOGINPUT5,
-    P0_30<Input<Floating>> => PSEL_A::ANALOGINPUT6,
+    P0_02<Analog> => PSEL_A::ANALOGINPUT0,
+    P0_03<Analog> => PSEL_A::ANALOGINPUT1,
+    P0_04<Analog> => PSEL_A::ANALOGINPUT2,
+    P0_05<Analog> => PSEL_A::ANALOGINPUT3,
+    P0_28<Analog> => PSEL_A::ANALOGINPUT4,
+    P0_29<Analog> => PSEL_A::ANALOGINPUT5,
+    P0_30<Analog> => PSEL_A::ANALOGINPUT6,
+}
+
+#[cfg(feature = "embedded-hal-async")]
+static WAKER: cortex_m::interrupt::Mutex<core::cell::RefCell<Option<core::task::Waker>>> =
+    cortex_m::interrupt::Mutex::new(core::cell::RefCell::new(None));
+
+#[cfg(feature = "embedded-hal-async")]
+impl Comp {
+    /// Handles the COMP interrupt for `wait_for_cross`. Must be called from the `COMP_LPCOMP`
+    /// interrupt handler, and the interrupt must be unmasked in the NVIC.
+    pub fn on_interrupt() {
+        let comp = unsafe { &*COMP::ptr() };
+        comp.intenclr
+            .write(|w| w.up().set_bit().down().set_bit().cross().set_bit());
+        if let Some(waker) = cortex_m::interrupt::free(|cs| WAKER.borrow(cs).take()) {
+            waker.wake();
+        }
+    }
+
+    /// Waits until the comparator output makes the given transition, without polling.
+    ///
+    /// The comparator must be enabled. Transitions that happened before the call are ignored.
+    pub async fn wait_for_cross(&self, direction: Transition) {
+        self.reset_event(direction);
+        core::future::poll_fn(|cx| {
+            cortex_m::interrupt::free(|cs| {
+                WAKER.borrow(cs).replace(Some(cx.waker().clone()));
+            });
+            if self.is_event_triggered(direction) {
+                core::task::Poll::Ready(())
+            } else {
+                self.enable_interrupt(direction);
+                core::task::Poll::Pending
+            }
+        })
+        .await
+    }
+
+    fn is_event_triggered(&self, event: Transition) -> bool {
+        match event {
+            Transition::Cross => self.is_cross(),
+            Transition::Down => self.is_down(),
+            Transition::Up => self.is_up(),
+        }
+    }
 }
@@ -0,0 +1,38 @@
+//! HAL interface to the ARM CryptoCell 310 (CC310) subsystem.
+//!
+//! The CC310 provides a TRNG, AES, SHA-2 and public key (RSA, ECC) acceleration. Its engines are
+//! not documented at the register level, and are driven by Nordic's `nrf_cc310` runtime library
+//! (part of nrfxlib), which is not part of this HAL. `CryptoCell` powers up the subsystem, which
+//! the library requires before its first call, and powers it down when released:
+//!
+//! ```ignore
+//! let cryptocell = CryptoCell::new(p.CRYPTOCELL);
+//! // Call into the runtime library, e.g. through bindings to `nrf_cc310_platform_init`.
+//! let raw = cryptocell.free();
+//! ```
+//!
+//! The subsystem draws current while it is enabled, so it should only be enabled while it is
+//! used.
+
+#[cfg(feature = "9160")]
+use crate::pac::CRYPTOCELL_S as CRYPTOCELL;
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::CRYPTOCELL;
+
+/// A CC310 subsystem that is powered up.
+pub struct CryptoCell(CRYPTOCELL);
+
+impl CryptoCell {
+    /// Takes ownership of the `CRYPTOCELL` peripheral and powers up the CC310 subsystem.
+    pub fn new(cryptocell: CRYPTOCELL) -> Self {
+        cryptocell.enable.write(|w| w.enable().enabled());
+        Self(cryptocell)
+    }
+
+    /// Powers down the CC310 subsystem and releases the `CRYPTOCELL` peripheral.
+    pub fn free(self) -> CRYPTOCELL {
+        self.0.enable.write(|w| w.enable().disabled());
+        self.0
+    }
+}
@@ -0,0 +1,177 @@
+//! Debounced digital inputs.
+//!
+//! Mechanical switches bounce for a few milliseconds when they change state. `DebouncedInput`
+//! filters this by only accepting a new pin level once it has been stable for the configured
+//! interval, measured with a free-running TIMER or RTC.
+
+use crate::gpio::Level;
+use crate::hal::digital::v2::InputPin;
+use crate::rtc::{self, Rtc};
+use crate::timer::{self, Timer};
+use void::{unreachable, Void};
+
+/// A free-running counter that can be used to measure the debounce interval.
+pub trait DebounceClock {
+    /// Returns the current counter value.
+    fn now(&self) -> u32;
+
+    /// Returns the counter frequency in Hz.
+    fn ticks_per_second(&self) -> u32;
+
+    /// Returns the mask of valid counter bits. The counter wraps from `mask` to 0.
+    fn mask(&self) -> u32;
+}
+
+/// The TIMER must be kept running, e.g. by starting a periodic timer with `u32::MAX` cycles.
+impl<T, U> DebounceClock for Timer<T, U>
+where
+    T: timer::Instance,
+{
+    fn now(&self) -> u32 {
+        self.read()
+    }
+
+    fn ticks_per_second(&self) -> u32 {
+        Self::TICKS_PER_SECOND
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX
+    }
+}
+
+/// The RTC counter must be enabled with `Rtc::enable_counter`.
+impl<T> DebounceClock for Rtc<T>
+where
+    T: rtc::Instance,
+{
+    fn now(&self) -> u32 {
+        self.get_counter()
+    }
+
+    fn ticks_per_second(&self) -> u32 {
+        self.frequency()
+    }
+
+    fn mask(&self) -> u32 {
+        0x00FF_FFFF
+    }
+}
+
+impl<C> DebounceClock for &C
+where
+    C: DebounceClock,
+{
+    fn now(&self) -> u32 {
+        (*self).now()
+    }
+
+    fn ticks_per_second(&self) -> u32 {
+        (*self).ticks_per_second()
+    }
+
+    fn mask(&self) -> u32 {
+        (*self).mask()
+    }
+}
+
+/// Debounced state changes reported by `DebouncedInput::poll`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ButtonEvent {
+    /// The input changed to its active level.
+    Pressed,
+    /// The input changed to its inactive level.
+    Released,
+}
+
+/// An input pin that filters out contact bounce.
+///
+/// The clock can be shared between several inputs by passing a reference to it.
+pub struct DebouncedInput<P, C> {
+    pin: P,
+    clock: C,
+    active_level: Level,
+    interval: u32,
+    stable_high: bool,
+    raw_high: bool,
+    last_change: u32,
+}
+
+impl<P, C> DebouncedInput<P, C>
+where
+    P: InputPin<Error = Void>,
+    C: DebounceClock,
+{
+    /// Creates a new debounced input.
+    ///
+    /// A level change is only accepted once the pin has read the same level for `debounce_us`
+    /// microseconds. `active_level` is the level the pin reads while pressed, which is usually
+    /// `Level::Low` for buttons using a pull-up.
+    pub fn new(pin: P, clock: C, debounce_us: u32, active_level: Level) -> Self {
+        let interval = (u64::from(debounce_us) * u64::from(clock.ticks_per_second()) / 1_000_000)
+            .min(u64::from(clock.mask())) as u32;
+        let raw_high = read_high(&pin);
+        let last_change = clock.now();
+
+        Self {
+            pin,
+            clock,
+            active_level,
+            interval,
+            stable_high: raw_high,
+            raw_high,
+            last_change,
+        }
+    }
+
+    /// Samples the pin and returns the debounced state change, if any.
+    ///
+    /// This should be called regularly, at least once per debounce interval.
+    pub fn poll(&mut self) -> Option<ButtonEvent> {
+        let now = self.clock.now();
+        let raw_high = read_high(&self.pin);
+
+        if raw_high != self.raw_high {
+            self.raw_high = raw_high;
+            self.last_change = now;
+            return None;
+        }
+
+        let elapsed = now.wrapping_sub(self.last_change) & self.clock.mask();
+        if raw_high == self.stable_high || elapsed < self.interval {
+            return None;
+        }
+
+        self.stable_high = raw_high;
+        if self.is_active() {
+            Some(ButtonEvent::Pressed)
+        } else {
+            Some(ButtonEvent::Released)
+        }
+    }
+
+    /// Samples the pin and returns whether the input is in its active level after debouncing.
+    pub fn is_pressed(&mut self) -> bool {
+        self.poll();
+        self.is_active()
+    }
+
+    /// Consumes `self` and returns back the pin and the clock.
+    pub fn free(self) -> (P, C) {
+        (self.pin, self.clock)
+    }
+
+    fn is_active(&self) -> bool {
+        match self.active_level {
+            Level::High => self.stable_high,
+            Level::Low => !self.stable_high,
+        }
+    }
+}
+
+fn read_high<P: InputPin<Error = Void>>(pin: &P) -> bool {
+    match pin.is_high() {
+        Ok(high) => high,
+        Err(x) => unreachable(x),
+    }
+}
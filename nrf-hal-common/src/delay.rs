@@ -1,7 +1,8 @@
 //! Delays.
 use cast::u32;
 use cortex_m::peripheral::syst::SystClkSource;
-use cortex_m::peripheral::SYST;
+use cortex_m::peripheral::{DCB, DWT, SYST};
+use fugit::Duration;
 
 use crate::clocks::HFCLK_FREQ;
 use crate::hal::blocking::delay::{DelayMs, DelayUs};
@@ -19,6 +20,14 @@ impl Delay {
         Delay { syst }
     }
 
+    /// Blocks for the given duration, e.g. `500.millis()`.
+    pub fn delay_for<const NOM: u32, const DENOM: u32>(
+        &mut self,
+        duration: Duration<u32, NOM, DENOM>,
+    ) {
+        self.delay_us(duration.to_micros());
+    }
+
     /// Releases the system timer (SysTick) resource.
     pub fn free(self) -> SYST {
         self.syst
@@ -82,3 +91,112 @@ impl DelayUs<u8> for Delay {
         self.delay_us(u32(us))
     }
 }
+
+/// The DWT cycle counter (CYCCNT), for cheap timestamps and profiling.
+///
+/// The counter is incremented on every CPU clock cycle, and wraps around after about 67 seconds
+/// at 64 MHz.
+pub struct CycleCounter {
+    dwt: DWT,
+}
+
+impl CycleCounter {
+    /// Enables tracing and the cycle counter.
+    pub fn new(dcb: &mut DCB, mut dwt: DWT) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+
+        CycleCounter { dwt }
+    }
+
+    /// Returns the current cycle count.
+    pub fn now(&self) -> u32 {
+        DWT::cycle_count()
+    }
+
+    /// Returns the number of cycles elapsed since `start`, a value previously returned by `now`.
+    pub fn cycles_since(&self, start: u32) -> u32 {
+        self.now().wrapping_sub(start)
+    }
+
+    /// Converts a number of cycles to microseconds.
+    pub fn cycles_to_us(&self, cycles: u32) -> u32 {
+        cycles / (HFCLK_FREQ / 1_000_000)
+    }
+
+    /// Returns a delay provider using this counter.
+    pub fn delay(&self) -> CycleDelay<'_> {
+        CycleDelay { _counter: self }
+    }
+
+    /// Disables the cycle counter, and releases the DWT resource.
+    pub fn free(mut self) -> DWT {
+        self.dwt.disable_cycle_counter();
+        self.dwt
+    }
+}
+
+/// A delay provider busy-waiting on the DWT cycle counter.
+///
+/// Delays are accurate to a few CPU cycles without any calibration, which makes them suitable
+/// for very short waits. Since the cycle counter is only read, any number of `CycleDelay`s can
+/// be used concurrently, e.g. from different interrupt handlers. They borrow the `CycleCounter`,
+/// so the counter can't be disabled while they are in use.
+#[derive(Clone)]
+pub struct CycleDelay<'a> {
+    _counter: &'a CycleCounter,
+}
+
+impl CycleDelay<'_> {
+    /// Busy-waits for the given number of CPU cycles.
+    pub fn delay_cycles(&self, cycles: u32) {
+        let start = DWT::cycle_count();
+        while DWT::cycle_count().wrapping_sub(start) < cycles {}
+    }
+}
+
+impl DelayMs<u32> for CycleDelay<'_> {
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms {
+            self.delay_us(1_000u32);
+        }
+    }
+}
+
+impl DelayMs<u16> for CycleDelay<'_> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(u32(ms));
+    }
+}
+
+impl DelayMs<u8> for CycleDelay<'_> {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(u32(ms));
+    }
+}
+
+impl DelayUs<u32> for CycleDelay<'_> {
+    fn delay_us(&mut self, us: u32) {
+        // Wait in steps that don't overflow the cycle count.
+        const MAX_STEP_US: u32 = u32::MAX / (HFCLK_FREQ / 1_000_000) / 2;
+
+        let mut remaining = us;
+        while remaining > 0 {
+            let step = remaining.min(MAX_STEP_US);
+            self.delay_cycles(step * (HFCLK_FREQ / 1_000_000));
+            remaining -= step;
+        }
+    }
+}
+
+impl DelayUs<u16> for CycleDelay<'_> {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(u32(us))
+    }
+}
+
+impl DelayUs<u8> for CycleDelay<'_> {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(u32(us))
+    }
+}
@@ -0,0 +1,296 @@
+//! HAL interface for the DPPI peripheral.
+//!
+//! The Distributed Programmable Peripheral Interconnect replaces the PPI on newer devices. Instead
+//! of each channel pointing at one event and one task, events publish on a channel and tasks
+//! subscribe to it, through the `PUBLISH_*` and `SUBSCRIBE_*` registers of the peripherals
+//! themselves. Any number of events and tasks can be connected to the same channel.
+//!
+//! Each channel is a distinct type obtained from [Parts](struct.Parts.html):
+//!
+//! ```ignore
+//! let dppi_channels = dppi::Parts::new(p.DPPIC_NS);
+//! let mut channel0 = dppi_channels.dppi0;
+//!
+//! channel0.set_event_endpoint(timer.event_compare_cc0());
+//! channel0.set_task_endpoint(&p.SAADC_NS.tasks_sample);
+//! channel0.enable();
+//! ```
+
+use crate::pac::dppic_ns::tasks_chg::{_DIS, _EN};
+use crate::pac::generic::Reg;
+use crate::pac::DPPIC_NS as DPPIC;
+use core::ptr;
+
+mod event_nrf9160;
+mod task_nrf9160;
+
+/// Offset of a `PUBLISH_*`/`SUBSCRIBE_*` register from its event/task register.
+const ENDPOINT_CONFIG_OFFSET: u32 = 0x80;
+/// `EN` bit of the `PUBLISH_*`/`SUBSCRIBE_*` registers.
+const ENDPOINT_ENABLE: u32 = 1 << 31;
+
+mod sealed {
+    use super::ENDPOINT_CONFIG_OFFSET;
+    use crate::pac::generic::Reg;
+
+    pub trait Channel {
+        const CH: usize;
+    }
+
+    pub trait Endpoint {
+        #[inline(always)]
+        fn config_reg(&self) -> *mut u32 {
+            (self as *const _ as *const u32 as u32 + ENDPOINT_CONFIG_OFFSET) as *mut u32
+        }
+    }
+    impl<REG> Endpoint for Reg<u32, REG> {}
+
+    pub trait ChannelGroup {
+        const CHG: usize;
+    }
+
+    pub trait DppiSealed {}
+    pub trait DppiChannelGroupSealed {}
+}
+use sealed::{Channel, ChannelGroup, DppiChannelGroupSealed, DppiSealed, Endpoint};
+
+/// Trait to represent a task register with a `SUBSCRIBE_*` register, usable as a DPPI task
+/// endpoint.
+///
+/// Implemented for the task registers of all peripherals, including the ones returned by the
+/// `task_*` methods of the HAL drivers.
+pub trait Task: Endpoint {}
+
+/// Trait to represent an event register with a `PUBLISH_*` register, usable as a DPPI event
+/// endpoint.
+///
+/// Implemented for the event registers of all peripherals, including the ones returned by the
+/// `event_*` methods of the HAL drivers.
+pub trait Event: Endpoint {}
+
+/// Trait to represent a Distributed Programmable Peripheral Interconnect channel.
+pub trait Dppi: DppiSealed {
+    /// Enables the channel.
+    fn enable(&mut self);
+
+    /// Disables the channel.
+    fn disable(&mut self);
+
+    /// Returns `true` if the channel is enabled.
+    fn is_enabled(&self) -> bool;
+
+    /// Publishes the given event on this channel. The user must provide a reference to the event.
+    ///
+    /// An event can only publish on one channel, so this replaces any channel the event was
+    /// previously publishing on. Several events can publish on the same channel.
+    fn set_event_endpoint<E: Event>(&mut self, event: &E);
+
+    /// Subscribes the given task to this channel. The user must provide a reference to the task.
+    ///
+    /// A task can only subscribe to one channel, so this replaces any channel the task was
+    /// previously subscribed to. Several tasks can subscribe to the same channel.
+    fn set_task_endpoint<T: Task>(&mut self, task: &T);
+}
+
+/// Stops the given event from publishing on any channel.
+#[inline(always)]
+pub fn clear_event_endpoint<E: Event>(event: &E) {
+    unsafe { ptr::write_volatile(event.config_reg(), 0) };
+}
+
+/// Unsubscribes the given task from any channel.
+#[inline(always)]
+pub fn clear_task_endpoint<T: Task>(task: &T) {
+    unsafe { ptr::write_volatile(task.config_reg(), 0) };
+}
+
+/// Trait for a DPPI channel group.
+pub trait DppiChannelGroup: DppiChannelGroupSealed {
+    /// Returns reference to `tasks_chg[x].en` endpoint for enabling channel group.
+    fn task_enable(&self) -> &Reg<u32, _EN>;
+    /// Returns reference to `tasks_chg[x].dis` endpoint for disabling channel group.
+    fn task_disable(&self) -> &Reg<u32, _DIS>;
+    /// Sets bitmask for DPPI channels which shall be included in this channel group.
+    fn set_channels(&self, mask: u32);
+    /// Includes the given DPPI channel in this channel group.
+    fn add_channel<P: Channel>(&self, channel: &P);
+    /// Removes the given DPPI channel from this channel group.
+    fn remove_channel<P: Channel>(&self, channel: &P);
+    /// Returns the bitmask of DPPI channels included in this channel group.
+    fn channels(&self) -> u32;
+    /// Enables this channel group.
+    fn enable(&self);
+    /// Disables this channel group.
+    fn disable(&self);
+}
+
+// All unsafe `ptr` calls only uses registers atomically, and only changes the resources owned by
+// the type (guaranteed by the abstraction).
+impl<P: Channel> DppiSealed for P {}
+impl<P: Channel> Dppi for P {
+    #[inline(always)]
+    fn enable(&mut self) {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chenset.write(|w| unsafe { w.bits(1 << P::CH) });
+    }
+
+    #[inline(always)]
+    fn disable(&mut self) {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chenclr.write(|w| unsafe { w.bits(1 << P::CH) });
+    }
+
+    #[inline(always)]
+    fn is_enabled(&self) -> bool {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chen.read().bits() & (1 << P::CH) != 0
+    }
+
+    #[inline(always)]
+    fn set_event_endpoint<E: Event>(&mut self, event: &E) {
+        unsafe { ptr::write_volatile(event.config_reg(), P::CH as u32 | ENDPOINT_ENABLE) };
+    }
+
+    #[inline(always)]
+    fn set_task_endpoint<T: Task>(&mut self, task: &T) {
+        unsafe { ptr::write_volatile(task.config_reg(), P::CH as u32 | ENDPOINT_ENABLE) };
+    }
+}
+
+impl<G: ChannelGroup> DppiChannelGroupSealed for G {}
+impl<G: ChannelGroup> DppiChannelGroup for G {
+    #[inline(always)]
+    fn task_enable(&self) -> &Reg<u32, _EN> {
+        let regs = unsafe { &*DPPIC::ptr() };
+        &regs.tasks_chg[Self::CHG].en
+    }
+    #[inline(always)]
+    fn task_disable(&self) -> &Reg<u32, _DIS> {
+        let regs = unsafe { &*DPPIC::ptr() };
+        &regs.tasks_chg[Self::CHG].dis
+    }
+    #[inline(always)]
+    fn set_channels(&self, mask: u32) {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chg[Self::CHG].write(|w| unsafe { w.bits(mask) });
+    }
+    #[inline(always)]
+    fn add_channel<P: Channel>(&self, _channel: &P) {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chg[Self::CHG].modify(|r, w| unsafe { w.bits(r.bits() | 1 << P::CH) });
+    }
+    #[inline(always)]
+    fn remove_channel<P: Channel>(&self, _channel: &P) {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chg[Self::CHG].modify(|r, w| unsafe { w.bits(r.bits() & !(1 << P::CH)) });
+    }
+    #[inline(always)]
+    fn channels(&self) -> u32 {
+        let regs = unsafe { &*DPPIC::ptr() };
+        regs.chg[Self::CHG].read().bits()
+    }
+    #[inline(always)]
+    fn enable(&self) {
+        self.task_enable().write(|w| unsafe { w.bits(1) });
+    }
+    #[inline(always)]
+    fn disable(&self) {
+        self.task_disable().write(|w| unsafe { w.bits(1) });
+    }
+}
+
+/// DPPI channel number `CH`.
+///
+/// Each channel can only be obtained once, through [Parts](struct.Parts.html), so two drivers
+/// can't claim the same channel.
+pub struct DppiChannel<const CH: usize> {
+    _private: (),
+}
+
+impl<const CH: usize> Channel for DppiChannel<CH> {
+    const CH: usize = CH;
+}
+
+/// DPPI channel group number `CHG`.
+pub struct Chg<const CHG: usize> {
+    _private: (),
+}
+
+impl<const CHG: usize> ChannelGroup for Chg<CHG> {
+    const CHG: usize = CHG;
+}
+
+macro_rules! dppi {
+    (
+        channels: [$(($dppix:ident, $DppixType:ident, $ch:expr),)+],
+        groups: [$(($chgx:ident, $ChgxType:ident, $chg:expr),)+],
+    ) => {
+        $(
+            /// DPPI Channel.
+            pub type $DppixType = DppiChannel<$ch>;
+        )+
+
+        $(
+            /// Channel groups.
+            pub type $ChgxType = Chg<$chg>;
+        )+
+
+        /// Type that abstracts all the DPPI channels.
+        pub struct Parts {
+            $(
+                pub $dppix: $DppixType,
+            )+
+            $(
+                pub $chgx: $ChgxType,
+            )+
+        }
+
+        impl Parts {
+            /// Gets access to the DPPI abstraction, making it possible to separate the channels
+            /// through different objects.
+            pub fn new(_regs: DPPIC) -> Self {
+                Self {
+                    $(
+                        $dppix: $DppixType {
+                            _private: (),
+                        },
+                    )+
+                    $(
+                        $chgx: $ChgxType {
+                            _private: (),
+                        },
+                    )+
+                }
+            }
+        }
+    };
+}
+
+dppi!(
+    channels: [
+        (dppi0, Dppi0, 0),
+        (dppi1, Dppi1, 1),
+        (dppi2, Dppi2, 2),
+        (dppi3, Dppi3, 3),
+        (dppi4, Dppi4, 4),
+        (dppi5, Dppi5, 5),
+        (dppi6, Dppi6, 6),
+        (dppi7, Dppi7, 7),
+        (dppi8, Dppi8, 8),
+        (dppi9, Dppi9, 9),
+        (dppi10, Dppi10, 10),
+        (dppi11, Dppi11, 11),
+        (dppi12, Dppi12, 12),
+        (dppi13, Dppi13, 13),
+        (dppi14, Dppi14, 14),
+        (dppi15, Dppi15, 15),
+    ],
+    groups: [
+        (chg0, Chg0, 0),
+        (chg1, Chg1, 1),
+        (chg2, Chg2, 2),
+        (chg3, Chg3, 3),
+        (chg4, Chg4, 4),
+        (chg5, Chg5, 5),
+    ],
+);
@@ -0,0 +1,69 @@
+use crate::dppi::Task;
+
+// Task Impls for DPPI channel groups
+impl Task for crate::pac::dppic_ns::tasks_chg::EN {}
+impl Task for crate::pac::dppic_ns::tasks_chg::DIS {}
+
+// Task Impls
+//
+// To reproduce, in the pac crate, search
+//   `rg 'type TASKS_.*crate::Reg' --type rust`
+// Find (regex):
+//   `^src/(.*)\.rs:pub type (.*) = .*$`
+// Replace (regex):
+//   `impl Task for crate::pac::$1::$2 { }`
+// and remove the tasks without a matching `SUBSCRIBE_*` register.
+impl Task for crate::pac::clock_ns::TASKS_HFCLKSTART {}
+impl Task for crate::pac::clock_ns::TASKS_HFCLKSTOP {}
+impl Task for crate::pac::clock_ns::TASKS_LFCLKSTART {}
+impl Task for crate::pac::clock_ns::TASKS_LFCLKSTOP {}
+impl Task for crate::pac::egu0_ns::TASKS_TRIGGER {}
+impl Task for crate::pac::gpiote0_s::TASKS_OUT {}
+impl Task for crate::pac::gpiote0_s::TASKS_SET {}
+impl Task for crate::pac::gpiote0_s::TASKS_CLR {}
+impl Task for crate::pac::i2s_ns::TASKS_START {}
+impl Task for crate::pac::i2s_ns::TASKS_STOP {}
+impl Task for crate::pac::ipc_ns::TASKS_SEND {}
+impl Task for crate::pac::pdm_ns::TASKS_START {}
+impl Task for crate::pac::pdm_ns::TASKS_STOP {}
+impl Task for crate::pac::power_ns::TASKS_CONSTLAT {}
+impl Task for crate::pac::power_ns::TASKS_LOWPWR {}
+impl Task for crate::pac::pwm0_ns::TASKS_STOP {}
+impl Task for crate::pac::pwm0_ns::TASKS_SEQSTART {}
+impl Task for crate::pac::pwm0_ns::TASKS_NEXTSTEP {}
+impl Task for crate::pac::rtc0_ns::TASKS_START {}
+impl Task for crate::pac::rtc0_ns::TASKS_STOP {}
+impl Task for crate::pac::rtc0_ns::TASKS_CLEAR {}
+impl Task for crate::pac::rtc0_ns::TASKS_TRIGOVRFLW {}
+impl Task for crate::pac::saadc_ns::TASKS_START {}
+impl Task for crate::pac::saadc_ns::TASKS_SAMPLE {}
+impl Task for crate::pac::saadc_ns::TASKS_STOP {}
+impl Task for crate::pac::saadc_ns::TASKS_CALIBRATEOFFSET {}
+impl Task for crate::pac::spim0_ns::TASKS_START {}
+impl Task for crate::pac::spim0_ns::TASKS_STOP {}
+impl Task for crate::pac::spim0_ns::TASKS_SUSPEND {}
+impl Task for crate::pac::spim0_ns::TASKS_RESUME {}
+impl Task for crate::pac::spis0_ns::TASKS_ACQUIRE {}
+impl Task for crate::pac::spis0_ns::TASKS_RELEASE {}
+impl Task for crate::pac::timer0_ns::TASKS_START {}
+impl Task for crate::pac::timer0_ns::TASKS_STOP {}
+impl Task for crate::pac::timer0_ns::TASKS_COUNT {}
+impl Task for crate::pac::timer0_ns::TASKS_CLEAR {}
+impl Task for crate::pac::timer0_ns::TASKS_SHUTDOWN {}
+impl Task for crate::pac::timer0_ns::TASKS_CAPTURE {}
+impl Task for crate::pac::twim0_ns::TASKS_STARTRX {}
+impl Task for crate::pac::twim0_ns::TASKS_STARTTX {}
+impl Task for crate::pac::twim0_ns::TASKS_STOP {}
+impl Task for crate::pac::twim0_ns::TASKS_SUSPEND {}
+impl Task for crate::pac::twim0_ns::TASKS_RESUME {}
+impl Task for crate::pac::twis0_ns::TASKS_STOP {}
+impl Task for crate::pac::twis0_ns::TASKS_SUSPEND {}
+impl Task for crate::pac::twis0_ns::TASKS_RESUME {}
+impl Task for crate::pac::twis0_ns::TASKS_PREPARERX {}
+impl Task for crate::pac::twis0_ns::TASKS_PREPARETX {}
+impl Task for crate::pac::uarte0_ns::TASKS_STARTRX {}
+impl Task for crate::pac::uarte0_ns::TASKS_STOPRX {}
+impl Task for crate::pac::uarte0_ns::TASKS_STARTTX {}
+impl Task for crate::pac::uarte0_ns::TASKS_STOPTX {}
+impl Task for crate::pac::uarte0_ns::TASKS_FLUSHRX {}
+impl Task for crate::pac::wdt_ns::TASKS_START {}
@@ -1,6 +1,34 @@
 //! HAL interface to the AES electronic codebook mode encryption.
 //!
 //! The ECB encryption block supports 128 bit AES encryption (encryption only, not decryption).
+//!
+//! `Ecb::encrypt_block` encrypts a block while blocking for around 7 µs. `Ecb::start` encrypts a
+//! block in the background, e.g. to wait for the `ECB` interrupt:
+//!
+//! ```ignore
+//! static mut DATA: EcbData = EcbData::new([0; 16], [0; 16]);
+//!
+//! let data = unsafe { &mut DATA };
+//! data.key = key;
+//! data.clear_text = block;
+//! let transfer = ecb.start(data);
+//! // ...
+//! let (result, ecb, data) = transfer.wait();
+//! ```
+//!
+//! `AesCtr` and `AesCmac` build the AES-CTR encryption and AES-CMAC authentication modes on the
+//! ECB, with incremental interfaces to process data in chunks:
+//!
+//! ```ignore
+//! let mut cmac = AesCmac::new(&mut ecb, key)?;
+//! for chunk in firmware.chunks(256) {
+//!     cmac.update(chunk)?;
+//! }
+//! let tag = cmac.finalize()?;
+//! ```
+//!
+//! With the `cipher` feature, `Aes128Ecb` implements the RustCrypto `BlockEncrypt` trait, so the
+//! ECB can be used by crates that are generic over the AES implementation.
 
 use crate::pac::ECB;
 use core::sync::atomic::{compiler_fence, Ordering};
@@ -9,6 +37,29 @@ use core::sync::atomic::{compiler_fence, Ordering};
 #[derive(Debug, Copy, Clone)]
 pub struct EncryptionError {}
 
+/// The key, cleartext and ciphertext of an encryption, in the layout used by the ECB.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EcbData {
+    /// The 128-bit key, most significant byte first.
+    pub key: [u8; 16],
+    /// The block to encrypt.
+    pub clear_text: [u8; 16],
+    /// The encrypted block, written by the ECB.
+    pub cipher_text: [u8; 16],
+}
+
+impl EcbData {
+    /// Creates the data for the encryption of `clear_text` with `key`.
+    pub const fn new(key: [u8; 16], clear_text: [u8; 16]) -> Self {
+        Self {
+            key,
+            clear_text,
+            cipher_text: [0; 16],
+        }
+    }
+}
+
 /// A safe, blocking wrapper around the AES-ECB peripheral.
 ///
 /// It's really just blockwise AES and not an ECB stream cipher. Blocks can be
@@ -53,20 +104,9 @@ impl Ecb {
         block: [u8; 16],
         key: [u8; 16],
     ) -> Result<[u8; 16], EncryptionError> {
-        #[repr(C)]
-        struct EcbData {
-            key: [u8; 16],
-            clear_text: [u8; 16],
-            cipher_text: [u8; 16],
-        }
-
         // We allocate the DMA'd buffer on the stack, which means that we must
         // not panic or return before the AES operation is finished.
-        let mut buf = EcbData {
-            key,
-            clear_text: block,
-            cipher_text: [0; 16],
-        };
+        let mut buf = EcbData::new(key, block);
 
         // NOTE(unsafe) Any 32bits pattern is safe to write to this register.
         self.regs
@@ -95,4 +135,297 @@ impl Ecb {
         }
         Ok(buf.cipher_text)
     }
+
+    /// Starts encrypting `data.clear_text` with `data.key` into `data.cipher_text`, and returns
+    /// without waiting for the encryption to finish.
+    pub fn start(self, data: &'static mut EcbData) -> EcbTransfer {
+        // NOTE(unsafe) Any 32bits pattern is safe to write to this register.
+        self.regs
+            .ecbdataptr
+            .write(|w| unsafe { w.bits(data as *mut _ as u32) });
+
+        self.regs.events_endecb.reset();
+        self.regs.events_errorecb.reset();
+
+        compiler_fence(Ordering::Release);
+        // NOTE(unsafe) 1 is a valid pattern to write to this register
+        self.regs.tasks_startecb.write(|w| unsafe { w.bits(1) });
+
+        EcbTransfer {
+            inner: Some((self, data)),
+        }
+    }
+
+    /// Enables the `ECB` interrupt, triggered when an encryption started with `start` ends or
+    /// fails.
+    pub fn enable_interrupt(&mut self) {
+        self.regs
+            .intenset
+            .write(|w| w.endecb().set().errorecb().set());
+    }
+
+    /// Disables the `ECB` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.regs
+            .intenclr
+            .write(|w| w.endecb().clear().errorecb().clear());
+    }
+}
+
+/// An encryption running in the background, started with `Ecb::start`.
+///
+/// Dropping it aborts the encryption.
+pub struct EcbTransfer {
+    // FIXME: Always `Some`, only using `Option` here to allow moving fields out of `inner`.
+    inner: Option<(Ecb, &'static mut EcbData)>,
+}
+
+impl EcbTransfer {
+    /// Checks if the encryption has ended or failed.
+    pub fn is_done(&self) -> bool {
+        let (ecb, _) = self
+            .inner
+            .as_ref()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        ecb.regs.events_endecb.read().bits() != 0 || ecb.regs.events_errorecb.read().bits() != 0
+    }
+
+    /// Blocks until the encryption is done, and returns its result with the ECB and the data.
+    ///
+    /// On success, the encrypted block is in `cipher_text`. See `Ecb::encrypt_block` for the
+    /// causes of errors.
+    pub fn wait(mut self) -> (Result<(), EncryptionError>, Ecb, &'static mut EcbData) {
+        while !self.is_done() {}
+        compiler_fence(Ordering::Acquire);
+
+        let (ecb, data) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        let result = match ecb.regs.events_errorecb.read().bits() {
+            0 => Ok(()),
+            _ => Err(EncryptionError {}),
+        };
+        (result, ecb, data)
+    }
+
+    /// Aborts the encryption, and returns the ECB and the data.
+    pub fn abort(mut self) -> (Ecb, &'static mut EcbData) {
+        let (ecb, data) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        stop(&ecb);
+        (ecb, data)
+    }
+}
+
+impl Drop for EcbTransfer {
+    fn drop(&mut self) {
+        if let Some((ecb, _)) = self.inner.as_ref() {
+            stop(ecb);
+        }
+    }
+}
+
+/// Aborts a running encryption.
+fn stop(ecb: &Ecb) {
+    // NOTE(unsafe) 1 is a valid pattern to write to this register
+    ecb.regs.tasks_stopecb.write(|w| unsafe { w.bits(1) });
+    compiler_fence(Ordering::Acquire);
+}
+
+/// AES-128 in counter (CTR) mode, using the ECB to generate the key stream.
+///
+/// Encryption and decryption are the same operation: XORing the data with the key stream.
+pub struct AesCtr<'a> {
+    ecb: &'a mut Ecb,
+    key: [u8; 16],
+    counter: [u8; 16],
+    key_stream: [u8; 16],
+    used: usize,
+}
+
+impl<'a> AesCtr<'a> {
+    /// Starts the key stream of `key` at the 128-bit big endian `counter` block, usually a nonce
+    /// followed by a block counter.
+    ///
+    /// The same key and counter must never be used for two messages.
+    pub fn new(ecb: &'a mut Ecb, key: [u8; 16], counter: [u8; 16]) -> Self {
+        Self {
+            ecb,
+            key,
+            counter,
+            key_stream: [0; 16],
+            used: 16,
+        }
+    }
+
+    /// Encrypts or decrypts `data` in place, continuing the key stream from the previous call.
+    ///
+    /// On error, `data` may be partially processed, and the key stream continues after the
+    /// processed bytes.
+    pub fn apply_key_stream(&mut self, data: &mut [u8]) -> Result<(), EncryptionError> {
+        for byte in data {
+            if self.used == 16 {
+                self.key_stream = self.ecb.encrypt_block(self.counter, self.key)?;
+                self.counter = u128::from_be_bytes(self.counter)
+                    .wrapping_add(1)
+                    .to_be_bytes();
+                self.used = 0;
+            }
+            *byte ^= self.key_stream[self.used];
+            self.used += 1;
+        }
+        Ok(())
+    }
+}
+
+/// AES-CMAC (RFC 4493) message authentication, using the ECB.
+pub struct AesCmac<'a> {
+    ecb: &'a mut Ecb,
+    key: [u8; 16],
+    subkeys: ([u8; 16], [u8; 16]),
+    state: [u8; 16],
+    // The last block is processed by `finalize`, so it's kept here until more data arrives.
+    block: [u8; 16],
+    len: usize,
+}
+
+impl<'a> AesCmac<'a> {
+    /// Starts the computation of a MAC with `key`.
+    pub fn new(ecb: &'a mut Ecb, key: [u8; 16]) -> Result<Self, EncryptionError> {
+        let k1 = cmac_double(ecb.encrypt_block([0; 16], key)?);
+        let k2 = cmac_double(k1);
+        Ok(Self {
+            ecb,
+            key,
+            subkeys: (k1, k2),
+            state: [0; 16],
+            block: [0; 16],
+            len: 0,
+        })
+    }
+
+    /// Adds `data` to the authenticated message.
+    pub fn update(&mut self, mut data: &[u8]) -> Result<(), EncryptionError> {
+        while !data.is_empty() {
+            if self.len == 16 {
+                self.state = self
+                    .ecb
+                    .encrypt_block(xor_block(self.state, self.block), self.key)?;
+                self.len = 0;
+            }
+            let n = data.len().min(16 - self.len);
+            self.block[self.len..self.len + n].copy_from_slice(&data[..n]);
+            self.len += n;
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    /// Returns the 128-bit MAC of the message.
+    pub fn finalize(self) -> Result<[u8; 16], EncryptionError> {
+        let mut last = self.block;
+        let subkey = if self.len == 16 {
+            self.subkeys.0
+        } else {
+            last[self.len] = 0x80;
+            last[self.len + 1..].fill(0);
+            self.subkeys.1
+        };
+        self.ecb
+            .encrypt_block(xor_block(xor_block(self.state, last), subkey), self.key)
+    }
+}
+
+/// Doubles a block in GF(2^128), to derive the CMAC subkeys.
+fn cmac_double(block: [u8; 16]) -> [u8; 16] {
+    let value = u128::from_be_bytes(block);
+    let doubled = (value << 1) ^ if value >> 127 != 0 { 0x87 } else { 0 };
+    doubled.to_be_bytes()
+}
+
+fn xor_block(mut a: [u8; 16], b: [u8; 16]) -> [u8; 16] {
+    a.iter_mut().zip(b.iter()).for_each(|(a, b)| *a ^= b);
+    a
+}
+
+/// AES-128 block encryption with the ECB and a fixed key, for the RustCrypto traits.
+#[cfg(feature = "cipher")]
+pub struct Aes128Ecb {
+    ecb: core::cell::RefCell<Ecb>,
+    key: [u8; 16],
+}
+
+#[cfg(feature = "cipher")]
+impl Aes128Ecb {
+    /// Uses the ECB to encrypt blocks with `key`.
+    pub fn new(ecb: Ecb, key: &cipher::Key<Self>) -> Self {
+        Self {
+            ecb: core::cell::RefCell::new(ecb),
+            key: (*key).into(),
+        }
+    }
+
+    /// Releases the ECB.
+    pub fn free(self) -> Ecb {
+        self.ecb.into_inner()
+    }
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::KeySizeUser for Aes128Ecb {
+    type KeySize = cipher::consts::U16;
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::BlockSizeUser for Aes128Ecb {
+    type BlockSize = cipher::consts::U16;
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::BlockCipher for Aes128Ecb {}
+
+#[cfg(feature = "cipher")]
+impl cipher::BlockEncrypt for Aes128Ecb {
+    /// Encrypts the blocks one after the other.
+    ///
+    /// Encryptions that fail because the CCM or AAR took over the AES hardware are retried.
+    fn encrypt_with_backend(&self, f: impl cipher::BlockClosure<BlockSize = Self::BlockSize>) {
+        let mut ecb = self.ecb.borrow_mut();
+        f.call(&mut Aes128EcbBackend {
+            ecb: &mut ecb,
+            key: &self.key,
+        });
+    }
+}
+
+#[cfg(feature = "cipher")]
+struct Aes128EcbBackend<'a> {
+    ecb: &'a mut Ecb,
+    key: &'a [u8; 16],
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::BlockSizeUser for Aes128EcbBackend<'_> {
+    type BlockSize = cipher::consts::U16;
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::ParBlocksSizeUser for Aes128EcbBackend<'_> {
+    type ParBlocksSize = cipher::consts::U1;
+}
+
+#[cfg(feature = "cipher")]
+impl cipher::BlockBackend for Aes128EcbBackend<'_> {
+    fn proc_block(&mut self, mut block: cipher::inout::InOut<'_, '_, cipher::Block<Self>>) {
+        let clear_text = (*block.get_in()).into();
+        let cipher_text = loop {
+            if let Ok(cipher_text) = self.ecb.encrypt_block(clear_text, *self.key) {
+                break cipher_text;
+            }
+        };
+        *block.get_out() = cipher_text.into();
+    }
 }
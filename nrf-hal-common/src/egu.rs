@@ -0,0 +1,265 @@
+//! HAL interface for the EGU peripheral.
+//!
+//! The Event Generator Unit (EGU) provides 16 software-triggerable events, each of which can
+//! generate the EGU interrupt. This makes it possible to signal from a high priority interrupt
+//! handler to a lower priority one: the high priority handler triggers a channel, and the
+//! remaining work is done in the EGU interrupt handler, which runs at the priority of the EGU
+//! interrupt.
+//!
+//! ```ignore
+//! let signals = Egu::new(p.EGU0).split();
+//! let mut signal = signals.signal0;
+//! let trigger = signal.trigger();
+//! signal.enable_interrupt();
+//!
+//! // In the high priority interrupt handler:
+//! trigger.raise();
+//!
+//! // In the `SWI0_EGU0` interrupt handler:
+//! if signal.take() {
+//!     // ...
+//! }
+//! ```
+//!
+//! The EGU interrupt must also be unmasked in the NVIC, with a priority lower than the one of the
+//! signalling interrupt handlers.
+
+use core::{marker::PhantomData, ops::Deref};
+
+#[cfg(feature = "9160")]
+use crate::pac::{
+    egu0_ns as egu0, EGU0_NS as EGU0, EGU1_NS as EGU1, EGU2_NS as EGU2, EGU3_NS as EGU3,
+    EGU4_NS as EGU4, EGU5_NS as EGU5,
+};
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{egu0, EGU0, EGU1};
+
+#[cfg(not(any(feature = "9160", feature = "52810", feature = "52811")))]
+use crate::pac::{EGU2, EGU3, EGU4, EGU5};
+
+use crate::pac::{generic::Reg, Interrupt};
+use egu0::{_EVENTS_TRIGGERED, _TASKS_TRIGGER};
+
+/// A safe wrapper around an EGU peripheral.
+pub struct Egu<T> {
+    egu: T,
+}
+
+impl<T> Egu<T>
+where
+    T: Instance,
+{
+    /// Takes ownership of the EGU peripheral, disabling all of its interrupts and clearing all of
+    /// its events.
+    pub fn new(egu: T) -> Self {
+        egu.intenclr.write(|w| unsafe { w.bits(0xFFFF) });
+        for event in egu.events_triggered.iter() {
+            event.reset();
+        }
+        Self { egu }
+    }
+
+    /// Splits the EGU into its 16 channels.
+    pub fn split(self) -> Parts<T> {
+        Parts::new(self.egu)
+    }
+
+    /// Releases the EGU peripheral.
+    pub fn free(self) -> T {
+        self.egu
+    }
+}
+
+/// An EGU channel, used to receive signals.
+///
+/// Signals are sent with the [SignalTrigger](struct.SignalTrigger.html) handles returned by
+/// `trigger`.
+pub struct Signal<T, const N: usize> {
+    _egu: PhantomData<T>,
+}
+
+impl<T, const N: usize> Signal<T, N>
+where
+    T: Instance,
+{
+    fn egu(&self) -> &egu0::RegisterBlock {
+        unsafe { &*T::ptr() }
+    }
+
+    /// Returns a handle that can be used to trigger this channel, e.g. from a higher priority
+    /// interrupt handler.
+    pub fn trigger(&self) -> SignalTrigger<T, N> {
+        SignalTrigger { _egu: PhantomData }
+    }
+
+    /// Enables the EGU interrupt for this channel.
+    pub fn enable_interrupt(&mut self) {
+        self.egu().intenset.write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Disables the EGU interrupt for this channel.
+    pub fn disable_interrupt(&mut self) {
+        self.egu().intenclr.write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Checks if the channel has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.egu().events_triggered[N].read().bits() != 0
+    }
+
+    /// Marks the signal as handled.
+    pub fn reset_event(&mut self) {
+        self.egu().events_triggered[N].reset();
+    }
+
+    /// Returns `true` and marks the signal as handled if the channel has been triggered.
+    pub fn take(&mut self) -> bool {
+        if self.is_triggered() {
+            self.reset_event();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns reference to the `TRIGGERED` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_triggered(&self) -> &Reg<u32, _EVENTS_TRIGGERED> {
+        &self.egu().events_triggered[N]
+    }
+}
+
+/// A handle used to trigger an EGU channel.
+///
+/// The handle can be copied freely, since triggering a channel is a single register write.
+pub struct SignalTrigger<T, const N: usize> {
+    _egu: PhantomData<T>,
+}
+
+impl<T, const N: usize> Clone for SignalTrigger<T, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, const N: usize> Copy for SignalTrigger<T, N> {}
+
+impl<T, const N: usize> SignalTrigger<T, N>
+where
+    T: Instance,
+{
+    /// Triggers the channel, generating the EGU interrupt if it is enabled for this channel.
+    #[inline(always)]
+    pub fn raise(&self) {
+        self.task_trigger().write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns reference to the `TRIGGER` task endpoint for PPI.
+    #[inline(always)]
+    pub fn task_trigger(&self) -> &Reg<u32, _TASKS_TRIGGER> {
+        unsafe { &(*T::ptr()).tasks_trigger[N] }
+    }
+}
+
+macro_rules! signals {
+    ($($signal:ident: $n:expr,)+) => {
+        /// The channels of an EGU peripheral.
+        pub struct Parts<T> {
+            $(
+                pub $signal: Signal<T, $n>,
+            )+
+            egu: T,
+        }
+
+        impl<T> Parts<T> {
+            fn new(egu: T) -> Self {
+                Self {
+                    $(
+                        $signal: Signal { _egu: PhantomData },
+                    )+
+                    egu,
+                }
+            }
+
+            /// Releases the EGU peripheral.
+            ///
+            /// This consumes all the channels, so it is only possible if none of them has been
+            /// moved out of `Parts`.
+            pub fn free(self) -> T {
+                self.egu
+            }
+        }
+    };
+}
+
+signals! {
+    signal0: 0,
+    signal1: 1,
+    signal2: 2,
+    signal3: 3,
+    signal4: 4,
+    signal5: 5,
+    signal6: 6,
+    signal7: 7,
+    signal8: 8,
+    signal9: 9,
+    signal10: 10,
+    signal11: 11,
+    signal12: 12,
+    signal13: 13,
+    signal14: 14,
+    signal15: 15,
+}
+
+/// Implemented by all EGU instances.
+pub trait Instance: Deref<Target = egu0::RegisterBlock> + sealed::Sealed {
+    /// The interrupt associated with this EGU instance.
+    const INTERRUPT: Interrupt;
+
+    /// Returns a pointer to the register block.
+    fn ptr() -> *const egu0::RegisterBlock;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_instance {
+    ($($name:ident => $interrupt:ident,)*) => {
+        $(
+            impl Instance for $name {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+
+                fn ptr() -> *const egu0::RegisterBlock {
+                    $name::ptr()
+                }
+            }
+            impl sealed::Sealed for $name {}
+        )*
+    }
+}
+
+#[cfg(feature = "9160")]
+impl_instance!(
+    EGU0 => EGU0,
+    EGU1 => EGU1,
+    EGU2 => EGU2,
+    EGU3 => EGU3,
+    EGU4 => EGU4,
+    EGU5 => EGU5,
+);
+
+#[cfg(not(feature = "9160"))]
+impl_instance!(
+    EGU0 => SWI0_EGU0,
+    EGU1 => SWI1_EGU1,
+);
+
+#[cfg(not(any(feature = "9160", feature = "52810", feature = "52811")))]
+impl_instance!(
+    EGU2 => SWI2_EGU2,
+    EGU3 => SWI3_EGU3,
+    EGU4 => SWI4_EGU4,
+    EGU5 => SWI5_EGU5,
+);
@@ -25,6 +25,12 @@ pub struct PushPull;
 /// Open drain output (type state).
 pub struct OpenDrain;
 
+/// Analog input mode (type state).
+///
+/// The digital input buffer is disconnected, so the pin can be used by the analog peripherals
+/// (SAADC, COMP and LPCOMP) without drawing extra current.
+pub struct Analog;
+
 /// Represents a digital input or output level.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Level {
@@ -32,6 +38,28 @@ pub enum Level {
     High,
 }
 
+/// Pin activity that triggers an event.
+///
+/// GPIOTE channels detect edges, so `High` and `Low` are treated as `Rising` and `Falling`.
+/// PORT events are generated from the pin SENSE levels, so `Rising` and `Falling` are treated as
+/// `High` and `Low`, and `Toggle` senses the level opposite to the current one.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Trigger {
+    Rising,
+    Falling,
+    Toggle,
+    High,
+    Low,
+}
+
+/// Pull resistor configuration of an input pin.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Pull {
+    Disabled,
+    Up,
+    Down,
+}
+
 /// A GPIO port with up to 32 pins.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Port {
@@ -86,7 +114,42 @@ use crate::pac::{p0 as gpio, P0};
 use crate::pac::P1;
 
 use crate::hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin};
-use void::Void;
+use core::convert::TryFrom;
+use void::{unreachable, Void};
+
+impl Port {
+    fn block(&self) -> &'static gpio::RegisterBlock {
+        let ptr = match self {
+            Port::Port0 => P0::ptr(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            Port::Port1 => P1::ptr(),
+        };
+
+        unsafe { &*ptr }
+    }
+
+    /// Returns the latched DETECT state of all pins on this port and clears it.
+    ///
+    /// A pin's bit is set when it has met the criteria of its SENSE configuration, even if that
+    /// happened while the CPU was asleep or busy.
+    #[cfg(not(feature = "51"))]
+    pub fn take_latched(&self) -> u32 {
+        let latch = &self.block().latch;
+        let latched = latch.read().bits();
+        // Bits are cleared by writing 1 to them.
+        latch.write(|w| unsafe { w.bits(latched) });
+        latched
+    }
+
+    /// Selects whether the DETECT signal is generated from the LATCH register (`true`) or
+    /// directly from the pins (`false`, the reset value).
+    #[cfg(not(feature = "51"))]
+    pub fn set_latched_detect(&self, latched: bool) {
+        self.block()
+            .detectmode
+            .write(|w| w.detectmode().bit(latched));
+    }
+}
 
 impl<MODE> Pin<MODE> {
     pub(crate) fn new(port: Port, pin: u8) -> Self {
@@ -132,19 +195,23 @@ impl<MODE> Pin<MODE> {
     }
 
     fn block(&self) -> &gpio::RegisterBlock {
-        let ptr = match self.port() {
-            Port::Port0 => P0::ptr(),
-            #[cfg(any(feature = "52833", feature = "52840"))]
-            Port::Port1 => P1::ptr(),
-        };
-
-        unsafe { &*ptr }
+        self.port().block()
     }
 
     pub(crate) fn conf(&self) -> &gpio::PIN_CNF {
         &self.block().pin_cnf[self.pin() as usize]
     }
 
+    /// Attempts to convert the generic pin back into a typed pin, e.g. `P0_13<MODE>`.
+    ///
+    /// Returns the pin unchanged if it does not refer to the same port and pin as `P`.
+    pub fn try_into_typed<P>(self) -> Result<P, Self>
+    where
+        P: TryFrom<Self, Error = Self>,
+    {
+        P::try_from(self)
+    }
+
     /// Convert the pin to be a floating input
     pub fn into_floating_input(self) -> Pin<Input<Floating>> {
         self.conf().write(|w| {
@@ -261,6 +328,53 @@ impl<MODE> Pin<MODE> {
             pin_port: self.pin_port,
         }
     }
+
+    /// Convert the pin to be an analog input.
+    ///
+    /// The digital input buffer and pull resistors are disabled.
+    pub fn into_analog(self) -> Pin<Analog> {
+        self.conf().write(|w| {
+            w.dir().input();
+            w.input().disconnect();
+            w.pull().disabled();
+            w.drive().s0s1();
+            w.sense().disabled();
+            w
+        });
+
+        Pin {
+            _mode: PhantomData,
+            pin_port: self.pin_port,
+        }
+    }
+}
+
+impl<MODE> Pin<Input<MODE>> {
+    /// Changes the pull resistor configuration without reconfiguring the rest of the pin.
+    ///
+    /// The type state is left unchanged, so it may no longer reflect the pull configuration.
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.conf().modify(|_, w| match pull {
+            Pull::Disabled => w.pull().disabled(),
+            Pull::Up => w.pull().pullup(),
+            Pull::Down => w.pull().pulldown(),
+        });
+    }
+
+    /// Returns whether the pin has met the criteria of its SENSE configuration since the latch
+    /// was last cleared.
+    #[cfg(not(feature = "51"))]
+    pub fn is_latched(&self) -> bool {
+        self.block().latch.read().bits() & (1 << self.pin()) != 0
+    }
+
+    /// Clears the latched DETECT state of the pin.
+    #[cfg(not(feature = "51"))]
+    pub fn clear_latched(&mut self) {
+        self.block()
+            .latch
+            .write(|w| unsafe { w.bits(1 << self.pin()) });
+    }
 }
 
 impl<MODE> InputPin for Pin<Input<MODE>> {
@@ -313,6 +427,238 @@ impl<MODE> StatefulOutputPin for Pin<Output<MODE>> {
     }
 }
 
+/// Pin mode of a `DynPin`, tracked at runtime.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DynMode {
+    Disconnected,
+    FloatingInput,
+    PullUpInput,
+    PullDownInput,
+    PushPullOutput,
+    OpenDrainOutput,
+    Analog,
+}
+
+/// Implemented by the pin mode type states that have a `DynMode` equivalent.
+pub trait PinMode: sealed::Sealed {
+    const DYN_MODE: DynMode;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! pin_modes {
+    ($($mode:ty => $dyn_mode:ident,)+) => {
+        $(
+            impl sealed::Sealed for $mode {}
+            impl PinMode for $mode {
+                const DYN_MODE: DynMode = DynMode::$dyn_mode;
+            }
+        )+
+    };
+}
+
+pin_modes! {
+    Disconnected => Disconnected,
+    Input<Floating> => FloatingInput,
+    Input<PullUp> => PullUpInput,
+    Input<PullDown> => PullDownInput,
+    Output<PushPull> => PushPullOutput,
+    Output<OpenDrain> => OpenDrainOutput,
+    Analog => Analog,
+}
+
+/// Error returned when a `DynPin` is used in a mode it is not configured for.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DynPinError {
+    InvalidMode,
+}
+
+/// A type-erased pin whose mode is tracked at runtime.
+///
+/// Unlike `Pin`, the mode of a `DynPin` can be changed in place, so pins can be stored in arrays
+/// or configuration tables. Use `try_into_pin` to get back a `Pin` with a type-level mode.
+pub struct DynPin {
+    pin_port: u8,
+    mode: DynMode,
+}
+
+impl DynPin {
+    #[inline]
+    pub fn pin(&self) -> u8 {
+        self.erased::<Disconnected>().pin()
+    }
+
+    #[inline]
+    pub fn port(&self) -> Port {
+        self.erased::<Disconnected>().port()
+    }
+
+    /// Returns the current mode of the pin.
+    #[inline]
+    pub fn mode(&self) -> DynMode {
+        self.mode
+    }
+
+    fn erased<MODE>(&self) -> Pin<MODE> {
+        Pin {
+            pin_port: self.pin_port,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Convert the pin to be a floating input
+    pub fn into_floating_input(&mut self) {
+        self.erased::<Disconnected>().into_floating_input();
+        self.mode = DynMode::FloatingInput;
+    }
+
+    /// Convert the pin to be a pulled up input
+    pub fn into_pullup_input(&mut self) {
+        self.erased::<Disconnected>().into_pullup_input();
+        self.mode = DynMode::PullUpInput;
+    }
+
+    /// Convert the pin to be a pulled down input
+    pub fn into_pulldown_input(&mut self) {
+        self.erased::<Disconnected>().into_pulldown_input();
+        self.mode = DynMode::PullDownInput;
+    }
+
+    /// Convert the pin to be a push-pull output with normal drive
+    pub fn into_push_pull_output(&mut self, initial_output: Level) {
+        self.erased::<Disconnected>()
+            .into_push_pull_output(initial_output);
+        self.mode = DynMode::PushPullOutput;
+    }
+
+    /// Convert the pin to be an open-drain output
+    pub fn into_open_drain_output(&mut self, config: OpenDrainConfig, initial_output: Level) {
+        self.erased::<Disconnected>()
+            .into_open_drain_output(config, initial_output);
+        self.mode = DynMode::OpenDrainOutput;
+    }
+
+    /// Convert the pin to be an analog input
+    pub fn into_analog(&mut self) {
+        self.erased::<Disconnected>().into_analog();
+        self.mode = DynMode::Analog;
+    }
+
+    /// Disconnects the pin.
+    pub fn into_disconnected(&mut self) {
+        self.erased::<Disconnected>().into_disconnected();
+        self.mode = DynMode::Disconnected;
+    }
+
+    /// Changes the pull resistor configuration of an input pin in place.
+    pub fn set_pull(&mut self, pull: Pull) -> Result<(), DynPinError> {
+        if !self.is_input() {
+            return Err(DynPinError::InvalidMode);
+        }
+        self.erased::<Input<Floating>>().set_pull(pull);
+        self.mode = match pull {
+            Pull::Disabled => DynMode::FloatingInput,
+            Pull::Up => DynMode::PullUpInput,
+            Pull::Down => DynMode::PullDownInput,
+        };
+        Ok(())
+    }
+
+    /// Attempts to convert into a `Pin` with the type-level mode `MODE`.
+    ///
+    /// Returns the pin unchanged if it is not currently configured in that mode.
+    pub fn try_into_pin<MODE: PinMode>(self) -> Result<Pin<MODE>, Self> {
+        if self.mode == MODE::DYN_MODE {
+            Ok(self.erased())
+        } else {
+            Err(self)
+        }
+    }
+
+    fn is_input(&self) -> bool {
+        matches!(
+            self.mode,
+            DynMode::FloatingInput | DynMode::PullUpInput | DynMode::PullDownInput
+        )
+    }
+
+    fn is_output(&self) -> bool {
+        matches!(
+            self.mode,
+            DynMode::PushPullOutput | DynMode::OpenDrainOutput
+        )
+    }
+}
+
+impl<MODE: PinMode> From<Pin<MODE>> for DynPin {
+    fn from(pin: Pin<MODE>) -> Self {
+        DynPin {
+            pin_port: pin.pin_port,
+            mode: MODE::DYN_MODE,
+        }
+    }
+}
+
+impl InputPin for DynPin {
+    type Error = DynPinError;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.is_low().map(|v| !v)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        if !self.is_input() {
+            return Err(DynPinError::InvalidMode);
+        }
+        match self.erased::<Input<Floating>>().is_low() {
+            Ok(low) => Ok(low),
+            Err(x) => unreachable(x),
+        }
+    }
+}
+
+impl OutputPin for DynPin {
+    type Error = DynPinError;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        match self.erased::<Output<PushPull>>().set_high() {
+            Ok(()) => Ok(()),
+            Err(x) => unreachable(x),
+        }
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        match self.erased::<Output<PushPull>>().set_low() {
+            Ok(()) => Ok(()),
+            Err(x) => unreachable(x),
+        }
+    }
+}
+
+impl StatefulOutputPin for DynPin {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.is_set_low().map(|v| !v)
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        match self.erased::<Output<PushPull>>().is_set_low() {
+            Ok(low) => Ok(low),
+            Err(x) => unreachable(x),
+        }
+    }
+}
+
 /// Pin configuration for open-drain mode.
 pub enum OpenDrainConfig {
     Disconnect0Standard1,
@@ -355,6 +701,7 @@ macro_rules! gpio {
                 Pin,
                 Port,
 
+                Analog,
                 Floating,
                 Disconnected,
                 Input,
@@ -362,6 +709,7 @@ macro_rules! gpio {
                 OpenDrain,
                 OpenDrainConfig,
                 Output,
+                Pull,
                 PullDown,
                 PullUp,
                 PushPull,
@@ -371,6 +719,7 @@ macro_rules! gpio {
             };
 
             use crate::hal::digital::v2::{OutputPin, StatefulOutputPin, InputPin};
+            use core::convert::TryFrom;
             use void::Void;
 
 
@@ -527,12 +876,72 @@ macro_rules! gpio {
                         }
                     }
 
+                    /// Convert the pin to be an analog input.
+                    ///
+                    /// The digital input buffer and pull resistors are disabled.
+                    pub fn into_analog(self) -> $PXi<Analog> {
+                        unsafe { &(*$PX::ptr()).pin_cnf[$i] }.write(|w| {
+                            w.dir().input();
+                            w.input().disconnect();
+                            w.pull().disabled();
+                            w.drive().s0s1();
+                            w.sense().disabled();
+                            w
+                        });
+
+                        $PXi {
+                            _mode: PhantomData,
+                        }
+                    }
+
                     /// Degrade to a generic pin struct, which can be used with peripherals
                     pub fn degrade(self) -> Pin<MODE> {
                         Pin::new($port_value, $i)
                     }
                 }
 
+                impl<MODE> TryFrom<Pin<MODE>> for $PXi<MODE> {
+                    type Error = Pin<MODE>;
+
+                    fn try_from(pin: Pin<MODE>) -> Result<Self, Self::Error> {
+                        if pin.port() == $port_value && pin.pin() == $i {
+                            Ok($PXi {
+                                _mode: PhantomData,
+                            })
+                        } else {
+                            Err(pin)
+                        }
+                    }
+                }
+
+                impl<MODE> $PXi<Input<MODE>> {
+                    /// Changes the pull resistor configuration without reconfiguring the rest
+                    /// of the pin.
+                    ///
+                    /// The type state is left unchanged, so it may no longer reflect the pull
+                    /// configuration.
+                    pub fn set_pull(&mut self, pull: Pull) {
+                        unsafe { &(*$PX::ptr()).pin_cnf[$i] }.modify(|_, w| match pull {
+                            Pull::Disabled => w.pull().disabled(),
+                            Pull::Up => w.pull().pullup(),
+                            Pull::Down => w.pull().pulldown(),
+                        });
+                    }
+
+                    /// Returns whether the pin has met the criteria of its SENSE configuration
+                    /// since the latch was last cleared.
+                    #[cfg(not(feature = "51"))]
+                    pub fn is_latched(&self) -> bool {
+                        unsafe { ((*$PX::ptr()).latch.read().bits() & (1 << $i)) != 0 }
+                    }
+
+                    /// Clears the latched DETECT state of the pin.
+                    #[cfg(not(feature = "51"))]
+                    pub fn clear_latched(&mut self) {
+                        unsafe { (*$PX::ptr()).latch.write(|w| w.bits(1 << $i)); }
+                    }
+                }
+
                 impl<MODE> InputPin for $PXi<Input<MODE>> {
                     type Error = Void;
 
@@ -12,9 +12,11 @@ use crate::pac::P0;
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::pac::P1;
 
+use core::ops::Deref;
+
 use {
     crate::gpio::{
-        Floating, Input, Level, OpenDrain, Output, Pin, Port, PullDown, PullUp, PushPull,
+        Floating, Input, Level, OpenDrain, Output, Pin, Port, PullDown, PullUp, PushPull, Trigger,
     },
     crate::pac::gpiote::{_EVENTS_IN, _EVENTS_PORT, _TASKS_OUT},
     crate::pac::{generic::Reg, GPIOTE},
@@ -221,6 +223,18 @@ impl<'a, P: GpioteInputPin> GpioteChannelEvent<'_, P> {
         config_channel_event_pin(self.gpiote, self.channel, self.pin, EventPolarity::None);
         self
     }
+    /// Generates event on the given pin activity.
+    ///
+    /// Level triggers are treated as the edge towards that level.
+    pub fn trigger(&self, trigger: Trigger) -> &Self {
+        let polarity = match trigger {
+            Trigger::Rising | Trigger::High => EventPolarity::LoToHi,
+            Trigger::Falling | Trigger::Low => EventPolarity::HiToLo,
+            Trigger::Toggle => EventPolarity::Toggle,
+        };
+        config_channel_event_pin(self.gpiote, self.channel, self.pin, polarity);
+        self
+    }
 
     /// Enables GPIOTE interrupt for pin.
     pub fn enable_interrupt(&self) -> &Self {
@@ -287,21 +301,39 @@ impl<'a, P: GpioteInputPin> GpiotePortEvent<'_, P> {
     pub fn disabled(&self) {
         config_port_event_pin(self.pin, PortEventSense::Disabled);
     }
+    /// Generates event on the given pin activity.
+    ///
+    /// Edge triggers are treated as the level they end in. `Trigger::Toggle` senses the level
+    /// opposite to the current pin level, so it has to be set again after every event.
+    pub fn trigger(&self, trigger: Trigger) {
+        let sense = match trigger {
+            Trigger::Rising | Trigger::High => PortEventSense::High,
+            Trigger::Falling | Trigger::Low => PortEventSense::Low,
+            Trigger::Toggle => {
+                if pin_block(self.pin).in_.read().bits() & (1 << self.pin.pin()) == 0 {
+                    PortEventSense::High
+                } else {
+                    PortEventSense::Low
+                }
+            }
+        };
+        config_port_event_pin(self.pin, sense);
+    }
 }
 
-fn config_port_event_pin<P: GpioteInputPin>(pin: &P, sense: PortEventSense) {
-    // Set pin sense to specified mode to trigger port events.
+fn pin_block<P: GpioteInputPin>(pin: &P) -> &'static <P0 as Deref>::Target {
     unsafe {
-        &(*{
-            match pin.port() {
-                Port::Port0 => P0::ptr(),
-                #[cfg(any(feature = "52833", feature = "52840"))]
-                Port::Port1 => P1::ptr(),
-            }
-        })
-        .pin_cnf[pin.pin() as usize]
+        &*match pin.port() {
+            Port::Port0 => P0::ptr(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            Port::Port1 => P1::ptr(),
+        }
     }
-    .modify(|_r, w| match sense {
+}
+
+fn config_port_event_pin<P: GpioteInputPin>(pin: &P, sense: PortEventSense) {
+    // Set pin sense to specified mode to trigger port events.
+    pin_block(pin).pin_cnf[pin.pin() as usize].modify(|_r, w| match sense {
         PortEventSense::Disabled => w.sense().disabled(),
         PortEventSense::High => w.sense().high(),
         PortEventSense::Low => w.sense().low(),
@@ -24,6 +24,8 @@ const MAX_DMA_MAXCNT: u32 = 1 << 14;
 
 impl I2S {
     /// Takes ownership of the raw I2S peripheral, returning a safe wrapper in controller mode.
+    ///
+    /// Transmission is enabled if `sdout_pin` is given, and reception if `sdin_pin` is given.
     pub fn new_controller(
         i2s: I2S_PAC,
         mck_pin: Option<&Pin<Output<PushPull>>>,
@@ -40,6 +42,8 @@ impl I2S {
         i2s.config.align.write(|w| w.align().left());
         i2s.config.format.write(|w| w.format().i2s());
         i2s.config.channels.write(|w| w.channels().stereo());
+        i2s.config.txen.write(|w| w.txen().bit(sdout_pin.is_some()));
+        i2s.config.rxen.write(|w| w.rxen().bit(sdin_pin.is_some()));
 
         if let Some(p) = mck_pin {
             i2s.psel.mck.write(|w| {
@@ -87,6 +91,8 @@ impl I2S {
     }
 
     /// Takes ownership of the raw I2S peripheral, returning a safe wrapper in peripheral mode.
+    ///
+    /// Transmission is enabled if `sdout_pin` is given, and reception if `sdin_pin` is given.
     pub fn new_peripheral(
         i2s: I2S_PAC,
         mck_pin: Option<&Pin<Input<Floating>>>,
@@ -95,13 +101,13 @@ impl I2S {
         sdin_pin: Option<&Pin<Input<Floating>>>,
         sdout_pin: Option<&Pin<Output<PushPull>>>,
     ) -> Self {
-        i2s.config.txen.write(|w| w.txen().enabled());
-        i2s.config.rxen.write(|w| w.rxen().enabled());
         i2s.config.mode.write(|w| w.mode().slave());
         i2s.config.swidth.write(|w| w.swidth()._16bit());
         i2s.config.align.write(|w| w.align().left());
         i2s.config.format.write(|w| w.format().i2s());
         i2s.config.channels.write(|w| w.channels().stereo());
+        i2s.config.txen.write(|w| w.txen().bit(sdout_pin.is_some()));
+        i2s.config.rxen.write(|w| w.rxen().bit(sdin_pin.is_some()));
 
         if let Some(p) = mck_pin {
             i2s.psel.mck.write(|w| {
@@ -213,6 +219,97 @@ impl I2S {
         self
     }
 
+    /// Returns the MCK generator frequency.
+    #[inline(always)]
+    pub fn mck_frequency(&self) -> MckFreq {
+        let mckfreq = self.i2s.config.mckfreq.read().bits();
+        MckFreq::ALL
+            .iter()
+            .copied()
+            .find(|&freq| u32::from(freq) == mckfreq)
+            .unwrap_or(MckFreq::_32MDiv8)
+    }
+
+    /// Returns the MCK / LRCK ratio.
+    #[inline(always)]
+    pub fn ratio(&self) -> Ratio {
+        Ratio::ALL
+            .get(usize::from(self.i2s.config.ratio.read().ratio().bits()))
+            .copied()
+            .unwrap_or(Ratio::_32x)
+    }
+
+    /// Enables or disables the MCK output, for codecs that don't need a master clock. The MCK
+    /// generator still clocks SCK and LRCK in controller mode.
+    #[inline(always)]
+    pub fn set_mck_enabled(&self, enabled: bool) -> &Self {
+        self.i2s.config.mcken.write(|w| w.mcken().bit(enabled));
+        self
+    }
+
+    /// Sets the MCK generator frequency and the MCK / LRCK ratio, and returns the resulting
+    /// sample rate in Hz.
+    ///
+    /// Returns `Error::RatioTooSmall` if the ratio is less than twice the sample width, as
+    /// each LRCK period must fit the SCK periods of both channels. The sample width must be set
+    /// first.
+    pub fn set_clock(&self, freq: MckFreq, ratio: Ratio) -> Result<u32, Error> {
+        if ratio.value() < self.min_ratio() {
+            return Err(Error::RatioTooSmall);
+        }
+        self.set_mck_frequency(freq).set_ratio(ratio);
+        Ok(self.sample_rate())
+    }
+
+    /// Selects the MCK frequency and the MCK / LRCK ratio giving the sample rate closest to
+    /// `rate` in Hz, and returns the actual sample rate.
+    ///
+    /// The ratio has to be at least twice the sample width, so the sample width must be set
+    /// first. For external DACs and codecs that require a specific ratio, use
+    /// `set_sample_rate_with_ratio` instead.
+    pub fn set_sample_rate(&self, rate: u32) -> u32 {
+        let min_ratio = self.min_ratio();
+        let mut best = (MckFreq::_32MDiv8, Ratio::_32x, 0u32);
+        for &ratio in Ratio::ALL.iter().filter(|r| r.value() >= min_ratio) {
+            for &freq in MckFreq::ALL.iter() {
+                let actual = 32_000_000 / freq.divider() / ratio.value();
+                if actual.abs_diff(rate) < best.2.abs_diff(rate) {
+                    best = (freq, ratio, actual);
+                }
+            }
+        }
+        self.set_mck_frequency(best.0).set_ratio(best.1);
+        best.2
+    }
+
+    /// Selects the MCK frequency giving the sample rate closest to `rate` in Hz with the given
+    /// MCK / LRCK ratio, e.g. `Ratio::_256x` for codecs that require MCK at 256 times the
+    /// sample rate, and returns the actual sample rate.
+    ///
+    /// Returns `Error::RatioTooSmall` if the ratio is less than twice the sample width.
+    pub fn set_sample_rate_with_ratio(&self, rate: u32, ratio: Ratio) -> Result<u32, Error> {
+        let freq = MckFreq::ALL
+            .iter()
+            .copied()
+            .min_by_key(|freq| (32_000_000 / freq.divider() / ratio.value()).abs_diff(rate))
+            .unwrap_or(MckFreq::_32MDiv8);
+        self.set_clock(freq, ratio)
+    }
+
+    /// Returns the sample rate in Hz, i.e. the LRCK frequency.
+    pub fn sample_rate(&self) -> u32 {
+        32_000_000 / self.mck_frequency().divider() / self.ratio().value()
+    }
+
+    // The smallest MCK / LRCK ratio for the sample width, with an SCK period per bit.
+    fn min_ratio(&self) -> u32 {
+        match self.sample_width() {
+            SampleWidth::_8bit => 16,
+            SampleWidth::_16bit => 32,
+            SampleWidth::_24bit => 48,
+        }
+    }
+
     /// Sets sample width.
     #[inline(always)]
     pub fn set_sample_width(&self, width: SampleWidth) -> &Self {
@@ -260,9 +357,59 @@ impl I2S {
         }
     }
 
+    /// Returns the sample width.
+    #[inline(always)]
+    pub fn sample_width(&self) -> SampleWidth {
+        match self.i2s.config.swidth.read().bits() {
+            0 => SampleWidth::_8bit,
+            1 => SampleWidth::_16bit,
+            _ => SampleWidth::_24bit,
+        }
+    }
+
+    /// Returns the sample alignment within a frame.
+    #[inline(always)]
+    pub fn align(&self) -> Align {
+        match self.i2s.config.align.read().bits() {
+            0 => Align::Left,
+            _ => Align::Right,
+        }
+    }
+
+    /// Returns the frame format.
+    #[inline(always)]
+    pub fn format(&self) -> Format {
+        match self.i2s.config.format.read().bits() {
+            0 => Format::I2S,
+            _ => Format::Aligned,
+        }
+    }
+
+    /// Sets the sample width, alignment, frame format and channels at once.
+    pub fn set_config(&self, config: I2sConfig) -> &Self {
+        self.set_sample_width(config.sample_width)
+            .set_align(config.align)
+            .set_format(config.format)
+            .set_channels(config.channels)
+    }
+
+    /// Returns the sample width, alignment, frame format and channels.
+    pub fn config(&self) -> I2sConfig {
+        I2sConfig {
+            sample_width: self.sample_width(),
+            align: self.align(),
+            format: self.format(),
+            channels: self.channels(),
+        }
+    }
+
     /// Receives data into the given `buffer` until it's filled.
     /// Buffer address must be 4 byte aligned and located in RAM.
     /// Returns a value that represents the in-progress DMA transfer.
+    ///
+    /// The size of the buffer is shared with transmission, through the `RXTXD.MAXCNT` register.
+    /// If transmission is also enabled, the transmit buffer must have the same size, so `transfer`
+    /// should be used instead.
     #[allow(unused_mut)]
     pub fn rx<W, B>(mut self, mut buffer: B) -> Result<Transfer<B>, Error>
     where
@@ -277,6 +424,9 @@ impl I2S {
         if maxcnt > MAX_DMA_MAXCNT {
             return Err(Error::BufferTooLong);
         }
+        if (ptr as usize) < SRAM_LOWER || (ptr as usize) > SRAM_UPPER {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
         self.i2s
             .rxd
             .ptr
@@ -292,6 +442,10 @@ impl I2S {
     /// into the given `rx_buffer` until it is filled.
     /// The buffers must be of equal size and their addresses must be 4 byte aligned and located in RAM.
     /// Returns a value that represents the in-progress DMA transfer.
+    ///
+    /// Both transmission and reception must be enabled, e.g. by giving both `sdin_pin` and
+    /// `sdout_pin` to the constructor. Each received frame is clocked by the same LRCK period as
+    /// the transmitted one, as used by audio codecs.
     #[allow(unused_mut)]
     pub fn transfer<W, TxB, RxB>(
         mut self,
@@ -315,7 +469,11 @@ impl I2S {
         if maxcnt > MAX_DMA_MAXCNT {
             return Err(Error::BufferTooLong);
         }
-        if (tx_ptr as usize) < SRAM_LOWER || (tx_ptr as usize) > SRAM_UPPER {
+        if (tx_ptr as usize) < SRAM_LOWER
+            || (tx_ptr as usize) > SRAM_UPPER
+            || (rx_ptr as usize) < SRAM_LOWER
+            || (rx_ptr as usize) > SRAM_UPPER
+        {
             return Err(Error::DMABufferNotInDataMemory);
         }
 
@@ -485,6 +643,7 @@ pub enum Error {
     BufferTooLong,
     BuffersDontMatch,
     BufferMisaligned,
+    RatioTooSmall,
 }
 
 /// I2S Mode
@@ -513,7 +672,58 @@ pub enum MckFreq {
 }
 impl From<MckFreq> for u32 {
     fn from(variant: MckFreq) -> Self {
-        variant as _
+        // The MCKFREQ register holds a fractional divider, not an enumeration index.
+        match variant {
+            MckFreq::_32MDiv8 => 0x2000_0000,
+            MckFreq::_32MDiv10 => 0x1800_0000,
+            MckFreq::_32MDiv11 => 0x1600_0000,
+            MckFreq::_32MDiv15 => 0x1100_0000,
+            MckFreq::_32MDiv16 => 0x1000_0000,
+            MckFreq::_32MDiv21 => 0x0C00_0000,
+            MckFreq::_32MDiv23 => 0x0B00_0000,
+            MckFreq::_32MDiv30 => 0x0880_0000,
+            MckFreq::_32MDiv31 => 0x0840_0000,
+            MckFreq::_32MDiv32 => 0x0800_0000,
+            MckFreq::_32MDiv42 => 0x0600_0000,
+            MckFreq::_32MDiv63 => 0x0410_0000,
+            MckFreq::_32MDiv125 => 0x020C_0000,
+        }
+    }
+}
+impl MckFreq {
+    const ALL: [MckFreq; 13] = [
+        MckFreq::_32MDiv8,
+        MckFreq::_32MDiv10,
+        MckFreq::_32MDiv11,
+        MckFreq::_32MDiv15,
+        MckFreq::_32MDiv16,
+        MckFreq::_32MDiv21,
+        MckFreq::_32MDiv23,
+        MckFreq::_32MDiv30,
+        MckFreq::_32MDiv31,
+        MckFreq::_32MDiv32,
+        MckFreq::_32MDiv42,
+        MckFreq::_32MDiv63,
+        MckFreq::_32MDiv125,
+    ];
+
+    /// Returns the divider of the 32 MHz clock.
+    pub fn divider(self) -> u32 {
+        match self {
+            MckFreq::_32MDiv8 => 8,
+            MckFreq::_32MDiv10 => 10,
+            MckFreq::_32MDiv11 => 11,
+            MckFreq::_32MDiv15 => 15,
+            MckFreq::_32MDiv16 => 16,
+            MckFreq::_32MDiv21 => 21,
+            MckFreq::_32MDiv23 => 23,
+            MckFreq::_32MDiv30 => 30,
+            MckFreq::_32MDiv31 => 31,
+            MckFreq::_32MDiv32 => 32,
+            MckFreq::_32MDiv42 => 42,
+            MckFreq::_32MDiv63 => 63,
+            MckFreq::_32MDiv125 => 125,
+        }
     }
 }
 
@@ -535,6 +745,34 @@ impl From<Ratio> for u8 {
         variant as _
     }
 }
+impl Ratio {
+    const ALL: [Ratio; 9] = [
+        Ratio::_32x,
+        Ratio::_48x,
+        Ratio::_64x,
+        Ratio::_96x,
+        Ratio::_128x,
+        Ratio::_192x,
+        Ratio::_256x,
+        Ratio::_384x,
+        Ratio::_512x,
+    ];
+
+    /// Returns the number of MCK periods per LRCK period.
+    pub fn value(self) -> u32 {
+        match self {
+            Ratio::_32x => 32,
+            Ratio::_48x => 48,
+            Ratio::_64x => 64,
+            Ratio::_96x => 96,
+            Ratio::_128x => 128,
+            Ratio::_192x => 192,
+            Ratio::_256x => 256,
+            Ratio::_384x => 384,
+            Ratio::_512x => 512,
+        }
+    }
+}
 
 /// Sample width.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
@@ -592,6 +830,48 @@ impl From<Channels> for u8 {
     }
 }
 
+/// Frame format configuration of the I2S peripheral.
+///
+/// See the documentation of the `Default` impl for the values set by the constructors.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct I2sConfig {
+    /// Width of each sample. Samples are stored in RAM as 8-bit, 16-bit, or 32-bit words for
+    /// 24-bit samples.
+    pub sample_width: SampleWidth,
+    /// Alignment of the samples within a half frame, when the half frames are wider than the
+    /// samples.
+    pub align: Align,
+    /// `I2S` delays the data by one clock period after the LRCK edge, as in the original I2S
+    /// format. `Aligned` starts the data on the LRCK edge, as in the left-justified format.
+    pub format: Format,
+    /// Channels that are transferred. In stereo, the left and right samples are interleaved in
+    /// the buffers.
+    pub channels: Channels,
+}
+
+/// Default I2S frame format, as set by the constructors: 16-bit stereo samples in the I2S
+/// format.
+///
+/// ```ignore
+/// I2sConfig {
+///     sample_width: SampleWidth::_16bit,
+///     align: Align::Left,
+///     format: Format::I2S,
+///     channels: Channels::Stereo,
+/// };
+/// ```
+impl Default for I2sConfig {
+    fn default() -> Self {
+        // Note: do not forget to update the docs above if you change values here
+        I2sConfig {
+            sample_width: SampleWidth::_16bit,
+            align: Align::Left,
+            format: Format::I2S,
+            channels: Channels::Stereo,
+        }
+    }
+}
+
 /// I2S events
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum I2SEvent {
@@ -670,6 +950,237 @@ impl<TxB, RxB> Drop for TransferFullDuplex<TxB, RxB> {
     }
 }
 
+/// The two buffers used by `I2sStream` for each direction.
+pub type Buffers = (&'static mut [u32], &'static mut [u32]);
+
+/// The buffers handed out by `I2sStream` once the I2S has moved on to the other buffers.
+pub struct StreamBuffers<'a> {
+    /// The transmit buffer that was sent, to be filled with the next data to send.
+    pub tx: Option<&'a mut [u32]>,
+    /// The receive buffer that has been filled.
+    pub rx: Option<&'a [u32]>,
+}
+
+/// Gap-free streaming into and out of two alternating buffers per direction.
+///
+/// The I2S copies `TXD.PTR` and `RXD.PTR` to internal registers when it starts with a buffer,
+/// which generates the `TxPtrUpdated` and `RxPtrUpdated` events. The pointers to the other
+/// buffers are written right away, so the I2S continues with them without a gap, while the CPU
+/// processes the buffers it is done with.
+///
+/// Each word of the buffers holds one frame, e.g. the left and right 16-bit samples in stereo.
+///
+/// ```ignore
+/// static mut TX_A: [u32; 256] = [0; 256];
+/// static mut TX_B: [u32; 256] = [0; 256];
+///
+/// let mut stream = I2sStream::tx(i2s, unsafe { (&mut TX_A, &mut TX_B) })?;
+/// stream.start();
+///
+/// loop {
+///     let buffers = stream.next_buffer().await;
+///     synthesize(buffers.tx.unwrap());
+/// }
+/// ```
+///
+/// Each buffer has to be processed before the I2S is done with the other one, i.e. within one
+/// buffer period.
+pub struct I2sStream {
+    i2s: I2S,
+    tx: Option<[&'static mut [u32]; 2]>,
+    rx: Option<[&'static mut [u32]; 2]>,
+    current: usize,
+}
+
+impl I2sStream {
+    /// Sets up transmission of `buffers`, which must have the same length, and be filled
+    /// with the first data to send. Streaming starts with `start`.
+    pub fn tx(i2s: I2S, buffers: Buffers) -> Result<Self, Error> {
+        Self::new(i2s, Some(buffers), None)
+    }
+
+    /// Sets up reception into `buffers`, which must have the same length. Streaming starts
+    /// with `start`.
+    pub fn rx(i2s: I2S, buffers: Buffers) -> Result<Self, Error> {
+        Self::new(i2s, None, Some(buffers))
+    }
+
+    /// Sets up simultaneous transmission and reception. All the buffers must have the same
+    /// length, because it is set by the shared `RXTXD.MAXCNT` register.
+    pub fn duplex(i2s: I2S, tx_buffers: Buffers, rx_buffers: Buffers) -> Result<Self, Error> {
+        Self::new(i2s, Some(tx_buffers), Some(rx_buffers))
+    }
+
+    fn new(i2s: I2S, tx: Option<Buffers>, rx: Option<Buffers>) -> Result<Self, Error> {
+        let len = tx.as_ref().or(rx.as_ref()).map_or(0, |b| b.0.len());
+        for (a, b) in tx.iter().chain(rx.iter()) {
+            if a.len() != len || b.len() != len {
+                return Err(Error::BuffersDontMatch);
+            }
+        }
+        if len as u32 > MAX_DMA_MAXCNT {
+            return Err(Error::BufferTooLong);
+        }
+
+        i2s.set_tx_enabled(tx.is_some());
+        i2s.set_rx_enabled(rx.is_some());
+        i2s.i2s
+            .rxtxd
+            .maxcnt
+            .write(|w| unsafe { w.bits(len as u32) });
+
+        Ok(Self {
+            i2s,
+            tx: tx.map(|(a, b)| [a, b]),
+            rx: rx.map(|(a, b)| [a, b]),
+            current: 0,
+        })
+    }
+
+    /// Writes the pointers to the buffers with the given index.
+    fn set_pointers(&mut self, index: usize) {
+        if let Some(tx) = &self.tx {
+            let ptr = tx[index].as_ptr() as u32;
+            self.i2s.i2s.txd.ptr.write(|w| unsafe { w.ptr().bits(ptr) });
+        }
+        if let Some(rx) = &mut self.rx {
+            let ptr = rx[index].as_mut_ptr() as u32;
+            self.i2s.i2s.rxd.ptr.write(|w| unsafe { w.ptr().bits(ptr) });
+        }
+    }
+
+    /// The event generated when the I2S starts with a buffer.
+    fn event(&self) -> I2SEvent {
+        if self.tx.is_some() {
+            I2SEvent::TxPtrUpdated
+        } else {
+            I2SEvent::RxPtrUpdated
+        }
+    }
+
+    /// Starts streaming with the first buffers.
+    pub fn start(&mut self) {
+        self.current = 0;
+        self.set_pointers(0);
+        self.i2s.reset_event(I2SEvent::TxPtrUpdated);
+        self.i2s.reset_event(I2SEvent::RxPtrUpdated);
+        self.i2s.reset_event(I2SEvent::Stopped);
+
+        // Conservative compiler fence to prevent starting the transfer before the pointers
+        // have been set.
+        compiler_fence(Ordering::SeqCst);
+
+        self.i2s.start();
+        let event = self.event();
+        while !self.i2s.is_event_triggered(event) {}
+        self.i2s.reset_event(I2SEvent::TxPtrUpdated);
+        self.i2s.reset_event(I2SEvent::RxPtrUpdated);
+
+        // The pointers have been copied, so the next buffers can be set right away.
+        self.set_pointers(1);
+    }
+
+    /// Stops streaming. The buffers that were being transferred are discarded.
+    pub fn stop(&mut self) {
+        self.i2s.stop();
+        self.i2s.reset_event(I2SEvent::Stopped);
+    }
+
+    /// Returns `true` if the I2S is done with a buffer that hasn't been handed out yet.
+    pub fn is_ready(&self) -> bool {
+        self.i2s.is_event_triggered(self.event())
+    }
+
+    /// Hands out the buffers the I2S is done with, and queues them up after the current ones.
+    fn swap(&mut self) -> StreamBuffers<'_> {
+        self.i2s.reset_event(I2SEvent::TxPtrUpdated);
+        self.i2s.reset_event(I2SEvent::RxPtrUpdated);
+
+        // The I2S reads the pointers when it is done with the current buffers, so the buffers
+        // can be queued up while they are processed.
+        let done = self.current;
+        self.current = 1 - done;
+        self.set_pointers(done);
+
+        // Prevent the buffers from being accessed before the event has been observed.
+        compiler_fence(Ordering::SeqCst);
+
+        StreamBuffers {
+            tx: self.tx.as_mut().map(|tx| &mut *tx[done]),
+            rx: self.rx.as_ref().map(|rx| &*rx[done]),
+        }
+    }
+
+    /// Passes the buffers the I2S is done with to `f`, and returns its result.
+    ///
+    /// Returns `WouldBlock` if the I2S isn't done with a buffer since the last call. This can be
+    /// called from the I2S interrupt handler, after `enable_interrupt`.
+    pub fn process<F, U>(&mut self, f: F) -> nb::Result<U, Error>
+    where
+        F: FnOnce(StreamBuffers<'_>) -> U,
+    {
+        if !self.is_ready() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(f(self.swap()))
+    }
+
+    /// Enables the I2S interrupt when the I2S is done with a buffer.
+    pub fn enable_interrupt(&mut self) {
+        self.i2s.enable_interrupt(self.event());
+    }
+
+    /// Disables the I2S interrupt enabled by `enable_interrupt`.
+    pub fn disable_interrupt(&mut self) {
+        self.i2s.disable_interrupt(self.event());
+    }
+
+    /// Stops streaming, and releases the I2S and the transmit and receive buffers.
+    pub fn free(mut self) -> (I2S, Option<Buffers>, Option<Buffers>) {
+        self.stop();
+        self.disable_interrupt();
+        let tx = self.tx.take().map(|[a, b]| (a, b));
+        let rx = self.rx.take().map(|[a, b]| (a, b));
+        (self.i2s, tx, rx)
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+static WAKER: cortex_m::interrupt::Mutex<core::cell::RefCell<Option<core::task::Waker>>> =
+    cortex_m::interrupt::Mutex::new(core::cell::RefCell::new(None));
+
+#[cfg(feature = "embedded-hal-async")]
+impl I2sStream {
+    /// Handles the I2S interrupt for `next_buffer`. Must be called from the I2S interrupt
+    /// handler, and the I2S interrupt must be unmasked in the NVIC.
+    pub fn on_interrupt() {
+        let i2s = unsafe { &*I2S_PAC::ptr() };
+        i2s.intenclr
+            .write(|w| w.txptrupd().clear().rxptrupd().clear());
+        if let Some(waker) = cortex_m::interrupt::free(|cs| WAKER.borrow(cs).take()) {
+            waker.wake();
+        }
+    }
+
+    /// Waits until the I2S is done with a buffer, without polling, and hands out the buffers
+    /// it is done with.
+    pub async fn next_buffer(&mut self) -> StreamBuffers<'_> {
+        core::future::poll_fn(|cx| {
+            cortex_m::interrupt::free(|cs| {
+                WAKER.borrow(cs).replace(Some(cx.waker().clone()));
+            });
+            if self.is_ready() {
+                core::task::Poll::Ready(())
+            } else {
+                self.enable_interrupt();
+                core::task::Poll::Pending
+            }
+        })
+        .await;
+        self.swap()
+    }
+}
+
 pub trait SupportedWordSize: private::Sealed {}
 impl private::Sealed for i8 {}
 impl SupportedWordSize for i8 {}
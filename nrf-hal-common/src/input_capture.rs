@@ -0,0 +1,112 @@
+//! Pulse width, period and frequency measurement.
+//!
+//! `InputCapture` timestamps the edges of an input signal in hardware: a GPIOTE channel event is
+//! connected through PPI to the `CAPTURE[0]` and `CLEAR` tasks of a free-running TIMER, so that
+//! after each edge, CC[0] holds the time since the previous edge. No CPU involvement is needed to
+//! take the measurement, so it is not affected by interrupt latency.
+//!
+//! What is measured depends on the GPIOTE channel configuration:
+//!
+//! - With `lo_to_hi()` or `hi_to_lo()`, `interval()` is the period of the signal, and
+//!   `frequency()` its frequency.
+//! - With `toggle()`, `interval()` is the width of the last pulse. After an edge, the pin is in
+//!   the level that started with it, so the pulse that ended had the opposite level.
+//!
+//! ```ignore
+//! let gpiote = Gpiote::new(p.GPIOTE);
+//! let channel = gpiote.channel0();
+//! channel.input_pin(&pin).lo_to_hi();
+//!
+//! let capture = InputCapture::new(p.TIMER1, ppi_channels.ppi0, channel.event());
+//!
+//! // Once at least two rising edges have been captured:
+//! let frequency = capture.frequency();
+//! ```
+
+use crate::pac::generic::Reg;
+use crate::pac::gpiote::_EVENTS_IN;
+use crate::ppi::ConfigurablePpi;
+use crate::timer::Instance;
+
+/// Hardware-timestamped measurement of the interval between edges of an input signal.
+pub struct InputCapture<T, P> {
+    timer: T,
+    ppi: P,
+}
+
+impl<T, P> InputCapture<T, P>
+where
+    T: Instance,
+    P: ConfigurablePpi,
+{
+    /// Frequency of the TIMER used for the measurements.
+    pub const TICKS_PER_SECOND: u32 = 16_000_000;
+
+    /// Starts measuring the interval between the events of a GPIOTE channel, e.g.
+    /// `gpiote.channel0().event()`.
+    ///
+    /// The TIMER runs at 16 MHz with a 32-bit counter, so intervals of up to about 268 seconds
+    /// can be measured. The GPIOTE channel has to be configured by the caller.
+    pub fn new(timer: T, mut ppi: P, event: &Reg<u32, _EVENTS_IN>) -> Self {
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.shorts.reset();
+        regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        regs.mode.write(|w| w.mode().timer());
+        regs.prescaler.write(|w| unsafe { w.prescaler().bits(0) });
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.cc[0].write(|w| unsafe { w.bits(0) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+
+        ppi.set_event_endpoint(event);
+        ppi.set_task_endpoint(&regs.tasks_capture[0]);
+        ppi.set_fork_task_endpoint(&regs.tasks_clear);
+        ppi.enable();
+
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Self { timer, ppi }
+    }
+
+    /// Returns the time between the last two edges, in TIMER ticks.
+    ///
+    /// Returns 0 until the first edge. The value captured on the first edge is the time since
+    /// the measurement started, so it should be discarded.
+    pub fn interval(&self) -> u32 {
+        self.timer.as_timer0().cc[0].read().bits()
+    }
+
+    /// Returns the time between the last two edges, in microseconds.
+    pub fn interval_us(&self) -> u32 {
+        self.interval() / (Self::TICKS_PER_SECOND / 1_000_000)
+    }
+
+    /// Returns the frequency of the signal in Hz, when measuring its period.
+    ///
+    /// Returns `None` until the first edge.
+    pub fn frequency(&self) -> Option<u32> {
+        match self.interval() {
+            0 => None,
+            interval => Some(Self::TICKS_PER_SECOND / interval),
+        }
+    }
+
+    /// Returns the time since the last edge, in TIMER ticks.
+    ///
+    /// This can be used to detect a signal that stopped toggling, since `interval` keeps
+    /// returning the last measurement.
+    pub fn elapsed(&self) -> u32 {
+        let regs = self.timer.as_timer0();
+        regs.tasks_capture[1].write(|w| unsafe { w.bits(1) });
+        regs.cc[1].read().bits()
+    }
+
+    /// Stops the measurement, and returns the TIMER and the PPI channel.
+    pub fn free(self) -> (T, P) {
+        let Self { timer, mut ppi } = self;
+        ppi.disable();
+        ppi.clear_fork_task_endpoint();
+        timer.as_timer0().tasks_stop.write(|w| unsafe { w.bits(1) });
+        (timer, ppi)
+    }
+}
@@ -0,0 +1,210 @@
+//! Infrared remote control transmitter.
+//!
+//! `IrTransmitter` sends NEC and RC-5 frames through an IR LED driven by a PWM channel. Each frame
+//! is encoded as a PWM sequence in `Common` load mode: a mark is a value with a 1/3 duty cycle,
+//! which modulates the carrier, and a space is a value with a 0 duty cycle. Each value is held
+//! for a protocol time unit with the sequence refresh count, so the whole frame is played by
+//! EasyDMA without CPU involvement.
+//!
+//! ```ignore
+//! static mut IR_BUFFER: [u16; ir::BUFFER_LEN] = [0; ir::BUFFER_LEN];
+//!
+//! let led = port0.p0_13.into_push_pull_output(Level::Low).degrade();
+//! let mut ir = IrTransmitter::new(Pwm::new(p.PWM0), &led, unsafe { &mut IR_BUFFER });
+//!
+//! ir.send_nec(0x04, 0x08)?;
+//! // While the button is held, every 108 ms:
+//! ir.send_nec_repeat()?;
+//! ```
+//!
+//! The LED is driven high during the marks, so it must be connected to the pin through an
+//! active-high driver or a current limiting resistor.
+
+use embedded_dma::ReadBuffer;
+
+use crate::gpio::{Output, Pin, PushPull};
+use crate::pwm::{Channel, CounterMode, Error, Instance, LoadMode, Prescaler, Pwm, PwmEvent, Seq};
+use crate::time::Hertz;
+
+/// Length of the buffer holding the encoded frames, in PWM sequence values.
+pub const BUFFER_LEN: usize = 160;
+
+/// Carrier frequency of the NEC protocol.
+const NEC_CARRIER: Hertz = Hertz(38_000);
+
+/// Carrier periods in the 562.5 µs time unit of the NEC protocol.
+const NEC_UNIT_PERIODS: u32 = 21;
+
+/// Carrier frequency of the RC-5 protocol.
+const RC5_CARRIER: Hertz = Hertz(36_000);
+
+/// Carrier periods in the 889 µs half-bit of the RC-5 protocol.
+const RC5_UNIT_PERIODS: u32 = 32;
+
+/// Polarity bit of the sequence values, for an output that is high during the first part of
+/// each PWM period.
+const POLARITY: u16 = 0x8000;
+
+/// A frame encoded as PWM sequence values, with one value per protocol time unit.
+struct Frame {
+    buffer: &'static mut [u16; BUFFER_LEN],
+    len: usize,
+}
+
+// The buffer is `'static`, and is only modified while the frame is not played.
+unsafe impl ReadBuffer for Frame {
+    type Word = u16;
+
+    unsafe fn read_buffer(&self) -> (*const u16, usize) {
+        (self.buffer.as_ptr(), self.len)
+    }
+}
+
+impl Frame {
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push(&mut self, value: u16, units: usize) {
+        for _ in 0..units {
+            self.buffer[self.len] = value;
+            self.len += 1;
+        }
+    }
+}
+
+/// An infrared transmitter for the NEC and RC-5 protocols, using channel 0 of a PWM instance.
+pub struct IrTransmitter<T: Instance> {
+    inner: Option<(Pwm<T>, Frame)>,
+}
+
+impl<T> IrTransmitter<T>
+where
+    T: Instance,
+{
+    /// Uses the PWM for an IR LED connected to `pin`, with `buffer` to encode the frames.
+    pub fn new(
+        pwm: Pwm<T>,
+        pin: &Pin<Output<PushPull>>,
+        buffer: &'static mut [u16; BUFFER_LEN],
+    ) -> Self {
+        pwm.set_output_pin(Channel::C0, pin);
+        pwm.set_counter_mode(CounterMode::Up)
+            .set_prescaler(Prescaler::Div1)
+            .set_load_mode(LoadMode::Common)
+            .set_seq_end_delay(Seq::Seq0, 0)
+            .one_shot();
+        Self {
+            inner: Some((pwm, Frame { buffer, len: 0 })),
+        }
+    }
+
+    /// Sends an NEC frame with an 8-bit address and command, each followed by its inverse.
+    ///
+    /// Blocks for the 68 ms of the frame.
+    pub fn send_nec(&mut self, address: u8, command: u8) -> Result<(), Error> {
+        self.send_nec_extended(u16::from(address) | u16::from(!address) << 8, command)
+    }
+
+    /// Sends an NEC frame with a 16-bit address, as used by the extended NEC protocol.
+    ///
+    /// Blocks for the duration of the frame, up to 78 ms.
+    pub fn send_nec_extended(&mut self, address: u16, command: u8) -> Result<(), Error> {
+        let data = u32::from(address) | u32::from(command) << 16 | u32::from(!command) << 24;
+        self.send(NEC_CARRIER, NEC_UNIT_PERIODS, |frame, mark, space| {
+            frame.push(mark, 16);
+            frame.push(space, 8);
+            // The bits are sent LSB first, and are encoded in the length of the space.
+            for bit in 0..32 {
+                frame.push(mark, 1);
+                frame.push(space, if data & (1 << bit) != 0 { 3 } else { 1 });
+            }
+            frame.push(mark, 1);
+            frame.push(space, 1);
+        })
+    }
+
+    /// Sends an NEC repeat code, which signals that the button of the last frame is still
+    /// held. It is sent 108 ms after the start of the frame, and then every 108 ms.
+    pub fn send_nec_repeat(&mut self) -> Result<(), Error> {
+        self.send(NEC_CARRIER, NEC_UNIT_PERIODS, |frame, mark, space| {
+            frame.push(mark, 16);
+            frame.push(space, 4);
+            frame.push(mark, 1);
+            frame.push(space, 1);
+        })
+    }
+
+    /// Sends an RC-5 frame with a 5-bit address and a 7-bit command.
+    ///
+    /// Commands above 63 use the second start bit as the inverted seventh command bit, as in
+    /// the extended RC-5 protocol. `toggle` must be inverted on each new button press, and kept
+    /// for the frames repeated while the button is held, every 114 ms.
+    ///
+    /// Blocks for the 25 ms of the frame.
+    pub fn send_rc5(&mut self, address: u8, command: u8, toggle: bool) -> Result<(), Error> {
+        let data = 1 << 13
+            | u16::from(command & 0x40 == 0) << 12
+            | u16::from(toggle) << 11
+            | u16::from(address & 0x1F) << 6
+            | u16::from(command & 0x3F);
+        self.send(RC5_CARRIER, RC5_UNIT_PERIODS, |frame, mark, space| {
+            // The bits are sent MSB first, with Manchester encoding: a one is a space followed
+            // by a mark, and a zero a mark followed by a space.
+            for bit in (0..14).rev() {
+                if data & (1 << bit) != 0 {
+                    frame.push(space, 1);
+                    frame.push(mark, 1);
+                } else {
+                    frame.push(mark, 1);
+                    frame.push(space, 1);
+                }
+            }
+            frame.push(space, 1);
+        })
+    }
+
+    /// Encodes a frame with `encode`, which is given the sequence values of a mark and a space,
+    /// and plays it with a carrier at `carrier`, holding each value for `unit_periods` periods.
+    fn send<F>(&mut self, carrier: Hertz, unit_periods: u32, encode: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Frame, u16, u16),
+    {
+        let (pwm, mut frame) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+
+        pwm.set_period(carrier);
+        pwm.set_seq_refresh(Seq::Seq0, unit_periods - 1);
+        let mark = (pwm.max_duty() / 3) | POLARITY;
+        frame.clear();
+        encode(&mut frame, mark, POLARITY);
+
+        match pwm.load(Some(frame), None::<Frame>, true) {
+            Ok(seq) => {
+                // The last value of the frame is a space, which is held until the PWM stops.
+                while !seq.is_event_triggered(PwmEvent::SeqEnd(Seq::Seq0)) {}
+                seq.stop();
+                let (frame, _, pwm) = seq.split();
+                let frame = frame.unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+                self.inner = Some((pwm, frame));
+                Ok(())
+            }
+            Err((error, pwm, frame, _)) => {
+                let frame = frame.unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+                self.inner = Some((pwm, frame));
+                Err(error)
+            }
+        }
+    }
+
+    /// Releases the PWM and the buffer.
+    pub fn free(mut self) -> (Pwm<T>, &'static mut [u16; BUFFER_LEN]) {
+        let (pwm, frame) = self
+            .inner
+            .take()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() });
+        (pwm, frame.buffer)
+    }
+}
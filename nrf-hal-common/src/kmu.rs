@@ -0,0 +1,252 @@
+//! HAL interface to the Key Management Unit (KMU).
+//!
+//! The KMU stores 128-bit keys in the key slots of the secure UICR. Each key slot has a set of
+//! permissions, which are written once when the key is provisioned: a key can be made unreadable
+//! by the CPU, and only usable by pushing it over a private bus to a destination address, e.g. the
+//! device root key (K_DR) register of the CryptoCell.
+//!
+//! ```ignore
+//! let mut kmu = Kmu::new(p.KMU_S, p.UICR_S);
+//! let perms = KeyPermissions { write: false, read: false, push: true };
+//! kmu.provision(&mut p.NVMC_S, 0, &key, KeyDestination::CryptoCellKdr, perms)?;
+//!
+//! // At each boot, before using the CryptoCell:
+//! kmu.push(0)?;
+//! ```
+//!
+//! The key slots can only be erased with an ERASEALL operation, so a key slot can only be
+//! provisioned once.
+
+use crate::pac::{CC_HOST_RGF_S, KMU_S as KMU, NVMC_S as NVMC, UICR_S as UICR};
+
+/// Number of key slots.
+pub const KEY_SLOTS: usize = 128;
+
+/// Value of the permissions of a key slot that has not been provisioned.
+const PERM_ERASED: u32 = 0xFFFF_FFFF;
+
+/// KMU error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The key slot index is not below `KEY_SLOTS`.
+    InvalidSlot,
+    /// The key slot has already been provisioned.
+    SlotInUse,
+    /// The key slot has not been provisioned.
+    SlotEmpty,
+    /// The key slot does not allow the key to be read.
+    NotReadable,
+    /// The key slot does not allow the key to be pushed.
+    NotPushable,
+    /// The key slot has been revoked.
+    Revoked,
+    /// The KMU reported an error while pushing the key.
+    PushFailed,
+}
+
+/// Permissions of a key slot.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyPermissions {
+    /// The key value can be written. It is cleared after provisioning to lock the key.
+    pub write: bool,
+    /// The key value can be read by the CPU.
+    pub read: bool,
+    /// The key value can be pushed to its destination.
+    pub push: bool,
+}
+
+/// Destination a key is pushed to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyDestination {
+    /// The device root key (K_DR) registers of the CryptoCell.
+    CryptoCellKdr,
+    /// The given secure peripheral address, which receives the key as 4 consecutive words.
+    Address(u32),
+}
+
+impl KeyDestination {
+    fn address(self) -> u32 {
+        match self {
+            // NOTE(unsafe) Only the address of the register is taken
+            KeyDestination::CryptoCellKdr => unsafe {
+                &(*CC_HOST_RGF_S::ptr()).host_iot_kdr0 as *const _ as u32
+            },
+            KeyDestination::Address(address) => address,
+        }
+    }
+}
+
+/// Interface to the KMU and the key slots.
+pub struct Kmu {
+    kmu: KMU,
+    uicr: UICR,
+}
+
+impl Kmu {
+    /// Takes ownership of the KMU and the UICR holding the key slots.
+    pub fn new(kmu: KMU, uicr: UICR) -> Self {
+        kmu.intenclr.write(|w| {
+            w.keyslot_pushed()
+                .clear()
+                .keyslot_revoked()
+                .clear()
+                .keyslot_error()
+                .clear()
+        });
+        kmu.selectkeyslot.reset();
+        Self { kmu, uicr }
+    }
+
+    /// Writes `key` to the key slot `slot`, with the given destination and permissions.
+    ///
+    /// The permissions are written last, so the key slot is only considered in use once fully
+    /// written. Fails with `SlotInUse` if the key slot has already been provisioned.
+    pub fn provision(
+        &mut self,
+        nvmc: &mut NVMC,
+        slot: usize,
+        key: &[u32; 4],
+        destination: KeyDestination,
+        permissions: KeyPermissions,
+    ) -> Result<(), Error> {
+        self.with_slot(slot, |uicr| {
+            if uicr.keyslot.config[slot].perm.read().bits() != PERM_ERASED {
+                return Err(Error::SlotInUse);
+            }
+
+            nvmc.config.write(|w| w.wen().wen());
+            for (register, word) in uicr.keyslot.key[slot].value.iter().zip(key) {
+                register.write(|w| unsafe { w.value().bits(*word) });
+                while nvmc.ready.read().ready().is_busy() {}
+            }
+            uicr.keyslot.config[slot]
+                .dest
+                .write(|w| unsafe { w.dest().bits(destination.address()) });
+            while nvmc.ready.read().ready().is_busy() {}
+            uicr.keyslot.config[slot].perm.write(|w| {
+                w.write()
+                    .bit(permissions.write)
+                    .read()
+                    .bit(permissions.read)
+                    .push()
+                    .bit(permissions.push)
+                    .state()
+                    .active()
+            });
+            while nvmc.ready.read().ready().is_busy() {}
+            nvmc.config.reset();
+            Ok(())
+        })
+    }
+
+    /// Pushes the key of `slot` to its destination, and blocks until the push is done.
+    pub fn push(&mut self, slot: usize) -> Result<(), Error> {
+        let kmu = &self.kmu;
+        self.with_slot(slot, |uicr| {
+            let perm = uicr.keyslot.config[slot].perm.read();
+            if perm.bits() == PERM_ERASED {
+                return Err(Error::SlotEmpty);
+            }
+            if perm.state().is_revoked() {
+                return Err(Error::Revoked);
+            }
+            if perm.push().is_disabled() {
+                return Err(Error::NotPushable);
+            }
+
+            kmu.events_keyslot_pushed.reset();
+            kmu.events_keyslot_revoked.reset();
+            kmu.events_keyslot_error.reset();
+            kmu.tasks_push_keyslot.write(|w| unsafe { w.bits(1) });
+            loop {
+                if kmu.events_keyslot_pushed.read().bits() != 0 {
+                    return Ok(());
+                }
+                if kmu.events_keyslot_revoked.read().bits() != 0 {
+                    return Err(Error::Revoked);
+                }
+                if kmu.events_keyslot_error.read().bits() != 0 {
+                    return Err(Error::PushFailed);
+                }
+            }
+        })
+    }
+
+    /// Reads the key of `slot`, if its permissions allow it.
+    pub fn read_key(&mut self, slot: usize) -> Result<[u32; 4], Error> {
+        self.with_slot(slot, |uicr| {
+            let perm = uicr.keyslot.config[slot].perm.read();
+            if perm.bits() == PERM_ERASED {
+                return Err(Error::SlotEmpty);
+            }
+            if perm.state().is_revoked() {
+                return Err(Error::Revoked);
+            }
+            if perm.read().is_disabled() {
+                return Err(Error::NotReadable);
+            }
+
+            let mut key = [0; 4];
+            for (word, register) in key.iter_mut().zip(uicr.keyslot.key[slot].value.iter()) {
+                *word = register.read().value().bits();
+            }
+            Ok(key)
+        })
+    }
+
+    /// Returns the permissions of `slot`, or `None` if it has not been provisioned.
+    pub fn permissions(&mut self, slot: usize) -> Result<Option<KeyPermissions>, Error> {
+        self.with_slot(slot, |uicr| {
+            let perm = uicr.keyslot.config[slot].perm.read();
+            if perm.bits() == PERM_ERASED {
+                return Ok(None);
+            }
+            Ok(Some(KeyPermissions {
+                write: perm.write().is_enabled(),
+                read: perm.read().is_enabled(),
+                push: perm.push().is_enabled(),
+            }))
+        })
+    }
+
+    /// Permanently revokes the key of `slot`, which can then no longer be read or pushed.
+    pub fn revoke(&mut self, nvmc: &mut NVMC, slot: usize) -> Result<(), Error> {
+        self.with_slot(slot, |uicr| {
+            if uicr.keyslot.config[slot].perm.read().bits() == PERM_ERASED {
+                return Err(Error::SlotEmpty);
+            }
+
+            // Only the state bit is programmed, as flash bits can't be set back to 1.
+            nvmc.config.write(|w| w.wen().wen());
+            uicr.keyslot.config[slot]
+                .perm
+                .write(|w| w.state().revoked());
+            while nvmc.ready.read().ready().is_busy() {}
+            nvmc.config.reset();
+            Ok(())
+        })
+    }
+
+    /// Selects `slot`, which gives access to its registers in the UICR, runs `f`, and deselects
+    /// it.
+    fn with_slot<R, F>(&self, slot: usize, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&UICR) -> Result<R, Error>,
+    {
+        if slot >= KEY_SLOTS {
+            return Err(Error::InvalidSlot);
+        }
+        // NOTE(unsafe) Key slots are selected by their index plus one, which fits in the field
+        self.kmu
+            .selectkeyslot
+            .write(|w| unsafe { w.id().bits(slot as u8 + 1) });
+        let result = f(&self.uicr);
+        self.kmu.selectkeyslot.reset();
+        result
+    }
+
+    /// Releases the KMU and the UICR.
+    pub fn free(self) -> (KMU, UICR) {
+        (self.kmu, self.uicr)
+    }
+}
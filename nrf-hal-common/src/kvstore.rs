@@ -0,0 +1,732 @@
+//! Wear-leveled key-value store in flash.
+//!
+//! `KvStore` keeps small values, e.g. configuration or calibration data, identified by a 16-bit
+//! key, in a region of flash made of a fixed number of pages, usually an `Nvmc` region:
+//!
+//! ```ignore
+//! let nvmc = Nvmc::new(p.NVMC, unsafe { &mut __storage });
+//! let mut store = KvStore::new(nvmc)?;
+//!
+//! store.set(KEY_VOLUME, &[7])?;
+//! let mut volume = [0];
+//! if let Some(len) = store.get(KEY_VOLUME, &mut volume)? {
+//!     // Use `volume[..len]`.
+//! }
+//! ```
+//!
+//! Values are appended to the current page, and the pages are used in turn, so that each page is
+//! only erased once all the pages have been filled. When only one erased page is left, the latest
+//! values are moved to it, and the other pages are erased. The latest values must therefore fit in
+//! one page. Each value is stored with a checksum, so a value that was being written when power
+//! was lost is ignored, and the previous value of its key is used instead. Values that were being
+//! moved when power was lost are moved again by `KvStore::new`.
+
+use embedded_storage::nor_flash::NorFlash;
+
+/// Marks a page in use, in the first word of the page.
+const PAGE_MAGIC: u32 = 0x3153_564B;
+
+/// Value of an erased word.
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// Size of the header of a page: magic and sequence number.
+const PAGE_HEADER_SIZE: usize = 8;
+
+/// Size of the header and checksum of a record.
+const RECORD_OVERHEAD: usize = 8;
+
+/// Length of a record marking its key as removed.
+const TOMBSTONE: u16 = 0xFFFF;
+
+/// Size of the buffer used to read and copy records.
+const CHUNK_SIZE: usize = 32;
+
+/// Key-value store error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error<E> {
+    /// The flash returned an error.
+    Flash(E),
+    /// The key 0xFFFF is reserved.
+    InvalidKey,
+    /// The value doesn't fit in a page.
+    ValueTooLarge,
+    /// The buffer passed to `get` is smaller than the value.
+    BufferTooSmall,
+    /// The latest values don't fit in a page.
+    Full,
+    /// The flash region has less than 2 pages, or an unsupported write size.
+    InvalidFlash,
+}
+
+/// A record in flash.
+#[derive(Copy, Clone)]
+struct Record {
+    /// Offset of the record in the flash region.
+    offset: usize,
+    key: u16,
+    len: u16,
+    valid: bool,
+}
+
+impl Record {
+    fn is_tombstone(&self) -> bool {
+        self.len == TOMBSTONE
+    }
+
+    fn data_len(&self) -> usize {
+        if self.is_tombstone() {
+            0
+        } else {
+            usize::from(self.len)
+        }
+    }
+
+    fn size(&self) -> usize {
+        record_size(self.data_len())
+    }
+}
+
+/// Size of a record holding `len` bytes of data.
+fn record_size(len: usize) -> usize {
+    RECORD_OVERHEAD + ((len + 3) & !3)
+}
+
+/// FNV-1a hash of a record, which never has the value of an erased word.
+struct Checksum(u32);
+
+impl Checksum {
+    fn new() -> Self {
+        Checksum(0x811C_9DC5)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u32::from(*byte)).wrapping_mul(0x0100_0193);
+        }
+    }
+
+    fn finish(self) -> u32 {
+        if self.0 == ERASED {
+            !ERASED
+        } else {
+            self.0
+        }
+    }
+}
+
+/// A key-value store in a flash region.
+pub struct KvStore<F> {
+    flash: F,
+    pages: usize,
+    // Page being written, with its sequence number, or `None` if the store is empty.
+    active: Option<(usize, u32)>,
+    // Offset of the free space in the active page.
+    write_offset: usize,
+}
+
+impl<F> KvStore<F>
+where
+    F: NorFlash,
+{
+    /// Opens the store in the whole `flash` region, which must have at least 2 pages.
+    ///
+    /// An erased region is an empty store. Pages that don't belong to the store are erased.
+    pub fn new(flash: F) -> Result<Self, Error<F::Error>> {
+        let pages = flash.capacity() / F::ERASE_SIZE;
+        if pages < 2 || !matches!(F::WRITE_SIZE, 1 | 2 | 4) || !matches!(F::READ_SIZE, 1 | 2 | 4) {
+            return Err(Error::InvalidFlash);
+        }
+
+        let mut store = Self {
+            flash,
+            pages,
+            active: None,
+            write_offset: 0,
+        };
+
+        let mut erased = 0;
+        for page in 0..pages {
+            match store.page_sequence(page)? {
+                Some(seq) => match store.active {
+                    Some((_, active_seq)) if active_seq >= seq => {}
+                    _ => store.active = Some((page, seq)),
+                },
+                None => {
+                    // The erase of the page may have been interrupted.
+                    if !store.is_page_erased(page)? {
+                        store.erase_page(page)?;
+                    }
+                    erased += 1;
+                }
+            }
+        }
+
+        // A page is always erased, except during a compaction, from the moment its target page
+        // is opened until the first old page is erased. The old pages still hold all the latest
+        // values, so the compaction is restarted from an erased target page.
+        if let (Some((page, seq)), 0) = (store.active, erased) {
+            store.erase_page(page)?;
+            store.compact(page, seq, 0)?;
+        }
+
+        if let Some((page, _)) = store.active {
+            let mut offset = page * F::ERASE_SIZE + PAGE_HEADER_SIZE;
+            while let Some(record) = store.record_at(page, offset)? {
+                offset += record.size();
+            }
+            store.write_offset = offset;
+        }
+
+        Ok(store)
+    }
+
+    /// Reads the value of `key` into `buffer`, and returns its length, or `None` if the key has no
+    /// value.
+    pub fn get(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Error<F::Error>> {
+        let record = match self.find_latest(key, None)? {
+            Some(record) if !record.is_tombstone() => record,
+            _ => return Ok(None),
+        };
+        let len = record.data_len();
+        if buffer.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+
+        let mut chunk = [0; CHUNK_SIZE];
+        let data = record.offset + 4;
+        for (i, out) in buffer[..len].chunks_mut(CHUNK_SIZE).enumerate() {
+            let words = (out.len() + 3) & !3;
+            self.read(data + i * CHUNK_SIZE, &mut chunk[..words])?;
+            out.copy_from_slice(&chunk[..out.len()]);
+        }
+        Ok(Some(len))
+    }
+
+    /// Sets the value of `key`. Nothing is written if the value is unchanged.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error<F::Error>> {
+        if key == 0xFFFF {
+            return Err(Error::InvalidKey);
+        }
+        if value.len() >= usize::from(TOMBSTONE)
+            || record_size(value.len()) > F::ERASE_SIZE - PAGE_HEADER_SIZE
+        {
+            return Err(Error::ValueTooLarge);
+        }
+        if let Some(record) = self.find_latest(key, None)? {
+            if !record.is_tombstone()
+                && record.data_len() == value.len()
+                && self.data_equals(&record, value)?
+            {
+                return Ok(());
+            }
+        }
+        self.append(key, value.len() as u16, value)
+    }
+
+    /// Removes the value of `key`.
+    pub fn remove(&mut self, key: u16) -> Result<(), Error<F::Error>> {
+        match self.find_latest(key, None)? {
+            Some(record) if !record.is_tombstone() => self.append(key, TOMBSTONE, &[]),
+            _ => Ok(()),
+        }
+    }
+
+    /// Releases the flash region.
+    pub fn free(self) -> F {
+        self.flash
+    }
+
+    /// Appends a record to the active page, moving to the next page if it is full.
+    fn append(&mut self, key: u16, len: u16, data: &[u8]) -> Result<(), Error<F::Error>> {
+        let size = record_size(data.len());
+        let page = match self.active {
+            None => {
+                self.open_page(0, 0)?;
+                0
+            }
+            Some((page, seq)) => {
+                if self.write_offset + size > (page + 1) * F::ERASE_SIZE {
+                    self.next_page(page, seq, size)?
+                } else {
+                    page
+                }
+            }
+        };
+        debug_assert!(self.write_offset + size <= (page + 1) * F::ERASE_SIZE);
+
+        let offset = self.write_offset;
+        let header = u32::from(key) | u32::from(len) << 16;
+        let mut checksum = Checksum::new();
+        checksum.update(&header.to_le_bytes());
+        checksum.update(data);
+
+        self.write(offset, &header.to_le_bytes())?;
+        let whole = data.len() & !3;
+        if whole > 0 {
+            self.write(offset + 4, &data[..whole])?;
+        }
+        if whole < data.len() {
+            let mut tail = [0xFF; 4];
+            tail[..data.len() - whole].copy_from_slice(&data[whole..]);
+            self.write(offset + 4 + whole, &tail)?;
+        }
+        // The checksum is written last, and validates the record.
+        self.write(offset + size - 4, &checksum.finish().to_le_bytes())?;
+
+        self.write_offset = offset + size;
+        Ok(())
+    }
+
+    /// Moves to the page after `page`, and returns it. The latest values are moved to it if it is
+    /// the last erased page.
+    fn next_page(
+        &mut self,
+        page: usize,
+        seq: u32,
+        needed: usize,
+    ) -> Result<usize, Error<F::Error>> {
+        let next = (page + 1) % self.pages;
+        let mut erased = 0;
+        for page in 0..self.pages {
+            if self.page_sequence(page)?.is_none() {
+                erased += 1;
+            }
+        }
+
+        // `new` finishes interrupted compactions, so a page is always erased.
+        debug_assert!(erased >= 1);
+        if erased >= 2 {
+            self.open_page(next, seq.wrapping_add(1))?;
+        } else {
+            self.compact(next, seq.wrapping_add(1), needed)?;
+        }
+        Ok(next)
+    }
+
+    /// Copies the latest values to the erased page `target`, and erases the other pages.
+    ///
+    /// The pages are used in turn, so `target` is the page after the active one, or the active
+    /// page itself when an interrupted compaction is restarted: the pages after it are the
+    /// oldest.
+    fn compact(&mut self, target: usize, seq: u32, needed: usize) -> Result<(), Error<F::Error>> {
+        let mut live = 0;
+        self.for_each_live(target, |_, record| {
+            live += record.size();
+            Ok(())
+        })?;
+        if PAGE_HEADER_SIZE + live + needed > F::ERASE_SIZE {
+            return Err(Error::Full);
+        }
+
+        // Until the old pages are erased, the copies are read as newer identical values.
+        self.open_page(target, seq)?;
+        self.for_each_live(target, |store, record| {
+            let mut chunk = [0; CHUNK_SIZE];
+            let size = record.size();
+            let mut copied = 0;
+            while copied < size {
+                let len = (size - copied).min(CHUNK_SIZE);
+                store.read(record.offset + copied, &mut chunk[..len])?;
+                store.write(store.write_offset + copied, &chunk[..len])?;
+                copied += len;
+            }
+            store.write_offset += size;
+            Ok(())
+        })?;
+
+        // The oldest pages are erased first, so that removed values are not restored if power is
+        // lost before all the pages are erased.
+        for i in 1..self.pages {
+            let page = (target + i) % self.pages;
+            if self.page_sequence(page)?.is_some() {
+                self.erase_page(page)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `f` with the latest record of each key that has a value, excluding the records of
+    /// `exclude`.
+    fn for_each_live<G>(&mut self, exclude: usize, mut f: G) -> Result<(), Error<F::Error>>
+    where
+        G: FnMut(&mut Self, &Record) -> Result<(), Error<F::Error>>,
+    {
+        for page in self.pages_in_order() {
+            if page == exclude || self.page_sequence(page)?.is_none() {
+                continue;
+            }
+            let mut offset = page * F::ERASE_SIZE + PAGE_HEADER_SIZE;
+            while let Some(record) = self.record_at(page, offset)? {
+                offset += record.size();
+                if !record.valid || record.is_tombstone() {
+                    continue;
+                }
+                let latest = self.find_latest(record.key, Some(exclude))?;
+                if latest.map(|latest| latest.offset) == Some(record.offset) {
+                    f(self, &record)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the latest valid record of `key`, excluding the records of `exclude`.
+    fn find_latest(
+        &mut self,
+        key: u16,
+        exclude: Option<usize>,
+    ) -> Result<Option<Record>, Error<F::Error>> {
+        let mut latest = None;
+        for page in self.pages_in_order() {
+            if Some(page) == exclude || self.page_sequence(page)?.is_none() {
+                continue;
+            }
+            let mut offset = page * F::ERASE_SIZE + PAGE_HEADER_SIZE;
+            while let Some(record) = self.record_at(page, offset)? {
+                offset += record.size();
+                if record.valid && record.key == key {
+                    latest = Some(record);
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Returns the pages from the oldest to the active one.
+    fn pages_in_order(&self) -> impl Iterator<Item = usize> {
+        let pages = self.pages;
+        let active = self.active.map_or(pages - 1, |(page, _)| page);
+        (1..=pages).map(move |i| (active + i) % pages)
+    }
+
+    /// Reads the record at `offset` of `page`, or returns `None` at the end of the records.
+    fn record_at(&mut self, page: usize, offset: usize) -> Result<Option<Record>, Error<F::Error>> {
+        let end = (page + 1) * F::ERASE_SIZE;
+        if offset + RECORD_OVERHEAD > end {
+            return Ok(None);
+        }
+        let header = self.read_word(offset)?;
+        if header == ERASED {
+            return Ok(None);
+        }
+        let mut record = Record {
+            offset,
+            key: header as u16,
+            len: (header >> 16) as u16,
+            valid: false,
+        };
+        if offset + record.size() > end {
+            // Corrupted header, the rest of the page is unusable.
+            return Ok(None);
+        }
+
+        let mut checksum = Checksum::new();
+        checksum.update(&header.to_le_bytes());
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut read = 0;
+        while read < record.data_len() {
+            let len = (record.data_len() - read).min(CHUNK_SIZE);
+            let words = (len + 3) & !3;
+            self.read(offset + 4 + read, &mut chunk[..words])?;
+            checksum.update(&chunk[..len]);
+            read += len;
+        }
+        record.valid = self.read_word(offset + record.size() - 4)? == checksum.finish();
+        Ok(Some(record))
+    }
+
+    /// Compares the data of `record` with `value`, which has the same length.
+    fn data_equals(&mut self, record: &Record, value: &[u8]) -> Result<bool, Error<F::Error>> {
+        let mut chunk = [0; CHUNK_SIZE];
+        for (i, expected) in value.chunks(CHUNK_SIZE).enumerate() {
+            let words = (expected.len() + 3) & !3;
+            self.read(record.offset + 4 + i * CHUNK_SIZE, &mut chunk[..words])?;
+            if &chunk[..expected.len()] != expected {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Writes the header of the erased page `page`, and makes it the active page.
+    fn open_page(&mut self, page: usize, seq: u32) -> Result<(), Error<F::Error>> {
+        let offset = page * F::ERASE_SIZE;
+        let mut header = [0; PAGE_HEADER_SIZE];
+        header[..4].copy_from_slice(&PAGE_MAGIC.to_le_bytes());
+        header[4..].copy_from_slice(&seq.to_le_bytes());
+        self.write(offset, &header)?;
+        self.active = Some((page, seq));
+        self.write_offset = offset + PAGE_HEADER_SIZE;
+        Ok(())
+    }
+
+    /// Returns the sequence number of `page`, or `None` if it is not in use.
+    fn page_sequence(&mut self, page: usize) -> Result<Option<u32>, Error<F::Error>> {
+        let offset = page * F::ERASE_SIZE;
+        if self.read_word(offset)? != PAGE_MAGIC {
+            return Ok(None);
+        }
+        Ok(Some(self.read_word(offset + 4)?))
+    }
+
+    fn is_page_erased(&mut self, page: usize) -> Result<bool, Error<F::Error>> {
+        let mut chunk = [0; CHUNK_SIZE];
+        for offset in (0..F::ERASE_SIZE).step_by(CHUNK_SIZE) {
+            let len = (F::ERASE_SIZE - offset).min(CHUNK_SIZE);
+            self.read(page * F::ERASE_SIZE + offset, &mut chunk[..len])?;
+            if chunk[..len].iter().any(|byte| *byte != 0xFF) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), Error<F::Error>> {
+        let from = (page * F::ERASE_SIZE) as u32;
+        self.flash
+            .erase(from, from + F::ERASE_SIZE as u32)
+            .map_err(Error::Flash)
+    }
+
+    fn read_word(&mut self, offset: usize) -> Result<u32, Error<F::Error>> {
+        let mut word = [0; 4];
+        self.read(offset, &mut word)?;
+        Ok(u32::from_le_bytes(word))
+    }
+
+    fn read(&mut self, offset: usize, bytes: &mut [u8]) -> Result<(), Error<F::Error>> {
+        self.flash.read(offset as u32, bytes).map_err(Error::Flash)
+    }
+
+    fn write(&mut self, offset: usize, bytes: &[u8]) -> Result<(), Error<F::Error>> {
+        self.flash.write(offset as u32, bytes).map_err(Error::Flash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    const PAGE_SIZE: usize = 256;
+    const PAGES: usize = 3;
+
+    /// Flash in RAM, which loses power after a given number of writes and erases.
+    #[derive(Clone)]
+    struct RamFlash {
+        memory: [u8; PAGE_SIZE * PAGES],
+        // Number of writes and erases left before power is lost, if limited.
+        budget: Option<usize>,
+        // Number of writes and erases done.
+        operations: usize,
+    }
+
+    impl RamFlash {
+        fn new() -> Self {
+            RamFlash {
+                memory: [0xFF; PAGE_SIZE * PAGES],
+                budget: None,
+                operations: 0,
+            }
+        }
+
+        /// Returns the flash as found after a reset, with unlimited writes and erases.
+        fn reset(&self) -> Self {
+            RamFlash {
+                budget: None,
+                ..self.clone()
+            }
+        }
+
+        fn operation(&mut self) -> Result<(), NorFlashErrorKind> {
+            if let Some(budget) = &mut self.budget {
+                if *budget == 0 {
+                    return Err(NorFlashErrorKind::Other);
+                }
+                *budget -= 1;
+            }
+            self.operations += 1;
+            Ok(())
+        }
+    }
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            assert_eq!(offset % Self::READ_SIZE, 0);
+            assert_eq!(bytes.len() % Self::READ_SIZE, 0);
+            bytes.copy_from_slice(&self.memory[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.memory.len()
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = PAGE_SIZE;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.operation()?;
+            self.memory[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            assert_eq!(offset % Self::WRITE_SIZE, 0);
+            assert_eq!(bytes.len() % Self::WRITE_SIZE, 0);
+            self.operation()?;
+            // Writing only clears bits.
+            for (cell, byte) in self.memory[offset..].iter_mut().zip(bytes) {
+                *cell &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    /// Number of keys updated in turn.
+    const KEYS: u16 = 4;
+    /// Number of keys set once, after the updated ones, which stay in the oldest pages.
+    const STATIC_KEYS: u16 = 2;
+    /// Key removed before the updates.
+    const REMOVED_KEY: u16 = KEYS + STATIC_KEYS;
+
+    type Versions = [Option<u8>; (KEYS + STATIC_KEYS) as usize];
+
+    /// Value of `key` after `version` updates.
+    fn value(key: u16, version: u8) -> ([u8; 32], usize) {
+        let len = 3 + 5 * usize::from(key);
+        let mut value = [0; 32];
+        for (i, byte) in value[..len].iter_mut().enumerate() {
+            *byte = version.wrapping_mul(31).wrapping_add(i as u8);
+        }
+        (value, len)
+    }
+
+    /// Checks the values of all the keys. `versions` holds the version of each key, or `None` if
+    /// it has no value.
+    fn check(store: &mut KvStore<RamFlash>, versions: &Versions) {
+        let mut buffer = [0; 32];
+        for (key, version) in versions.iter().enumerate() {
+            let len = store.get(key as u16, &mut buffer).unwrap();
+            match *version {
+                Some(version) => {
+                    let (expected, expected_len) = value(key as u16, version);
+                    assert_eq!(len, Some(expected_len));
+                    assert_eq!(buffer[..expected_len], expected[..expected_len]);
+                }
+                None => assert_eq!(len, None),
+            }
+        }
+        assert_eq!(store.get(REMOVED_KEY, &mut buffer).unwrap(), None);
+    }
+
+    /// Sets the static keys, and sets then removes `REMOVED_KEY`.
+    fn populate() -> (KvStore<RamFlash>, Versions) {
+        let mut store = KvStore::new(RamFlash::new()).unwrap();
+        let mut versions = [None; (KEYS + STATIC_KEYS) as usize];
+        for key in KEYS..KEYS + STATIC_KEYS {
+            let (value, len) = value(key, 0);
+            store.set(key, &value[..len]).unwrap();
+            versions[usize::from(key)] = Some(0);
+        }
+        store.set(REMOVED_KEY, &[1, 2, 3]).unwrap();
+        store.remove(REMOVED_KEY).unwrap();
+        (store, versions)
+    }
+
+    /// Returns the key and the version set by update `step`.
+    fn next_update(versions: &Versions, step: usize) -> (u16, u8) {
+        let key = (step % usize::from(KEYS)) as u16;
+        let version = versions[usize::from(key)].map_or(0, |version| version.wrapping_add(1));
+        (key, version)
+    }
+
+    /// Runs the updates `steps`, updating the keys in turn, and checks the values after each one.
+    fn update(
+        store: &mut KvStore<RamFlash>,
+        versions: &mut Versions,
+        steps: core::ops::Range<usize>,
+    ) {
+        for step in steps {
+            let (key, version) = next_update(versions, step);
+            let (value, len) = value(key, version);
+            store.set(key, &value[..len]).unwrap();
+            versions[usize::from(key)] = Some(version);
+            check(store, versions);
+        }
+    }
+
+    #[test]
+    fn compaction_keeps_latest_values() {
+        let (mut store, mut versions) = populate();
+        // Enough updates to go around the pages several times.
+        update(&mut store, &mut versions, 0..100);
+
+        let mut store = KvStore::new(store.free().reset()).unwrap();
+        check(&mut store, &versions);
+    }
+
+    /// Loses power at each write and erase of update `step`, which may compact the store, and
+    /// checks the values after a reset, and after the next page switches.
+    fn interrupt_update(flash: &RamFlash, versions: &Versions, step: usize) {
+        let (key, version) = next_update(versions, step);
+        let (value, len) = value(key, version);
+
+        let mut store = KvStore::new(flash.reset()).unwrap();
+        store.set(key, &value[..len]).unwrap();
+        let operations = store.free().operations - flash.operations;
+
+        for budget in 0..operations {
+            let mut interrupted = flash.reset();
+            interrupted.budget = Some(budget);
+            assert!(KvStore::new(&mut interrupted)
+                .unwrap()
+                .set(key, &value[..len])
+                .is_err());
+
+            let mut recovered = interrupted.reset();
+            let mut store = KvStore::new(&mut recovered).unwrap();
+            // The update is either lost or complete.
+            let mut buffer = [0; 32];
+            let mut versions = *versions;
+            if store.get(key, &mut buffer).unwrap() == Some(len) && buffer[..len] == value[..len] {
+                versions[usize::from(key)] = Some(version);
+            }
+            store.free();
+
+            // Also lose power while an interrupted compaction is restarted.
+            for budget in 0..recovered.operations - interrupted.operations {
+                let mut flash = interrupted.reset();
+                flash.budget = Some(budget);
+                assert!(KvStore::new(&mut flash).is_err());
+                let mut store = KvStore::new(flash.reset()).unwrap();
+                check(&mut store, &versions);
+            }
+
+            let mut store = KvStore::new(recovered).unwrap();
+            check(&mut store, &versions);
+            update(&mut store, &mut versions, step + 1..step + 40);
+        }
+    }
+
+    #[test]
+    fn interrupted_updates_keep_values() {
+        let (mut store, mut versions) = populate();
+        for step in 0..60 {
+            let flash = store.free();
+            interrupt_update(&flash, &versions, step);
+            store = KvStore::new(flash).unwrap();
+            update(&mut store, &mut versions, step..step + 1);
+        }
+    }
+}
@@ -27,35 +27,73 @@ pub use nrf52840_pac as pac;
 #[cfg(feature = "9160")]
 pub use nrf9160_pac as pac;
 
+#[cfg(not(feature = "9160"))]
+pub mod aar;
+#[cfg(any(feature = "52833", feature = "52840"))]
+pub mod acl;
 #[cfg(feature = "51")]
 pub mod adc;
+#[cfg(feature = "embedded-hal-async")]
+pub mod async_delay;
+#[cfg(not(feature = "51"))]
+pub mod battery;
+#[cfg(any(feature = "52810", feature = "52811", feature = "52832"))]
+pub mod bprot;
 #[cfg(not(feature = "9160"))]
 pub mod ccm;
 pub mod clocks;
 #[cfg(not(any(feature = "51", feature = "9160")))]
 pub mod comp;
+#[cfg(any(feature = "52840", feature = "9160"))]
+pub mod cryptocell;
+pub mod debounce;
 #[cfg(not(feature = "51"))]
 pub mod delay;
+#[cfg(feature = "9160")]
+pub mod dppi;
 #[cfg(not(feature = "9160"))]
 pub mod ecb;
+#[cfg(not(feature = "51"))]
+pub mod egu;
 pub mod gpio;
 #[cfg(not(feature = "9160"))]
 pub mod gpiote;
 #[cfg(not(any(feature = "51", feature = "52810", feature = "52811")))]
 pub mod i2s;
+#[cfg(not(any(feature = "51", feature = "9160")))]
+pub mod input_capture;
+#[cfg(not(feature = "51"))]
+pub mod ir;
+#[cfg(feature = "9160")]
+pub mod kmu;
+pub mod kvstore;
 #[cfg(not(any(feature = "52811", feature = "52810", feature = "9160")))]
 pub mod lpcomp;
+#[cfg(feature = "rtic-monotonic")]
+pub mod monotonic;
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+pub mod nfct;
+pub mod nvmc;
+#[cfg(not(feature = "51"))]
+pub mod pdm;
+pub mod power;
 #[cfg(not(feature = "9160"))]
 pub mod ppi;
-#[cfg(not(any(feature = "51", feature = "52832", feature = "9160")))]
+#[cfg(not(feature = "51"))]
 pub mod pwm;
 #[cfg(not(any(feature = "51", feature = "9160")))]
 pub mod qdec;
+#[cfg(feature = "52840")]
+pub mod qspi;
+#[cfg(not(any(feature = "51", feature = "9160")))]
+pub mod radio;
 #[cfg(not(feature = "9160"))]
 pub mod rng;
 pub mod rtc;
 #[cfg(not(feature = "51"))]
 pub mod saadc;
+#[cfg(feature = "9160")]
+pub mod secrets;
 #[cfg(feature = "51")]
 pub mod spi;
 #[cfg(not(feature = "51"))]
@@ -78,6 +116,13 @@ pub mod uart;
 pub mod uarte;
 #[cfg(not(feature = "9160"))]
 pub mod uicr;
+pub mod uptime;
+#[cfg(all(feature = "usb", any(feature = "52833", feature = "52840")))]
+pub mod usb_dfu;
+#[cfg(all(feature = "usb", any(feature = "52833", feature = "52840")))]
+pub mod usb_serial;
+#[cfg(all(feature = "usb", any(feature = "52833", feature = "52840")))]
+pub mod usbd;
 #[cfg(not(feature = "9160"))]
 pub mod wdt;
 
@@ -85,9 +130,12 @@ pub mod prelude {
     pub use crate::hal::digital::v2::*;
     pub use crate::hal::prelude::*;
 
+    #[cfg(feature = "9160")]
+    pub use crate::dppi::{Dppi, DppiChannelGroup};
     #[cfg(not(feature = "9160"))]
     pub use crate::ppi::{ConfigurablePpi, Ppi};
     pub use crate::time::U32Ext;
+    pub use fugit::{ExtU32 as _, RateExtU32 as _};
 }
 
 /// Length of Nordic EasyDMA differs for MCUs
@@ -6,13 +6,24 @@
 //! Additionally, the low power comparator can be used as an analog wakeup source from System OFF.
 //! The comparator threshold can be programmed to a range of fractions of the supply voltage
 //! or to use an external analog reference input pin.
+//!
+//! To wake up from System OFF when the input rises above half the supply voltage:
+//!
+//! ```ignore
+//! let lpcomp = LpComp::new(p.LPCOMP, &port0.p0_04.into_analog());
+//! lpcomp.vref(VRef::_4_8Vdd).analog_detect(Transition::Up);
+//! lpcomp.enable();
+//! lpcomp.system_off(&p.POWER);
+//! ```
+//!
+//! The device then starts from reset, and `LpComp::is_wakeup_reason` returns `true`.
 
 use {
-    crate::gpio::{p0::*, Floating, Input},
+    crate::gpio::{p0::*, Analog},
     crate::pac::{
         generic::Reg,
         lpcomp::{extrefsel::EXTREFSEL_A, psel::PSEL_A, _EVENTS_CROSS, _EVENTS_DOWN, _EVENTS_UP},
-        LPCOMP,
+        LPCOMP, POWER,
     },
 };
 
@@ -60,7 +71,6 @@ impl LpComp {
     }
 
     /// `Analog detect` event configuration, used for analog signal power up from OFF.
-    #[cfg(not(feature = "51"))]
     #[inline(always)]
     pub fn analog_detect(&self, event: Transition) -> &Self {
         self.lpcomp.anadetect.write(|w| match event {
@@ -172,6 +182,28 @@ impl LpComp {
         }
     }
 
+    /// Enters System OFF, from which the device wakes up with a reset on the transition set
+    /// with `analog_detect`.
+    ///
+    /// The comparator must be enabled, and keeps running in System OFF.
+    pub fn system_off(&self, power: &POWER) -> ! {
+        // A pending event would wake up the device immediately.
+        self.reset_events();
+        power.systemoff.write(|w| w.systemoff().enter());
+        // System OFF is emulated in debug interface mode, so the CPU keeps running.
+        loop {
+            cortex_m::asm::wfe();
+        }
+    }
+
+    /// Checks if the last reset was a wakeup from System OFF by the LPCOMP, and clears the
+    /// reset reason.
+    pub fn is_wakeup_reason(power: &POWER) -> bool {
+        let detected = power.resetreas.read().lpcomp().is_detected();
+        power.resetreas.write(|w| w.lpcomp().detected());
+        detected
+    }
+
     /// Consumes `self` and returns back the raw `LPCOMP` peripheral.
     #[inline(always)]
     pub fn free(self) -> LPCOMP {
@@ -261,36 +293,82 @@ macro_rules! comp_ref_pins {
 
 #[cfg(not(feature = "51"))]
 comp_ref_pins! {
-    P0_02<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE0,
-    P0_03<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE1,
+    P0_02<Analog> => EXTREFSEL_A::ANALOGREFERENCE0,
+    P0_03<Analog> => EXTREFSEL_A::ANALOGREFERENCE1,
 }
 
 #[cfg(not(feature = "51"))]
 comp_input_pins! {
-    P0_02<Input<Floating>> => PSEL_A::ANALOGINPUT0,
-    P0_03<Input<Floating>> => PSEL_A::ANALOGINPUT1,
-    P0_04<Input<Floating>> => PSEL_A::ANALOGINPUT2,
-    P0_05<Input<Floating>> => PSEL_A::ANALOGINPUT3,
-    P0_28<Input<Floating>> => PSEL_A::ANALOGINPUT4,
-    P0_29<Input<Floating>> => PSEL_A::ANALOGINPUT5,
-    P0_30<Input<Floating>> => PSEL_A::ANALOGINPUT6,
-    P0_31<Input<Floating>> => PSEL_A::ANALOGINPUT7,
+    P0_02<Analog> => PSEL_A::ANALOGINPUT0,
+    P0_03<Analog> => PSEL_A::ANALOGINPUT1,
+    P0_04<Analog> => PSEL_A::ANALOGINPUT2,
+    P0_05<Analog> => PSEL_A::ANALOGINPUT3,
+    P0_28<Analog> => PSEL_A::ANALOGINPUT4,
+    P0_29<Analog> => PSEL_A::ANALOGINPUT5,
+    P0_30<Analog> => PSEL_A::ANALOGINPUT6,
+    P0_31<Analog> => PSEL_A::ANALOGINPUT7,
 }
 
 #[cfg(feature = "51")]
 comp_ref_pins! {
-    P0_00<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE0,
-    P0_06<Input<Floating>> => EXTREFSEL_A::ANALOGREFERENCE1,
+    P0_00<Analog> => EXTREFSEL_A::ANALOGREFERENCE0,
+    P0_06<Analog> => EXTREFSEL_A::ANALOGREFERENCE1,
 }
 
 #[cfg(feature = "51")]
 comp_input_pins! {
-    P0_26<Input<Floating>> => PSEL_A::ANALOGINPUT0,
-    P0_27<Input<Floating>> => PSEL_A::ANALOGINPUT1,
-    P0_01<Input<Floating>> => PSEL_A::ANALOGINPUT2,
-    P0_02<Input<Floating>> => PSEL_A::ANALOGINPUT3,
-    P0_03<Input<Floating>> => PSEL_A::ANALOGINPUT4,
-    P0_04<Input<Floating>> => PSEL_A::ANALOGINPUT5,
-    P0_05<Input<Floating>> => PSEL_A::ANALOGINPUT6,
-    P0_06<Input<Floating>> => PSEL_A::ANALOGINPUT7,
+    P0_26<Analog> => PSEL_A::ANALOGINPUT0,
+    P0_27<Analog> => PSEL_A::ANALOGINPUT1,
+    P0_01<Analog> => PSEL_A::ANALOGINPUT2,
+    P0_02<Analog> => PSEL_A::ANALOGINPUT3,
+    P0_03<Analog> => PSEL_A::ANALOGINPUT4,
+    P0_04<Analog> => PSEL_A::ANALOGINPUT5,
+    P0_05<Analog> => PSEL_A::ANALOGINPUT6,
+    P0_06<Analog> => PSEL_A::ANALOGINPUT7,
+}
+
+#[cfg(feature = "embedded-hal-async")]
+static WAKER: cortex_m::interrupt::Mutex<core::cell::RefCell<Option<core::task::Waker>>> =
+    cortex_m::interrupt::Mutex::new(core::cell::RefCell::new(None));
+
+#[cfg(feature = "embedded-hal-async")]
+impl LpComp {
+    /// Handles the LPCOMP interrupt for `wait_for_cross`. Must be called from the `COMP_LPCOMP`
+    /// interrupt handler (`LPCOMP` on the nRF51), and the interrupt must be unmasked in the NVIC.
+    pub fn on_interrupt() {
+        let lpcomp = unsafe { &*LPCOMP::ptr() };
+        lpcomp
+            .intenclr
+            .write(|w| w.up().set_bit().down().set_bit().cross().set_bit());
+        if let Some(waker) = cortex_m::interrupt::free(|cs| WAKER.borrow(cs).take()) {
+            waker.wake();
+        }
+    }
+
+    /// Waits until the comparator output makes the given transition, without polling.
+    ///
+    /// The comparator must be enabled. Transitions that happened before the call are ignored.
+    pub async fn wait_for_cross(&self, direction: Transition) {
+        self.reset_event(direction);
+        core::future::poll_fn(|cx| {
+            cortex_m::interrupt::free(|cs| {
+                WAKER.borrow(cs).replace(Some(cx.waker().clone()));
+            });
+            if self.is_event_triggered(direction) {
+                core::task::Poll::Ready(())
+            } else {
+                self.enable_interrupt(direction);
+                core::task::Poll::Pending
+            }
+        })
+        .await
+    }
+
+    fn is_event_triggered(&self, event: Transition) -> bool {
+        match event {
+            Transition::Cross => self.is_cross(),
+            Transition::Down => self.is_down(),
+            Transition::Up => self.is_up(),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+//! Monotonic timers for RTIC.
+//!
+//! These implement [`rtic_monotonic::Monotonic`](https://docs.rs/rtic-monotonic), so they can be
+//! used as `#[monotonic]` timers in RTIC 1.x applications:
+//!
+//! ```ignore
+//! #[monotonic(binds = RTC0, default = true)]
+//! type Mono = MonoRtc<RTC0>;
+//! ```
+//!
+//! - `MonoTimer` uses a TIMER running at 1 MHz. Its 32-bit instants wrap around after about 71
+//!   minutes, so it can only schedule tasks up to about 35 minutes in the future. On the nRF51,
+//!   only TIMER0 can be used, as TIMER1 and TIMER2 are 16 bits wide.
+//! - `MonoRtc` uses an RTC running at 32.768 kHz, and extends its 24-bit counter to 64 bits by
+//!   counting overflows. It keeps running in System ON idle without the high frequency clock,
+//!   but requires the low frequency clock to be started.
+
+use crate::rtc;
+use crate::timer;
+use fugit::{TimerDurationU32, TimerDurationU64, TimerInstantU32, TimerInstantU64};
+use rtic_monotonic::Monotonic;
+
+/// A TIMER-based monotonic timer with a 1 MHz tick rate.
+pub struct MonoTimer<T> {
+    timer: T,
+}
+
+impl<T> MonoTimer<T>
+where
+    T: timer::Timer32,
+{
+    /// Configures the TIMER as a 1 MHz, 32-bit free-running counter.
+    ///
+    /// The counter is started by RTIC, through `Monotonic::reset`.
+    pub fn new(timer: T) -> Self {
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.shorts.reset();
+        regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        regs.mode.write(|w| w.mode().timer());
+        regs.prescaler.write(
+            |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
+        );
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+
+        Self { timer }
+    }
+
+    /// Releases the TIMER.
+    pub fn free(self) -> T {
+        self.timer
+    }
+}
+
+impl<T> Monotonic for MonoTimer<T>
+where
+    T: timer::Timer32,
+{
+    type Instant = TimerInstantU32<1_000_000>;
+    type Duration = TimerDurationU32<1_000_000>;
+
+    fn now(&mut self) -> Self::Instant {
+        let regs = self.timer.as_timer0();
+        regs.tasks_capture[1].write(|w| unsafe { w.bits(1) });
+        Self::Instant::from_ticks(regs.cc[1].read().bits())
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        self.timer.as_timer0().cc[0].write(|w| unsafe { w.bits(instant.ticks()) });
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.timer.as_timer0().events_compare[0].reset();
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        let regs = self.timer.as_timer0();
+        regs.intenset.write(|w| w.compare0().set());
+        regs.tasks_clear.write(|w| w.bits(1));
+        regs.tasks_start.write(|w| w.bits(1));
+    }
+}
+
+/// Number of ticks of the 24-bit RTC counter before it overflows.
+const RTC_PERIOD: u64 = 1 << 24;
+
+/// An RTC-based monotonic timer with a 32.768 kHz tick rate and 64-bit instants.
+pub struct MonoRtc<T> {
+    rtc: T,
+    overflows: u64,
+}
+
+impl<T> MonoRtc<T>
+where
+    T: rtc::Instance,
+{
+    /// Configures the RTC to count at 32.768 kHz.
+    ///
+    /// The counter is started by RTIC, through `Monotonic::reset`.
+    pub fn new(rtc: T) -> Self {
+        rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        rtc.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        rtc.prescaler.write(|w| unsafe { w.bits(0) });
+
+        Self { rtc, overflows: 0 }
+    }
+
+    /// Releases the RTC.
+    pub fn free(self) -> T {
+        self.rtc
+    }
+}
+
+impl<T> Monotonic for MonoRtc<T>
+where
+    T: rtc::Instance,
+{
+    // The OVRFLW interrupt has to stay enabled to extend the counter.
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    type Instant = TimerInstantU64<32_768>;
+    type Duration = TimerDurationU64<32_768>;
+
+    fn now(&mut self) -> Self::Instant {
+        // An overflow that hasn't been handled in `on_interrupt` yet is still pending in the
+        // OVRFLW event. Retry if the counter overflows while it is being read.
+        let (pending, counter) = loop {
+            let pending = self.rtc.events_ovrflw.read().bits() != 0;
+            let counter = self.rtc.counter.read().bits();
+            if pending == (self.rtc.events_ovrflw.read().bits() != 0) {
+                break (pending, counter);
+            }
+        };
+        let overflows = self.overflows + u64::from(pending);
+
+        Self::Instant::from_ticks(overflows * RTC_PERIOD + u64::from(counter))
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let now = self.now().ticks();
+        // The RTC may miss a compare value less than 2 ticks ahead of the counter.
+        let target = instant.ticks().max(now + 2);
+        // Instants beyond the next overflow are scheduled again on the following overflow
+        // interrupts, until they are in range.
+        if target - now < RTC_PERIOD {
+            self.rtc.cc[0].write(|w| unsafe { w.bits((target % RTC_PERIOD) as u32) });
+        }
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.rtc.events_compare[0].reset();
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        self.rtc
+            .intenset
+            .write(|w| w.compare0().set().ovrflw().set());
+        self.rtc.tasks_clear.write(|w| w.bits(1));
+        self.rtc.tasks_start.write(|w| w.bits(1));
+        self.overflows = 0;
+    }
+
+    fn on_interrupt(&mut self) {
+        if self.rtc.events_ovrflw.read().bits() != 0 {
+            self.rtc.events_ovrflw.reset();
+            self.overflows += 1;
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! HAL interface to the NFCT peripheral, emulating an NFC Forum Type 2 tag.
+//!
+//! `Type2Tag` listens as an NFC-A tag, and exposes an NDEF message, e.g. a URL or a text, to the
+//! phones and readers that touch it. The NFCT resolves collisions and handles the selection by
+//! itself; the driver answers the READ and HALT commands of the reader from a Type 2 tag memory
+//! holding the NDEF message. The tag is read-only.
+//!
+//! ```ignore
+//! static mut NFC_BUFFER: [u8; nfct::FRAME_BUFFER_LEN] = [0; nfct::FRAME_BUFFER_LEN];
+//!
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let mut tag = Type2Tag::new(p.NFCT, &clocks, uid, unsafe { &mut NFC_BUFFER });
+//! tag.set_uri("https://github.com/nrf-rs/nrf-hal")?;
+//!
+//! loop {
+//!     tag.poll();
+//! }
+//! ```
+//!
+//! The reader expects the answer to a command within a few milliseconds, so `poll` must be
+//! called often, e.g. from the NFCT interrupt handler after `enable_interrupt`.
+//!
+//! `FieldDetector` only senses the NFC field, without the tag stack or the external oscillator.
+//! Its events can trigger an interrupt or a PPI task, and the field can wake the device up from
+//! System OFF:
+//!
+//! ```ignore
+//! if FieldDetector::is_wakeup_reason(&p.POWER) {
+//!     // Woken up by a phone or a reader: start the tag.
+//! }
+//!
+//! let detector = FieldDetector::new(p.NFCT);
+//! detector.system_off(&p.POWER);
+//! ```
+//!
+//! On the nRF52833 and nRF52840, the NFC pins can also be used as GPIOs, and must be configured
+//! as NFC antenna pins in UICR.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::{
+    generic::Reg,
+    nfct::{_EVENTS_FIELDDETECTED, _EVENTS_FIELDLOST},
+    NFCT, POWER,
+};
+
+/// Length of the buffer used to receive the commands and send the answers, in bytes.
+pub const FRAME_BUFFER_LEN: usize = 16;
+
+/// Length of the data area of the tag, holding the NDEF message, in bytes.
+pub const DATA_AREA_LEN: usize = 128;
+
+/// Length of the tag memory: UID, lock bytes and capability container, then the data area.
+const MEMORY_LEN: usize = 16 + DATA_AREA_LEN;
+/// Number of 4-byte pages of the tag memory.
+const PAGES: usize = MEMORY_LEN / 4;
+
+/// Cascade tag, prepended to the first 3 bytes of a 7-byte UID.
+const CASCADE_TAG: u8 = 0x88;
+/// Capability container: NDEF data, version 1.0, data area size in 8-byte units, read-only.
+const CAPABILITY_CONTAINER: [u8; 4] = [0xE1, 0x10, (DATA_AREA_LEN / 8) as u8, 0x0F];
+
+/// NDEF message TLV.
+const TLV_NDEF: u8 = 0x03;
+/// Terminator TLV.
+const TLV_TERMINATOR: u8 = 0xFE;
+
+/// NDEF record header: first and last record of the message, short record, well-known type.
+const RECORD_HEADER: u8 = 0xD1;
+/// Well-known type of URI records.
+const RECORD_TYPE_URI: u8 = b'U';
+/// Well-known type of text records.
+const RECORD_TYPE_TEXT: u8 = b'T';
+
+/// URI prefixes abbreviated by a code in URI records.
+const URI_PREFIXES: [(u8, &str); 6] = [
+    (0x02, "https://www."),
+    (0x01, "http://www."),
+    (0x04, "https://"),
+    (0x03, "http://"),
+    (0x05, "tel:"),
+    (0x06, "mailto:"),
+];
+
+const CMD_READ: u8 = 0x30;
+const CMD_HALT: u8 = 0x50;
+
+/// Negative acknowledgement, sent as a 4-bit frame.
+const NAK: u8 = 0x0;
+
+/// Type 2 tag error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The NDEF message does not fit in the data area of the tag.
+    MessageTooLong,
+    /// The language code of a text record is longer than 63 bytes.
+    LanguageTooLong,
+}
+
+/// Event of the tag, returned by `poll`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TagEvent {
+    /// An NFC field has been detected.
+    FieldDetected,
+    /// The tag has been selected by a reader.
+    Selected,
+    /// The reader has read 4 pages, starting at `page`.
+    Read { page: u8 },
+    /// The reader has put the tag to sleep.
+    Halted,
+    /// The NFC field has been lost.
+    FieldLost,
+}
+
+/// NFC Forum Type 2 tag.
+pub struct Type2Tag {
+    nfct: NFCT,
+    buffer: &'static mut [u8; FRAME_BUFFER_LEN],
+    memory: [u8; MEMORY_LEN],
+}
+
+impl Type2Tag {
+    /// Configures the NFCT as a Type 2 tag with the 7-byte `uid`, whose first byte is the
+    /// manufacturer ID, e.g. 0x5F for Nordic, and an empty NDEF message. Starts sensing the NFC
+    /// field.
+    ///
+    /// `buffer` is used to receive the commands and send the answers. The external
+    /// high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(
+        nfct: NFCT,
+        _clocks: &Clocks<ExternalOscillator, L, LSTAT>,
+        uid: [u8; 7],
+        buffer: &'static mut [u8; FRAME_BUFFER_LEN],
+    ) -> Self {
+        nfct.tasks_disable.write(|w| unsafe { w.bits(1) });
+
+        // Double size UID, and bit frame SDD as required by the Type 2 tag platform.
+        nfct.sensres
+            .write(|w| w.nfcidsize().nfcid1double().bitframesdd().sdd00100());
+        // Not compliant with ISO/IEC 14443-4.
+        nfct.selres.write(|w| unsafe { w.bits(0) });
+        nfct.nfcid1_2nd_last
+            .write(|w| unsafe { w.bits(u32::from_be_bytes([0, uid[0], uid[1], uid[2]])) });
+        nfct.nfcid1_last
+            .write(|w| unsafe { w.bits(u32::from_be_bytes([uid[3], uid[4], uid[5], uid[6]])) });
+
+        // Answer in the first time slot after the processing of the command, as late as the
+        // NFCT allows.
+        nfct.framedelaymode
+            .write(|w| w.framedelaymode().window_grid());
+        nfct.framedelaymax.write(|w| unsafe { w.bits(0xFFFF) });
+
+        nfct.shorts.write(|w| {
+            w.fielddetected_activate()
+                .enabled()
+                .fieldlost_sense()
+                .enabled()
+        });
+
+        let mut memory = [0; MEMORY_LEN];
+        memory[..3].copy_from_slice(&uid[..3]);
+        memory[3] = CASCADE_TAG ^ uid[0] ^ uid[1] ^ uid[2];
+        memory[4..8].copy_from_slice(&uid[3..]);
+        memory[8] = uid[3] ^ uid[4] ^ uid[5] ^ uid[6];
+        memory[12..16].copy_from_slice(&CAPABILITY_CONTAINER);
+
+        let mut tag = Self {
+            nfct,
+            buffer,
+            memory,
+        };
+        tag.set_ndef_message(&[]).ok();
+        tag.nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+        tag
+    }
+
+    /// Sets the NDEF message of the tag, made of one or more encoded NDEF records.
+    pub fn set_ndef_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        self.write_message(message.len(), |data| data.copy_from_slice(message))
+    }
+
+    /// Sets the NDEF message of the tag to a single URI record, e.g. a URL.
+    pub fn set_uri(&mut self, uri: &str) -> Result<(), Error> {
+        let (code, rest) = URI_PREFIXES
+            .iter()
+            .find(|(_, prefix)| uri.starts_with(prefix))
+            .map(|&(code, prefix)| (code, &uri[prefix.len()..]))
+            .unwrap_or((0, uri));
+        self.write_record(RECORD_TYPE_URI, &[&[code], rest.as_bytes()])
+    }
+
+    /// Sets the NDEF message of the tag to a single text record, in UTF-8, with the IANA
+    /// `language` code, e.g. "en".
+    pub fn set_text(&mut self, language: &str, text: &str) -> Result<(), Error> {
+        if language.len() > 63 {
+            return Err(Error::LanguageTooLong);
+        }
+        self.write_record(
+            RECORD_TYPE_TEXT,
+            &[
+                &[language.len() as u8],
+                language.as_bytes(),
+                text.as_bytes(),
+            ],
+        )
+    }
+
+    /// Returns `true` if an NFC field is present.
+    pub fn is_field_present(&self) -> bool {
+        self.nfct
+            .fieldpresent
+            .read()
+            .fieldpresent()
+            .is_field_present()
+    }
+
+    /// Handles the pending events of the NFCT, answering the commands of the reader. Returns the
+    /// event that has been handled, if any.
+    pub fn poll(&mut self) -> Option<TagEvent> {
+        if self.nfct.events_fieldlost.read().bits() != 0 {
+            // The NFCT goes back to sensing the field, so any pending frame is dropped.
+            self.nfct.events_fieldlost.reset();
+            self.nfct.events_selected.reset();
+            self.nfct.events_rxframeend.reset();
+            self.nfct.events_txframeend.reset();
+            return Some(TagEvent::FieldLost);
+        }
+        if self.nfct.events_fielddetected.read().bits() != 0 {
+            self.nfct.events_fielddetected.reset();
+            return Some(TagEvent::FieldDetected);
+        }
+        if self.nfct.events_selected.read().bits() != 0 {
+            self.nfct.events_selected.reset();
+            self.receive();
+            return Some(TagEvent::Selected);
+        }
+        if self.nfct.events_rxframeend.read().bits() != 0 {
+            self.nfct.events_rxframeend.reset();
+            return self.handle_command();
+        }
+        if self.nfct.events_txframeend.read().bits() != 0 {
+            self.nfct.events_txframeend.reset();
+            self.receive();
+        }
+        None
+    }
+
+    /// Enables the NFCT interrupt for the events handled by `poll`.
+    ///
+    /// Note that the interrupt also has to be unmasked in the NVIC, or the handler won't get
+    /// called.
+    pub fn enable_interrupt(&mut self) {
+        self.nfct.intenset.write(|w| {
+            w.fielddetected()
+                .set()
+                .fieldlost()
+                .set()
+                .selected()
+                .set()
+                .rxframeend()
+                .set()
+                .txframeend()
+                .set()
+        });
+    }
+
+    /// Disables the NFCT interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.nfct.intenclr.write(|w| {
+            w.fielddetected()
+                .clear()
+                .fieldlost()
+                .clear()
+                .selected()
+                .clear()
+                .rxframeend()
+                .clear()
+                .txframeend()
+                .clear()
+        });
+    }
+
+    /// Disables the NFCT, and releases it with the buffer.
+    pub fn free(self) -> (NFCT, &'static mut [u8; FRAME_BUFFER_LEN]) {
+        self.nfct.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        self.nfct.shorts.reset();
+        self.nfct.tasks_disable.write(|w| unsafe { w.bits(1) });
+        (self.nfct, self.buffer)
+    }
+
+    /// Writes a single well-known record of type `record_type`, with the concatenation of
+    /// `payload` as payload.
+    fn write_record(&mut self, record_type: u8, payload: &[&[u8]]) -> Result<(), Error> {
+        let payload_len: usize = payload.iter().map(|part| part.len()).sum();
+        if payload_len > usize::from(u8::MAX) {
+            return Err(Error::MessageTooLong);
+        }
+        self.write_message(4 + payload_len, |data| {
+            data[..4].copy_from_slice(&[RECORD_HEADER, 1, payload_len as u8, record_type]);
+            let mut offset = 4;
+            for part in payload {
+                data[offset..offset + part.len()].copy_from_slice(part);
+                offset += part.len();
+            }
+        })
+    }
+
+    /// Writes an NDEF message of `len` bytes, encoded by `encode`, in the data area.
+    fn write_message(&mut self, len: usize, encode: impl FnOnce(&mut [u8])) -> Result<(), Error> {
+        // NDEF TLV with a 1-byte length, followed by the terminator TLV.
+        if len + 3 > DATA_AREA_LEN || len >= 0xFF {
+            return Err(Error::MessageTooLong);
+        }
+        let data = &mut self.memory[16..];
+        data[0] = TLV_NDEF;
+        data[1] = len as u8;
+        encode(&mut data[2..2 + len]);
+        data[2 + len] = TLV_TERMINATOR;
+        data[3 + len..].fill(0);
+        Ok(())
+    }
+
+    fn handle_command(&mut self) -> Option<TagEvent> {
+        let status = self.nfct.framestatus.rx.read().bits();
+        self.nfct
+            .framestatus
+            .rx
+            .write(|w| unsafe { w.bits(status) });
+        compiler_fence(Ordering::SeqCst);
+        if status != 0 {
+            // CRC, parity or overrun error: wait for the reader to send the command again.
+            self.receive();
+            return None;
+        }
+
+        // The received length includes the CRC.
+        let len = usize::from(self.nfct.rxd.amount.read().rxdatabytes().bits()).saturating_sub(2);
+        match self.buffer[..len] {
+            [CMD_READ, page, ..] if usize::from(page) < PAGES => {
+                // 4 pages are read, rolling over to page 0 at the end of the memory.
+                let start = usize::from(page) * 4;
+                for (i, byte) in self.buffer.iter_mut().enumerate() {
+                    *byte = self.memory[(start + i) % MEMORY_LEN];
+                }
+                self.transmit(FRAME_BUFFER_LEN as u16, 0, true);
+                Some(TagEvent::Read { page })
+            }
+            [CMD_HALT, 0, ..] => {
+                self.nfct.tasks_gosleep.write(|w| unsafe { w.bits(1) });
+                Some(TagEvent::Halted)
+            }
+            _ => {
+                self.buffer[0] = NAK;
+                self.transmit(0, 4, false);
+                None
+            }
+        }
+    }
+
+    /// Enables the reception of the next command.
+    fn receive(&mut self) {
+        let ptr = self.buffer.as_mut_ptr() as u32;
+        self.nfct.packetptr.write(|w| unsafe { w.bits(ptr) });
+        self.nfct
+            .maxlen
+            .write(|w| unsafe { w.bits(FRAME_BUFFER_LEN as u32) });
+        self.nfct
+            .rxd
+            .frameconfig
+            .write(|w| w.parity().parity().sof().so_f().crcmoderx().crc16rx());
+        compiler_fence(Ordering::SeqCst);
+        self.nfct.tasks_enablerxdata.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Sends `bytes` bytes and `bits` bits of the buffer, with a CRC if `crc` is `true`.
+    fn transmit(&mut self, bytes: u16, bits: u8, crc: bool) {
+        let ptr = self.buffer.as_ptr() as u32;
+        self.nfct.packetptr.write(|w| unsafe { w.bits(ptr) });
+        self.nfct.txd.frameconfig.write(|w| {
+            let w = w.parity().parity().discardmode().discard_end().sof().so_f();
+            if crc {
+                w.crcmodetx().crc16tx()
+            } else {
+                w.crcmodetx().no_crctx()
+            }
+        });
+        self.nfct
+            .txd
+            .amount
+            .write(|w| unsafe { w.txdatabytes().bits(bytes).txdatabits().bits(bits) });
+        compiler_fence(Ordering::SeqCst);
+        self.nfct.tasks_starttx.write(|w| unsafe { w.bits(1) });
+    }
+}
+
+/// Event of the NFC field, detected by a `FieldDetector`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FieldEvent {
+    /// An NFC field has been detected.
+    Detected,
+    /// The NFC field has been lost.
+    Lost,
+}
+
+/// NFC field detector, sensing the field without emulating a tag.
+///
+/// Sensing the field needs neither the external high-frequency oscillator nor the frame buffer,
+/// and keeps working in System OFF, from which a field wakes the device up.
+pub struct FieldDetector {
+    nfct: NFCT,
+}
+
+impl FieldDetector {
+    /// Starts sensing the NFC field.
+    pub fn new(nfct: NFCT) -> Self {
+        nfct.tasks_disable.write(|w| unsafe { w.bits(1) });
+        nfct.shorts.reset();
+        let detector = Self { nfct };
+        detector.reset_event(FieldEvent::Detected);
+        detector.reset_event(FieldEvent::Lost);
+        detector.sense();
+        detector
+    }
+
+    /// Returns `true` if an NFC field is present.
+    pub fn is_field_present(&self) -> bool {
+        self.nfct
+            .fieldpresent
+            .read()
+            .fieldpresent()
+            .is_field_present()
+    }
+
+    /// Checks if `event` has been triggered.
+    pub fn is_event_triggered(&self, event: FieldEvent) -> bool {
+        match event {
+            FieldEvent::Detected => self.nfct.events_fielddetected.read().bits() != 0,
+            FieldEvent::Lost => self.nfct.events_fieldlost.read().bits() != 0,
+        }
+    }
+
+    /// Resets `event`.
+    pub fn reset_event(&self, event: FieldEvent) {
+        match event {
+            FieldEvent::Detected => self.nfct.events_fielddetected.reset(),
+            FieldEvent::Lost => self.nfct.events_fieldlost.reset(),
+        }
+    }
+
+    /// Starts sensing the NFC field again, e.g. after the NFCT has been activated through PPI.
+    pub fn sense(&self) {
+        self.nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Enables the NFCT interrupt for `event`.
+    ///
+    /// Note that the interrupt also has to be unmasked in the NVIC, or the handler won't get
+    /// called.
+    pub fn enable_interrupt(&mut self, event: FieldEvent) {
+        match event {
+            FieldEvent::Detected => self.nfct.intenset.write(|w| w.fielddetected().set()),
+            FieldEvent::Lost => self.nfct.intenset.write(|w| w.fieldlost().set()),
+        }
+    }
+
+    /// Disables the NFCT interrupt for `event`.
+    pub fn disable_interrupt(&mut self, event: FieldEvent) {
+        match event {
+            FieldEvent::Detected => self.nfct.intenclr.write(|w| w.fielddetected().clear()),
+            FieldEvent::Lost => self.nfct.intenclr.write(|w| w.fieldlost().clear()),
+        }
+    }
+
+    /// Returns a reference to the FIELDDETECTED event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_field_detected(&self) -> &Reg<u32, _EVENTS_FIELDDETECTED> {
+        &self.nfct.events_fielddetected
+    }
+
+    /// Returns a reference to the FIELDLOST event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_field_lost(&self) -> &Reg<u32, _EVENTS_FIELDLOST> {
+        &self.nfct.events_fieldlost
+    }
+
+    /// Enters System OFF, from which the device wakes up with a reset when an NFC field is
+    /// detected.
+    pub fn system_off(&self, power: &POWER) -> ! {
+        // A pending event would wake up the device immediately.
+        self.reset_event(FieldEvent::Detected);
+        self.reset_event(FieldEvent::Lost);
+        self.sense();
+        power.systemoff.write(|w| w.systemoff().enter());
+        // System OFF is emulated in debug interface mode, so the CPU keeps running.
+        loop {
+            cortex_m::asm::wfe();
+        }
+    }
+
+    /// Checks if the last reset was a wakeup from System OFF by an NFC field, and clears the
+    /// reset reason.
+    pub fn is_wakeup_reason(power: &POWER) -> bool {
+        let detected = power.resetreas.read().nfc().is_detected();
+        power.resetreas.write(|w| w.nfc().detected());
+        detected
+    }
+
+    /// Stops sensing the NFC field, and releases the NFCT.
+    pub fn free(self) -> NFCT {
+        self.nfct
+            .intenclr
+            .write(|w| w.fielddetected().clear().fieldlost().clear());
+        self.nfct.tasks_disable.write(|w| unsafe { w.bits(1) });
+        self.nfct
+    }
+}
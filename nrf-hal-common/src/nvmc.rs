@@ -0,0 +1,439 @@
+//! HAL interface to the Non-Volatile Memory Controller (NVMC) peripheral.
+//!
+//! `Nvmc` gives access to a region of the internal flash through the `embedded-storage` NOR flash
+//! traits, e.g. to use it with a filesystem or key-value store built on these traits:
+//!
+//! ```ignore
+//! extern "C" {
+//!     // Defined in the linker script, e.g. at the end of the flash.
+//!     static mut __storage: [u8; 4 * 4096];
+//! }
+//!
+//! let mut nvmc = Nvmc::new(p.NVMC, unsafe { &mut __storage });
+//! nvmc.erase(0, 4096)?;
+//! nvmc.write(0, &[1, 2, 3, 4])?;
+//! ```
+//!
+//! Offsets are relative to the start of the region. The region must be page aligned, and must not
+//! overlap the code of the application. The CPU is halted while the flash is written or erased.
+//!
+//! Erasing a page halts the CPU for up to 85 ms, which may be too long for time-critical
+//! applications. On devices that support it, a page can instead be erased in time slices of a
+//! few milliseconds, e.g. between BLE connection events:
+//!
+//! ```ignore
+//! nvmc.set_partial_erase_duration(5);
+//! // Each call halts the CPU for 5 ms.
+//! while let Err(nb::Error::WouldBlock) = nvmc.erase_page_partial(0) {
+//!     // Let the rest of the application run.
+//! }
+//! ```
+//!
+//! On the nRF9160, `Nvmc<NVMC_NS>` configures the flash through the non-secure CONFIGNS register,
+//! and partial erase is only available to secure firmware, with `Nvmc<NVMC_S>`.
+//!
+//! On the nRF52832, nRF52833 and nRF52840, `Nvmc` also controls the instruction cache, and can
+//! count its hits and misses to measure its effect on a piece of code:
+//!
+//! ```ignore
+//! nvmc.reset_cache_counters();
+//! nvmc.set_cache_profiling(true);
+//! run_benchmark();
+//! nvmc.set_cache_profiling(false);
+//! let (hits, misses) = nvmc.cache_counters();
+//! ```
+
+use core::ops::Deref;
+
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{nvmc, NVMC};
+
+#[cfg(feature = "9160")]
+use crate::pac::{nvmc_ns as nvmc, NVMC_NS, NVMC_S};
+
+/// Size of a flash word, the unit in which the flash is written.
+const WORD_SIZE: usize = 4;
+
+/// Size of a flash page, the unit in which the flash is erased.
+#[cfg(feature = "51")]
+const PAGE_SIZE: usize = 1024;
+#[cfg(not(feature = "51"))]
+const PAGE_SIZE: usize = 4096;
+
+/// Maximum time needed to erase a page, in milliseconds.
+#[cfg(not(any(feature = "51", feature = "52832", feature = "9160")))]
+const PAGE_ERASE_MS: u32 = 85;
+#[cfg(feature = "9160")]
+const PAGE_ERASE_MS: u32 = 88;
+
+/// Interface to a region of the internal flash.
+pub struct Nvmc<T: Instance> {
+    nvmc: T,
+    storage: &'static mut [u8],
+    // Page being erased in time slices, and the time it has been erased for, in milliseconds.
+    #[cfg(not(any(feature = "51", feature = "52832")))]
+    partial_erase: Option<(usize, u32)>,
+}
+
+impl<T> Nvmc<T>
+where
+    T: Instance,
+{
+    /// Takes ownership of the NVMC and of the flash region `storage`.
+    ///
+    /// Panics if `storage` is not made of whole, aligned pages.
+    pub fn new(nvmc: T, storage: &'static mut [u8]) -> Self {
+        assert_eq!(storage.as_ptr() as usize & (PAGE_SIZE - 1), 0);
+        assert_eq!(storage.len() & (PAGE_SIZE - 1), 0);
+        Self {
+            nvmc,
+            storage,
+            #[cfg(not(any(feature = "51", feature = "52832")))]
+            partial_erase: None,
+        }
+    }
+
+    /// Returns `true` if the NVMC is ready for a new write or erase operation.
+    pub fn is_ready(&self) -> bool {
+        self.nvmc.ready.read().ready().is_ready()
+    }
+
+    /// Sets the duration of each partial erase operation, from 1 to 127 ms. The default is 10 ms.
+    #[cfg(not(any(feature = "51", feature = "52832")))]
+    pub fn set_partial_erase_duration(&mut self, duration_ms: u8)
+    where
+        T: PartialErase,
+    {
+        let duration_ms = duration_ms.clamp(1, 127);
+        self.nvmc
+            .erasepagepartialcfg
+            .write(|w| unsafe { w.duration().bits(duration_ms) });
+    }
+
+    /// Erases the page at `offset` for the duration of one partial erase operation.
+    ///
+    /// Returns `WouldBlock` until the page has been erased for long enough to be fully erased.
+    /// Erasing another page, or writing to the flash, restarts the erase of the page.
+    #[cfg(not(any(feature = "51", feature = "52832")))]
+    pub fn erase_page_partial(&mut self, offset: u32) -> nb::Result<(), NvmcError>
+    where
+        T: PartialErase,
+    {
+        let offset = offset as usize;
+        if offset >= self.storage.len() {
+            return Err(nb::Error::Other(NvmcError::OutOfBounds));
+        }
+        if offset & (PAGE_SIZE - 1) != 0 {
+            return Err(nb::Error::Other(NvmcError::Unaligned));
+        }
+
+        let elapsed_ms = match self.partial_erase {
+            Some((page, elapsed_ms)) if page == offset => elapsed_ms,
+            _ => 0,
+        };
+        let duration_ms = u32::from(self.nvmc.erasepagepartialcfg.read().duration().bits());
+
+        self.erase_page_slice(offset);
+
+        let elapsed_ms = elapsed_ms + duration_ms;
+        if elapsed_ms < PAGE_ERASE_MS {
+            self.partial_erase = Some((offset, elapsed_ms));
+            return Err(nb::Error::WouldBlock);
+        }
+        self.partial_erase = None;
+        Ok(())
+    }
+
+    /// Enables the instruction cache, which speeds up code execution from flash.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn enable_cache(&mut self) {
+        self.nvmc.icachecnf.modify(|_, w| w.cacheen().enabled());
+    }
+
+    /// Disables the instruction cache.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn disable_cache(&mut self) {
+        self.nvmc.icachecnf.modify(|_, w| w.cacheen().disabled());
+    }
+
+    /// Returns `true` if the instruction cache is enabled.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn is_cache_enabled(&self) -> bool {
+        self.nvmc.icachecnf.read().cacheen().is_enabled()
+    }
+
+    /// Enables or disables the counting of instruction cache hits and misses.
+    ///
+    /// Profiling increases the power consumption, so it should only be enabled while measuring.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn set_cache_profiling(&mut self, enabled: bool) {
+        self.nvmc
+            .icachecnf
+            .modify(|_, w| w.cacheprofen().bit(enabled));
+    }
+
+    /// Returns the number of instruction cache hits and misses counted while profiling is enabled.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn cache_counters(&self) -> (u32, u32) {
+        (
+            self.nvmc.ihit.read().hits().bits(),
+            self.nvmc.imiss.read().misses().bits(),
+        )
+    }
+
+    /// Resets the instruction cache hit and miss counters to zero.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn reset_cache_counters(&mut self) {
+        self.nvmc.ihit.reset();
+        self.nvmc.imiss.reset();
+    }
+
+    /// Releases the NVMC and the flash region.
+    pub fn free(self) -> (T, &'static mut [u8]) {
+        (self.nvmc, self.storage)
+    }
+
+    fn reset_partial_erase(&mut self) {
+        #[cfg(not(any(feature = "51", feature = "52832")))]
+        {
+            self.partial_erase = None;
+        }
+    }
+
+    fn wait_ready(&self) {
+        while !self.is_ready() {}
+    }
+
+    fn enable_read(&self) {
+        T::set_mode(&self.nvmc, Mode::Read);
+    }
+
+    fn enable_write(&self) {
+        T::set_mode(&self.nvmc, Mode::Write);
+    }
+
+    fn enable_erase(&self) {
+        T::set_mode(&self.nvmc, Mode::Erase);
+    }
+
+    /// Erases the page at `offset`. Erase must be enabled.
+    #[cfg(not(feature = "9160"))]
+    fn erase_page(&mut self, offset: usize) {
+        let address = self.storage[offset..].as_ptr() as u32;
+        self.nvmc.erasepage().write(|w| unsafe { w.bits(address) });
+        self.wait_ready();
+    }
+
+    /// Erases the page at `offset`. Erase must be enabled.
+    #[cfg(feature = "9160")]
+    fn erase_page(&mut self, offset: usize) {
+        // Pages are erased by writing to their first word while erase is enabled.
+        let address = self.storage[offset..].as_mut_ptr() as *mut u32;
+        unsafe { core::ptr::write_volatile(address, 0xFFFF_FFFF) };
+        self.wait_ready();
+    }
+
+    /// Erases the page at `offset` for the duration of one partial erase operation.
+    #[cfg(not(any(feature = "51", feature = "52832", feature = "9160")))]
+    fn erase_page_slice(&mut self, offset: usize) {
+        let address = self.storage[offset..].as_ptr() as u32;
+        self.enable_erase();
+        self.nvmc
+            .erasepagepartial
+            .write(|w| unsafe { w.erasepagepartial().bits(address) });
+        self.wait_ready();
+        self.enable_read();
+    }
+
+    /// Erases the page at `offset` for the duration of one partial erase operation.
+    #[cfg(feature = "9160")]
+    fn erase_page_slice(&mut self, offset: usize)
+    where
+        T: PartialErase,
+    {
+        // Pages are partially erased by writing to their first word while partial erase is
+        // enabled. Only the secure CONFIG register can enable it.
+        let address = self.storage[offset..].as_mut_ptr() as *mut u32;
+        self.nvmc.config.write(|w| w.wen().peen());
+        unsafe { core::ptr::write_volatile(address, 0xFFFF_FFFF) };
+        self.wait_ready();
+        self.enable_read();
+    }
+
+    /// Writes `word` at `offset`, which must be word aligned. Write must be enabled.
+    fn write_word(&mut self, offset: usize, word: u32) {
+        let address = self.storage[offset..].as_mut_ptr() as *mut u32;
+        unsafe { core::ptr::write_volatile(address, word) };
+        self.wait_ready();
+    }
+}
+
+/// NVMC error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NvmcError {
+    /// The offset or length is not aligned to the write or erase size.
+    Unaligned,
+    /// The operation extends past the end of the flash region.
+    OutOfBounds,
+}
+
+impl NorFlashError for NvmcError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            NvmcError::Unaligned => NorFlashErrorKind::NotAligned,
+            NvmcError::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+impl<T> ErrorType for Nvmc<T>
+where
+    T: Instance,
+{
+    type Error = NvmcError;
+}
+
+impl<T> ReadNorFlash for Nvmc<T>
+where
+    T: Instance,
+{
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        if bytes.len() > self.storage.len() || offset > self.storage.len() - bytes.len() {
+            return Err(NvmcError::OutOfBounds);
+        }
+        bytes.copy_from_slice(&self.storage[offset..][..bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl<T> NorFlash for Nvmc<T>
+where
+    T: Instance,
+{
+    const WRITE_SIZE: usize = WORD_SIZE;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let (from, to) = (from as usize, to as usize);
+        if from > to || to > self.storage.len() {
+            return Err(NvmcError::OutOfBounds);
+        }
+        if (from | to) & (PAGE_SIZE - 1) != 0 {
+            return Err(NvmcError::Unaligned);
+        }
+
+        self.reset_partial_erase();
+        self.enable_erase();
+        for offset in (from..to).step_by(PAGE_SIZE) {
+            self.erase_page(offset);
+        }
+        self.enable_read();
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        if bytes.len() > self.storage.len() || offset > self.storage.len() - bytes.len() {
+            return Err(NvmcError::OutOfBounds);
+        }
+        if (offset | bytes.len()) & (WORD_SIZE - 1) != 0 {
+            return Err(NvmcError::Unaligned);
+        }
+
+        self.reset_partial_erase();
+        self.enable_write();
+        for (i, chunk) in bytes.chunks_exact(WORD_SIZE).enumerate() {
+            let word = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            self.write_word(offset + i * WORD_SIZE, word);
+        }
+        self.enable_read();
+        Ok(())
+    }
+}
+
+// Each word can be written twice between erases, which only clears bits.
+impl<T> MultiwriteNorFlash for Nvmc<T> where T: Instance {}
+
+/// Implemented by all NVMC instances.
+pub trait Instance: Deref<Target = nvmc::RegisterBlock> + sealed::Sealed {}
+
+/// Implemented by the NVMC instances that can erase pages partially.
+///
+/// On the nRF9160, partial erase is only available to secure firmware, through `NVMC_S`.
+#[cfg(not(any(feature = "51", feature = "52832")))]
+pub trait PartialErase: Instance {}
+
+mod sealed {
+    use super::nvmc;
+
+    /// Access mode of the flash.
+    #[derive(Clone, Copy)]
+    pub enum Mode {
+        Read,
+        Write,
+        Erase,
+    }
+
+    pub trait Sealed {
+        /// Sets the access mode of the flash, in the configuration register accessible to the
+        /// instance.
+        fn set_mode(nvmc: &nvmc::RegisterBlock, mode: Mode);
+    }
+
+    /// Sets the access mode of the flash in the CONFIG register, which is secure on the nRF9160.
+    pub fn set_config(nvmc: &nvmc::RegisterBlock, mode: Mode) {
+        nvmc.config.write(|w| match mode {
+            Mode::Read => w.wen().ren(),
+            Mode::Write => w.wen().wen(),
+            Mode::Erase => w.wen().een(),
+        });
+    }
+}
+
+use sealed::Mode;
+
+#[cfg(not(feature = "9160"))]
+impl sealed::Sealed for NVMC {
+    fn set_mode(nvmc: &nvmc::RegisterBlock, mode: Mode) {
+        sealed::set_config(nvmc, mode);
+    }
+}
+#[cfg(not(feature = "9160"))]
+impl Instance for NVMC {}
+#[cfg(not(any(feature = "51", feature = "52832", feature = "9160")))]
+impl PartialErase for NVMC {}
+
+#[cfg(feature = "9160")]
+impl sealed::Sealed for NVMC_NS {
+    fn set_mode(nvmc: &nvmc::RegisterBlock, mode: Mode) {
+        nvmc.configns.write(|w| match mode {
+            Mode::Read => w.wen().ren(),
+            Mode::Write => w.wen().wen(),
+            Mode::Erase => w.wen().een(),
+        });
+    }
+}
+#[cfg(feature = "9160")]
+impl Instance for NVMC_NS {}
+
+#[cfg(feature = "9160")]
+impl sealed::Sealed for NVMC_S {
+    fn set_mode(nvmc: &nvmc::RegisterBlock, mode: Mode) {
+        sealed::set_config(nvmc, mode);
+    }
+}
+#[cfg(feature = "9160")]
+impl Instance for NVMC_S {}
+#[cfg(feature = "9160")]
+impl PartialErase for NVMC_S {}
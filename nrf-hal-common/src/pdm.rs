@@ -0,0 +1,460 @@
+//! HAL interface to the PDM peripheral.
+//!
+//! The pulse density modulation (PDM) interface converts the 1-bit output of digital MEMS
+//! microphones to 16-bit PCM samples, which are written to RAM with EasyDMA.
+//!
+//! ```ignore
+//! let clk = port0.p0_26.into_push_pull_output(Level::Low).degrade();
+//! let din = port0.p0_25.into_floating_input().degrade();
+//! let mut pdm = Pdm::new(p.PDM, &clk, &din, PdmConfig::default());
+//!
+//! let mut samples = [0i16; 256];
+//! pdm.read(&mut samples)?;
+//! ```
+//!
+//! `PdmStream` records continuously into two alternating buffers.
+
+#[cfg(feature = "9160")]
+use crate::pac::{pdm_ns as pdm, PDM_NS as PDM};
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{pdm, PDM};
+
+use crate::{
+    gpio::{Floating, Input, Output, Pin, PushPull},
+    pac::generic::{Reg, Variant},
+    slice_in_ram_or,
+};
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use pdm::{_EVENTS_END, _EVENTS_STARTED, _EVENTS_STOPPED, _TASKS_START, _TASKS_STOP};
+
+pub use pdm::{
+    mode::{EDGE_A as Edge, OPERATION_A as Operation},
+    pdmclkctrl::FREQ_A as Frequency,
+};
+
+#[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+pub use pdm::ratio::RATIO_A as Ratio;
+
+/// Maximum number of samples in a buffer.
+const MAX_SAMPLES: usize = 0x7FFF;
+
+/// A PDM microphone interface.
+pub struct Pdm(PDM);
+
+impl Pdm {
+    /// Takes ownership of the raw PDM peripheral, for a microphone connected to `clk` and `din`.
+    pub fn new(
+        pdm: PDM,
+        clk: &Pin<Output<PushPull>>,
+        din: &Pin<Input<Floating>>,
+        config: PdmConfig,
+    ) -> Self {
+        pdm.psel.clk.write(|w| {
+            unsafe { w.pin().bits(clk.pin()) };
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            w.port().bit(clk.port().bit());
+            w.connect().connected()
+        });
+        pdm.psel.din.write(|w| {
+            unsafe { w.pin().bits(din.pin()) };
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            w.port().bit(din.port().bit());
+            w.connect().connected()
+        });
+
+        pdm.pdmclkctrl.write(|w| w.freq().variant(config.frequency));
+        #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+        pdm.ratio.write(|w| w.ratio().variant(config.ratio));
+        pdm.mode.write(|w| {
+            w.operation().variant(config.operation);
+            w.edge().variant(config.edge)
+        });
+        pdm.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        pdm.enable.write(|w| w.enable().enabled());
+
+        let pdm = Self(pdm);
+        pdm.set_gain(config.gain_left, config.gain_right);
+        pdm
+    }
+
+    /// Sets the gain of the left and right channels in 0.5 dB steps, from -40 (-20 dB) to 40
+    /// (+20 dB). The gain can be changed while recording.
+    pub fn set_gain(&self, gain_left: i8, gain_right: i8) {
+        let gain_left = (i16::from(gain_left).clamp(-40, 40) + 40) as u8;
+        let gain_right = (i16::from(gain_right).clamp(-40, 40) + 40) as u8;
+        self.0.gainl.write(|w| unsafe { w.gainl().bits(gain_left) });
+        self.0
+            .gainr
+            .write(|w| unsafe { w.gainr().bits(gain_right) });
+    }
+
+    /// Returns the sample rate in Hz, which is the PDM clock frequency divided by the ratio.
+    pub fn sample_rate(&self) -> u32 {
+        // The PDM clock is derived from a 32 MHz clock.
+        let divider = match self.0.pdmclkctrl.read().freq().variant() {
+            Variant::Val(Frequency::_1000K) => 32,
+            Variant::Val(Frequency::DEFAULT) => 31,
+            Variant::Val(Frequency::_1067K) => 30,
+            #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+            Variant::Val(Frequency::_1231K) => 26,
+            #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+            Variant::Val(Frequency::_1280K) => 25,
+            #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+            Variant::Val(Frequency::_1333K) => 24,
+            Variant::Res(_) => unreachable!(),
+        };
+        #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+        let ratio = match self.0.ratio.read().ratio().variant() {
+            Ratio::RATIO64 => 64,
+            Ratio::RATIO80 => 80,
+        };
+        #[cfg(not(any(feature = "52833", feature = "52840", feature = "9160")))]
+        let ratio = 64;
+
+        32_000_000 / divider / ratio
+    }
+
+    /// Records samples until `buffer` is full.
+    ///
+    /// In stereo operation, the buffer is filled with interleaved left and right samples, so its
+    /// length should be even.
+    ///
+    /// The microphone output takes some time to settle after the clock is started, so the first
+    /// milliseconds of samples may have to be discarded.
+    pub fn read(&mut self, buffer: &mut [i16]) -> Result<(), Error> {
+        self.set_buffer(buffer)?;
+
+        // Conservative compiler fence to prevent starting the transfer before the pointer and
+        // maxcount have been set.
+        compiler_fence(SeqCst);
+
+        self.0.events_end.reset();
+        self.0.events_started.reset();
+        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
+        while self.0.events_end.read().bits() == 0 {}
+        // The PDM continues with the same buffer, so it is stopped before the next sample.
+        self.stop();
+
+        // Prevent the buffer from being read before the transfer has been stopped.
+        compiler_fence(SeqCst);
+
+        Ok(())
+    }
+
+    /// Sets the buffer that the next samples are written to.
+    fn set_buffer(&self, buffer: &mut [i16]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            return Err(Error::BufferEmpty);
+        }
+        if buffer.len() > MAX_SAMPLES {
+            return Err(Error::BufferTooLong);
+        }
+        let bytes =
+            unsafe { core::slice::from_raw_parts(buffer.as_ptr() as *const u8, buffer.len() * 2) };
+        slice_in_ram_or(bytes, Error::DMABufferNotInDataMemory)?;
+
+        self.0
+            .sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(buffer.as_mut_ptr() as u32) });
+        self.0
+            .sample
+            .maxcnt
+            .write(|w| unsafe { w.buffsize().bits(buffer.len() as u16) });
+        Ok(())
+    }
+
+    /// Stops recording, and waits until the transfer has stopped.
+    fn stop(&self) {
+        self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.0.events_stopped.read().bits() == 0 {}
+        self.0.events_stopped.reset();
+        self.0.events_started.reset();
+        self.0.events_end.reset();
+    }
+
+    /// Checks if an event has been triggered.
+    #[inline(always)]
+    pub fn is_event_triggered(&self, event: PdmEvent) -> bool {
+        match event {
+            PdmEvent::Started => self.0.events_started.read().bits() != 0,
+            PdmEvent::Stopped => self.0.events_stopped.read().bits() != 0,
+            PdmEvent::End => self.0.events_end.read().bits() != 0,
+        }
+    }
+
+    /// Marks event as handled.
+    #[inline(always)]
+    pub fn reset_event(&self, event: PdmEvent) {
+        match event {
+            PdmEvent::Started => self.0.events_started.reset(),
+            PdmEvent::Stopped => self.0.events_stopped.reset(),
+            PdmEvent::End => self.0.events_end.reset(),
+        }
+    }
+
+    /// Enables interrupt triggering on the specified event.
+    #[inline(always)]
+    pub fn enable_interrupt(&self, event: PdmEvent) -> &Self {
+        match event {
+            PdmEvent::Started => self.0.intenset.write(|w| w.started().set()),
+            PdmEvent::Stopped => self.0.intenset.write(|w| w.stopped().set()),
+            PdmEvent::End => self.0.intenset.write(|w| w.end().set()),
+        };
+        self
+    }
+
+    /// Disables interrupt triggering on the specified event.
+    #[inline(always)]
+    pub fn disable_interrupt(&self, event: PdmEvent) -> &Self {
+        match event {
+            PdmEvent::Started => self.0.intenclr.write(|w| w.started().clear()),
+            PdmEvent::Stopped => self.0.intenclr.write(|w| w.stopped().clear()),
+            PdmEvent::End => self.0.intenclr.write(|w| w.end().clear()),
+        };
+        self
+    }
+
+    /// Returns reference to `Started` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_started(&self) -> &Reg<u32, _EVENTS_STARTED> {
+        &self.0.events_started
+    }
+
+    /// Returns reference to `Stopped` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_stopped(&self) -> &Reg<u32, _EVENTS_STOPPED> {
+        &self.0.events_stopped
+    }
+
+    /// Returns reference to `End` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_end(&self) -> &Reg<u32, _EVENTS_END> {
+        &self.0.events_end
+    }
+
+    /// Returns reference to `Start` task endpoint for PPI.
+    #[inline(always)]
+    pub fn task_start(&self) -> &Reg<u32, _TASKS_START> {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to `Stop` task endpoint for PPI.
+    #[inline(always)]
+    pub fn task_stop(&self) -> &Reg<u32, _TASKS_STOP> {
+        &self.0.tasks_stop
+    }
+
+    /// Disables the PDM, disconnects the pins, and returns the raw peripheral.
+    pub fn free(self) -> PDM {
+        self.0.enable.write(|w| w.enable().disabled());
+        self.0.psel.clk.write(|w| w.connect().disconnected());
+        self.0.psel.din.write(|w| w.connect().disconnected());
+        self.0
+    }
+}
+
+/// The two sample buffers used by `PdmStream`.
+pub type Buffers = (&'static mut [i16], &'static mut [i16]);
+
+/// Continuous recording into two alternating buffers.
+///
+/// The PDM latches the buffer pointer when it starts filling a buffer, so the next buffer can be
+/// set while the current one is being filled. When a buffer is full, the PDM continues with the
+/// other one without losing samples, while the CPU processes the full one.
+///
+/// ```ignore
+/// static mut BUF_A: [i16; 512] = [0; 512];
+/// static mut BUF_B: [i16; 512] = [0; 512];
+///
+/// let mut stream = PdmStream::new(pdm, unsafe { (&mut BUF_A, &mut BUF_B) })?;
+/// stream.start();
+///
+/// loop {
+///     let peak = block!(stream.process(|samples| {
+///         samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0)
+///     }))?;
+/// }
+/// ```
+///
+/// Each buffer has to be processed before the other one is full, i.e. within one buffer period.
+pub struct PdmStream {
+    pdm: Pdm,
+    buffers: [&'static mut [i16]; 2],
+    filling: usize,
+}
+
+impl PdmStream {
+    /// Sets up continuous recording into `buffers`, which must have the same length.
+    ///
+    /// Recording starts with `start`.
+    pub fn new(pdm: Pdm, buffers: Buffers) -> Result<Self, Error> {
+        let (buf_a, buf_b) = buffers;
+        if buf_a.len() != buf_b.len() {
+            return Err(Error::BufferLengthMismatch);
+        }
+        pdm.set_buffer(buf_a)?;
+
+        Ok(Self {
+            pdm,
+            buffers: [buf_a, buf_b],
+            filling: 0,
+        })
+    }
+
+    /// Starts recording into the first buffer.
+    pub fn start(&mut self) {
+        let first = self.buffers[0].as_mut_ptr() as u32;
+        let second = self.buffers[1].as_mut_ptr() as u32;
+        let pdm = &self.pdm.0;
+        self.filling = 0;
+        pdm.events_end.reset();
+        pdm.events_started.reset();
+        pdm.sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(first) });
+
+        // Conservative compiler fence to prevent starting the transfer before the pointer and
+        // maxcount have been set.
+        compiler_fence(SeqCst);
+
+        pdm.tasks_start.write(|w| unsafe { w.bits(1) });
+        while pdm.events_started.read().bits() == 0 {}
+        pdm.events_started.reset();
+
+        // The pointer is latched on START, so the next buffer can be set right away.
+        pdm.sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(second) });
+    }
+
+    /// Stops recording. The partially filled buffer is discarded.
+    pub fn stop(&mut self) {
+        self.pdm.stop();
+        compiler_fence(SeqCst);
+    }
+
+    /// Passes the buffer that was filled last to `f`, and returns its result.
+    ///
+    /// Returns `WouldBlock` if no buffer has been filled since the last call. The buffer is
+    /// filled again once the other one is full, so `f` must return before that.
+    pub fn process<F, U>(&mut self, f: F) -> nb::Result<U, Error>
+    where
+        F: FnOnce(&[i16]) -> U,
+    {
+        let pdm = &self.pdm.0;
+        if pdm.events_end.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        pdm.events_end.reset();
+
+        // The PDM continues with the other buffer. Once it has started, the full buffer can be
+        // queued up after it.
+        while pdm.events_started.read().bits() == 0 {}
+        pdm.events_started.reset();
+
+        let full = self.filling;
+        self.filling = 1 - full;
+        let ptr = self.buffers[full].as_mut_ptr() as u32;
+        pdm.sample.ptr.write(|w| unsafe { w.sampleptr().bits(ptr) });
+
+        // Prevent the buffer from being read before the `END` event has been observed.
+        compiler_fence(SeqCst);
+
+        Ok(f(&*self.buffers[full]))
+    }
+
+    /// Returns reference to the `END` event endpoint, which is generated when a buffer is full.
+    #[inline(always)]
+    pub fn event_end(&self) -> &Reg<u32, _EVENTS_END> {
+        self.pdm.event_end()
+    }
+
+    /// Enables the PDM interrupt on the `END` event, to be notified of full buffers.
+    pub fn enable_interrupt(&mut self) {
+        self.pdm.enable_interrupt(PdmEvent::End);
+    }
+
+    /// Disables the PDM interrupt on the `END` event.
+    pub fn disable_interrupt(&mut self) {
+        self.pdm.disable_interrupt(PdmEvent::End);
+    }
+
+    /// Stops recording, and releases the PDM and the buffers.
+    pub fn free(mut self) -> (Pdm, Buffers) {
+        self.stop();
+        self.disable_interrupt();
+        let [buf_a, buf_b] = self.buffers;
+        (self.pdm, (buf_a, buf_b))
+    }
+}
+
+/// PDM events
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum PdmEvent {
+    /// The PDM has started filling a buffer, and the pointer for the next one can be set.
+    Started,
+    /// The PDM has stopped.
+    Stopped,
+    /// A buffer is full.
+    End,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Error {
+    DMABufferNotInDataMemory,
+    BufferEmpty,
+    BufferTooLong,
+    BufferLengthMismatch,
+}
+
+/// Used to configure the PDM peripheral.
+///
+/// See the documentation of the `Default` impl for suitable default values.
+#[derive(Debug, Clone, Copy)]
+pub struct PdmConfig {
+    /// Frequency of the PDM clock.
+    pub frequency: Frequency,
+    /// Ratio between the PDM clock frequency and the sample rate.
+    #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+    pub ratio: Ratio,
+    /// Mono or stereo operation. Two microphones can share the clock and data lines, each
+    /// driving the data line on a different clock edge.
+    pub operation: Operation,
+    /// Clock edge on which the left channel, or the only channel in mono operation, is sampled.
+    pub edge: Edge,
+    /// Gain of the left channel in 0.5 dB steps, from -40 (-20 dB) to 40 (+20 dB).
+    pub gain_left: i8,
+    /// Gain of the right channel in 0.5 dB steps, from -40 (-20 dB) to 40 (+20 dB).
+    pub gain_right: i8,
+}
+
+/// Default PDM configuration, for a sample rate of 16.125 kHz from a single microphone.
+/// The returned PdmConfig is configured with the following values:
+///
+/// ```ignore
+/// PdmConfig {
+///     frequency: Frequency::DEFAULT, // 1.032 MHz
+///     ratio: Ratio::RATIO64, // On the nRF52833, nRF52840 and nRF9160
+///     operation: Operation::MONO,
+///     edge: Edge::LEFTFALLING,
+///     gain_left: 0,
+///     gain_right: 0,
+/// };
+/// ```
+impl Default for PdmConfig {
+    fn default() -> Self {
+        // Note: do not forget to update the docs above if you change values here
+        PdmConfig {
+            frequency: Frequency::DEFAULT,
+            #[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
+            ratio: Ratio::RATIO64,
+            operation: Operation::MONO,
+            edge: Edge::LEFTFALLING,
+            gain_left: 0,
+            gain_right: 0,
+        }
+    }
+}
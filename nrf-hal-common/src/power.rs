@@ -0,0 +1,296 @@
+//! HAL interface to the POWER peripheral.
+//!
+//! The general purpose retention registers (GPREGRET) keep their value across soft resets, e.g.
+//! a reset requested with `SCB::sys_reset`, and are commonly used by an application to request a
+//! bootloader to enter DFU mode:
+//!
+//! ```ignore
+//! let mut power = Power::new(p.POWER);
+//! power.set_boot_request(BootRequest::Dfu);
+//! cortex_m::peripheral::SCB::sys_reset();
+//! ```
+//!
+//! `reset_into_bootloader` does both, e.g. when a USB host requests a firmware update.
+//!
+//! In the bootloader, `take_boot_request` reads the request and clears it, so that the next boot
+//! is a normal one:
+//!
+//! ```ignore
+//! if power.take_boot_request() == BootRequest::Dfu {
+//!     enter_dfu();
+//! }
+//! ```
+//!
+//! The retention registers are cleared by a power-on reset, a brown-out reset, and a wakeup from
+//! System OFF mode.
+//!
+//! On the nRF52833 and nRF52840, `Power` also reports the state of the USB supply, and its
+//! `UsbSupply` lets the USBD driver connect to the bus only once the USB regulator is ready, and
+//! disconnect when VBUS is removed.
+
+use core::ops::Deref;
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{power, POWER};
+
+#[cfg(feature = "9160")]
+use crate::pac::{power_ns as power, POWER_NS, POWER_S};
+
+/// Request passed to the bootloader through GPREGRET, using the values of the nRF5 SDK and
+/// Adafruit bootloaders.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BootRequest {
+    /// Boot the application.
+    None,
+    /// Enter DFU mode (nRF5 SDK bootloader, `0xB1`).
+    Dfu,
+    /// Enter UF2 mass storage DFU mode (Adafruit bootloader, `0x57`).
+    Uf2,
+    /// Enter serial DFU mode only (Adafruit bootloader, `0x4E`).
+    SerialDfu,
+    /// Enter BLE OTA DFU mode (Adafruit bootloader, `0xA8`).
+    OtaDfu,
+    /// Any other value, for custom bootloaders.
+    Other(u8),
+}
+
+impl From<u8> for BootRequest {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => BootRequest::None,
+            0xB1 => BootRequest::Dfu,
+            0x57 => BootRequest::Uf2,
+            0x4E => BootRequest::SerialDfu,
+            0xA8 => BootRequest::OtaDfu,
+            value => BootRequest::Other(value),
+        }
+    }
+}
+
+impl From<BootRequest> for u8 {
+    fn from(request: BootRequest) -> Self {
+        match request {
+            BootRequest::None => 0x00,
+            BootRequest::Dfu => 0xB1,
+            BootRequest::Uf2 => 0x57,
+            BootRequest::SerialDfu => 0x4E,
+            BootRequest::OtaDfu => 0xA8,
+            BootRequest::Other(value) => value,
+        }
+    }
+}
+
+/// Event of the USB supply.
+#[cfg(any(feature = "52833", feature = "52840"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UsbEvent {
+    /// A voltage has been detected on VBUS.
+    Detected,
+    /// The voltage has been removed from VBUS.
+    Removed,
+    /// The USB regulator is ready, so the USBD can connect to the bus.
+    PowerReady,
+}
+
+/// State of the USB supply, used by the USBD driver to connect to the bus when the supply is ready.
+#[cfg(any(feature = "52833", feature = "52840"))]
+pub struct UsbSupply {
+    _private: (),
+}
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+impl UsbSupply {
+    /// Returns `true` if a voltage is present on VBUS.
+    pub fn is_vbus_present(&self) -> bool {
+        // NOTE(unsafe) Only the read-only status register is accessed
+        unsafe { &*POWER::ptr() }
+            .usbregstatus
+            .read()
+            .vbusdetect()
+            .is_vbus_present()
+    }
+
+    /// Returns `true` if the USB regulator is ready.
+    pub fn is_power_ready(&self) -> bool {
+        // NOTE(unsafe) Only the read-only status register is accessed
+        unsafe { &*POWER::ptr() }
+            .usbregstatus
+            .read()
+            .outputrdy()
+            .is_ready()
+    }
+}
+
+/// Interface to the POWER peripheral.
+pub struct Power<T: Instance>(T);
+
+impl<T> Power<T>
+where
+    T: Instance,
+{
+    /// Takes ownership of the POWER peripheral.
+    pub fn new(power: T) -> Self {
+        Self(power)
+    }
+
+    /// Returns the value of the GPREGRET register.
+    #[cfg(not(feature = "9160"))]
+    pub fn gpregret(&self) -> u8 {
+        self.0.gpregret.read().gpregret().bits()
+    }
+
+    /// Returns the value of the GPREGRET register.
+    #[cfg(feature = "9160")]
+    pub fn gpregret(&self) -> u8 {
+        self.0.gpregret[0].read().gpregret().bits()
+    }
+
+    /// Sets the value of the GPREGRET register, which is retained across soft resets.
+    #[cfg(not(feature = "9160"))]
+    pub fn set_gpregret(&mut self, value: u8) {
+        self.0
+            .gpregret
+            .write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Sets the value of the GPREGRET register, which is retained across soft resets.
+    #[cfg(feature = "9160")]
+    pub fn set_gpregret(&mut self, value: u8) {
+        self.0.gpregret[0].write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Returns the value of the GPREGRET2 register.
+    #[cfg(not(any(feature = "51", feature = "9160")))]
+    pub fn gpregret2(&self) -> u8 {
+        self.0.gpregret2.read().gpregret().bits()
+    }
+
+    /// Returns the value of the GPREGRET2 register.
+    #[cfg(feature = "9160")]
+    pub fn gpregret2(&self) -> u8 {
+        self.0.gpregret[1].read().gpregret().bits()
+    }
+
+    /// Sets the value of the GPREGRET2 register, which is retained across soft resets.
+    #[cfg(not(any(feature = "51", feature = "9160")))]
+    pub fn set_gpregret2(&mut self, value: u8) {
+        self.0
+            .gpregret2
+            .write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Sets the value of the GPREGRET2 register, which is retained across soft resets.
+    #[cfg(feature = "9160")]
+    pub fn set_gpregret2(&mut self, value: u8) {
+        self.0.gpregret[1].write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Returns the request stored in GPREGRET for the bootloader.
+    pub fn boot_request(&self) -> BootRequest {
+        self.gpregret().into()
+    }
+
+    /// Stores a request for the bootloader in GPREGRET, to be read after the next soft reset.
+    pub fn set_boot_request(&mut self, request: BootRequest) {
+        self.set_gpregret(request.into());
+    }
+
+    /// Returns the request stored in GPREGRET for the bootloader, and clears it.
+    pub fn take_boot_request(&mut self) -> BootRequest {
+        let request = self.boot_request();
+        self.set_boot_request(BootRequest::None);
+        request
+    }
+
+    /// Stores `request` for the bootloader in GPREGRET, and resets the chip to start the
+    /// bootloader, e.g. after a DFU_DETACH request or a "1200 baud touch".
+    pub fn reset_into_bootloader(&mut self, request: BootRequest) -> ! {
+        self.set_boot_request(request);
+        cortex_m::peripheral::SCB::sys_reset()
+    }
+
+    /// Returns `true` if a voltage is present on VBUS.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn is_vbus_present(&self) -> bool {
+        self.0.usbregstatus.read().vbusdetect().is_vbus_present()
+    }
+
+    /// Returns `true` if the USB regulator is ready.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn is_usb_power_ready(&self) -> bool {
+        self.0.usbregstatus.read().outputrdy().is_ready()
+    }
+
+    /// Enables the interrupt for the USB supply `event`.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn enable_usb_interrupt(&mut self, event: UsbEvent) {
+        self.0.intenset.write(|w| match event {
+            UsbEvent::Detected => w.usbdetected().set_bit(),
+            UsbEvent::Removed => w.usbremoved().set_bit(),
+            UsbEvent::PowerReady => w.usbpwrrdy().set_bit(),
+        });
+    }
+
+    /// Disables the interrupt for the USB supply `event`.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn disable_usb_interrupt(&mut self, event: UsbEvent) {
+        self.0.intenclr.write(|w| match event {
+            UsbEvent::Detected => w.usbdetected().set_bit(),
+            UsbEvent::Removed => w.usbremoved().set_bit(),
+            UsbEvent::PowerReady => w.usbpwrrdy().set_bit(),
+        });
+    }
+
+    /// Returns `true` if the USB supply `event` has been triggered.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn is_usb_event_triggered(&self, event: UsbEvent) -> bool {
+        match event {
+            UsbEvent::Detected => self.0.events_usbdetected.read().bits() != 0,
+            UsbEvent::Removed => self.0.events_usbremoved.read().bits() != 0,
+            UsbEvent::PowerReady => self.0.events_usbpwrrdy.read().bits() != 0,
+        }
+    }
+
+    /// Marks the USB supply `event` as handled.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn reset_usb_event(&mut self, event: UsbEvent) {
+        match event {
+            UsbEvent::Detected => self.0.events_usbdetected.reset(),
+            UsbEvent::Removed => self.0.events_usbremoved.reset(),
+            UsbEvent::PowerReady => self.0.events_usbpwrrdy.reset(),
+        }
+    }
+
+    /// Returns the `UsbSupply` to be passed to the USBD driver.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn usb_supply(&self) -> UsbSupply {
+        UsbSupply { _private: () }
+    }
+
+    /// Releases the POWER peripheral.
+    pub fn free(self) -> T {
+        self.0
+    }
+}
+
+/// Implemented by all POWER instances.
+pub trait Instance: Deref<Target = power::RegisterBlock> + sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(not(feature = "9160"))]
+impl sealed::Sealed for POWER {}
+#[cfg(not(feature = "9160"))]
+impl Instance for POWER {}
+
+#[cfg(feature = "9160")]
+impl sealed::Sealed for POWER_NS {}
+#[cfg(feature = "9160")]
+impl Instance for POWER_NS {}
+
+#[cfg(feature = "9160")]
+impl sealed::Sealed for POWER_S {}
+#[cfg(feature = "9160")]
+impl Instance for POWER_S {}
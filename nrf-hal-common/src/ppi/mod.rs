@@ -8,10 +8,37 @@
 //!
 //! On nRF52 devices, there is also a fork task endpoint, where the user can configure one more task
 //! to be triggered by the same event, even fixed PPI channels have a configurable fork task.
+//!
+//! Each channel is a distinct type obtained from [Parts](struct.Parts.html), so a channel can only
+//! be configured by its owner:
+//!
+//! ```ignore
+//! let ppi_channels = ppi::Parts::new(p.PPI);
+//! let mut channel0 = ppi_channels.ppi0;
+//!
+//! channel0.set_event_endpoint(timer.event_compare_cc0());
+//! channel0.set_task_endpoint(&p.RADIO.tasks_disable);
+//! channel0.enable();
+//! ```
+//!
+//! The fork task lets one channel trigger two tasks, e.g. a TIMER compare event that starts a
+//! SAADC sample and toggles a debug pin through GPIOTE at the same time:
+//!
+//! ```ignore
+//! channel1.set_event_endpoint(timer.event_compare_cc0());
+//! channel1.set_task_endpoint(&p.SAADC.tasks_sample);
+//! channel1.set_fork_task_endpoint(gpiote.channel0().task_out());
+//! channel1.enable();
+//! ```
 
 use crate::pac::generic::Reg;
+use crate::pac::gpiote::{_EVENTS_IN, _TASKS_OUT};
 use crate::pac::ppi::tasks_chg::{_DIS, _EN};
+use crate::pac::rtc0::_EVENTS_COMPARE as _RTC_EVENTS_COMPARE;
+use crate::pac::timer0::_TASKS_CAPTURE;
 use crate::pac::PPI;
+#[cfg(not(feature = "51"))]
+use crate::pac::{saadc::_TASKS_SAMPLE, timer0::_EVENTS_COMPARE as _TIMER_EVENTS_COMPARE};
 use cfg_if::cfg_if;
 
 cfg_if! {
@@ -37,24 +64,14 @@ cfg_if! {
 }
 
 mod sealed {
-    use super::{EventAddr, TaskAddr};
+    use crate::pac::generic::Reg;
 
     pub trait Channel {
         const CH: usize;
     }
 
-    pub trait Task {
-        #[inline(always)]
-        fn task_addr(&self) -> TaskAddr {
-            TaskAddr(self as *const _ as *const u32 as u32)
-        }
-    }
-    pub trait Event {
-        #[inline(always)]
-        fn event_addr(&self) -> EventAddr {
-            EventAddr(self as *const _ as *const u32 as u32)
-        }
-    }
+    pub trait Endpoint {}
+    impl<REG> Endpoint for Reg<u32, REG> {}
 
     pub trait NotFixed {}
 
@@ -65,11 +82,35 @@ mod sealed {
     pub trait PpiSealed {}
     pub trait PpiChannelGroupSealed {}
 }
-use sealed::{Channel, ChannelGroup, Event, NotFixed, PpiChannelGroupSealed, PpiSealed, Task};
+use sealed::{Channel, ChannelGroup, Endpoint, NotFixed, PpiChannelGroupSealed, PpiSealed};
 
 pub struct TaskAddr(pub(crate) u32);
 pub struct EventAddr(pub(crate) u32);
 
+/// Trait to represent a task register that can be used as a PPI task endpoint.
+///
+/// Implemented for the task registers of all peripherals, including the ones returned by the
+/// `task_*` methods of the HAL drivers.
+pub trait Task: Endpoint {
+    /// Returns the address of the task register.
+    #[inline(always)]
+    fn task_addr(&self) -> TaskAddr {
+        TaskAddr(self as *const _ as *const u32 as u32)
+    }
+}
+
+/// Trait to represent an event register that can be used as a PPI event endpoint.
+///
+/// Implemented for the event registers of all peripherals, including the ones returned by the
+/// `event_*` methods of the HAL drivers.
+pub trait Event: Endpoint {
+    /// Returns the address of the event register.
+    #[inline(always)]
+    fn event_addr(&self) -> EventAddr {
+        EventAddr(self as *const _ as *const u32 as u32)
+    }
+}
+
 /// Trait to represent a Programmable Peripheral Interconnect channel.
 pub trait Ppi: PpiSealed {
     /// Enables the channel.
@@ -78,10 +119,17 @@ pub trait Ppi: PpiSealed {
     /// Disables the channel.
     fn disable(&mut self);
 
+    /// Returns `true` if the channel is enabled.
+    fn is_enabled(&self) -> bool;
+
     #[cfg(not(feature = "51"))]
     /// Sets the fork task that must be triggered when the configured event occurs. The user must
     /// provide a reference to the task.
     fn set_fork_task_endpoint<T: Task>(&mut self, task: &T);
+
+    #[cfg(not(feature = "51"))]
+    /// Removes the fork task, so that only the main task is triggered by the event.
+    fn clear_fork_task_endpoint(&mut self);
 }
 
 /// Traits that extends the [Ppi](trait.Ppi.html) trait, marking a channel as fully configurable.
@@ -103,6 +151,12 @@ pub trait PpiChannelGroup: PpiChannelGroupSealed {
     fn task_disable(&self) -> &Reg<u32, _DIS>;
     /// Sets bitmask for PPI channels which shall be included in this channel group.
     fn set_channels(&self, mask: u32);
+    /// Includes the given PPI channel in this channel group.
+    fn add_channel<P: Channel>(&self, channel: &P);
+    /// Removes the given PPI channel from this channel group.
+    fn remove_channel<P: Channel>(&self, channel: &P);
+    /// Returns the bitmask of PPI channels included in this channel group.
+    fn channels(&self) -> u32;
     /// Enables this channel group.
     fn enable(&self);
     /// Disables this channel group.
@@ -125,6 +179,12 @@ impl<P: Channel> Ppi for P {
         regs.chenclr.write(|w| unsafe { w.bits(1 << P::CH) });
     }
 
+    #[inline(always)]
+    fn is_enabled(&self) -> bool {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chen.read().bits() & (1 << P::CH) != 0
+    }
+
     #[cfg(not(feature = "51"))]
     #[inline(always)]
     fn set_fork_task_endpoint<T: Task>(&mut self, task: &T) {
@@ -133,6 +193,13 @@ impl<P: Channel> Ppi for P {
             .tep
             .write(|w| unsafe { w.bits(task.task_addr().0) });
     }
+
+    #[cfg(not(feature = "51"))]
+    #[inline(always)]
+    fn clear_fork_task_endpoint(&mut self) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.fork[P::CH].tep.write(|w| unsafe { w.bits(0) });
+    }
 }
 
 // All unsafe `ptr` calls only uses registers atomically, and only changes the resources owned by
@@ -173,6 +240,21 @@ impl<G: ChannelGroup> PpiChannelGroup for G {
         regs.chg[Self::CHG].write(|w| unsafe { w.bits(mask) });
     }
     #[inline(always)]
+    fn add_channel<P: Channel>(&self, _channel: &P) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[Self::CHG].modify(|r, w| unsafe { w.bits(r.bits() | 1 << P::CH) });
+    }
+    #[inline(always)]
+    fn remove_channel<P: Channel>(&self, _channel: &P) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[Self::CHG].modify(|r, w| unsafe { w.bits(r.bits() & !(1 << P::CH)) });
+    }
+    #[inline(always)]
+    fn channels(&self) -> u32 {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[Self::CHG].read().bits()
+    }
+    #[inline(always)]
     fn enable(&self) {
         self.task_enable().write(|w| unsafe { w.bits(1) });
     }
@@ -182,13 +264,121 @@ impl<G: ChannelGroup> PpiChannelGroup for G {
     }
 }
 
+/// PPI channel number `CH`.
+///
+/// Each channel can only be obtained once, through [Parts](struct.Parts.html), so two drivers
+/// can't claim the same channel. Drivers that need a specific channel can take a `PpiChannel<CH>`,
+/// while generic code can use the [Ppi](trait.Ppi.html) and
+/// [ConfigurablePpi](trait.ConfigurablePpi.html) traits.
+pub struct PpiChannel<const CH: usize> {
+    _private: (),
+}
+
+impl<const CH: usize> Channel for PpiChannel<CH> {
+    const CH: usize = CH;
+}
+
+/// PPI channel group number `CHG`.
+pub struct Chg<const CHG: usize> {
+    _private: (),
+}
+
+impl<const CHG: usize> ChannelGroup for Chg<CHG> {
+    const CHG: usize = CHG;
+}
+
+/// A connection between an event and a task through a PPI channel.
+///
+/// The channel is disabled and its endpoints are cleared when the connection is dropped.
+pub struct Connection<'a, P: ConfigurablePpi + Channel + NotFixed> {
+    channel: &'a mut P,
+}
+
+impl<'a, P: ConfigurablePpi + Channel + NotFixed> Connection<'a, P> {
+    /// Adds a fork task that is triggered together with the main task.
+    #[cfg(not(feature = "51"))]
+    pub fn fork<T: Task>(&mut self, task: &T) -> &mut Self {
+        self.channel.set_fork_task_endpoint(task);
+        self
+    }
+}
+
+impl<'a, P: ConfigurablePpi + Channel + NotFixed> Drop for Connection<'a, P> {
+    fn drop(&mut self) {
+        self.channel.disable();
+        let regs = unsafe { &*PPI::ptr() };
+        regs.ch[P::CH].eep.write(|w| unsafe { w.bits(0) });
+        regs.ch[P::CH].tep.write(|w| unsafe { w.bits(0) });
+        #[cfg(not(feature = "51"))]
+        self.channel.clear_fork_task_endpoint();
+    }
+}
+
+/// Connects `event` to `task` through `channel` and enables the channel.
+pub fn connect<'a, P, E, T>(channel: &'a mut P, event: &E, task: &T) -> Connection<'a, P>
+where
+    P: ConfigurablePpi + Channel + NotFixed,
+    E: Event,
+    T: Task,
+{
+    channel.set_event_endpoint(event);
+    channel.set_task_endpoint(task);
+    channel.enable();
+    Connection { channel }
+}
+
+/// Takes a SAADC sample on every TIMER compare event, e.g. `timer.event_compare_cc0()` of a
+/// periodic timer and `saadc.task_sample()`.
+#[cfg(not(feature = "51"))]
+pub fn timer_compare_to_saadc_sample<'a, P>(
+    channel: &'a mut P,
+    compare: &Reg<u32, _TIMER_EVENTS_COMPARE>,
+    sample: &Reg<u32, _TASKS_SAMPLE>,
+) -> Connection<'a, P>
+where
+    P: ConfigurablePpi + Channel + NotFixed,
+{
+    connect(channel, compare, sample)
+}
+
+/// Captures the TIMER value on every GPIOTE channel event, e.g. `gpiote.channel0().event()` and
+/// `timer.task_capture_cc2()`.
+pub fn gpiote_in_to_timer_capture<'a, P>(
+    channel: &'a mut P,
+    input: &Reg<u32, _EVENTS_IN>,
+    capture: &Reg<u32, _TASKS_CAPTURE>,
+) -> Connection<'a, P>
+where
+    P: ConfigurablePpi + Channel + NotFixed,
+{
+    connect(channel, input, capture)
+}
+
+/// Toggles a GPIOTE output on every RTC compare event, e.g.
+/// `rtc.event_compare(RtcCompareReg::Compare0)` and `gpiote.channel1().task_out()`.
+///
+/// The compare event must also be enabled with `Rtc::enable_event`.
+pub fn rtc_compare_to_gpiote_out<'a, P>(
+    channel: &'a mut P,
+    compare: &Reg<u32, _RTC_EVENTS_COMPARE>,
+    out: &Reg<u32, _TASKS_OUT>,
+) -> Connection<'a, P>
+where
+    P: ConfigurablePpi + Channel + NotFixed,
+{
+    connect(channel, compare, out)
+}
+
 macro_rules! ppi {
     (
         not_fixed: [ $(
             $(#[$attr:meta])*
             ($ppix:ident, $PpixType:ident, $ch:expr),)+
         ],
-        fixed: [$(($ppix_fixed:ident, $PpixTypeFixed:ident, $ch_fixed:expr),)+],
+        fixed: [$(
+            $(#[$fixedattr:meta])*
+            ($ppix_fixed:ident, $PpixTypeFixed:ident, $ch_fixed:expr),)+
+        ],
         groups: [$(
             $(#[$chgattr:meta])*
             ($chgx:ident, $ChgxType:ident, $chg:expr),)+],
@@ -197,41 +387,24 @@ macro_rules! ppi {
         $(
             /// Fully configurable PPI Channel.
             $(#[$attr])*
-            pub struct $PpixType {
-                _private: (),
-            }
+            pub type $PpixType = PpiChannel<$ch>;
 
             $(#[$attr])*
-            impl Channel for $PpixType {
-                const CH: usize = $ch;
-            }
-
-            $(#[$attr])*
-            impl NotFixed for $PpixType {}
+            impl NotFixed for PpiChannel<$ch> {}
         )+
 
         $(
             /// Fixed PPI channel.
-            pub struct $PpixTypeFixed {
-                _private: (),
-            }
-
-            impl Channel for $PpixTypeFixed {
-                const CH: usize = $ch_fixed;
-            }
+            ///
+            /// Only the fork task can be configured, the event and main task are pre-programmed:
+            $(#[$fixedattr])*
+            pub type $PpixTypeFixed = PpiChannel<$ch_fixed>;
         )+
 
         $(
             /// Channel groups.
             $(#[$chgattr])*
-            pub struct $ChgxType {
-                _private: (),
-            }
-
-            $(#[$chgattr])*
-            impl ChannelGroup for $ChgxType {
-                const CHG: usize = $chg;
-            }
+            pub type $ChgxType = Chg<$chg>;
         )*
 
         /// Type that abstracts all the PPI channels.
@@ -241,6 +414,7 @@ macro_rules! ppi {
                 pub $ppix: $PpixType,
             )+
             $(
+                $(#[$fixedattr])*
                 pub $ppix_fixed: $PpixTypeFixed,
             )+
             $(
@@ -305,17 +479,29 @@ ppi!(
         (ppi19, Ppi19, 19),
     ],
     fixed: [
+        /// `TIMER0.EVENTS_COMPARE[0]` → `RADIO.TASKS_TXEN`
         (ppi20, Ppi20, 20),
+        /// `TIMER0.EVENTS_COMPARE[0]` → `RADIO.TASKS_RXEN`
         (ppi21, Ppi21, 21),
+        /// `TIMER0.EVENTS_COMPARE[1]` → `RADIO.TASKS_DISABLE`
         (ppi22, Ppi22, 22),
+        /// `RADIO.EVENTS_BCMATCH` → `AAR.TASKS_START`
         (ppi23, Ppi23, 23),
+        /// `RADIO.EVENTS_READY` → `CCM.TASKS_KSGEN`
         (ppi24, Ppi24, 24),
+        /// `RADIO.EVENTS_ADDRESS` → `CCM.TASKS_CRYPT`
         (ppi25, Ppi25, 25),
+        /// `RADIO.EVENTS_ADDRESS` → `TIMER0.TASKS_CAPTURE[1]`
         (ppi26, Ppi26, 26),
+        /// `RADIO.EVENTS_END` → `TIMER0.TASKS_CAPTURE[2]`
         (ppi27, Ppi27, 27),
+        /// `RTC0.EVENTS_COMPARE[0]` → `RADIO.TASKS_TXEN`
         (ppi28, Ppi28, 28),
+        /// `RTC0.EVENTS_COMPARE[0]` → `RADIO.TASKS_RXEN`
         (ppi29, Ppi29, 29),
+        /// `RTC0.EVENTS_COMPARE[0]` → `TIMER0.TASKS_CLEAR`
         (ppi30, Ppi30, 30),
+        /// `RTC0.EVENTS_COMPARE[0]` → `TIMER0.TASKS_START`
         (ppi31, Ppi31, 31),
     ],
     groups: [
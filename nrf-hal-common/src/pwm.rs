@@ -2,15 +2,29 @@
 //!
 //! The pulse with modulation (PWM) module enables the generation of pulse width modulated signals on GPIO.
 
-#[cfg(not(any(feature = "52810", feature = "52811")))]
-use crate::{
-    gpio::Port,
-    pac::PWM3,
-    pac::{PWM1, PWM2},
-};
+#[cfg(any(feature = "52833", feature = "52840"))]
+use crate::gpio::Port;
+
+#[cfg(feature = "9160")]
+use crate::pac::{pwm0_ns::*, PWM0_NS as PWM0, PWM1_NS as PWM1, PWM2_NS as PWM2, PWM3_NS as PWM3};
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{pwm0::*, PWM0};
+
+#[cfg(not(any(feature = "52810", feature = "52811", feature = "9160")))]
+use crate::pac::{PWM1, PWM2};
+
+#[cfg(not(any(
+    feature = "52810",
+    feature = "52811",
+    feature = "52832",
+    feature = "9160"
+)))]
+use crate::pac::PWM3;
+
 use crate::{
     gpio::{Output, Pin, PushPull},
-    pac::{generic::Reg, pwm0::*, Interrupt, PWM0},
+    pac::{generic::Reg, Interrupt},
     target_constants::{SRAM_LOWER, SRAM_UPPER},
     time::*,
 };
@@ -92,6 +106,44 @@ where
     /// Sets the PWM output frequency.
     #[inline(always)]
     pub fn set_period(&self, freq: Hertz) -> &Self {
+        self.set_max_duty(self.countertop_for(freq))
+    }
+
+    /// Configures center-aligned PWM at the output frequency `freq`.
+    ///
+    /// The counter counts up to the maximum duty cycle value and back down, so that the pulses
+    /// of all channels are centered in the period. This phase-symmetric PWM is preferred for motor
+    /// drives, and reduces EMI when dimming LEDs. The smallest prescaler that can reach `freq`
+    /// is selected, for the best duty cycle resolution.
+    pub fn set_center_aligned(&self, freq: Hertz) -> &Self {
+        self.set_counter_mode(CounterMode::UpAndDown);
+        let prescalers = [
+            Prescaler::Div1,
+            Prescaler::Div2,
+            Prescaler::Div4,
+            Prescaler::Div8,
+            Prescaler::Div16,
+            Prescaler::Div32,
+            Prescaler::Div64,
+            Prescaler::Div128,
+        ];
+        // Each period lasts twice the maximum duty cycle value in this mode.
+        let prescaler = prescalers
+            .iter()
+            .copied()
+            .find(|&div| (16_000_000u32 >> u8::from(div)) / freq.0.max(1) / 2 <= 32767)
+            .unwrap_or(Prescaler::Div128);
+        self.set_prescaler(prescaler);
+        self.set_period(freq)
+    }
+
+    /// Returns the maximum duty cycle value giving the PWM output frequency `freq`, with the
+    /// current prescaler and counter mode.
+    ///
+    /// In `Waveform` load mode, this is the value to use as the fourth value of each sequence
+    /// entry.
+    #[inline(always)]
+    pub fn countertop_for(&self, freq: Hertz) -> u16 {
         let duty = match self.prescaler() {
             Prescaler::Div1 => 16_000_000u32 / freq.0,
             Prescaler::Div2 => 8_000_000u32 / freq.0,
@@ -103,10 +155,9 @@ where
             Prescaler::Div128 => 125_000u32 / freq.0,
         };
         match self.counter_mode() {
-            CounterMode::Up => self.set_max_duty(duty.min(32767) as u16),
-            CounterMode::UpAndDown => self.set_max_duty((duty / 2).min(32767) as u16),
-        };
-        self
+            CounterMode::Up => duty.min(32767) as u16,
+            CounterMode::UpAndDown => (duty / 2).min(32767) as u16,
+        }
     }
 
     /// Returns the PWM output frequency.
@@ -205,12 +256,19 @@ where
     }
 
     /// Cofigures how a sequence is read from RAM and is spread to the compare register.
+    ///
+    /// Channel 3 is disabled in `Waveform` mode, and enabled again when switching to another mode
+    /// if it has an output pin.
     #[inline(always)]
     pub fn set_load_mode(&self, mode: LoadMode) -> &Self {
+        let previous = self.load_mode();
         self.pwm.decoder.modify(|_r, w| w.load().bits(mode.into()));
         if mode == LoadMode::Waveform {
             self.disable_channel(Channel::C3);
-        } else {
+        } else if previous == LoadMode::Waveform
+            && self.pwm.psel.out[usize::from(Channel::C3)].read().bits() != 0xFFFF_FFFF
+        {
+            // The register keeps its reset value until an output pin is set.
             self.enable_channel(Channel::C3);
         }
         self
@@ -264,10 +322,12 @@ where
     #[inline(always)]
     fn duty_on_value(&self, index: usize) -> u16 {
         let val = T::buffer().get()[index];
-        let is_inverted = (val >> 15) & 1 == 0;
+        // The polarity bit is set by `set_duty_off`, and isn't part of the duty cycle.
+        let is_inverted = (val >> 15) & 1 == 1;
+        let duty = val & 0x7FFF;
         match is_inverted {
-            false => val,
-            true => self.max_duty() - (val & 0x7FFF),
+            false => duty,
+            true => self.max_duty() - duty,
         }
     }
 
@@ -275,10 +335,11 @@ where
     #[inline(always)]
     fn duty_off_value(&self, index: usize) -> u16 {
         let val = T::buffer().get()[index];
-        let is_inverted = (val >> 15) & 1 == 0;
+        let is_inverted = (val >> 15) & 1 == 1;
+        let duty = val & 0x7FFF;
         match is_inverted {
-            false => self.max_duty() - val,
-            true => val & 0x7FFF,
+            false => self.max_duty() - duty,
+            true => duty,
         }
     }
 
@@ -396,6 +457,30 @@ where
         self.start_seq(Seq::Seq0); 
     }
 
+    /// Sets the PWM output frequency along with the duty cycles (15 bit) of channels 0 to 2, in
+    /// `Waveform` load mode. Channel 3 is not available in this mode.
+    /// Will replace any ongoing sequence playback.
+    ///
+    /// Unlike `set_period`, this changes the frequency without glitches, at the end of the
+    /// current PWM period, e.g. to play notes on a piezo buzzer.
+    pub fn set_waveform(&self, freq: Hertz, duty: [u16; 3]) {
+        let top = self.countertop_for(freq);
+        T::buffer().set([
+            duty[0].min(top) & 0x7FFF,
+            duty[1].min(top) & 0x7FFF,
+            duty[2].min(top) & 0x7FFF,
+            top,
+        ]);
+        self.one_shot();
+        self.set_load_mode(LoadMode::Waveform);
+        self.pwm
+            .seq0
+            .ptr
+            .write(|w| unsafe { w.bits(T::buffer().as_ptr() as u32) });
+        self.pwm.seq0.cnt.write(|w| unsafe { w.bits(4) });
+        self.start_seq(Seq::Seq0);
+    }
+
     /// Returns the duty cycle value for a PWM channel.        
     #[inline(always)]
     pub fn duty_on(&self, channel: Channel) -> u16 {
@@ -469,7 +554,7 @@ where
     pub fn start_seq(&self, seq: Seq) {
         compiler_fence(Ordering::SeqCst);
         self.pwm.enable.write(|w| w.enable().enabled());
-        self.pwm.tasks_seqstart[usize::from(seq)].write(|w| w.tasks_seqstart().set_bit());
+        self.pwm.tasks_seqstart[usize::from(seq)].write(|w| unsafe { w.bits(1) });
         while self.pwm.events_seqstarted[usize::from(seq)].read().bits() == 0 {}
         self.pwm.events_seqend[0].write(|w| w);
         self.pwm.events_seqend[1].write(|w| w);
@@ -479,21 +564,23 @@ where
     /// Does not cause PWM generation to start if not running.
     #[inline(always)]
     pub fn next_step(&self) {
-        self.pwm
-            .tasks_nextstep
-            .write(|w| w.tasks_nextstep().set_bit());
+        self.pwm.tasks_nextstep.write(|w| unsafe { w.bits(1) });
     }
 
     /// Stops PWM pulse generation on all channels at the end of current PWM period, and stops sequence playback.
     #[inline(always)]
     pub fn stop(&self) {
         compiler_fence(Ordering::SeqCst);
-        self.pwm.tasks_stop.write(|w| w.tasks_stop().set_bit());
+        self.pwm.tasks_stop.write(|w| unsafe { w.bits(1) });
         while self.pwm.events_stopped.read().bits() == 0 {}
     }
 
     /// Loads the given sequence buffers and optionally (re-)starts sequence playback.
     /// Returns a `PemSeq`, containing `Pwm<T>` and the buffers.
+    ///
+    /// The playback is done by EasyDMA, without CPU involvement. With looping enabled, e.g. with
+    /// `repeat` or `loop_inf`, `Seq0` and `Seq1` are played one after the other, and the
+    /// `LoopsDone` event is generated after the last loop.
     #[allow(unused_mut)]
     pub fn load<B0, B1>(
         mut self,
@@ -544,7 +631,9 @@ where
             compiler_fence(Ordering::SeqCst);
             self.pwm.seq1.ptr.write(|w| unsafe { w.bits(ptr as u32) });
             self.pwm.seq1.cnt.write(|w| unsafe { w.bits(len as u32) });
-            if start {
+            // With both sequences loaded, the playback starts with `Seq0`, and `Seq1` follows it
+            // when looping is enabled.
+            if start && seq0_buffer.is_none() {
                 self.start_seq(Seq::Seq1);
             }
         } else {
@@ -728,6 +817,20 @@ struct Inner<T: Instance, B0, B1> {
     pwm: Pwm<T>,
 }
 
+impl<T: Instance, B0, B1> Deref for PwmSeq<T, B0, B1> {
+    type Target = Pwm<T>;
+
+    /// Gives access to the PWM during the playback, e.g. to configure interrupts or to get PPI
+    /// endpoints.
+    fn deref(&self) -> &Pwm<T> {
+        &self
+            .inner
+            .as_ref()
+            .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() })
+            .pwm
+    }
+}
+
 impl<T: Instance, B0, B1> PwmSeq<T, B0, B1>
 where
     B0: ReadBuffer<Word = u16> + 'static,
@@ -822,6 +925,83 @@ impl<T: Instance> embedded_hal::Pwm for Pwm<T> {
     }
 }
 
+#[cfg(feature = "embedded-hal-async")]
+impl<T: Instance> Pwm<T> {
+    /// Handles the PWM interrupt for `PwmSeq::wait` and `Pwm::play`. Must be called from the
+    /// interrupt handler of the PWM, whose interrupt must be unmasked in the NVIC.
+    pub fn on_interrupt() {
+        let pwm = unsafe { &*T::ptr() };
+        pwm.intenclr.write(|w| {
+            w.seqend0().clear();
+            w.seqend1().clear();
+            w.loopsdone().clear();
+            w.stopped().clear()
+        });
+        if let Some(waker) = cortex_m::interrupt::free(|cs| T::waker().borrow(cs).take()) {
+            waker.wake();
+        }
+    }
+
+    /// Loads the given sequence buffers, plays them, and waits until the playback finishes.
+    ///
+    /// See `PwmSeq::wait` for when the playback is considered finished.
+    pub async fn play<B0, B1>(
+        self,
+        seq0_buffer: Option<B0>,
+        seq1_buffer: Option<B1>,
+    ) -> Result<PwmSeq<T, B0, B1>, (Error, Pwm<T>, Option<B0>, Option<B1>)>
+    where
+        B0: ReadBuffer<Word = u16> + 'static,
+        B1: ReadBuffer<Word = u16> + 'static,
+    {
+        self.reset_event(PwmEvent::LoopsDone);
+        self.reset_event(PwmEvent::Stopped);
+        let seq = self.load(seq0_buffer, seq1_buffer, true)?;
+        seq.wait().await;
+        Ok(seq)
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<T: Instance, B0, B1> PwmSeq<T, B0, B1> {
+    /// Waits until the playback finishes, without polling.
+    ///
+    /// With looping enabled, the playback finishes after the last loop. Otherwise, it finishes at
+    /// the end of the played sequence. With `loop_inf`, it only finishes when the PWM is stopped,
+    /// e.g. from another task or through PPI.
+    ///
+    /// `Pwm::on_interrupt` must be called from the interrupt handler of the PWM.
+    pub async fn wait(&self) {
+        let done = if self.pwm.shorts.read().loopsdone_seqstart0().is_enabled() {
+            PwmEvent::Stopped
+        } else if self.pwm.loop_.read().cnt().bits() != 0 {
+            PwmEvent::LoopsDone
+        } else if self
+            .inner
+            .as_ref()
+            .is_some_and(|inner| inner.seq0_buffer.is_some())
+        {
+            PwmEvent::SeqEnd(Seq::Seq0)
+        } else {
+            PwmEvent::SeqEnd(Seq::Seq1)
+        };
+
+        core::future::poll_fn(|cx| {
+            cortex_m::interrupt::free(|cs| {
+                T::waker().borrow(cs).replace(Some(cx.waker().clone()));
+            });
+            if self.is_event_triggered(done) || self.is_event_triggered(PwmEvent::Stopped) {
+                core::task::Poll::Ready(())
+            } else {
+                self.enable_interrupt(done);
+                self.enable_interrupt(PwmEvent::Stopped);
+                core::task::Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
 /// PWM channel
 #[derive(Debug)]
 pub struct PwmChannel<'a, T: Instance> {
@@ -886,6 +1066,115 @@ impl<'a, T: Instance> embedded_hal::PwmPin for PwmChannel<'a, T> {
     }
 }
 
+impl<'a, T: Instance> embedded_hal_1::pwm::ErrorType for PwmChannel<'a, T> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'a, T: Instance> embedded_hal_1::pwm::SetDutyCycle for PwmChannel<'a, T> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.max_duty()
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.set_duty_on(duty);
+        Ok(())
+    }
+}
+
+/// A hobby servo driven by a PWM channel.
+///
+/// Servos expect a pulse every 20 ms, whose width sets their position: usually from 1 ms for one
+/// end of their range to 2 ms for the other one. Since the period is shared by all the channels
+/// of a PWM instance, the other channels can only drive servos too.
+///
+/// ```ignore
+/// pwm.set_output_pin(Channel::C0, &servo_pin);
+/// let (ch0, _, _, _) = pwm.split_channels();
+/// let mut servo = Servo::new(ch0);
+/// servo.set_pulse_range(500, 2_500);
+/// servo.set_angle(90);
+/// ```
+#[derive(Debug)]
+pub struct Servo<'a, T: Instance> {
+    channel: PwmChannel<'a, T>,
+    min_pulse_us: u16,
+    max_pulse_us: u16,
+    range_deg: u16,
+}
+
+impl<'a, T: Instance> Servo<'a, T> {
+    /// Pulse period in microseconds, for 50 Hz.
+    pub const PERIOD_US: u16 = 20_000;
+
+    /// Configures the PWM instance of the channel for 50 Hz pulses with a 1 µs resolution, and
+    /// uses the channel for a servo with a 1000 to 2000 µs pulse range covering 180 degrees.
+    ///
+    /// The servo isn't driven until a position is set.
+    pub fn new(channel: PwmChannel<'a, T>) -> Self {
+        channel
+            .pwm
+            .set_counter_mode(CounterMode::Up)
+            .set_prescaler(Prescaler::Div16)
+            .set_max_duty(Self::PERIOD_US);
+        Self {
+            channel,
+            min_pulse_us: 1_000,
+            max_pulse_us: 2_000,
+            range_deg: 180,
+        }
+    }
+
+    /// Sets the pulse widths in microseconds for the two ends of the range of the servo.
+    ///
+    /// `min_pulse_us` can be greater than `max_pulse_us` to reverse the direction.
+    pub fn set_pulse_range(&mut self, min_pulse_us: u16, max_pulse_us: u16) {
+        self.min_pulse_us = min_pulse_us.min(Self::PERIOD_US);
+        self.max_pulse_us = max_pulse_us.min(Self::PERIOD_US);
+    }
+
+    /// Sets the angle in degrees between the two ends of the range of the servo.
+    pub fn set_angle_range(&mut self, range_deg: u16) {
+        self.range_deg = range_deg.max(1);
+    }
+
+    /// Sets the width of the pulses in microseconds.
+    pub fn set_pulse_width_us(&mut self, width_us: u16) {
+        // The output is high for `width_us` at the start of each period when the polarity bit
+        // is set, which `set_duty_off` does.
+        self.channel.set_duty_off(width_us.min(Self::PERIOD_US));
+    }
+
+    /// Returns the width of the pulses in microseconds.
+    pub fn pulse_width_us(&self) -> u16 {
+        self.channel.duty_off()
+    }
+
+    /// Moves the servo to `angle_deg` degrees from the end of its range with the minimum pulse
+    /// width. Angles beyond the range are clamped to its end.
+    pub fn set_angle(&mut self, angle_deg: u16) {
+        let angle = i32::from(angle_deg.min(self.range_deg));
+        let min = i32::from(self.min_pulse_us);
+        let max = i32::from(self.max_pulse_us);
+        let width = min + (max - min) * angle / i32::from(self.range_deg);
+        self.set_pulse_width_us(width as u16);
+    }
+
+    /// Stops driving the servo, which then stops holding its position.
+    pub fn disable(&mut self) {
+        self.channel.disable();
+    }
+
+    /// Drives the servo again after `disable`.
+    pub fn enable(&mut self) {
+        self.channel.enable();
+    }
+
+    /// Releases the PWM channel.
+    pub fn free(self) -> PwmChannel<'a, T> {
+        self.channel
+    }
+}
+
 /// PWM group
 #[derive(Debug)]
 pub struct PwmGroup<'a, T: Instance> {
@@ -949,6 +1238,21 @@ impl<'a, T: Instance> embedded_hal::PwmPin for PwmGroup<'a, T> {
     }
 }
 
+impl<'a, T: Instance> embedded_hal_1::pwm::ErrorType for PwmGroup<'a, T> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'a, T: Instance> embedded_hal_1::pwm::SetDutyCycle for PwmGroup<'a, T> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.max_duty()
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.set_duty_on(duty);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum Channel {
     C0,
@@ -973,13 +1277,31 @@ impl From<Group> for usize {
     }
 }
 
+/// How the values of a sequence are loaded into the channels.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum LoadMode {
+    /// One value per step, used for all 4 channels.
     Common,
+    /// Two values per step, the first one used for channels 0 and 1, the second one for channels
+    /// 2 and 3.
     Grouped,
+    /// Four values per step, one per channel.
     Individual,
+    /// Four values per step: one for each of channels 0 to 2, followed by the maximum duty cycle
+    /// value of the step, which sets its period. Channel 3 is not available.
     Waveform,
 }
+impl LoadMode {
+    /// Returns the number of sequence values used for each step, so that sequences can be packed
+    /// accordingly.
+    pub fn values_per_step(self) -> usize {
+        match self {
+            LoadMode::Common => 1,
+            LoadMode::Grouped => 2,
+            LoadMode::Individual | LoadMode::Waveform => 4,
+        }
+    }
+}
 impl From<LoadMode> for u8 {
     fn from(variant: LoadMode) -> Self {
         variant as _
@@ -1014,9 +1336,13 @@ impl From<Prescaler> for u8 {
     }
 }
 
+/// Operating mode of the wave counter.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum CounterMode {
+    /// Edge-aligned PWM: the counter counts up to the maximum duty cycle value, then restarts.
     Up,
+    /// Center-aligned PWM: the counter counts up to the maximum duty cycle value, then back down,
+    /// which halves the output frequency.
     UpAndDown,
 }
 impl From<CounterMode> for bool {
@@ -1064,13 +1390,30 @@ pub enum Error {
     BufferTooLong,
 }
 
-pub trait Instance: sealed::Sealed + Deref<Target = crate::pac::pwm0::RegisterBlock> {
+pub trait Instance: sealed::Sealed + Deref<Target = RegisterBlock> {
     const INTERRUPT: Interrupt;
 
     /// Provides access to the associated internal duty buffer for the instance.
     fn buffer() -> &'static Cell<[u16; 4]>;
+
+    /// Returns a pointer to the register block.
+    fn ptr() -> *const RegisterBlock;
+
+    /// Provides access to the waker of the task waiting for the instance.
+    #[cfg(feature = "embedded-hal-async")]
+    fn waker() -> &'static WakerSlot;
 }
 
+#[cfg(feature = "embedded-hal-async")]
+type WakerSlot = cortex_m::interrupt::Mutex<core::cell::RefCell<Option<core::task::Waker>>>;
+
+// Internal static wakers. One per instance.
+#[cfg(feature = "embedded-hal-async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: WakerSlot = cortex_m::interrupt::Mutex::new(core::cell::RefCell::new(None));
+#[cfg(feature = "embedded-hal-async")]
+static WAKERS: [WakerSlot; 4] = [NO_WAKER; 4];
+
 // Internal static duty buffers. One per instance.
 static mut BUF0: Cell<[u16; 4]> = Cell::new([0; 4]);
 #[cfg(not(any(feature = "52810", feature = "52811")))]
@@ -1086,6 +1429,15 @@ impl Instance for PWM0 {
     fn buffer() -> &'static Cell<[u16; 4]> {
          unsafe { &BUF0 }
     }
+
+    fn ptr() -> *const RegisterBlock {
+        PWM0::ptr()
+    }
+
+    #[cfg(feature = "embedded-hal-async")]
+    fn waker() -> &'static WakerSlot {
+        &WAKERS[0]
+    }
 }
 
 #[cfg(not(any(feature = "52810", feature = "52811")))]
@@ -1094,6 +1446,15 @@ impl Instance for PWM1 {
     fn buffer() -> &'static Cell<[u16; 4]> {
          unsafe { &BUF1 }
     }
+
+    fn ptr() -> *const RegisterBlock {
+        PWM1::ptr()
+    }
+
+    #[cfg(feature = "embedded-hal-async")]
+    fn waker() -> &'static WakerSlot {
+        &WAKERS[1]
+    }
 }
 
 #[cfg(not(any(feature = "52810", feature = "52811")))]
@@ -1102,6 +1463,15 @@ impl Instance for PWM2 {
     fn buffer() -> &'static Cell<[u16; 4]> {
         unsafe { &BUF2 } 
     }
+
+    fn ptr() -> *const RegisterBlock {
+        PWM2::ptr()
+    }
+
+    #[cfg(feature = "embedded-hal-async")]
+    fn waker() -> &'static WakerSlot {
+        &WAKERS[2]
+    }
 }
 
 #[cfg(not(any(feature = "52810", feature = "52811", feature = "52832")))]
@@ -1110,6 +1480,15 @@ impl Instance for PWM3 {
     fn buffer() -> &'static Cell<[u16; 4]> {
         unsafe { &BUF3 } 
     }
+
+    fn ptr() -> *const RegisterBlock {
+        PWM3::ptr()
+    }
+
+    #[cfg(feature = "embedded-hal-async")]
+    fn waker() -> &'static WakerSlot {
+        &WAKERS[3]
+    }
 }
 
 mod sealed {
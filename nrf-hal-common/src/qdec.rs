@@ -2,12 +2,20 @@
 //!
 //! The Quadrature decoder (QDEC) provides buffered decoding of quadrature-encoded sensor signals.
 //! It is suitable for mechanical and optical sensors.
+//!
+//! Optical and reflective sensors can have their emitter driven by the LED output, which the QDEC
+//! switches on `led_pre` microseconds before each sample and off after it. The LED pin must be
+//! configured as an output at its inactive level, which it keeps while the QDEC is disabled.
 
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::gpio::Port;
 use {
-    crate::gpio::{Input, Pin, PullUp},
-    crate::pac::QDEC,
+    crate::gpio::{Input, Output, Pin, PullUp, PushPull},
+    crate::pac::{
+        generic::Reg,
+        qdec::{_EVENTS_ACCOF, _EVENTS_REPORTRDY, _TASKS_READCLRACC},
+        QDEC,
+    },
 };
 
 /// A safe wrapper around the `QDEC` peripheral with associated pins.
@@ -15,16 +23,19 @@ pub struct Qdec {
     qdec: QDEC,
     pin_a: Pin<Input<PullUp>>,
     pin_b: Pin<Input<PullUp>>,
-    pin_led: Option<Pin<Input<PullUp>>>,
+    pin_led: Option<Pin<Output<PushPull>>>,
 }
 
 impl Qdec {
     /// Takes ownership of the `QDEC` peripheral and associated pins, returning a safe wrapper.
+    ///
+    /// `pin_led` is the optional LED output, which is active high by default, see
+    /// `led_polarity`.
     pub fn new(
         qdec: QDEC,
         pin_a: Pin<Input<PullUp>>,
         pin_b: Pin<Input<PullUp>>,
-        pin_led: Option<Pin<Input<PullUp>>>,
+        pin_led: Option<Pin<Output<PushPull>>>,
         sample_period: SamplePeriod,
     ) -> Self {
         qdec.psel.a.write(|w| {
@@ -90,7 +101,10 @@ impl Qdec {
         self
     }
 
-    /// LED output pin polarity.
+    /// Sets the level of the LED output while the LED is switched ON.
+    ///
+    /// The level of the LED pin should be set to the opposite level, so that the LED stays off
+    /// while the QDEC is disabled.
     #[inline(always)]
     pub fn led_polarity(&self, polarity: LedPolarity) -> &Self {
         self.qdec.ledpol.write(|w| match polarity {
@@ -101,6 +115,9 @@ impl Qdec {
     }
 
     /// Time period the LED is switched ON prior to sampling (0..511 us).
+    ///
+    /// Must be shorter than the sample period, and long enough for the sensor output to settle
+    /// after the LED is switched ON. Defaults to 16 us.
     #[inline(always)]
     pub fn led_pre(&self, usecs: u16) -> &Self {
         self.qdec
@@ -162,6 +179,54 @@ impl Qdec {
         self.qdec.accread.read().bits() as i16
     }
 
+    /// Returns the number of double transitions, i.e. samples where both A and B changed, until
+    /// the last `read` (0..15).
+    ///
+    /// Double transitions are not counted in the accumulated change, since their direction is
+    /// unknown. They indicate that the sample period is too long for the rotation speed.
+    #[inline(always)]
+    pub fn double_transitions(&self) -> u8 {
+        self.qdec.accdblread.read().accdblread().bits()
+    }
+
+    /// Returns `true` if the accumulator overflowed since the last call, and clears the
+    /// overflow.
+    ///
+    /// The accumulator stops counting when it is full, so changes are lost on overflow. Calling
+    /// `read` more often, e.g. on the `REPORTRDY` interrupt, avoids overflows.
+    #[inline(always)]
+    pub fn overflowed(&self) -> bool {
+        let overflowed = self.qdec.events_accof.read().bits() != 0;
+        self.qdec.events_accof.reset();
+        overflowed
+    }
+
+    /// Checks if a report is ready, i.e. the number of samples given to `enable_interrupt` has
+    /// been reached.
+    #[inline(always)]
+    pub fn is_report_ready(&self) -> bool {
+        self.qdec.events_reportrdy.read().bits() != 0
+    }
+
+    /// Returns reference to the `REPORTRDY` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_report_ready(&self) -> &Reg<u32, _EVENTS_REPORTRDY> {
+        &self.qdec.events_reportrdy
+    }
+
+    /// Returns reference to the `ACCOF` event endpoint for PPI, which is generated when the
+    /// accumulator overflows.
+    #[inline(always)]
+    pub fn event_overflow(&self) -> &Reg<u32, _EVENTS_ACCOF> {
+        &self.qdec.events_accof
+    }
+
+    /// Returns reference to the `READCLRACC` task endpoint for PPI.
+    #[inline(always)]
+    pub fn task_read_clear_acc(&self) -> &Reg<u32, _TASKS_READCLRACC> {
+        &self.qdec.tasks_readclracc
+    }
+
     /// Consumes `self` and returns back the raw `QDEC` peripheral.
     #[inline]
     pub fn free(
@@ -170,7 +235,7 @@ impl Qdec {
         QDEC,
         Pin<Input<PullUp>>,
         Pin<Input<PullUp>>,
-        Option<Pin<Input<PullUp>>>,
+        Option<Pin<Output<PushPull>>>,
     ) {
         (self.qdec, self.pin_a, self.pin_b, self.pin_led)
     }
@@ -204,6 +269,7 @@ pub enum NumSamples {
     _1smpl,
 }
 
+/// Level of the LED output while the LED is switched ON.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum LedPolarity {
     ActiveHigh,
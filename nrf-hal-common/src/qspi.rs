@@ -0,0 +1,614 @@
+//! HAL interface to the QSPI peripheral, for external flash memories.
+//!
+//! `Qspi` reads, writes and erases a serial NOR flash memory with EasyDMA, using one, two or
+//! four data lines, and implements the `embedded-storage` NOR flash traits on it:
+//!
+//! ```ignore
+//! let pins = qspi::Pins {
+//!     sck: port0.p0_19.into_push_pull_output(Level::High).degrade(),
+//!     csn: port0.p0_17.into_push_pull_output(Level::High).degrade(),
+//!     io0: port0.p0_20.into_push_pull_output(Level::High).degrade(),
+//!     io1: port0.p0_21.into_push_pull_output(Level::High).degrade(),
+//!     io2: Some(port0.p0_22.into_push_pull_output(Level::High).degrade()),
+//!     io3: Some(port0.p0_23.into_push_pull_output(Level::High).degrade()),
+//! };
+//! let config = qspi::Config {
+//!     capacity: 8 * 1024 * 1024,
+//!     ..Default::default()
+//! };
+//! let mut flash = Qspi::new(p.QSPI, pins, config)?;
+//! flash.erase(0, 4096)?;
+//! flash.write(0, &data)?;
+//! ```
+//!
+//! The four data line modes need the quad enable (QE) bit of the flash to be set, which is
+//! specific to each flash memory. It is set with custom instructions, e.g. for Macronix flash
+//! memories, where it is bit 6 of the status register:
+//!
+//! ```ignore
+//! let mut status = [0];
+//! flash.custom_instruction(opcode::READ_STATUS, &[], &mut status)?;
+//! flash.custom_instruction(opcode::WRITE_ENABLE, &[], &mut [])?;
+//! flash.custom_instruction(opcode::WRITE_STATUS, &[status[0] | 0x40], &mut [])?;
+//! ```
+//!
+//! The flash memory can be put in deep power-down mode while it is unused, which reduces its
+//! current consumption to a few microamperes:
+//!
+//! ```ignore
+//! // Durations from the datasheet of the flash memory.
+//! flash.configure_deep_power_down(10, 35);
+//! flash.enter_deep_power_down();
+//! // ...
+//! flash.exit_deep_power_down();
+//! ```
+//!
+//! While the QSPI peripheral is active, the flash memory is also mapped for execute in place
+//! (XIP) at `XIP_BASE`, from an offset set with `set_xip_offset`, so that code or large assets
+//! can be placed in it:
+//!
+//! ```ignore
+//! flash.set_xip_offset(0)?;
+//! let assets: &[u8] = flash.xip_memory();
+//! ```
+//!
+//! Flash memories larger than 16 MB need 32-bit addresses, and usually an instruction to enter
+//! the 32-bit addressing mode, which is set in `Config::address_config` and sent when the
+//! peripheral is activated.
+
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::gpio::{Output, Pin, PushPull};
+use crate::pac::{qspi, QSPI};
+use crate::slice_in_ram;
+
+pub use qspi::addrconf::MODE_A as AddressConfigMode;
+pub use qspi::ifconfig0::{
+    ADDRMODE_A as AddressMode, PPSIZE_A as PageSize, READOC_A as ReadOpcode,
+    WRITEOC_A as WriteOpcode,
+};
+pub use qspi::ifconfig1::SPIMODE_A as SpiMode;
+
+/// Start address of the execute in place (XIP) region.
+pub const XIP_BASE: usize = 0x1200_0000;
+
+/// Size of the execute in place (XIP) region.
+pub const XIP_SIZE: usize = 0x0800_0000;
+
+/// Size of a sector, the smallest area of the flash that can be erased.
+const SECTOR_SIZE: usize = 4096;
+
+/// Size of a block, the area of the flash erased at once when aligned.
+const BLOCK_SIZE: usize = 65536;
+
+/// Maximum length of an EasyDMA transfer.
+const MAX_TRANSFER: usize = 0x1F_FFFC;
+
+/// Size of the buffer used for transfers from or to buffers that EasyDMA can't access.
+const BOUNCE_SIZE: usize = 256;
+
+/// Maximum number of data bytes sent or received with a custom instruction.
+pub const MAX_CUSTOM_DATA: usize = 8;
+
+/// Opcodes of common flash memory instructions, for use with `Qspi::custom_instruction`.
+pub mod opcode {
+    /// Write enable, needed before instructions that modify the flash memory.
+    pub const WRITE_ENABLE: u8 = 0x06;
+    /// Reads the status register.
+    pub const READ_STATUS: u8 = 0x05;
+    /// Writes the status register.
+    pub const WRITE_STATUS: u8 = 0x01;
+    /// Reads the manufacturer and device identification.
+    pub const READ_JEDEC_ID: u8 = 0x9F;
+    /// Enters deep power-down mode.
+    pub const DEEP_POWER_DOWN: u8 = 0xB9;
+    /// Exits deep power-down mode.
+    pub const RELEASE_DEEP_POWER_DOWN: u8 = 0xAB;
+}
+
+/// QSPI pins.
+pub struct Pins {
+    /// Serial clock.
+    pub sck: Pin<Output<PushPull>>,
+    /// Chip select.
+    pub csn: Pin<Output<PushPull>>,
+    /// Data line 0, also used as MOSI in single line mode.
+    pub io0: Pin<Output<PushPull>>,
+    /// Data line 1, also used as MISO in single line mode.
+    pub io1: Pin<Output<PushPull>>,
+    /// Data line 2, only used in four line modes. It must be driven high externally if unused.
+    pub io2: Option<Pin<Output<PushPull>>>,
+    /// Data line 3, only used in four line modes. It must be driven high externally if unused.
+    pub io3: Option<Pin<Output<PushPull>>>,
+}
+
+/// Configuration of the QSPI interface and of the commands of the flash memory.
+#[derive(Debug, Copy, Clone)]
+pub struct Config {
+    /// Size of the flash memory in bytes.
+    pub capacity: usize,
+    /// Command and data lines used for reads.
+    pub read_opcode: ReadOpcode,
+    /// Command and data lines used for page programs.
+    pub write_opcode: WriteOpcode,
+    /// 24-bit or 32-bit addressing.
+    pub address_mode: AddressMode,
+    /// Page size of the flash memory.
+    pub page_size: PageSize,
+    /// SPI mode of the interface.
+    pub spi_mode: SpiMode,
+    /// SCK frequency divider: SCK runs at 32 MHz / (`sck_divider` + 1), from 0 to 15.
+    pub sck_divider: u8,
+    /// Minimum time the chip select stays high between commands, in 62.5 ns units.
+    pub sck_delay: u8,
+    /// Instruction sent to enter the 32-bit addressing mode when the peripheral is activated.
+    pub address_config: AddressConfig,
+}
+
+impl Default for Config {
+    /// Single line commands at 8 MHz with 24-bit addresses, supported by all flash memories.
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            read_opcode: ReadOpcode::FASTREAD,
+            write_opcode: WriteOpcode::PP,
+            address_mode: AddressMode::_24BIT,
+            page_size: PageSize::_256BYTES,
+            spi_mode: SpiMode::MODE0,
+            sck_divider: 3,
+            sck_delay: 1,
+            address_config: AddressConfig::default(),
+        }
+    }
+}
+
+/// Instruction that makes the flash memory enter the 32-bit addressing mode.
+#[derive(Debug, Copy, Clone)]
+pub struct AddressConfig {
+    /// Opcode of the instruction.
+    pub opcode: u8,
+    /// First byte sent after the opcode.
+    pub byte0: u8,
+    /// Second byte sent after the opcode.
+    pub byte1: u8,
+    /// Which of the opcode and bytes are sent, if any.
+    pub mode: AddressConfigMode,
+    /// Waits for the end of any write operation of the flash memory before the instruction.
+    pub wait_for_write: bool,
+    /// Sends a write enable instruction before the instruction.
+    pub write_enable: bool,
+}
+
+impl Default for AddressConfig {
+    /// No instruction. The 0xB7 opcode is used by most flash memories, if enabled by `mode`.
+    fn default() -> Self {
+        Self {
+            opcode: 0xB7,
+            byte0: 0,
+            byte1: 0,
+            mode: AddressConfigMode::NOINSTR,
+            wait_for_write: false,
+            write_enable: false,
+        }
+    }
+}
+
+/// QSPI error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// A configuration uses four data lines, but IO2 or IO3 is missing.
+    MissingPins,
+    /// More than `MAX_CUSTOM_DATA` bytes were passed to a custom instruction.
+    TooMuchData,
+    /// The address or length is not aligned to the read, write or erase size.
+    Unaligned,
+    /// The operation extends past the end of the flash memory.
+    OutOfBounds,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Error::MissingPins | Error::TooMuchData => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// Word aligned buffer for EasyDMA transfers.
+#[repr(align(4))]
+struct Bounce([u8; BOUNCE_SIZE]);
+
+/// Interface to an external flash memory through the QSPI peripheral.
+pub struct Qspi {
+    qspi: QSPI,
+    pins: Pins,
+    capacity: usize,
+}
+
+impl Qspi {
+    /// Configures the QSPI peripheral and activates it.
+    pub fn new(qspi: QSPI, pins: Pins, config: Config) -> Result<Self, Error> {
+        let quad = matches!(config.read_opcode, ReadOpcode::READ4O | ReadOpcode::READ4IO)
+            || matches!(config.write_opcode, WriteOpcode::PP4O | WriteOpcode::PP4IO);
+        if quad && (pins.io2.is_none() || pins.io3.is_none()) {
+            return Err(Error::MissingPins);
+        }
+
+        qspi.psel.sck.write(|w| {
+            unsafe { w.pin().bits(pins.sck.pin()) };
+            w.port().bit(pins.sck.port().bit());
+            w.connect().connected()
+        });
+        qspi.psel.csn.write(|w| {
+            unsafe { w.pin().bits(pins.csn.pin()) };
+            w.port().bit(pins.csn.port().bit());
+            w.connect().connected()
+        });
+        qspi.psel.io0.write(|w| {
+            unsafe { w.pin().bits(pins.io0.pin()) };
+            w.port().bit(pins.io0.port().bit());
+            w.connect().connected()
+        });
+        qspi.psel.io1.write(|w| {
+            unsafe { w.pin().bits(pins.io1.pin()) };
+            w.port().bit(pins.io1.port().bit());
+            w.connect().connected()
+        });
+        match &pins.io2 {
+            Some(io2) => qspi.psel.io2.write(|w| {
+                unsafe { w.pin().bits(io2.pin()) };
+                w.port().bit(io2.port().bit());
+                w.connect().connected()
+            }),
+            None => qspi.psel.io2.write(|w| w.connect().disconnected()),
+        }
+        match &pins.io3 {
+            Some(io3) => qspi.psel.io3.write(|w| {
+                unsafe { w.pin().bits(io3.pin()) };
+                w.port().bit(io3.port().bit());
+                w.connect().connected()
+            }),
+            None => qspi.psel.io3.write(|w| w.connect().disconnected()),
+        }
+
+        qspi.ifconfig0.write(|w| {
+            w.readoc().variant(config.read_opcode);
+            w.writeoc().variant(config.write_opcode);
+            w.addrmode().variant(config.address_mode);
+            w.ppsize().variant(config.page_size)
+        });
+        qspi.ifconfig1.write(|w| {
+            unsafe { w.sckdelay().bits(config.sck_delay) };
+            unsafe { w.sckfreq().bits(config.sck_divider.min(15)) };
+            w.spimode().variant(config.spi_mode);
+            w.dpmen().exit()
+        });
+
+        let address_config = config.address_config;
+        qspi.addrconf.write(|w| {
+            unsafe { w.opcode().bits(address_config.opcode) };
+            unsafe { w.byte0().bits(address_config.byte0) };
+            unsafe { w.byte1().bits(address_config.byte1) };
+            w.mode().variant(address_config.mode);
+            w.wipwait().bit(address_config.wait_for_write);
+            w.wren().bit(address_config.write_enable)
+        });
+
+        qspi.intenclr.write(|w| w.ready().clear());
+        qspi.enable.write(|w| w.enable().enabled());
+
+        let qspi = Self {
+            qspi,
+            pins,
+            capacity: config.capacity,
+        };
+        qspi.prepare();
+        qspi.qspi.tasks_activate.write(|w| unsafe { w.bits(1) });
+        qspi.wait_ready();
+
+        Ok(qspi)
+    }
+
+    /// Returns `true` if the QSPI peripheral is ready for a new operation.
+    pub fn is_ready(&self) -> bool {
+        self.qspi.status.read().ready().is_ready()
+    }
+
+    /// Reads `buffer.len()` bytes from the flash at `address`.
+    ///
+    /// The address and the length must be multiples of 4.
+    pub fn read_flash(&mut self, address: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        self.check(address, buffer.len(), 4)?;
+
+        if Self::is_dma_accessible(buffer) {
+            for (i, chunk) in buffer.chunks_mut(MAX_TRANSFER).enumerate() {
+                self.read_dma(address + i * MAX_TRANSFER, chunk);
+            }
+        } else {
+            let mut bounce = Bounce([0; BOUNCE_SIZE]);
+            for (i, chunk) in buffer.chunks_mut(BOUNCE_SIZE).enumerate() {
+                let bounce = &mut bounce.0[..chunk.len()];
+                self.read_dma(address + i * BOUNCE_SIZE, bounce);
+                chunk.copy_from_slice(bounce);
+            }
+        }
+        Ok(())
+    }
+
+    /// Programs `data` into the flash at `address`. The flash must have been erased before.
+    ///
+    /// The address and the length must be multiples of 4.
+    pub fn write_flash(&mut self, address: usize, data: &[u8]) -> Result<(), Error> {
+        self.check(address, data.len(), 4)?;
+
+        if Self::is_dma_accessible(data) {
+            for (i, chunk) in data.chunks(MAX_TRANSFER).enumerate() {
+                self.write_dma(address + i * MAX_TRANSFER, chunk);
+            }
+        } else {
+            let mut bounce = Bounce([0; BOUNCE_SIZE]);
+            for (i, chunk) in data.chunks(BOUNCE_SIZE).enumerate() {
+                let bounce = &mut bounce.0[..chunk.len()];
+                bounce.copy_from_slice(chunk);
+                self.write_dma(address + i * BOUNCE_SIZE, bounce);
+            }
+        }
+        Ok(())
+    }
+
+    /// Erases the flash from `from` to `to`, which must be multiples of 4 kB.
+    ///
+    /// Aligned 64 kB blocks are erased at once, which is faster than erasing their sectors.
+    pub fn erase_flash(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        if from > to {
+            return Err(Error::OutOfBounds);
+        }
+        self.check(from, to - from, SECTOR_SIZE)?;
+
+        let mut address = from;
+        while address < to {
+            let block = address & (BLOCK_SIZE - 1) == 0 && to - address >= BLOCK_SIZE;
+            self.qspi
+                .erase
+                .ptr
+                .write(|w| unsafe { w.ptr().bits(address as u32) });
+            if block {
+                self.qspi.erase.len.write(|w| w.len()._64kb());
+            } else {
+                self.qspi.erase.len.write(|w| w.len()._4kb());
+            }
+            self.prepare();
+            self.qspi.tasks_erasestart.write(|w| unsafe { w.bits(1) });
+            self.wait_ready();
+            address += if block { BLOCK_SIZE } else { SECTOR_SIZE };
+        }
+        Ok(())
+    }
+
+    /// Erases the whole flash memory.
+    pub fn erase_chip(&mut self) {
+        self.qspi.erase.ptr.write(|w| unsafe { w.ptr().bits(0) });
+        self.qspi.erase.len.write(|w| w.len().all());
+        self.prepare();
+        self.qspi.tasks_erasestart.write(|w| unsafe { w.bits(1) });
+        self.wait_ready();
+    }
+
+    /// Maps the flash memory from `offset` at the start of the XIP region. `offset` must be a
+    /// multiple of 4.
+    pub fn set_xip_offset(&mut self, offset: usize) -> Result<(), Error> {
+        self.check(offset, 0, 4)?;
+        self.qspi
+            .xipoffset
+            .write(|w| unsafe { w.xipoffset().bits(offset as u32) });
+        Ok(())
+    }
+
+    /// Returns the flash memory mapped in the XIP region, from the XIP offset to the end of the
+    /// flash memory, or of the XIP region.
+    pub fn xip_memory(&self) -> &[u8] {
+        let offset = self.qspi.xipoffset.read().bits() as usize;
+        let len = self.capacity.saturating_sub(offset).min(XIP_SIZE);
+        // NOTE(unsafe) The XIP region is mapped while the peripheral is active, and the flash
+        // memory is only modified through `&mut self`.
+        unsafe { core::slice::from_raw_parts(XIP_BASE as *const u8, len) }
+    }
+
+    /// Sends the custom instruction `opcode` followed by the bytes of `tx`, and receives
+    /// `rx.len()` bytes after it.
+    ///
+    /// The data is sent and received on a single line, and the number of bytes transferred after
+    /// the opcode is the largest of `tx.len()` and `rx.len()`, up to `MAX_CUSTOM_DATA`.
+    pub fn custom_instruction(
+        &mut self,
+        opcode: u8,
+        tx: &[u8],
+        rx: &mut [u8],
+    ) -> Result<(), Error> {
+        let len = tx.len().max(rx.len());
+        if len > MAX_CUSTOM_DATA {
+            return Err(Error::TooMuchData);
+        }
+
+        let mut data = [0; MAX_CUSTOM_DATA];
+        data[..tx.len()].copy_from_slice(tx);
+        let dat0 = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let dat1 = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        self.qspi.cinstrdat0.write(|w| unsafe { w.bits(dat0) });
+        self.qspi.cinstrdat1.write(|w| unsafe { w.bits(dat1) });
+
+        self.prepare();
+        // Keep IO2 and IO3, i.e. WP and HOLD in single line mode, high during the instruction.
+        self.qspi.cinstrconf.write(|w| {
+            unsafe { w.opcode().bits(opcode) };
+            unsafe { w.length().bits(len as u8 + 1) };
+            w.lio2().set_bit();
+            w.lio3().set_bit();
+            w.wipwait().disable();
+            w.wren().disable()
+        });
+        self.wait_ready();
+
+        let dat0 = self.qspi.cinstrdat0.read().bits().to_le_bytes();
+        let dat1 = self.qspi.cinstrdat1.read().bits().to_le_bytes();
+        data[..4].copy_from_slice(&dat0);
+        data[4..].copy_from_slice(&dat1);
+        rx.copy_from_slice(&data[..rx.len()]);
+        Ok(())
+    }
+
+    /// Reads the JEDEC manufacturer ID, memory type and capacity of the flash memory.
+    pub fn read_jedec_id(&mut self) -> Result<[u8; 3], Error> {
+        let mut id = [0; 3];
+        self.custom_instruction(opcode::READ_JEDEC_ID, &[], &mut id)?;
+        Ok(id)
+    }
+
+    /// Reads the status register of the flash memory.
+    pub fn read_status(&mut self) -> Result<u8, Error> {
+        let mut status = [0];
+        self.custom_instruction(opcode::READ_STATUS, &[], &mut status)?;
+        Ok(status[0])
+    }
+
+    /// Configures the deep power-down mode (DPM) of the flash memory, with the time it takes to
+    /// enter and exit it in microseconds, rounded up to 16 µs.
+    pub fn configure_deep_power_down(&mut self, enter_us: u32, exit_us: u32) {
+        let enter = enter_us.div_ceil(16).min(0xFFFF) as u16;
+        let exit = exit_us.div_ceil(16).min(0xFFFF) as u16;
+        self.qspi.dpmdur.write(|w| {
+            unsafe { w.enter().bits(enter) };
+            unsafe { w.exit().bits(exit) }
+        });
+        self.qspi.ifconfig0.modify(|_, w| w.dpmenable().enable());
+    }
+
+    /// Puts the flash memory in deep power-down mode, and waits until it has entered it.
+    ///
+    /// The deep power-down mode must have been configured with `configure_deep_power_down`.
+    pub fn enter_deep_power_down(&mut self) {
+        self.qspi.ifconfig1.modify(|_, w| w.dpmen().enter());
+        while self.qspi.status.read().dpm().is_disabled() {}
+    }
+
+    /// Wakes the flash memory from deep power-down mode, and waits until it has exited it.
+    pub fn exit_deep_power_down(&mut self) {
+        self.qspi.ifconfig1.modify(|_, w| w.dpmen().exit());
+        while self.qspi.status.read().dpm().is_enabled() {}
+    }
+
+    /// Returns `true` if the flash memory is in deep power-down mode.
+    pub fn is_deep_power_down(&self) -> bool {
+        self.qspi.status.read().dpm().is_enabled()
+    }
+
+    /// Deactivates and disables the QSPI peripheral, and releases it and its pins.
+    pub fn free(self) -> (QSPI, Pins) {
+        self.qspi.tasks_deactivate.write(|w| unsafe { w.bits(1) });
+        self.qspi.enable.write(|w| w.enable().disabled());
+        (self.qspi, self.pins)
+    }
+
+    fn check(&self, address: usize, len: usize, align: usize) -> Result<(), Error> {
+        if len > self.capacity || address > self.capacity - len {
+            return Err(Error::OutOfBounds);
+        }
+        if (address | len) & (align - 1) != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok(())
+    }
+
+    fn is_dma_accessible(buffer: &[u8]) -> bool {
+        buffer.as_ptr() as usize & 3 == 0 && slice_in_ram(buffer)
+    }
+
+    fn read_dma(&mut self, address: usize, buffer: &mut [u8]) {
+        self.qspi
+            .read
+            .src
+            .write(|w| unsafe { w.src().bits(address as u32) });
+        self.qspi
+            .read
+            .dst
+            .write(|w| unsafe { w.dst().bits(buffer.as_mut_ptr() as u32) });
+        self.qspi
+            .read
+            .cnt
+            .write(|w| unsafe { w.cnt().bits(buffer.len() as u32) });
+        self.prepare();
+        self.qspi.tasks_readstart.write(|w| unsafe { w.bits(1) });
+        self.wait_ready();
+    }
+
+    fn write_dma(&mut self, address: usize, data: &[u8]) {
+        self.qspi
+            .write
+            .dst
+            .write(|w| unsafe { w.dst().bits(address as u32) });
+        self.qspi
+            .write
+            .src
+            .write(|w| unsafe { w.src().bits(data.as_ptr() as u32) });
+        self.qspi
+            .write
+            .cnt
+            .write(|w| unsafe { w.cnt().bits(data.len() as u32) });
+        self.prepare();
+        self.qspi.tasks_writestart.write(|w| unsafe { w.bits(1) });
+        self.wait_ready();
+    }
+
+    /// Prepares the start of an operation.
+    fn prepare(&self) {
+        self.qspi.events_ready.reset();
+        // Conservative compiler fence to prevent optimizations that do not take in to account
+        // actions by DMA. The fence has been placed here, before any DMA action has started.
+        compiler_fence(SeqCst);
+    }
+
+    /// Waits for the end of the operation.
+    fn wait_ready(&self) {
+        while self.qspi.events_ready.read().bits() == 0 {}
+        self.qspi.events_ready.reset();
+        // Conservative compiler fence to prevent optimizations that do not take in to account
+        // actions by DMA. The fence has been placed here, after all possible DMA actions have
+        // completed.
+        compiler_fence(SeqCst);
+    }
+}
+
+impl ErrorType for Qspi {
+    type Error = Error;
+}
+
+impl ReadNorFlash for Qspi {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_flash(offset as usize, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl NorFlash for Qspi {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.erase_flash(from as usize, to as usize)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write_flash(offset as usize, bytes)
+    }
+}
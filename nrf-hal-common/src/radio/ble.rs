@@ -0,0 +1,225 @@
+//! Bluetooth Low Energy non-connectable advertiser, for beacons.
+//!
+//! `Advertiser` sends ADV_NONCONN_IND packets on the three primary advertising channels, 37, 38
+//! and 39, using the LE 1M PHY. It needs neither a BLE stack nor the SoftDevice:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let mut advertiser = Advertiser::new(p.RADIO, &clocks, address, AddressType::Random);
+//! // Flags, and complete local name "nRF".
+//! advertiser.set_data(&[0x02, 0x01, 0x06, 0x04, 0x09, b'n', b'R', b'F'])?;
+//! advertiser.set_interval(100)?;
+//!
+//! loop {
+//!     advertiser.advertise(&mut delay);
+//! }
+//! ```
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::TxPower;
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::RADIO;
+
+/// Maximum length of the advertising data.
+pub const MAX_DATA_LEN: usize = 31;
+
+/// Access address of the advertising channels.
+const ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+/// CRC polynomial x^24 + x^10 + x^9 + x^6 + x^4 + x^3 + x + 1.
+const CRC_POLY: u32 = 0x0000_065B;
+/// CRC initial value of the advertising channels.
+const CRC_INIT: u32 = 0x0055_5555;
+
+/// PDU type of non-connectable undirected advertising.
+const ADV_NONCONN_IND: u8 = 0b0010;
+/// Set in the PDU header if the advertiser address is random.
+const TX_ADD: u8 = 1 << 6;
+/// Length of the advertiser address.
+const ADDRESS_LEN: usize = 6;
+
+/// Advertising channels, with their frequency offsets from 2400 MHz.
+const CHANNELS: [(u8, u8); 3] = [(37, 2), (38, 26), (39, 80)];
+
+/// Minimum and maximum advertising intervals, in milliseconds.
+const INTERVAL_MS: core::ops::RangeInclusive<u32> = 20..=10_240;
+
+/// BLE advertiser error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The advertising data is longer than `MAX_DATA_LEN`.
+    DataTooLong,
+    /// The advertising interval is not between 20 ms and 10.24 s.
+    InvalidInterval,
+}
+
+/// Type of the advertiser address.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AddressType {
+    /// Public address, assigned by the IEEE.
+    Public,
+    /// Random address, e.g. the one in the DEVICEADDR registers of FICR.
+    Random,
+}
+
+/// BLE non-connectable advertiser.
+pub struct Advertiser {
+    radio: RADIO,
+    /// PDU header, length, advertiser address and advertising data.
+    pdu: [u8; 2 + ADDRESS_LEN + MAX_DATA_LEN],
+    interval_ms: u32,
+    /// State of the generator of the random delays added to the interval.
+    seed: u32,
+}
+
+impl Advertiser {
+    /// Configures the RADIO for BLE advertising, with the advertiser `address`, least significant
+    /// byte first, no advertising data and an interval of 100 ms.
+    ///
+    /// The external high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        _clocks: &Clocks<ExternalOscillator, L, LSTAT>,
+        address: [u8; ADDRESS_LEN],
+        address_type: AddressType,
+    ) -> Self {
+        // Power cycle the RADIO to reset its registers.
+        radio.power.write(|w| w.power().disabled());
+        radio.power.write(|w| w.power().enabled());
+
+        radio.mode.write(|w| w.mode().ble_1mbit());
+        // 1-byte header, 8-bit length and no S1 field.
+        radio.pcnf0.write(|w| unsafe {
+            w.lflen()
+                .bits(8)
+                .s0len()
+                .set_bit()
+                .s1len()
+                .bits(0)
+                .plen()
+                ._8bit()
+        });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits((ADDRESS_LEN + MAX_DATA_LEN) as u8)
+                .statlen()
+                .bits(0)
+                .balen()
+                .bits(3)
+                .endian()
+                .little()
+                .whiteen()
+                .enabled()
+        });
+        // The 3 most significant bytes of BASE0 are the base address when BALEN is 3.
+        radio
+            .base0
+            .write(|w| unsafe { w.bits(ACCESS_ADDRESS << 8) });
+        radio
+            .prefix0
+            .write(|w| unsafe { w.bits(ACCESS_ADDRESS >> 24) });
+        radio.txaddress.write(|w| unsafe { w.txaddress().bits(0) });
+        radio.crccnf.write(|w| w.len().three().skipaddr().skip());
+        radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(CRC_POLY) });
+        radio
+            .crcinit
+            .write(|w| unsafe { w.crcinit().bits(CRC_INIT) });
+        radio.txpower.write(|w| TxPower::ZerodBm.write(w));
+
+        let mut pdu = [0; 2 + ADDRESS_LEN + MAX_DATA_LEN];
+        pdu[0] = ADV_NONCONN_IND
+            | match address_type {
+                AddressType::Public => 0,
+                AddressType::Random => TX_ADD,
+            };
+        pdu[1] = ADDRESS_LEN as u8;
+        pdu[2..2 + ADDRESS_LEN].copy_from_slice(&address);
+
+        Self {
+            radio,
+            pdu,
+            interval_ms: 100,
+            seed: u32::from_le_bytes([address[0], address[1], address[2], address[3]]) | 1,
+        }
+    }
+
+    /// Sets the advertising data, a sequence of length, type and value structures.
+    pub fn set_data(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > MAX_DATA_LEN {
+            return Err(Error::DataTooLong);
+        }
+        self.pdu[1] = (ADDRESS_LEN + data.len()) as u8;
+        self.pdu[2 + ADDRESS_LEN..2 + ADDRESS_LEN + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Sets the advertising interval, from 20 ms to 10.24 s.
+    pub fn set_interval(&mut self, interval_ms: u32) -> Result<(), Error> {
+        if !INTERVAL_MS.contains(&interval_ms) {
+            return Err(Error::InvalidInterval);
+        }
+        self.interval_ms = interval_ms;
+        Ok(())
+    }
+
+    /// Sets the output power.
+    pub fn set_tx_power(&mut self, tx_power: TxPower) {
+        self.radio.txpower.write(|w| tx_power.write(w));
+    }
+
+    /// Sends the advertising packet once on each advertising channel.
+    pub fn advertise_once(&mut self) {
+        for &(index, frequency) in CHANNELS.iter() {
+            self.radio
+                .frequency
+                .write(|w| unsafe { w.frequency().bits(frequency) });
+            self.radio
+                .datawhiteiv
+                .write(|w| unsafe { w.datawhiteiv().bits(index) });
+            self.transmit();
+        }
+    }
+
+    /// Sends the advertising packet on each advertising channel, then waits for the advertising
+    /// interval plus a random delay of up to 10 ms, which avoids persistent collisions with other
+    /// advertisers.
+    pub fn advertise<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        self.advertise_once();
+        delay.delay_ms(self.interval_ms + self.random_delay_ms());
+    }
+
+    /// Releases the RADIO.
+    pub fn free(self) -> RADIO {
+        self.radio
+    }
+
+    /// Returns a pseudo-random delay from 0 to 10 ms.
+    fn random_delay_ms(&mut self) -> u32 {
+        // Xorshift generator.
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed % 11
+    }
+
+    fn transmit(&mut self) {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.pdu.as_ptr() as u32) });
+        self.radio.events_disabled.reset();
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+        // The PDU must be written before the RADIO reads it.
+        compiler_fence(Ordering::SeqCst);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        while self.radio.events_disabled.read().bits() == 0 {}
+        self.radio.events_disabled.reset();
+        self.radio.shorts.reset();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,489 @@
+//! Enhanced ShockBurst (ESB) driver for the RADIO.
+//!
+//! ESB is the proprietary protocol of Nordic's nRF24 transceivers and of the ESB library of the
+//! nRF5 SDK. A primary transmitter (PTX) sends packets to up to 8 pipes, i.e. addresses, of a
+//! primary receiver (PRX). The PRX acknowledges the packets, optionally with a payload, and the
+//! PTX retransmits the packets that have not been acknowledged.
+//!
+//! The packets use dynamic payload lengths of up to 32 bytes, a 2-bit packet ID, and a 16-bit
+//! CRC, which is compatible over the air with the nRF5 SDK and with nRF24 transceivers in
+//! dynamic payload length mode.
+//!
+//! The PTX waits for the acknowledgements with a TIMER, which a PPI channel stops as soon as an
+//! address is received, so that an acknowledgement is never cut by the timeout:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let ppi = ppi::Parts::new(p.PPI);
+//! let mut esb = Esb::new(p.RADIO, p.TIMER0, ppi.ppi0, &clocks, Config::default())?;
+//!
+//! // PTX
+//! let ack = esb.send(0, b"hello", true)?;
+//!
+//! // PRX
+//! esb.set_ack_payload(0, b"world")?;
+//! let packet = esb.recv()?;
+//! ```
+
+use core::ops::Deref;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_hal::timer::{Cancel, CountDown};
+
+use super::TxPower;
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::RADIO;
+use crate::ppi::ConfigurablePpi;
+use crate::timer::{self, OneShot, Timer};
+
+/// Maximum length of the payload of a packet.
+pub const MAX_PAYLOAD_LEN: u8 = 32;
+
+/// Number of pipes.
+pub const PIPES: usize = 8;
+
+/// Time the PTX waits for the address of an acknowledgement after sending a packet, in
+/// microseconds. It covers the turnaround of both RADIOs, and the preamble and address of the
+/// acknowledgement.
+const ACK_TIMEOUT_US: u32 = 250;
+
+/// ESB error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The pipe is not below `PIPES`.
+    InvalidPipe,
+    /// The payload is longer than `MAX_PAYLOAD_LEN`.
+    PayloadTooLong,
+    /// The channel is above 100.
+    InvalidChannel,
+    /// The address length is not between 3 and 5 bytes.
+    InvalidAddressLength,
+    /// The packet has not been acknowledged after all the retransmits.
+    MaxRetransmits,
+}
+
+/// On-air bitrate.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Bitrate {
+    /// 1 Mbit/s.
+    _1Mbps,
+    /// 2 Mbit/s.
+    _2Mbps,
+}
+
+/// Addresses of the pipes.
+///
+/// The address of pipe 0 is `base0` followed by `prefixes[0]`, and the address of pipe `n`, for
+/// `n` from 1 to 7, is `base1` followed by `prefixes[n]`. Only the first `length - 1` bytes of
+/// the base addresses are used.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Addresses {
+    /// Base address of pipe 0.
+    pub base0: [u8; 4],
+    /// Base address of pipes 1 to 7.
+    pub base1: [u8; 4],
+    /// Prefixes of the pipes.
+    pub prefixes: [u8; PIPES],
+    /// Length of the addresses, from 3 to 5 bytes.
+    pub length: u8,
+}
+
+impl Default for Addresses {
+    /// Returns the default addresses of the nRF5 SDK and nRF24 transceivers.
+    fn default() -> Self {
+        Self {
+            base0: [0xE7; 4],
+            base1: [0xC2; 4],
+            prefixes: [0xE7, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8],
+            length: 5,
+        }
+    }
+}
+
+/// ESB configuration.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Config {
+    /// On-air bitrate.
+    pub bitrate: Bitrate,
+    /// Channel, i.e. frequency in MHz above 2400 MHz, from 0 to 100.
+    pub channel: u8,
+    /// Output power.
+    pub tx_power: TxPower,
+    /// Addresses of the pipes.
+    pub addresses: Addresses,
+    /// Pipes on which the PRX receives, one bit per pipe.
+    pub rx_pipes: u8,
+    /// Delay between two transmissions of a packet that has not been acknowledged, in
+    /// microseconds.
+    pub retransmit_delay: u32,
+    /// Number of retransmits of a packet that has not been acknowledged.
+    pub retransmit_count: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bitrate: Bitrate::_2Mbps,
+            channel: 2,
+            tx_power: TxPower::ZerodBm,
+            addresses: Addresses::default(),
+            rx_pipes: 0xFF,
+            retransmit_delay: 250,
+            retransmit_count: 3,
+        }
+    }
+}
+
+/// ESB packet, in a buffer that the RADIO can access through EasyDMA.
+#[derive(Clone)]
+pub struct Packet {
+    /// Length of the payload, S1 field (packet ID and acknowledgement request), and payload.
+    buffer: [u8; 2 + MAX_PAYLOAD_LEN as usize],
+    pipe: u8,
+    rssi: u8,
+}
+
+impl Packet {
+    fn new() -> Self {
+        Self {
+            buffer: [0; 2 + MAX_PAYLOAD_LEN as usize],
+            pipe: 0,
+            rssi: 0,
+        }
+    }
+
+    fn with_payload(payload: &[u8]) -> Result<Self, Error> {
+        if payload.len() > MAX_PAYLOAD_LEN as usize {
+            return Err(Error::PayloadTooLong);
+        }
+        let mut packet = Self::new();
+        packet.buffer[0] = payload.len() as u8;
+        packet.buffer[2..2 + payload.len()].copy_from_slice(payload);
+        Ok(packet)
+    }
+
+    /// Returns the pipe on which the packet has been received.
+    pub fn pipe(&self) -> u8 {
+        self.pipe
+    }
+
+    /// Returns the received signal strength (RSSI) of the packet, in dBm.
+    pub fn rssi(&self) -> i8 {
+        -(self.rssi.min(127) as i8)
+    }
+
+    fn pid(&self) -> u8 {
+        (self.buffer[1] >> 1) & 0b11
+    }
+
+    fn is_ack_requested(&self) -> bool {
+        self.buffer[1] & 1 != 0
+    }
+}
+
+impl Deref for Packet {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let len = usize::from(self.buffer[0].min(MAX_PAYLOAD_LEN));
+        &self.buffer[2..2 + len]
+    }
+}
+
+/// Enhanced ShockBurst radio, acting as a PTX with `send` or as a PRX with `recv`.
+pub struct Esb<T, P> {
+    radio: RADIO,
+    timer: Timer<T, OneShot>,
+    ppi: P,
+    config: Config,
+    /// Last packet ID sent on each pipe.
+    tx_pid: [u8; PIPES],
+    /// Packet ID and CRC of the last packet received on each pipe, to detect retransmits.
+    rx_last: [Option<(u8, u16)>; PIPES],
+    /// Acknowledgement of the last packet received on each pipe, sent again on retransmits.
+    acks: [Packet; PIPES],
+    /// Payloads to be sent with the acknowledgement of the next packet on each pipe.
+    ack_payloads: [Option<Packet>; PIPES],
+}
+
+impl<T, P> Esb<T, P>
+where
+    T: timer::Instance,
+    P: ConfigurablePpi,
+{
+    /// Configures the RADIO for ESB, using `timer` for the acknowledgement timeouts and
+    /// retransmit delays, and `ppi` to stop the timer when an address is received.
+    ///
+    /// The external high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        timer: T,
+        mut ppi: P,
+        _clocks: &Clocks<ExternalOscillator, L, LSTAT>,
+        config: Config,
+    ) -> Result<Self, Error> {
+        let addresses = &config.addresses;
+        if !(3..=5).contains(&addresses.length) {
+            return Err(Error::InvalidAddressLength);
+        }
+        if config.channel > 100 {
+            return Err(Error::InvalidChannel);
+        }
+
+        // Power cycle the RADIO to reset its registers.
+        radio.power.write(|w| w.power().disabled());
+        radio.power.write(|w| w.power().enabled());
+
+        match config.bitrate {
+            Bitrate::_1Mbps => radio.mode.write(|w| w.mode().nrf_1mbit()),
+            Bitrate::_2Mbps => radio.mode.write(|w| w.mode().nrf_2mbit()),
+        }
+        // 6-bit length and 3-bit S1 (2-bit packet ID and acknowledgement request), as the nRF24
+        // packet control field.
+        radio.pcnf0.write(|w| unsafe {
+            let w = w.lflen().bits(6).s0len().clear_bit().s1len().bits(3);
+            match config.bitrate {
+                Bitrate::_1Mbps => w.plen()._8bit(),
+                Bitrate::_2Mbps => w.plen()._16bit(),
+            }
+        });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(MAX_PAYLOAD_LEN)
+                .statlen()
+                .bits(0)
+                .balen()
+                .bits(addresses.length - 1)
+                .endian()
+                .big()
+                .whiteen()
+                .clear_bit()
+        });
+        // CRC-16/CCITT-FALSE over the address and the packet.
+        radio.crccnf.write(|w| w.len().two().skipaddr().include());
+        radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(0x0001_1021) });
+        radio.crcinit.write(|w| unsafe { w.crcinit().bits(0xFFFF) });
+
+        // The bits of each address byte are sent most significant first, as by the nRF24.
+        let reverse = |bytes: &[u8]| {
+            let mut word = [0; 4];
+            for (reversed, byte) in word.iter_mut().zip(bytes) {
+                *reversed = byte.reverse_bits();
+            }
+            word
+        };
+        let (base0, base1) = (reverse(&addresses.base0), reverse(&addresses.base1));
+        radio
+            .base0
+            .write(|w| unsafe { w.bits(u32::from_be_bytes(base0)) });
+        radio
+            .base1
+            .write(|w| unsafe { w.bits(u32::from_be_bytes(base1)) });
+        let prefix0 = reverse(&addresses.prefixes[..4]);
+        let prefix1 = reverse(&addresses.prefixes[4..]);
+        radio
+            .prefix0
+            .write(|w| unsafe { w.bits(u32::from_le_bytes(prefix0)) });
+        radio
+            .prefix1
+            .write(|w| unsafe { w.bits(u32::from_le_bytes(prefix1)) });
+
+        radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(config.channel) });
+        radio.txpower.write(|w| config.tx_power.write(w));
+
+        let timer = Timer::one_shot(timer);
+        ppi.set_event_endpoint(&radio.events_address);
+        ppi.set_task_endpoint(timer.task_stop());
+        ppi.enable();
+
+        Ok(Self {
+            radio,
+            timer,
+            ppi,
+            config,
+            tx_pid: [0; PIPES],
+            rx_last: [None; PIPES],
+            acks: [
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+                Packet::new(),
+            ],
+            ack_payloads: [None, None, None, None, None, None, None, None],
+        })
+    }
+
+    /// Sends `payload` on `pipe` as a PTX, and blocks until it has been acknowledged if `ack` is
+    /// `true`. Returns the acknowledgement, whose payload is empty if the PRX sent none, or an
+    /// empty packet if `ack` is `false`.
+    ///
+    /// Returns `Error::MaxRetransmits` if the packet has not been acknowledged after
+    /// `Config::retransmit_count` retransmits.
+    pub fn send(&mut self, pipe: u8, payload: &[u8], ack: bool) -> Result<Packet, Error> {
+        let index = Self::pipe_index(pipe)?;
+        let mut packet = Packet::with_payload(payload)?;
+        self.tx_pid[index] = (self.tx_pid[index] + 1) & 0b11;
+        packet.buffer[1] = self.tx_pid[index] << 1 | u8::from(ack);
+
+        self.radio
+            .txaddress
+            .write(|w| unsafe { w.txaddress().bits(pipe) });
+        self.radio
+            .rxaddresses
+            .write(|w| unsafe { w.bits(1 << pipe) });
+
+        let mut response = Packet::new();
+        response.pipe = pipe;
+        for attempt in 0..=self.config.retransmit_count {
+            if attempt > 0 {
+                self.timer.delay(self.config.retransmit_delay);
+            }
+            self.transmit(&mut packet);
+            if !ack {
+                return Ok(response);
+            }
+            if self.receive_ack(&mut response) {
+                return Ok(response);
+            }
+        }
+        Err(Error::MaxRetransmits)
+    }
+
+    /// Blocks until a new packet has been received as a PRX, on one of `Config::rx_pipes`, and
+    /// acknowledges it if requested.
+    ///
+    /// Retransmits of a packet that has already been received are acknowledged again, but not
+    /// returned.
+    pub fn recv(&mut self) -> Result<Packet, Error> {
+        loop {
+            let mut packet = Packet::new();
+            self.radio
+                .rxaddresses
+                .write(|w| unsafe { w.bits(u32::from(self.config.rx_pipes)) });
+            self.start_rx(&mut packet);
+            self.wait_disabled();
+            if !self.radio.crcstatus.read().crcstatus().is_crcok() {
+                continue;
+            }
+
+            let pipe = self.radio.rxmatch.read().rxmatch().bits();
+            let index = usize::from(pipe);
+            packet.pipe = pipe;
+            packet.rssi = self.radio.rssisample.read().rssisample().bits();
+
+            let id = (packet.pid(), self.radio.rxcrc.read().rxcrc().bits() as u16);
+            let retransmit = self.rx_last[index] == Some(id);
+            if !retransmit {
+                self.rx_last[index] = Some(id);
+                self.acks[index] = self.ack_payloads[index].take().unwrap_or_else(Packet::new);
+            }
+
+            if packet.is_ack_requested() {
+                // The acknowledgement is sent to the address of the pipe, with the same packet ID.
+                let mut ack = self.acks[index].clone();
+                ack.buffer[1] = packet.buffer[1];
+                self.radio
+                    .txaddress
+                    .write(|w| unsafe { w.txaddress().bits(pipe) });
+                self.transmit(&mut ack);
+            }
+            if !retransmit {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Sets the payload sent with the acknowledgement of the next packet received on `pipe`,
+    /// replacing any payload that has not been sent yet.
+    pub fn set_ack_payload(&mut self, pipe: u8, payload: &[u8]) -> Result<(), Error> {
+        let index = Self::pipe_index(pipe)?;
+        self.ack_payloads[index] = Some(Packet::with_payload(payload)?);
+        Ok(())
+    }
+
+    /// Releases the RADIO, TIMER and PPI channel.
+    pub fn free(mut self) -> (RADIO, T, P) {
+        self.ppi.disable();
+        (self.radio, self.timer.free(), self.ppi)
+    }
+
+    fn pipe_index(pipe: u8) -> Result<usize, Error> {
+        if usize::from(pipe) < PIPES {
+            Ok(usize::from(pipe))
+        } else {
+            Err(Error::InvalidPipe)
+        }
+    }
+
+    /// Sends `packet` and blocks until the RADIO is disabled.
+    fn transmit(&mut self, packet: &mut Packet) {
+        self.set_packet(packet);
+        self.radio.events_disabled.reset();
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        self.wait_disabled();
+    }
+
+    /// Waits for an acknowledgement in `packet`. Returns `false` if none has been received.
+    fn receive_ack(&mut self, packet: &mut Packet) -> bool {
+        self.start_rx(packet);
+        // The timer is stopped through PPI once an address is received.
+        self.timer.start(ACK_TIMEOUT_US);
+        loop {
+            if self.radio.events_disabled.read().bits() != 0 {
+                break;
+            }
+            if self.timer.is_compare_triggered(0) && self.radio.events_address.read().bits() == 0 {
+                self.radio.tasks_disable.write(|w| unsafe { w.bits(1) });
+                self.wait_disabled();
+                return false;
+            }
+        }
+        self.timer.cancel().ok();
+        self.wait_disabled();
+        packet.rssi = self.radio.rssisample.read().rssisample().bits();
+        self.radio.crcstatus.read().crcstatus().is_crcok()
+    }
+
+    fn start_rx(&mut self, packet: &mut Packet) {
+        self.set_packet(packet);
+        self.radio.events_address.reset();
+        self.radio.events_disabled.reset();
+        self.radio.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .disabled_rssistop()
+                .enabled()
+        });
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+    }
+
+    fn set_packet(&mut self, packet: &mut Packet) {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.buffer.as_mut_ptr() as u32) });
+        // The packet must be written before the RADIO reads it, and read after the RADIO wrote it.
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn wait_disabled(&mut self) {
+        while self.radio.events_disabled.read().bits() == 0 {}
+        self.radio.events_disabled.reset();
+        self.radio.shorts.reset();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,440 @@
+//! IEEE 802.15.4 driver for the RADIO, at 250 kbit/s in the 2.4 GHz band.
+//!
+//! The driver sends and receives raw PHY frames: the MAC header, e.g. addresses and sequence
+//! number, is part of the payload of the `Packet`. The frame check sequence (FCS), a 16-bit CRC,
+//! is appended by the RADIO when sending, and checked when receiving.
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let mut radio = Radio::new(p.RADIO, &clocks);
+//! radio.set_channel(Channel::_20);
+//! radio.set_tx_power(TxPower::ZerodBm);
+//!
+//! let mut packet = Packet::new();
+//! packet.copy_from_slice(&frame);
+//! radio.try_send(&mut packet)?;
+//!
+//! radio.recv(&mut packet)?;
+//! let (rssi, lqi) = (packet.rssi(), packet.lqi());
+//! ```
+//!
+//! `try_send` performs a clear channel assessment (CCA) before sending, and returns
+//! `Error::ChannelBusy` if another transmission is detected. `recv_timestamped` also returns the
+//! time at which the frame started, read from a `MonotonicClock`, e.g. for a sniffer.
+//!
+//! `energy_detect` and `energy_scan` measure the energy on one or several channels, e.g. to pick
+//! the quietest channel before starting a network.
+
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::TxPower;
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::RADIO;
+use crate::rtc;
+use crate::uptime::MonotonicClock;
+
+/// Default energy detection threshold of the CCA, i.e. -73 dBm.
+const DEFAULT_ED_THRESHOLD: u8 = 20;
+/// Duration of one energy detection, i.e. 8 symbol periods, in microseconds.
+const ED_DURATION_US: u32 = 128;
+
+/// IEEE 802.15.4 error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The clear channel assessment detected that the channel is in use.
+    ChannelBusy,
+    /// The frame has been received, but its CRC is invalid.
+    Crc,
+}
+
+/// IEEE 802.15.4 channel of the 2.4 GHz band.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Channel {
+    /// 2405 MHz.
+    _11,
+    /// 2410 MHz.
+    _12,
+    /// 2415 MHz.
+    _13,
+    /// 2420 MHz.
+    _14,
+    /// 2425 MHz.
+    _15,
+    /// 2430 MHz.
+    _16,
+    /// 2435 MHz.
+    _17,
+    /// 2440 MHz.
+    _18,
+    /// 2445 MHz.
+    _19,
+    /// 2450 MHz.
+    _20,
+    /// 2455 MHz.
+    _21,
+    /// 2460 MHz.
+    _22,
+    /// 2465 MHz.
+    _23,
+    /// 2470 MHz.
+    _24,
+    /// 2475 MHz.
+    _25,
+    /// 2480 MHz.
+    _26,
+}
+
+impl Channel {
+    /// Returns the frequency of the channel, in MHz above 2400 MHz.
+    fn frequency_offset(self) -> u8 {
+        5 + 5 * (self as u8)
+    }
+}
+
+/// Clear channel assessment mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Cca {
+    /// The channel is busy if an IEEE 802.15.4 signal is detected.
+    CarrierSense,
+    /// The channel is busy if the energy detection (ED) level is above `threshold`.
+    EnergyDetection {
+        /// Energy detection level, see `Radio::energy_level_to_dbm`.
+        threshold: u8,
+    },
+}
+
+/// IEEE 802.15.4 frame, in a buffer that the RADIO can access through EasyDMA.
+pub struct Packet {
+    /// PHY header (the length of the PSDU), followed by the PSDU.
+    buffer: [u8; 1 + Packet::MAX_PSDU_LEN as usize],
+    rssi: u8,
+}
+
+impl Packet {
+    /// Maximum length of the PSDU, including the FCS.
+    const MAX_PSDU_LEN: u8 = 127;
+    /// Length of the FCS.
+    const FCS_LEN: u8 = 2;
+    /// Maximum length of the payload of a packet.
+    pub const CAPACITY: u8 = Self::MAX_PSDU_LEN - Self::FCS_LEN;
+
+    /// Returns an empty packet.
+    pub fn new() -> Self {
+        let mut packet = Self {
+            buffer: [0; 1 + Self::MAX_PSDU_LEN as usize],
+            rssi: 0,
+        };
+        packet.set_len(0);
+        packet
+    }
+
+    /// Returns the length of the payload, excluding the FCS.
+    pub fn len(&self) -> u8 {
+        self.buffer[0].saturating_sub(Self::FCS_LEN)
+    }
+
+    /// Returns `true` if the payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets the length of the payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than `Packet::CAPACITY`.
+    pub fn set_len(&mut self, len: u8) {
+        assert!(len <= Self::CAPACITY);
+        self.buffer[0] = len + Self::FCS_LEN;
+    }
+
+    /// Sets the payload to `payload`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `payload` is longer than `Packet::CAPACITY`.
+    pub fn copy_from_slice(&mut self, payload: &[u8]) {
+        assert!(payload.len() <= Self::CAPACITY as usize);
+        self.set_len(payload.len() as u8);
+        self.buffer[1..=payload.len()].copy_from_slice(payload);
+    }
+
+    /// Returns the link quality indicator (LQI) of the received frame, from 0 to 255.
+    ///
+    /// The RADIO writes the LQI after the payload, in place of the FCS.
+    pub fn lqi(&self) -> u8 {
+        self.buffer[1 + self.len() as usize]
+    }
+
+    /// Returns the received signal strength (RSSI) of the received frame, in dBm.
+    pub fn rssi(&self) -> i8 {
+        -(self.rssi.min(127) as i8)
+    }
+}
+
+impl Default for Packet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Packet {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[1..=self.len() as usize]
+    }
+}
+
+impl DerefMut for Packet {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len() as usize;
+        &mut self.buffer[1..=len]
+    }
+}
+
+/// IEEE 802.15.4 radio.
+pub struct Radio {
+    radio: RADIO,
+}
+
+impl Radio {
+    /// Configures the RADIO for IEEE 802.15.4, on channel 11 at 0 dBm, with energy detection CCA.
+    ///
+    /// The external high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(radio: RADIO, _clocks: &Clocks<ExternalOscillator, L, LSTAT>) -> Self {
+        // Power cycle the RADIO to reset its registers.
+        radio.power.write(|w| w.power().disabled());
+        radio.power.write(|w| w.power().enabled());
+
+        radio.mode.write(|w| w.mode().ieee802154_250kbit());
+        // 8-bit PHY header, 32-bit zero preamble followed by the SFD, and a length including the
+        // FCS.
+        radio
+            .pcnf0
+            .write(|w| unsafe { w.lflen().bits(8).plen()._32bit_zero().crcinc().include() });
+        radio
+            .pcnf1
+            .write(|w| unsafe { w.maxlen().bits(Packet::MAX_PSDU_LEN) });
+        // CRC-16/KERMIT, i.e. the ITU-T polynomial x^16 + x^12 + x^5 + 1 with a zero initial value.
+        radio
+            .crccnf
+            .write(|w| w.len().two().skipaddr().ieee802154());
+        radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(0x0001_1021) });
+        radio.crcinit.write(|w| unsafe { w.crcinit().bits(0) });
+
+        let mut radio = Self { radio };
+        radio.set_channel(Channel::_11);
+        radio.set_tx_power(TxPower::ZerodBm);
+        radio.set_cca(Cca::EnergyDetection {
+            threshold: DEFAULT_ED_THRESHOLD,
+        });
+        radio
+    }
+
+    /// Sets the channel used to send and receive.
+    pub fn set_channel(&mut self, channel: Channel) {
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(channel.frequency_offset()) });
+    }
+
+    /// Sets the output power.
+    pub fn set_tx_power(&mut self, power: TxPower) {
+        self.radio.txpower.write(|w| power.write(w));
+    }
+
+    /// Sets the mode of the clear channel assessment done by `try_send`.
+    pub fn set_cca(&mut self, cca: Cca) {
+        self.radio.ccactrl.write(|w| match cca {
+            Cca::CarrierSense => w.ccamode().carrier_mode(),
+            Cca::EnergyDetection { threshold } => unsafe {
+                w.ccamode().ed_mode().ccaedthres().bits(threshold)
+            },
+        });
+    }
+
+    /// Converts an energy detection level, as used by the CCA threshold, to dBm.
+    pub fn energy_level_to_dbm(level: u8) -> i8 {
+        const ED_RSSIOFFS: i16 = -93;
+        (ED_RSSIOFFS + i16::from(level)).min(0) as i8
+    }
+
+    /// Measures the peak energy on the current channel for `duration_us` microseconds, rounded up
+    /// to a multiple of 128 µs, and returns it in dBm.
+    pub fn energy_detect(&mut self, duration_us: u32) -> i8 {
+        self.disable();
+        // EDCNT + 1 detections are performed, and the maximum level is kept.
+        let count = duration_us.div_ceil(ED_DURATION_US).max(1) - 1;
+        self.radio
+            .edcnt
+            .write(|w| unsafe { w.edcnt().bits(count.min(0x001F_FFFF)) });
+        self.radio.events_edend.reset();
+        self.radio
+            .shorts
+            .write(|w| w.ready_edstart().enabled().edend_disable().enabled());
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        while self.radio.events_edend.read().bits() == 0 {}
+        self.radio.events_edend.reset();
+        self.wait_disabled();
+        Self::energy_level_to_dbm(self.radio.edsample.read().edlvl().bits())
+    }
+
+    /// Measures the peak energy on each of `channels` for `dwell_us` microseconds, and writes it
+    /// in dBm to the matching entry of `results`. Channels without an entry in `results` are not
+    /// measured.
+    ///
+    /// The channel set by `set_channel` is restored afterwards.
+    pub fn energy_scan(&mut self, channels: &[Channel], dwell_us: u32, results: &mut [i8]) {
+        let frequency = self.radio.frequency.read().frequency().bits();
+        for (&channel, result) in channels.iter().zip(results) {
+            self.set_channel(channel);
+            *result = self.energy_detect(dwell_us);
+        }
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(frequency) });
+    }
+
+    /// Sends `packet` after a clear channel assessment, and blocks until it has been sent.
+    ///
+    /// Returns `Error::ChannelBusy`, without sending the packet, if the channel is in use.
+    pub fn try_send(&mut self, packet: &mut Packet) -> Result<(), Error> {
+        self.disable();
+        self.set_packet(packet);
+        self.radio.events_ccabusy.reset();
+        self.radio.events_phyend.reset();
+
+        // RXEN -> CCA -> TXEN -> transmission -> DISABLE, or CCA busy -> DISABLE.
+        self.radio.shorts.write(|w| {
+            w.rxready_ccastart()
+                .enabled()
+                .ccaidle_txen()
+                .enabled()
+                .txready_start()
+                .enabled()
+                .ccabusy_disable()
+                .enabled()
+                .phyend_disable()
+                .enabled()
+        });
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+        let result = loop {
+            if self.radio.events_phyend.read().bits() != 0 {
+                break Ok(());
+            }
+            if self.radio.events_ccabusy.read().bits() != 0 {
+                break Err(Error::ChannelBusy);
+            }
+        };
+        self.wait_disabled();
+        result
+    }
+
+    /// Sends `packet` without a clear channel assessment, and blocks until it has been sent.
+    pub fn send(&mut self, packet: &mut Packet) {
+        self.disable();
+        self.set_packet(packet);
+        self.radio.events_phyend.reset();
+
+        self.radio
+            .shorts
+            .write(|w| w.txready_start().enabled().phyend_disable().enabled());
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+
+        while self.radio.events_phyend.read().bits() == 0 {}
+        self.wait_disabled();
+    }
+
+    /// Blocks until a frame has been received in `packet`.
+    ///
+    /// On a CRC error, `Error::Crc` is returned, and the content of `packet` is undefined.
+    pub fn recv(&mut self, packet: &mut Packet) -> Result<(), Error> {
+        self.recv_inner(packet, || {})
+    }
+
+    /// Blocks until a frame has been received in `packet`, and returns the time at which it
+    /// started, in microseconds of `clock`.
+    pub fn recv_timestamped<T: rtc::Instance>(
+        &mut self,
+        packet: &mut Packet,
+        clock: &MonotonicClock<T>,
+    ) -> Result<u64, Error> {
+        let mut timestamp = 0;
+        self.recv_inner(packet, || timestamp = clock.now_us())?;
+        Ok(timestamp)
+    }
+
+    /// Releases the RADIO.
+    pub fn free(self) -> RADIO {
+        self.radio
+    }
+
+    fn recv_inner(
+        &mut self,
+        packet: &mut Packet,
+        mut on_frame_start: impl FnMut(),
+    ) -> Result<(), Error> {
+        self.disable();
+        self.set_packet(packet);
+        self.radio.events_framestart.reset();
+        self.radio.events_end.reset();
+
+        // The RSSI is sampled when the SFD has been received.
+        self.radio.shorts.write(|w| {
+            w.rxready_start()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .end_disable()
+                .enabled()
+        });
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+        let mut started = false;
+        while self.radio.events_end.read().bits() == 0 {
+            if !started && self.radio.events_framestart.read().bits() != 0 {
+                started = true;
+                on_frame_start();
+            }
+        }
+        self.wait_disabled();
+
+        packet.rssi = self.radio.rssisample.read().rssisample().bits();
+        if self.radio.crcstatus.read().crcstatus().is_crcok() {
+            Ok(())
+        } else {
+            Err(Error::Crc)
+        }
+    }
+
+    fn set_packet(&mut self, packet: &mut Packet) {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.buffer.as_mut_ptr() as u32) });
+        // The packet must be written before the RADIO reads it, and read after the RADIO wrote it.
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    /// Disables the RADIO, interrupting any ongoing operation.
+    fn disable(&mut self) {
+        self.radio.shorts.reset();
+        if !self.radio.state.read().state().is_disabled() {
+            self.radio.events_disabled.reset();
+            self.radio.tasks_disable.write(|w| unsafe { w.bits(1) });
+            while self.radio.events_disabled.read().bits() == 0 {}
+        }
+        self.radio.events_disabled.reset();
+    }
+
+    fn wait_disabled(&mut self) {
+        while !self.radio.state.read().state().is_disabled() {}
+        self.radio.shorts.reset();
+        self.radio.events_disabled.reset();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
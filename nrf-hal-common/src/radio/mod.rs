@@ -0,0 +1,92 @@
+//! HAL interface to the RADIO peripheral.
+//!
+//! The RADIO is a 2.4 GHz transceiver supporting several protocols. Each protocol has its own
+//! driver in a submodule, which takes ownership of the RADIO and configures it for that protocol:
+//!
+//! - `ble`: Bluetooth Low Energy non-connectable advertising, for beacons.
+//! - `esb`: Enhanced ShockBurst, compatible with Nordic's ESB and nRF24 transceivers.
+//! - `ieee802154`: IEEE 802.15.4 at 250 kbit/s, on the nRF52811, nRF52833 and nRF52840.
+//! - `raw`: typed access to the RADIO configuration, tasks and events, for other protocols.
+//! - `timing`: timestamps and scheduling of the RADIO with a TIMER and PPI, for link layers.
+//!
+//! The drivers need the external high-frequency oscillator, which must be running while the RADIO
+//! is in use.
+
+use crate::pac::radio;
+
+pub mod ble;
+pub mod esb;
+#[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+pub mod ieee802154;
+pub mod raw;
+pub mod timing;
+
+/// Output power of the RADIO.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TxPower {
+    /// +8 dBm.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    Pos8dBm,
+    /// +7 dBm.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    Pos7dBm,
+    /// +6 dBm.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    Pos6dBm,
+    /// +5 dBm.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    Pos5dBm,
+    /// +4 dBm.
+    Pos4dBm,
+    /// +3 dBm.
+    Pos3dBm,
+    /// +2 dBm.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    Pos2dBm,
+    /// 0 dBm.
+    ZerodBm,
+    /// -4 dBm.
+    Neg4dBm,
+    /// -8 dBm.
+    Neg8dBm,
+    /// -12 dBm.
+    Neg12dBm,
+    /// -16 dBm.
+    Neg16dBm,
+    /// -20 dBm.
+    Neg20dBm,
+    /// -30 dBm.
+    #[cfg(not(feature = "52832"))]
+    Neg30dBm,
+    /// -40 dBm.
+    Neg40dBm,
+}
+
+impl TxPower {
+    pub(crate) fn write(self, w: &mut radio::txpower::W) -> &mut radio::txpower::W {
+        let txpower = w.txpower();
+        match self {
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            TxPower::Pos8dBm => txpower.pos8d_bm(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            TxPower::Pos7dBm => txpower.pos7d_bm(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            TxPower::Pos6dBm => txpower.pos6d_bm(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            TxPower::Pos5dBm => txpower.pos5d_bm(),
+            TxPower::Pos4dBm => txpower.pos4d_bm(),
+            TxPower::Pos3dBm => txpower.pos3d_bm(),
+            #[cfg(any(feature = "52833", feature = "52840"))]
+            TxPower::Pos2dBm => txpower.pos2d_bm(),
+            TxPower::ZerodBm => txpower._0d_bm(),
+            TxPower::Neg4dBm => txpower.neg4d_bm(),
+            TxPower::Neg8dBm => txpower.neg8d_bm(),
+            TxPower::Neg12dBm => txpower.neg12d_bm(),
+            TxPower::Neg16dBm => txpower.neg16d_bm(),
+            TxPower::Neg20dBm => txpower.neg20d_bm(),
+            #[cfg(not(feature = "52832"))]
+            TxPower::Neg30dBm => txpower.neg30d_bm(),
+            TxPower::Neg40dBm => txpower.neg40d_bm(),
+        }
+    }
+}
@@ -0,0 +1,808 @@
+//! Low-level, typed interface to the RADIO, for protocols without a driver in this crate.
+//!
+//! `Radio` exposes the configuration registers of the RADIO (mode, frequency, packet format,
+//! addresses and CRC), its tasks and events, and its shortcuts, without enforcing any protocol.
+//! The configuration must match the one of the peer, and the tasks must follow the RADIO state
+//! machine described in the product specification:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let mut radio = Radio::new(p.RADIO, &clocks);
+//! radio.set_mode(Mode::Nrf1Mbit);
+//! radio.set_frequency(7)?;
+//! radio.set_packet_config(&PacketConfig {
+//!     length_bits: 8,
+//!     max_len: 32,
+//!     base_address_len: 4,
+//!     ..PacketConfig::default()
+//! })?;
+//! radio.set_base0(0x0123_4567);
+//! radio.set_prefixes([0x89, 0, 0, 0, 0, 0, 0, 0]);
+//! radio.set_crc(&CrcConfig {
+//!     length: CrcLength::Two,
+//!     skip_address: false,
+//!     polynomial: 0x1_1021,
+//!     initial: 0xFFFF,
+//! });
+//!
+//! // Length, then payload. The packet must be in RAM, so it can't be a constant.
+//! let packet = [3, b'a', b'b', b'c'];
+//! radio.transmit(&packet)?;
+//! ```
+//!
+//! On the nRF52811, nRF52833 and nRF52840, the Bluetooth Low Energy coded PHYs need the long
+//! range preamble, and the CI and TERM fields:
+//!
+//! ```ignore
+//! radio.set_mode(Mode::BleLr125Kbit);
+//! radio.set_packet_config(&PacketConfig {
+//!     length_bits: 8,
+//!     s0_len: 1,
+//!     preamble: Preamble::LongRange,
+//!     max_len: 255,
+//!     base_address_len: 3,
+//!     ci_len: 2,
+//!     term_len: 3,
+//!     whitening: true,
+//!     ..PacketConfig::default()
+//! })?;
+//! ```
+//!
+//! `rssi_sample` measures the received signal strength on the current frequency, and
+//! `energy_scan` sweeps several frequencies, e.g. the BLE advertising channels, for clear channel
+//! assessment or site surveys.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_hal::timer::CountDown;
+
+use super::TxPower;
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::RADIO;
+use crate::slice_in_ram_or;
+
+/// RADIO error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The frequency is above 2500 MHz.
+    InvalidFrequency,
+    /// A field of the packet configuration is out of range.
+    InvalidPacketConfig,
+    /// The pipe is above 7.
+    InvalidPipe,
+    /// The buffer is smaller than the packet configuration allows, or than the packet to send.
+    BufferTooSmall,
+    /// The buffer is not in RAM, so the RADIO can't access it through EasyDMA.
+    BufferNotInRam,
+    /// The received packet has an invalid CRC.
+    Crc,
+}
+
+/// Modulation and bitrate of the RADIO.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Mode {
+    /// Nordic proprietary, 1 Mbit/s.
+    Nrf1Mbit,
+    /// Nordic proprietary, 2 Mbit/s.
+    Nrf2Mbit,
+    /// Bluetooth Low Energy, 1 Mbit/s.
+    Ble1Mbit,
+    /// Bluetooth Low Energy, 2 Mbit/s.
+    Ble2Mbit,
+    /// Bluetooth Low Energy long range, 125 kbit/s, i.e. the coded PHY with S=8.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    BleLr125Kbit,
+    /// Bluetooth Low Energy long range, 500 kbit/s, i.e. the coded PHY with S=2.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    BleLr500Kbit,
+    /// IEEE 802.15.4, 250 kbit/s.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    Ieee802154_250Kbit,
+}
+
+/// Length of the preamble.
+///
+/// The Bluetooth Low Energy PHYs use an 8-bit preamble at 1 Mbit/s, a 16-bit preamble at
+/// 2 Mbit/s, and the long range preamble with the coded PHYs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Preamble {
+    /// 8 bits.
+    _8bit,
+    /// 16 bits.
+    _16bit,
+    /// 32 zero bits, as used by IEEE 802.15.4.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    _32bitZero,
+    /// Preamble of the Bluetooth Low Energy coded PHYs.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    LongRange,
+}
+
+/// Byte order of the S0, LENGTH, S1 and PAYLOAD fields on air.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Endianness {
+    /// Least significant bit first.
+    Little,
+    /// Most significant bit first.
+    Big,
+}
+
+/// Packet format, as configured in PCNF0 and PCNF1.
+///
+/// In RAM, a packet is made of the S0 field, the LENGTH field, the S1 field and the payload, each
+/// field taking a whole number of bytes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PacketConfig {
+    /// Length of the LENGTH field on air, in bits, up to 15.
+    pub length_bits: u8,
+    /// Length of the S0 field, in bytes, up to 1.
+    pub s0_len: u8,
+    /// Length of the S1 field on air, in bits, up to 15.
+    pub s1_len: u8,
+    /// Includes the S1 field in RAM even if it is 0 bits long.
+    pub s1_include: bool,
+    /// Length of the preamble.
+    pub preamble: Preamble,
+    /// Maximum length of the payload, in bytes.
+    pub max_len: u8,
+    /// Number of bytes added to the length of the payload given by the LENGTH field.
+    pub static_len: u8,
+    /// Length of the base addresses, from 2 to 4 bytes.
+    pub base_address_len: u8,
+    /// Byte order on air.
+    pub endianness: Endianness,
+    /// Enables data whitening.
+    pub whitening: bool,
+    /// Length of the coding indicator (CI) field of the coded PHYs, in bits, up to 3. The
+    /// Bluetooth Low Energy coded PHYs use 2 bits.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    pub ci_len: u8,
+    /// Length of the TERM field of the coded PHYs, in bits, up to 3. The Bluetooth Low Energy
+    /// coded PHYs use 3 bits.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    pub term_len: u8,
+    /// The LENGTH field includes the length of the CRC, as in IEEE 802.15.4.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    pub length_includes_crc: bool,
+}
+
+impl Default for PacketConfig {
+    fn default() -> Self {
+        Self {
+            length_bits: 0,
+            s0_len: 0,
+            s1_len: 0,
+            s1_include: false,
+            preamble: Preamble::_8bit,
+            max_len: 0,
+            static_len: 0,
+            base_address_len: 4,
+            endianness: Endianness::Little,
+            whitening: false,
+            #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+            ci_len: 0,
+            #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+            term_len: 0,
+            #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+            length_includes_crc: false,
+        }
+    }
+}
+
+impl PacketConfig {
+    /// Returns the size, in RAM, of the largest packet with this configuration.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len() + usize::from(self.max_len)
+    }
+
+    /// Returns the size, in RAM, of `packet` as the RADIO sends it, according to its LENGTH
+    /// field, or `None` if `packet` doesn't even hold the S0, LENGTH and S1 fields.
+    fn packet_len(&self, packet: &[u8]) -> Option<usize> {
+        let header_len = self.header_len();
+        if packet.len() < header_len {
+            return None;
+        }
+        let length_start = usize::from(self.s0_len);
+        let length_end = length_start + usize::from(self.length_bits.div_ceil(8));
+        // The LENGTH field is little endian in RAM.
+        let length = packet[length_start..length_end]
+            .iter()
+            .rev()
+            .fold(0, |length, &byte| (length << 8) | usize::from(byte))
+            & ((1 << self.length_bits) - 1);
+        // The RADIO truncates the payload, including the static length, to MAXLEN.
+        let payload_len = (length + usize::from(self.static_len)).min(usize::from(self.max_len));
+        Some(header_len + payload_len)
+    }
+
+    /// Returns the size, in RAM, of the S0, LENGTH and S1 fields.
+    fn header_len(&self) -> usize {
+        let s1_bytes = match self.s1_len.div_ceil(8) {
+            0 if self.s1_include => 1,
+            bytes => bytes,
+        };
+        usize::from(self.s0_len) + usize::from(self.length_bits.div_ceil(8)) + usize::from(s1_bytes)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.length_bits <= 15
+            && self.s0_len <= 1
+            && self.s1_len <= 15
+            && (2..=4).contains(&self.base_address_len)
+            && self.is_coding_valid()
+    }
+
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    fn is_coding_valid(&self) -> bool {
+        self.ci_len <= 3 && self.term_len <= 3
+    }
+
+    #[cfg(not(any(feature = "52811", feature = "52833", feature = "52840")))]
+    fn is_coding_valid(&self) -> bool {
+        true
+    }
+}
+
+/// Length of the CRC.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CrcLength {
+    /// No CRC.
+    Disabled,
+    /// 1 byte.
+    One,
+    /// 2 bytes.
+    Two,
+    /// 3 bytes.
+    Three,
+}
+
+/// CRC configuration.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CrcConfig {
+    /// Length of the CRC.
+    pub length: CrcLength,
+    /// Leaves the address out of the CRC.
+    pub skip_address: bool,
+    /// CRC polynomial, with the coefficient of each power of x at the same bit. The coefficient
+    /// of the highest power may be left out.
+    pub polynomial: u32,
+    /// Initial value of the CRC.
+    pub initial: u32,
+}
+
+/// Tasks of the RADIO.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Task {
+    /// Enables the RADIO in transmit mode.
+    TxEn,
+    /// Enables the RADIO in receive mode.
+    RxEn,
+    /// Starts sending or receiving a packet.
+    Start,
+    /// Stops sending or receiving a packet.
+    Stop,
+    /// Disables the RADIO.
+    Disable,
+    /// Starts the RSSI measurement.
+    RssiStart,
+    /// Stops the RSSI measurement.
+    RssiStop,
+    /// Starts the bit counter.
+    BcStart,
+    /// Stops the bit counter.
+    BcStop,
+}
+
+/// Events of the RADIO.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// The RADIO has ramped up and is ready to start.
+    Ready,
+    /// The address has been sent or received.
+    Address,
+    /// The payload has been sent or received.
+    Payload,
+    /// The packet has been sent or received.
+    End,
+    /// The RADIO has been disabled.
+    Disabled,
+    /// A device address matches.
+    DevMatch,
+    /// No device address matches.
+    DevMiss,
+    /// The RSSI measurement has completed.
+    RssiEnd,
+    /// The bit counter has reached its compare value.
+    BcMatch,
+    /// The received packet has a valid CRC.
+    CrcOk,
+    /// The received packet has an invalid CRC.
+    CrcError,
+}
+
+/// Shortcuts between events and tasks of the RADIO, as configured in SHORTS.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Shorts {
+    /// READY event to START task.
+    pub ready_start: bool,
+    /// END event to DISABLE task.
+    pub end_disable: bool,
+    /// DISABLED event to TXEN task.
+    pub disabled_txen: bool,
+    /// DISABLED event to RXEN task.
+    pub disabled_rxen: bool,
+    /// ADDRESS event to RSSISTART task.
+    pub address_rssistart: bool,
+    /// END event to START task.
+    pub end_start: bool,
+    /// ADDRESS event to BCSTART task.
+    pub address_bcstart: bool,
+    /// DISABLED event to RSSISTOP task.
+    pub disabled_rssistop: bool,
+}
+
+/// State of the RADIO.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum State {
+    /// Disabled.
+    Disabled,
+    /// Ramping up in receive mode.
+    RxRampUp,
+    /// Ready to receive.
+    RxIdle,
+    /// Receiving.
+    Rx,
+    /// Being disabled from receive mode.
+    RxDisable,
+    /// Ramping up in transmit mode.
+    TxRampUp,
+    /// Ready to send.
+    TxIdle,
+    /// Sending.
+    Tx,
+    /// Being disabled from transmit mode.
+    TxDisable,
+}
+
+/// Coding indicator (CI) of a packet received with a Bluetooth Low Energy coded PHY.
+#[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CodingIndicator {
+    /// 125 kbit/s, S=8.
+    S8,
+    /// 500 kbit/s, S=2.
+    S2,
+}
+
+/// RADIO with a typed configuration.
+pub struct Radio {
+    radio: RADIO,
+    packet_config: PacketConfig,
+}
+
+impl Radio {
+    /// Powers on the RADIO, with the reset configuration.
+    ///
+    /// The external high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(radio: RADIO, _clocks: &Clocks<ExternalOscillator, L, LSTAT>) -> Self {
+        // Power cycle the RADIO to reset its registers.
+        radio.power.write(|w| w.power().disabled());
+        radio.power.write(|w| w.power().enabled());
+        Self {
+            radio,
+            packet_config: PacketConfig::default(),
+        }
+    }
+
+    /// Sets the modulation and bitrate.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.radio.mode.write(|w| {
+            let w = w.mode();
+            match mode {
+                Mode::Nrf1Mbit => w.nrf_1mbit(),
+                Mode::Nrf2Mbit => w.nrf_2mbit(),
+                Mode::Ble1Mbit => w.ble_1mbit(),
+                Mode::Ble2Mbit => w.ble_2mbit(),
+                #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+                Mode::BleLr125Kbit => w.ble_lr125kbit(),
+                #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+                Mode::BleLr500Kbit => w.ble_lr500kbit(),
+                #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+                Mode::Ieee802154_250Kbit => w.ieee802154_250kbit(),
+            }
+        });
+    }
+
+    /// Sets the frequency, in MHz above 2400 MHz, from 0 to 100.
+    pub fn set_frequency(&mut self, offset_mhz: u8) -> Result<(), Error> {
+        if offset_mhz > 100 {
+            return Err(Error::InvalidFrequency);
+        }
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(offset_mhz) });
+        Ok(())
+    }
+
+    /// Sets the output power.
+    pub fn set_tx_power(&mut self, tx_power: TxPower) {
+        self.radio.txpower.write(|w| tx_power.write(w));
+    }
+
+    /// Sets the packet format.
+    pub fn set_packet_config(&mut self, config: &PacketConfig) -> Result<(), Error> {
+        if !config.is_valid() {
+            return Err(Error::InvalidPacketConfig);
+        }
+        self.radio.pcnf0.write(|w| unsafe {
+            let w = w
+                .lflen()
+                .bits(config.length_bits)
+                .s0len()
+                .bit(config.s0_len == 1)
+                .s1len()
+                .bits(config.s1_len);
+            let w = if config.s1_include {
+                w.s1incl().include()
+            } else {
+                w.s1incl().automatic()
+            };
+            let w = match config.preamble {
+                Preamble::_8bit => w.plen()._8bit(),
+                Preamble::_16bit => w.plen()._16bit(),
+                #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+                Preamble::_32bitZero => w.plen()._32bit_zero(),
+                #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+                Preamble::LongRange => w.plen().long_range(),
+            };
+            #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+            w.cilen()
+                .bits(config.ci_len)
+                .termlen()
+                .bits(config.term_len)
+                .crcinc()
+                .bit(config.length_includes_crc);
+            w
+        });
+        self.radio.pcnf1.write(|w| unsafe {
+            let w = w
+                .maxlen()
+                .bits(config.max_len)
+                .statlen()
+                .bits(config.static_len)
+                .balen()
+                .bits(config.base_address_len)
+                .whiteen()
+                .bit(config.whitening);
+            match config.endianness {
+                Endianness::Little => w.endian().little(),
+                Endianness::Big => w.endian().big(),
+            }
+        });
+        self.packet_config = *config;
+        Ok(())
+    }
+
+    /// Sets the base address of logical address 0.
+    pub fn set_base0(&mut self, base: u32) {
+        self.radio.base0.write(|w| unsafe { w.bits(base) });
+    }
+
+    /// Sets the base address of logical addresses 1 to 7.
+    pub fn set_base1(&mut self, base: u32) {
+        self.radio.base1.write(|w| unsafe { w.bits(base) });
+    }
+
+    /// Sets the prefixes of logical addresses 0 to 7.
+    pub fn set_prefixes(&mut self, prefixes: [u8; 8]) {
+        let [p0, p1, p2, p3, p4, p5, p6, p7] = prefixes;
+        self.radio
+            .prefix0
+            .write(|w| unsafe { w.bits(u32::from_le_bytes([p0, p1, p2, p3])) });
+        self.radio
+            .prefix1
+            .write(|w| unsafe { w.bits(u32::from_le_bytes([p4, p5, p6, p7])) });
+    }
+
+    /// Sets the logical address used when sending.
+    pub fn set_tx_address(&mut self, pipe: u8) -> Result<(), Error> {
+        if pipe > 7 {
+            return Err(Error::InvalidPipe);
+        }
+        self.radio
+            .txaddress
+            .write(|w| unsafe { w.txaddress().bits(pipe) });
+        Ok(())
+    }
+
+    /// Sets the logical addresses used when receiving, one bit per address.
+    pub fn set_rx_addresses(&mut self, pipes: u8) {
+        self.radio
+            .rxaddresses
+            .write(|w| unsafe { w.bits(u32::from(pipes)) });
+    }
+
+    /// Sets the CRC configuration.
+    pub fn set_crc(&mut self, config: &CrcConfig) {
+        self.radio.crccnf.write(|w| {
+            let w = match config.length {
+                CrcLength::Disabled => w.len().disabled(),
+                CrcLength::One => w.len().one(),
+                CrcLength::Two => w.len().two(),
+                CrcLength::Three => w.len().three(),
+            };
+            if config.skip_address {
+                w.skipaddr().skip()
+            } else {
+                w.skipaddr().include()
+            }
+        });
+        self.radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(config.polynomial) });
+        self.radio
+            .crcinit
+            .write(|w| unsafe { w.crcinit().bits(config.initial) });
+    }
+
+    /// Sets the initial value of the data whitening polynomial.
+    pub fn set_whitening_iv(&mut self, iv: u8) {
+        self.radio
+            .datawhiteiv
+            .write(|w| unsafe { w.datawhiteiv().bits(iv) });
+    }
+
+    /// Sets the shortcuts between events and tasks.
+    pub fn set_shorts(&mut self, shorts: Shorts) {
+        self.radio.shorts.write(|w| {
+            w.ready_start()
+                .bit(shorts.ready_start)
+                .end_disable()
+                .bit(shorts.end_disable)
+                .disabled_txen()
+                .bit(shorts.disabled_txen)
+                .disabled_rxen()
+                .bit(shorts.disabled_rxen)
+                .address_rssistart()
+                .bit(shorts.address_rssistart)
+                .end_start()
+                .bit(shorts.end_start)
+                .address_bcstart()
+                .bit(shorts.address_bcstart)
+                .disabled_rssistop()
+                .bit(shorts.disabled_rssistop)
+        });
+    }
+
+    /// Sets the packet the RADIO sends from or receives into.
+    ///
+    /// # Safety
+    ///
+    /// `packet` must point to a RAM buffer of at least `PacketConfig::buffer_len` bytes, which
+    /// must stay valid, and must not be accessed by the CPU, while the RADIO sends or receives.
+    pub unsafe fn set_packet_ptr(&mut self, packet: *const u8) {
+        self.radio.packetptr.write(|w| w.bits(packet as u32));
+    }
+
+    /// Triggers the given task.
+    pub fn trigger(&mut self, task: Task) {
+        let radio = &self.radio;
+        match task {
+            Task::TxEn => radio.tasks_txen.write(|w| unsafe { w.bits(1) }),
+            Task::RxEn => radio.tasks_rxen.write(|w| unsafe { w.bits(1) }),
+            Task::Start => radio.tasks_start.write(|w| unsafe { w.bits(1) }),
+            Task::Stop => radio.tasks_stop.write(|w| unsafe { w.bits(1) }),
+            Task::Disable => radio.tasks_disable.write(|w| unsafe { w.bits(1) }),
+            Task::RssiStart => radio.tasks_rssistart.write(|w| unsafe { w.bits(1) }),
+            Task::RssiStop => radio.tasks_rssistop.write(|w| unsafe { w.bits(1) }),
+            Task::BcStart => radio.tasks_bcstart.write(|w| unsafe { w.bits(1) }),
+            Task::BcStop => radio.tasks_bcstop.write(|w| unsafe { w.bits(1) }),
+        }
+    }
+
+    /// Checks if the given event has been triggered.
+    pub fn is_event_triggered(&self, event: Event) -> bool {
+        let radio = &self.radio;
+        let bits = match event {
+            Event::Ready => radio.events_ready.read().bits(),
+            Event::Address => radio.events_address.read().bits(),
+            Event::Payload => radio.events_payload.read().bits(),
+            Event::End => radio.events_end.read().bits(),
+            Event::Disabled => radio.events_disabled.read().bits(),
+            Event::DevMatch => radio.events_devmatch.read().bits(),
+            Event::DevMiss => radio.events_devmiss.read().bits(),
+            Event::RssiEnd => radio.events_rssiend.read().bits(),
+            Event::BcMatch => radio.events_bcmatch.read().bits(),
+            Event::CrcOk => radio.events_crcok.read().bits(),
+            Event::CrcError => radio.events_crcerror.read().bits(),
+        };
+        bits != 0
+    }
+
+    /// Resets the given event.
+    pub fn reset_event(&mut self, event: Event) {
+        let radio = &self.radio;
+        match event {
+            Event::Ready => radio.events_ready.reset(),
+            Event::Address => radio.events_address.reset(),
+            Event::Payload => radio.events_payload.reset(),
+            Event::End => radio.events_end.reset(),
+            Event::Disabled => radio.events_disabled.reset(),
+            Event::DevMatch => radio.events_devmatch.reset(),
+            Event::DevMiss => radio.events_devmiss.reset(),
+            Event::RssiEnd => radio.events_rssiend.reset(),
+            Event::BcMatch => radio.events_bcmatch.reset(),
+            Event::CrcOk => radio.events_crcok.reset(),
+            Event::CrcError => radio.events_crcerror.reset(),
+        }
+    }
+
+    /// Returns the current state of the RADIO.
+    pub fn state(&self) -> State {
+        let state = self.radio.state.read().state();
+        if state.is_rx_ru() {
+            State::RxRampUp
+        } else if state.is_rx_idle() {
+            State::RxIdle
+        } else if state.is_rx() {
+            State::Rx
+        } else if state.is_rx_disable() {
+            State::RxDisable
+        } else if state.is_tx_ru() {
+            State::TxRampUp
+        } else if state.is_tx_idle() {
+            State::TxIdle
+        } else if state.is_tx() {
+            State::Tx
+        } else if state.is_tx_disable() {
+            State::TxDisable
+        } else {
+            State::Disabled
+        }
+    }
+
+    /// Returns `true` if the last received packet has a valid CRC.
+    pub fn is_crc_ok(&self) -> bool {
+        self.radio.crcstatus.read().crcstatus().is_crcok()
+    }
+
+    /// Returns the CRC of the last received packet.
+    pub fn rx_crc(&self) -> u32 {
+        self.radio.rxcrc.read().rxcrc().bits()
+    }
+
+    /// Returns the logical address on which the last packet has been received.
+    pub fn rx_match(&self) -> u8 {
+        self.radio.rxmatch.read().rxmatch().bits()
+    }
+
+    /// Returns the coding indicator of the last packet received with a coded PHY.
+    #[cfg(any(feature = "52811", feature = "52833", feature = "52840"))]
+    pub fn rx_coding_indicator(&self) -> CodingIndicator {
+        if self.radio.pdustat.read().cistat().is_lr500kbit() {
+            CodingIndicator::S2
+        } else {
+            CodingIndicator::S8
+        }
+    }
+
+    /// Measures the received signal strength (RSSI) on the current frequency, in dBm.
+    ///
+    /// If the RADIO is not receiving, the shortcuts are cleared and the receiver is enabled first.
+    /// The receiver is left enabled.
+    pub fn rssi_sample(&mut self) -> i8 {
+        if !matches!(self.state(), State::RxIdle | State::Rx) {
+            self.disable();
+            self.reset_event(Event::Ready);
+            self.trigger(Task::RxEn);
+            while !self.is_event_triggered(Event::Ready) {}
+            self.reset_event(Event::Ready);
+        }
+        self.reset_event(Event::RssiEnd);
+        self.trigger(Task::RssiStart);
+        while !self.is_event_triggered(Event::RssiEnd) {}
+        self.reset_event(Event::RssiEnd);
+        self.trigger(Task::RssiStop);
+        -(self.radio.rssisample.read().rssisample().bits().min(127) as i8)
+    }
+
+    /// Measures the peak RSSI on each of `channels`, frequencies in MHz above 2400 MHz, for
+    /// `dwell` ticks of `timer`, i.e. microseconds for a `Timer`. The RSSI is written in dBm to
+    /// the matching entry of `results`; channels without an entry in `results` are not measured.
+    ///
+    /// The RADIO is left disabled, with the frequency set before the scan and no shortcut.
+    pub fn energy_scan<C>(
+        &mut self,
+        timer: &mut C,
+        channels: &[u8],
+        dwell: u32,
+        results: &mut [i8],
+    ) -> Result<(), Error>
+    where
+        C: CountDown<Time = u32>,
+    {
+        if channels.iter().any(|&channel| channel > 100) {
+            return Err(Error::InvalidFrequency);
+        }
+        let frequency = self.radio.frequency.read().frequency().bits();
+        for (&channel, result) in channels.iter().zip(results) {
+            self.disable();
+            self.set_frequency(channel)?;
+            let mut peak = i8::MIN;
+            timer.start(dwell);
+            loop {
+                peak = peak.max(self.rssi_sample());
+                if timer.wait().is_ok() {
+                    break;
+                }
+            }
+            *result = peak;
+        }
+        self.disable();
+        self.set_frequency(frequency)
+    }
+
+    /// Sends `packet`, laid out in RAM as described in `PacketConfig`, and blocks until it has
+    /// been sent. This overwrites the shortcuts.
+    ///
+    /// `packet` must hold at least the payload given by its LENGTH field and the static length.
+    pub fn transmit(&mut self, packet: &[u8]) -> Result<(), Error> {
+        slice_in_ram_or(packet, Error::BufferNotInRam)?;
+        match self.packet_config.packet_len(packet) {
+            Some(len) if len <= packet.len() => {}
+            _ => return Err(Error::BufferTooSmall),
+        }
+        unsafe { self.set_packet_ptr(packet.as_ptr()) };
+        self.run(true);
+        Ok(())
+    }
+
+    /// Blocks until a packet has been received into `packet`, laid out in RAM as described in
+    /// `PacketConfig`. This overwrites the shortcuts.
+    pub fn receive(&mut self, packet: &mut [u8]) -> Result<(), Error> {
+        if packet.len() < self.packet_config.buffer_len() {
+            return Err(Error::BufferTooSmall);
+        }
+        unsafe { self.set_packet_ptr(packet.as_mut_ptr()) };
+        self.run(false);
+        if self.is_crc_ok() {
+            Ok(())
+        } else {
+            Err(Error::Crc)
+        }
+    }
+
+    /// Releases the RADIO.
+    pub fn free(self) -> RADIO {
+        self.radio
+    }
+
+    /// Disables the RADIO, interrupting any ongoing operation, and clears the shortcuts.
+    fn disable(&mut self) {
+        self.set_shorts(Shorts::default());
+        if self.state() != State::Disabled {
+            self.reset_event(Event::Disabled);
+            self.trigger(Task::Disable);
+            while !self.is_event_triggered(Event::Disabled) {}
+        }
+        self.reset_event(Event::Disabled);
+    }
+
+    /// Sends or receives a packet and waits for the RADIO to be disabled.
+    fn run(&mut self, tx: bool) {
+        self.reset_event(Event::Disabled);
+        self.set_shorts(Shorts {
+            ready_start: true,
+            end_disable: true,
+            ..Shorts::default()
+        });
+        // The packet must be written before the RADIO reads it.
+        compiler_fence(Ordering::SeqCst);
+        self.trigger(if tx { Task::TxEn } else { Task::RxEn });
+        while !self.is_event_triggered(Event::Disabled) {}
+        self.reset_event(Event::Disabled);
+        self.set_shorts(Shorts::default());
+        // The received packet must be read after the RADIO wrote it.
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,258 @@
+//! Precise RADIO timing with a TIMER and PPI.
+//!
+//! `RadioTimer` runs a TIMER freely at 1 MHz, and connects it to the RADIO through PPI, to:
+//!
+//! - capture the time of the ADDRESS and END events of the RADIO,
+//! - trigger the TXEN or RXEN task of the RADIO at a given time.
+//!
+//! Both happen in hardware, without any interrupt latency, which lets a link layer timestamp
+//! packets and meet the interframe spacing of its protocol, e.g. 150 µs for Bluetooth Low Energy.
+//!
+//! The PPI channels are either any three configurable channels, `PpiChannels`, or, with TIMER0,
+//! the pre-programmed channels 20, 21, 26 and 27, `FixedChannels`, which leave the configurable
+//! channels free:
+//!
+//! ```ignore
+//! let ppi = ppi::Parts::new(p.PPI);
+//! let mut timer = RadioTimer::new(
+//!     p.TIMER0,
+//!     FixedChannels {
+//!         txen: ppi.ppi20,
+//!         rxen: ppi.ppi21,
+//!         address: ppi.ppi26,
+//!         end: ppi.ppi27,
+//!     },
+//! );
+//!
+//! // Receive a packet, then answer exactly 150 µs after its end. `RAMP_UP_US` is the ramp-up time
+//! // of the RADIO in transmit mode, see the product specification.
+//! radio.receive(&mut request)?;
+//! timer.schedule_tx(timer.end_timestamp() + TIFS_US - RAMP_UP_US)?;
+//! ```
+
+use crate::pac::{RADIO, TIMER0};
+use crate::ppi::{ConfigurablePpi, Ppi, Ppi20, Ppi21, Ppi26, Ppi27};
+use crate::timer::{self, Bitmode, OneShot, Timer};
+
+/// Interframe spacing of Bluetooth Low Energy, in microseconds.
+pub const TIFS_US: u32 = 150;
+
+/// CC channel triggering the scheduled task.
+const CC_SCHEDULE: usize = 0;
+/// CC channel capturing the ADDRESS event.
+const CC_ADDRESS: usize = 1;
+/// CC channel capturing the END event.
+const CC_END: usize = 2;
+/// CC channel capturing the current time.
+const CC_NOW: usize = 3;
+
+/// RADIO timing error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The time at which the task was scheduled has already passed.
+    TooLate,
+}
+
+/// RADIO task triggered at a given time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScheduledTask {
+    /// Enables the RADIO in transmit mode.
+    TxEn,
+    /// Enables the RADIO in receive mode.
+    RxEn,
+}
+
+/// Configurable PPI channels connecting the TIMER and the RADIO.
+pub struct PpiChannels<S, A, E> {
+    /// Triggers the scheduled task.
+    pub schedule: S,
+    /// Captures the time of the ADDRESS event.
+    pub address: A,
+    /// Captures the time of the END event.
+    pub end: E,
+}
+
+/// Pre-programmed PPI channels connecting TIMER0 and the RADIO.
+pub struct FixedChannels {
+    /// `TIMER0.EVENTS_COMPARE[0]` → `RADIO.TASKS_TXEN`
+    pub txen: Ppi20,
+    /// `TIMER0.EVENTS_COMPARE[0]` → `RADIO.TASKS_RXEN`
+    pub rxen: Ppi21,
+    /// `RADIO.EVENTS_ADDRESS` → `TIMER0.TASKS_CAPTURE[1]`
+    pub address: Ppi26,
+    /// `RADIO.EVENTS_END` → `TIMER0.TASKS_CAPTURE[2]`
+    pub end: Ppi27,
+}
+
+/// PPI channels usable by a `RadioTimer` with the TIMER `T`.
+pub trait Channels<T>: sealed::Channels<T> {}
+
+impl<T, C: sealed::Channels<T>> Channels<T> for C {}
+
+mod sealed {
+    use super::ScheduledTask;
+    use crate::timer::{OneShot, Timer};
+
+    pub trait Channels<T> {
+        fn connect(&mut self, timer: &Timer<T, OneShot>);
+        fn schedule(&mut self, task: Option<ScheduledTask>);
+        fn disconnect(&mut self);
+    }
+}
+
+/// Returns the RADIO registers, to get the addresses of its tasks and events.
+fn radio() -> &'static crate::pac::radio::RegisterBlock {
+    // Only the addresses of the tasks and events are used, the registers are not accessed.
+    unsafe { &*RADIO::ptr() }
+}
+
+impl<T, S, A, E> sealed::Channels<T> for PpiChannels<S, A, E>
+where
+    T: timer::Instance,
+    S: ConfigurablePpi,
+    A: ConfigurablePpi,
+    E: ConfigurablePpi,
+{
+    fn connect(&mut self, timer: &Timer<T, OneShot>) {
+        let radio = radio();
+        self.schedule
+            .set_event_endpoint(timer.event_compare(CC_SCHEDULE));
+        self.address.set_event_endpoint(&radio.events_address);
+        self.address
+            .set_task_endpoint(timer.task_capture(CC_ADDRESS));
+        self.address.enable();
+        self.end.set_event_endpoint(&radio.events_end);
+        self.end.set_task_endpoint(timer.task_capture(CC_END));
+        self.end.enable();
+    }
+
+    fn schedule(&mut self, task: Option<ScheduledTask>) {
+        self.schedule.disable();
+        let radio = radio();
+        match task {
+            Some(ScheduledTask::TxEn) => self.schedule.set_task_endpoint(&radio.tasks_txen),
+            Some(ScheduledTask::RxEn) => self.schedule.set_task_endpoint(&radio.tasks_rxen),
+            None => return,
+        }
+        self.schedule.enable();
+    }
+
+    fn disconnect(&mut self) {
+        self.schedule.disable();
+        self.address.disable();
+        self.end.disable();
+    }
+}
+
+impl sealed::Channels<TIMER0> for FixedChannels {
+    fn connect(&mut self, _timer: &Timer<TIMER0, OneShot>) {
+        self.address.enable();
+        self.end.enable();
+    }
+
+    fn schedule(&mut self, task: Option<ScheduledTask>) {
+        self.txen.disable();
+        self.rxen.disable();
+        match task {
+            Some(ScheduledTask::TxEn) => self.txen.enable(),
+            Some(ScheduledTask::RxEn) => self.rxen.enable(),
+            None => {}
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.txen.disable();
+        self.rxen.disable();
+        self.address.disable();
+        self.end.disable();
+    }
+}
+
+/// TIMER running at 1 MHz, which timestamps the RADIO events and schedules the RADIO tasks.
+///
+/// The times are 32-bit counter values, in microseconds, wrapping around every 71 minutes.
+pub struct RadioTimer<T, C> {
+    timer: Timer<T, OneShot>,
+    channels: C,
+}
+
+impl<T, C> RadioTimer<T, C>
+where
+    T: timer::Instance,
+    C: Channels<T>,
+{
+    /// Starts `timer` from 0, and connects it to the RADIO through `channels`.
+    pub fn new(timer: T, mut channels: C) -> Self {
+        let mut timer = Timer::one_shot(timer);
+        timer.set_bitmode(Bitmode::Bits32);
+        timer.disable_shorts();
+        timer.clear_counter();
+        channels.connect(&timer);
+        timer.start_counter();
+        Self { timer, channels }
+    }
+
+    /// Returns the current time.
+    pub fn now(&mut self) -> u32 {
+        self.timer.capture(CC_NOW)
+    }
+
+    /// Returns the time of the last ADDRESS event, i.e. when the address of the last packet was
+    /// sent or received.
+    pub fn address_timestamp(&self) -> u32 {
+        self.timer.compare(CC_ADDRESS)
+    }
+
+    /// Returns the time of the last END event, i.e. when the last packet was sent or received
+    /// entirely.
+    pub fn end_timestamp(&self) -> u32 {
+        self.timer.compare(CC_END)
+    }
+
+    /// Enables the RADIO in transmit mode at time `at`. See `schedule`.
+    pub fn schedule_tx(&mut self, at: u32) -> Result<(), Error> {
+        self.schedule(ScheduledTask::TxEn, at)
+    }
+
+    /// Enables the RADIO in receive mode at time `at`. See `schedule`.
+    pub fn schedule_rx(&mut self, at: u32) -> Result<(), Error> {
+        self.schedule(ScheduledTask::RxEn, at)
+    }
+
+    /// Triggers `task` at time `at`, replacing any task scheduled before.
+    ///
+    /// Returns `Error::TooLate`, without scheduling the task, if `at` has already passed. The
+    /// task stays scheduled, and is triggered again whenever the counter wraps around to `at`,
+    /// until `cancel` is called.
+    pub fn schedule(&mut self, task: ScheduledTask, at: u32) -> Result<(), Error> {
+        self.channels.schedule(None);
+        self.timer.set_compare(CC_SCHEDULE, at);
+        self.timer.reset_compare_event(CC_SCHEDULE);
+        self.channels.schedule(Some(task));
+
+        // The deadline may have passed while the task was scheduled.
+        let late = self.now().wrapping_sub(at) as i32 > 0;
+        if late && !self.timer.is_compare_triggered(CC_SCHEDULE) {
+            self.cancel();
+            return Err(Error::TooLate);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the scheduled task has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.timer.is_compare_triggered(CC_SCHEDULE)
+    }
+
+    /// Cancels the scheduled task.
+    pub fn cancel(&mut self) {
+        self.channels.schedule(None);
+    }
+
+    /// Stops the TIMER, disables the PPI channels, and releases them.
+    pub fn free(mut self) -> (T, C) {
+        self.channels.disconnect();
+        self.timer.stop_counter();
+        (self.timer.free(), self.channels)
+    }
+}
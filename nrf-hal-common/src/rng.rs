@@ -1,6 +1,25 @@
 //! HAL interface to the RNG peripheral.
 //!
 //! See nRF52832 product specification, chapter 26.
+//!
+//! Generating a byte takes around 120 µs with bias correction. `BufferedRng` fills a pool of
+//! random bytes in the background from the RNG interrupt, so that requests up to the pool size
+//! usually complete immediately:
+//!
+//! ```ignore
+//! let mut rng = BufferedRng::new(Rng::new(p.RNG));
+//!
+//! // After unmasking RNG in the NVIC, in the `RNG` interrupt handler:
+//! rng.on_interrupt();
+//!
+//! // Anywhere else:
+//! let mut nonce = [0; 16];
+//! rng.fill_bytes(&mut nonce);
+//! ```
+//!
+//! With the `global-rng` feature, an `Rng` can be installed as the global entropy source with
+//! `set_global`, for libraries that can't be handed an RNG. They get random bytes through the
+//! `GlobalRng` handle, which can be created anywhere.
 
 use rand_core::{CryptoRng, RngCore};
 
@@ -8,15 +27,30 @@ use crate::pac::RNG;
 
 /// Interface to the RNG peripheral.
 ///
-/// Right now, this is very basic, only providing blocking interfaces.
+/// Right now, this is very basic, only providing blocking interfaces. It implements the
+/// `rand_core` `RngCore` and `CryptoRng` traits, so it can be used as an entropy source by `rand`
+/// and cryptographic libraries.
 pub struct Rng(RNG);
 
 impl Rng {
+    /// Takes ownership of the RNG peripheral, with bias correction enabled.
     pub fn new(rng: RNG) -> Self {
         rng.config.write(|w| w.dercen().enabled());
         Self(rng)
     }
 
+    /// Enables or disables the bias correction.
+    ///
+    /// The bias correction ensures a uniform distribution of 0 and 1, but makes the generation
+    /// of each byte slower and its duration less predictable. It should stay enabled when the
+    /// random numbers are used for cryptography.
+    pub fn set_bias_correction(&mut self, enabled: bool) {
+        self.0.config.write(|w| match enabled {
+            true => w.dercen().enabled(),
+            false => w.dercen().disabled(),
+        });
+    }
+
     /// Fill the provided buffer with random bytes.
     ///
     /// Will block until the buffer is full.
@@ -68,6 +102,11 @@ impl Rng {
             | (buf[6] as u64) << 48
             | (buf[7] as u64) << 56
     }
+
+    /// Releases the RNG peripheral.
+    pub fn free(self) -> RNG {
+        self.0
+    }
 }
 
 impl RngCore for Rng {
@@ -90,3 +129,183 @@ impl RngCore for Rng {
 }
 
 impl CryptoRng for Rng {}
+
+/// Number of random bytes kept in the pool of `BufferedRng`.
+pub const POOL_LEN: usize = 64;
+
+/// An RNG that fills a pool of random bytes in the background, from the RNG interrupt.
+pub struct BufferedRng {
+    rng: Rng,
+    pool: [u8; POOL_LEN],
+    len: usize,
+}
+
+impl BufferedRng {
+    /// Starts filling the pool in the background.
+    ///
+    /// The RNG interrupt must be unmasked in the NVIC, and its handler must call `on_interrupt`.
+    pub fn new(rng: Rng) -> Self {
+        let mut this = Self {
+            rng,
+            pool: [0; POOL_LEN],
+            len: 0,
+        };
+        this.refill();
+        this
+    }
+
+    /// Handles the RNG interrupt, adding the new byte to the pool. Must be called from the RNG
+    /// interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        let rng = &self.rng.0;
+        if rng.events_valrdy.read().bits() == 0 {
+            return;
+        }
+        rng.events_valrdy.write(|w| unsafe { w.bits(0) });
+
+        if self.len < POOL_LEN {
+            self.pool[self.len] = rng.value.read().value().bits();
+            self.len += 1;
+        }
+        if self.len == POOL_LEN {
+            rng.intenclr.write(|w| w.valrdy().clear());
+            rng.tasks_stop.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    /// Returns the number of random bytes in the pool.
+    pub fn available(&self) -> usize {
+        self.len
+    }
+
+    /// Fills the provided buffer with random bytes.
+    ///
+    /// The bytes are taken from the pool. If it doesn't hold enough of them, the remaining ones
+    /// are generated while blocking.
+    pub fn random(&mut self, buf: &mut [u8]) {
+        let from_pool = buf.len().min(self.len);
+        let (pooled, rest) = buf.split_at_mut(from_pool);
+        self.len -= from_pool;
+        pooled.copy_from_slice(&self.pool[self.len..self.len + from_pool]);
+        // Don't keep copies of the bytes handed out.
+        self.pool[self.len..self.len + from_pool].fill(0);
+
+        if !rest.is_empty() {
+            self.rng.0.intenclr.write(|w| w.valrdy().clear());
+            self.rng.random(rest);
+        }
+        self.refill();
+    }
+
+    /// Resumes filling the pool, if it isn't full.
+    fn refill(&mut self) {
+        if self.len < POOL_LEN {
+            self.rng.0.intenset.write(|w| w.valrdy().set());
+            self.rng.0.tasks_start.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    /// Stops filling the pool, and releases the RNG.
+    pub fn free(self) -> Rng {
+        self.rng.0.intenclr.write(|w| w.valrdy().clear());
+        self.rng.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.rng
+    }
+}
+
+impl RngCore for BufferedRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.random(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.random(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.random(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for BufferedRng {}
+
+#[cfg(feature = "global-rng")]
+static GLOBAL: cortex_m::interrupt::Mutex<core::cell::RefCell<Option<Rng>>> =
+    cortex_m::interrupt::Mutex::new(core::cell::RefCell::new(None));
+
+/// Installs `rng` as the global entropy source used by `GlobalRng`, and returns the previously
+/// installed one.
+#[cfg(feature = "global-rng")]
+pub fn set_global(rng: Rng) -> Option<Rng> {
+    cortex_m::interrupt::free(|cs| GLOBAL.borrow(cs).replace(Some(rng)))
+}
+
+/// Uninstalls the global entropy source, and returns it.
+#[cfg(feature = "global-rng")]
+pub fn take_global() -> Option<Rng> {
+    cortex_m::interrupt::free(|cs| GLOBAL.borrow(cs).take())
+}
+
+/// A handle to the global entropy source installed with `set_global`.
+///
+/// Random bytes are generated one at a time, each in its own critical section, so interrupts are
+/// delayed by up to around 120 µs.
+#[cfg(feature = "global-rng")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobalRng;
+
+#[cfg(feature = "global-rng")]
+impl GlobalRng {
+    /// Error code of `try_fill_bytes` when no global entropy source is installed.
+    pub const NOT_INSTALLED: u32 = rand_core::Error::CUSTOM_START;
+}
+
+#[cfg(feature = "global-rng")]
+impl RngCore for GlobalRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    /// Fills `dest` with random bytes.
+    ///
+    /// Panics if no global entropy source is installed.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("no global entropy source installed")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        for byte in dest {
+            *byte = cortex_m::interrupt::free(|cs| {
+                GLOBAL.borrow(cs).borrow_mut().as_mut().map(Rng::random_u8)
+            })
+            .ok_or_else(|| {
+                rand_core::Error::from(
+                    core::num::NonZeroU32::new(Self::NOT_INSTALLED)
+                        .unwrap_or_else(|| unsafe { core::hint::unreachable_unchecked() }),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "global-rng")]
+impl CryptoRng for GlobalRng {}
@@ -1,6 +1,10 @@
 //! A high level interface for RTC peripherals.
 
-use core::ops::Deref;
+use core::{marker::PhantomData, ops::Deref};
+
+use crate::clocks::LFCLK_FREQ;
+use crate::pac::generic::Reg;
+use fugit::{Duration, MillisDurationU32};
 
 #[cfg(feature = "9160")]
 use crate::pac::{rtc0_ns as rtc0, Interrupt, NVIC, RTC0_NS as RTC0, RTC1_NS as RTC1};
@@ -8,6 +12,11 @@ use crate::pac::{rtc0_ns as rtc0, Interrupt, NVIC, RTC0_NS as RTC0, RTC1_NS as R
 #[cfg(not(feature = "9160"))]
 use crate::pac::{rtc0, Interrupt, NVIC, RTC0, RTC1};
 
+use rtc0::{
+    _EVENTS_COMPARE, _EVENTS_OVRFLW, _EVENTS_TICK, _TASKS_CLEAR, _TASKS_START, _TASKS_STOP,
+    _TASKS_TRIGOVRFLW,
+};
+
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 use crate::pac::RTC2;
 
@@ -17,6 +26,7 @@ pub struct Rtc<T> {
 }
 
 /// Interrupts/Events that can be generated by the RTCn peripheral.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum RtcInterrupt {
     Tick,
     Overflow,
@@ -27,6 +37,7 @@ pub enum RtcInterrupt {
 }
 
 /// Compare registers available on the RTCn.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum RtcCompareReg {
     Compare0,
     Compare1,
@@ -50,6 +61,22 @@ where
         Ok(Rtc { periph: rtc })
     }
 
+    /// Changes the 12 bits prescaler.
+    /// fRTC = 32_768 / (`prescaler` + 1 )
+    ///
+    /// The prescaler can only be changed while the counter is stopped, so this stops the counter.
+    /// It has to be restarted with `enable_counter`.
+    pub fn set_prescaler(&mut self, prescaler: u32) -> Result<(), Error> {
+        if prescaler >= (1 << 12) {
+            return Err(Error::PrescalerOutOfRange);
+        }
+
+        self.disable_counter();
+        unsafe { self.periph.prescaler.write(|w| w.bits(prescaler)) };
+
+        Ok(())
+    }
+
     /// Enable/start the Real Time Counter.
     pub fn enable_counter(&self) {
         unsafe {
@@ -184,11 +211,57 @@ where
         Ok(())
     }
 
+    /// Sets a compare register to match the counter after the given duration from now, e.g.
+    /// `500.millis()`.
+    ///
+    /// Returns `Error::CompareOutOfRange` if the duration doesn't fit in the 24 bits counter.
+    pub fn set_compare_in<const NOM: u32, const DENOM: u32>(
+        &mut self,
+        reg: RtcCompareReg,
+        duration: Duration<u32, NOM, DENOM>,
+    ) -> Result<(), Error> {
+        let ticks = self.ticks_for(duration);
+        if ticks >= (1 << 24) {
+            return Err(Error::CompareOutOfRange);
+        }
+
+        let val = (self.get_counter() + ticks) & 0x00FF_FFFF;
+        self.set_compare(reg, val)
+    }
+
+    /// Converts a duration, e.g. `500.millis()`, to a number of counter ticks at the configured
+    /// frequency.
+    pub fn ticks_for<const NOM: u32, const DENOM: u32>(
+        &self,
+        duration: Duration<u32, NOM, DENOM>,
+    ) -> u32 {
+        let ticks = u64::from(duration.ticks()) * u64::from(NOM) * u64::from(self.frequency())
+            / u64::from(DENOM);
+        ticks.min(u64::from(u32::MAX)) as u32
+    }
+
+    /// Returns the compare value of a given register.
+    pub fn get_compare(&self, reg: RtcCompareReg) -> u32 {
+        let reg = match reg {
+            RtcCompareReg::Compare0 => 0,
+            RtcCompareReg::Compare1 => 1,
+            RtcCompareReg::Compare2 => 2,
+            RtcCompareReg::Compare3 => 3,
+        };
+
+        self.periph.cc[reg].read().bits()
+    }
+
     /// Obtain the current value of the Real Time Counter, 24 bits of range.
     pub fn get_counter(&self) -> u32 {
         self.periph.counter.read().bits()
     }
 
+    /// Returns the counter frequency in Hz, as configured by the prescaler.
+    pub fn frequency(&self) -> u32 {
+        LFCLK_FREQ / (self.periph.prescaler.read().bits() + 1)
+    }
+
     /// Clear the Real Time Counter.
     pub fn clear_counter(&self) {
         unsafe {
@@ -204,11 +277,317 @@ where
         }
     }
 
+    /// Returns reference to the `START` task endpoint for PPI.
+    /// Starts the Real Time Counter.
+    #[inline(always)]
+    pub fn task_start(&self) -> &Reg<u32, _TASKS_START> {
+        &self.periph.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    /// Stops the Real Time Counter.
+    #[inline(always)]
+    pub fn task_stop(&self) -> &Reg<u32, _TASKS_STOP> {
+        &self.periph.tasks_stop
+    }
+
+    /// Returns reference to the `CLEAR` task endpoint for PPI.
+    /// Clears the Real Time Counter.
+    #[inline(always)]
+    pub fn task_clear(&self) -> &Reg<u32, _TASKS_CLEAR> {
+        &self.periph.tasks_clear
+    }
+
+    /// Returns reference to the `TRIGOVRFLW` task endpoint for PPI.
+    /// Sets the Real Time Counter value to 0xFFFFF0.
+    #[inline(always)]
+    pub fn task_trigger_overflow(&self) -> &Reg<u32, _TASKS_TRIGOVRFLW> {
+        &self.periph.tasks_trigovrflw
+    }
+
+    /// Returns reference to the `TICK` event endpoint for PPI.
+    /// Generated on every increment of the Real Time Counter, if enabled with `enable_event`.
+    #[inline(always)]
+    pub fn event_tick(&self) -> &Reg<u32, _EVENTS_TICK> {
+        &self.periph.events_tick
+    }
+
+    /// Returns reference to the `OVRFLW` event endpoint for PPI.
+    /// Generated when the Real Time Counter overflows, if enabled with `enable_event`.
+    #[inline(always)]
+    pub fn event_overflow(&self) -> &Reg<u32, _EVENTS_OVRFLW> {
+        &self.periph.events_ovrflw
+    }
+
+    /// Returns reference to the `COMPARE` event endpoint of the given compare register for PPI.
+    /// Generated when the Real Time Counter matches the compare value, if enabled with
+    /// `enable_event`.
+    #[inline(always)]
+    pub fn event_compare(&self, reg: RtcCompareReg) -> &Reg<u32, _EVENTS_COMPARE> {
+        let reg = match reg {
+            RtcCompareReg::Compare0 => 0,
+            RtcCompareReg::Compare1 => 1,
+            RtcCompareReg::Compare2 => 2,
+            RtcCompareReg::Compare3 => 3,
+        };
+        &self.periph.events_compare[reg]
+    }
+
     /// Destructure the high level interface. Does not reset any configuration made
     /// to the given RTC peripheral.
     pub fn release(self) -> T {
         self.periph
     }
+
+    /// Splits the compare registers into independent alarms.
+    ///
+    /// The counter keeps its current state, so it should be enabled with `enable_counter`
+    /// beforehand.
+    pub fn into_alarms(self) -> Alarms<T> {
+        Alarms {
+            alarm0: Alarm { _rtc: PhantomData },
+            alarm1: Alarm { _rtc: PhantomData },
+            alarm2: Alarm { _rtc: PhantomData },
+            alarm3: sealed::NewAlarm::new(),
+            periph: self.periph,
+        }
+    }
+}
+
+/// The compare registers of an RTC, as independent alarms.
+pub struct Alarms<T: Instance> {
+    pub alarm0: Alarm<T, 0>,
+    pub alarm1: Alarm<T, 1>,
+    pub alarm2: Alarm<T, 2>,
+    /// `Alarm<T, 3>`, or `()` for the RTC0 of the nRF51 and nRF52, which only has 3 compare
+    /// registers.
+    pub alarm3: T::Alarm3,
+    periph: T,
+}
+
+impl<T> Alarms<T>
+where
+    T: Instance,
+{
+    /// Returns the RTC, if none of the alarms have been moved out.
+    pub fn release(self) -> Rtc<T> {
+        Rtc {
+            periph: self.periph,
+        }
+    }
+}
+
+/// An alarm, using the compare register `N` of an RTC.
+///
+/// Alarms are delivered as the `COMPARE[N]` event, which can generate the RTC interrupt, be
+/// polled with `take_fired`, or be routed to other peripherals through PPI. Each alarm only
+/// accesses the registers of its own compare channel, so alarms can be used independently from
+/// different contexts.
+pub struct Alarm<T, const N: usize> {
+    _rtc: PhantomData<T>,
+}
+
+impl<T, const N: usize> Alarm<T, N>
+where
+    T: Instance,
+{
+    fn rtc(&self) -> &rtc0::RegisterBlock {
+        unsafe { &*T::ptr() }
+    }
+
+    /// Returns the current value of the counter.
+    pub fn now(&self) -> u32 {
+        self.rtc().counter.read().bits()
+    }
+
+    /// Schedules the alarm for when the counter reaches `counter`, which is truncated to 24
+    /// bits. Replaces any previously scheduled time.
+    pub fn schedule_at(&mut self, counter: u32) {
+        let rtc = self.rtc();
+        rtc.events_compare[N].reset();
+        rtc.cc[N].write(|w| unsafe { w.bits(counter & 0x00FF_FFFF) });
+    }
+
+    /// Schedules the alarm `ticks` counter ticks from now. Replaces any previously scheduled
+    /// time.
+    ///
+    /// The RTC can miss a compare value less than 2 ticks ahead of the counter, so shorter
+    /// delays are rounded up to 2 ticks. Returns `Error::CompareOutOfRange` if `ticks` doesn't
+    /// fit in the 24 bits counter.
+    pub fn schedule_in(&mut self, ticks: u32) -> Result<(), Error> {
+        if ticks >= (1 << 24) {
+            return Err(Error::CompareOutOfRange);
+        }
+
+        let target = self.now() + ticks.max(2);
+        self.schedule_at(target);
+        Ok(())
+    }
+
+    /// Cancels the alarm, by disabling its interrupt and clearing its event.
+    ///
+    /// The compare register keeps matching the counter, so the event has to be re-enabled with
+    /// `enable_interrupt` or `enable_event` after scheduling the alarm again.
+    pub fn cancel(&mut self) {
+        let rtc = self.rtc();
+        rtc.intenclr.write(|w| unsafe { w.bits(1 << (16 + N)) });
+        rtc.evtenclr.write(|w| unsafe { w.bits(1 << (16 + N)) });
+        rtc.events_compare[N].reset();
+    }
+
+    /// Enables the RTC interrupt for this alarm.
+    ///
+    /// The interrupt also has to be unmasked in the NVIC.
+    pub fn enable_interrupt(&mut self) {
+        self.rtc()
+            .intenset
+            .write(|w| unsafe { w.bits(1 << (16 + N)) });
+    }
+
+    /// Disables the RTC interrupt for this alarm.
+    pub fn disable_interrupt(&mut self) {
+        self.rtc()
+            .intenclr
+            .write(|w| unsafe { w.bits(1 << (16 + N)) });
+    }
+
+    /// Enables routing of the alarm event to PPI.
+    pub fn enable_event(&mut self) {
+        self.rtc()
+            .evtenset
+            .write(|w| unsafe { w.bits(1 << (16 + N)) });
+    }
+
+    /// Disables routing of the alarm event to PPI.
+    pub fn disable_event(&mut self) {
+        self.rtc()
+            .evtenclr
+            .write(|w| unsafe { w.bits(1 << (16 + N)) });
+    }
+
+    /// Returns `true` and clears the event if the alarm has fired.
+    pub fn take_fired(&mut self) -> bool {
+        let rtc = self.rtc();
+        if rtc.events_compare[N].read().bits() != 0 {
+            rtc.events_compare[N].reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Calls `callback` if the alarm has fired, and clears the event. Intended to be called from
+    /// the RTC interrupt handler.
+    pub fn on_interrupt<F: FnOnce()>(&mut self, callback: F) {
+        if self.take_fired() {
+            callback();
+        }
+    }
+
+    /// Returns reference to the `COMPARE` event endpoint of this alarm for PPI.
+    /// Generated when the alarm fires, if enabled with `enable_event`.
+    #[inline(always)]
+    pub fn event_compare(&self) -> &Reg<u32, _EVENTS_COMPARE> {
+        &self.rtc().events_compare[N]
+    }
+}
+
+/// Prescaler of `PeriodicRtc`, for 8 Hz ticks.
+const PERIODIC_PRESCALER: u32 = 4095;
+
+/// Shortest and longest periods of `PeriodicRtc`: one tick, and half the range of the counter.
+const PERIODIC_RANGE: (MillisDurationU32, MillisDurationU32) = (
+    MillisDurationU32::from_ticks(125),
+    MillisDurationU32::from_ticks(0x7F_FFFF * 125),
+);
+
+/// An RTC generating its COMPARE[0] event periodically, which schedules background
+/// measurements, e.g. in `BatteryMonitor` and `TempMonitor`.
+///
+/// The period is rounded to 125 ms. The low frequency clock has to be started for the RTC to
+/// run.
+pub(crate) struct PeriodicRtc<T> {
+    rtc: Rtc<T>,
+    period: MillisDurationU32,
+}
+
+impl<T> PeriodicRtc<T>
+where
+    T: Instance,
+{
+    /// Stops the RTC, and disables its interrupts.
+    pub(crate) fn new(rtc: T, period: MillisDurationU32) -> Self {
+        rtc.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        let mut rtc = Rtc { periph: rtc };
+        // Also stops the counter.
+        rtc.set_prescaler(PERIODIC_PRESCALER).ok();
+        Self {
+            rtc,
+            period: period.clamp(PERIODIC_RANGE.0, PERIODIC_RANGE.1),
+        }
+    }
+
+    /// Starts the counter, with the first event one period from now.
+    pub(crate) fn start(&mut self) {
+        self.schedule_next();
+        self.rtc.enable_counter();
+    }
+
+    /// Stops the counter.
+    pub(crate) fn stop(&mut self) {
+        self.rtc.disable_counter();
+    }
+
+    /// Returns `true` if the event has been triggered.
+    #[cfg(not(feature = "51"))]
+    pub(crate) fn is_due(&self) -> bool {
+        self.rtc.is_event_triggered(RtcInterrupt::Compare0)
+    }
+
+    /// Resets the event, and schedules the next one a period from now.
+    pub(crate) fn schedule_next(&mut self) {
+        self.rtc.reset_event(RtcInterrupt::Compare0);
+        // The period is clamped to the range of the counter in `new`.
+        self.rtc
+            .set_compare_in(RtcCompareReg::Compare0, self.period)
+            .ok();
+    }
+
+    /// Enables the RTC interrupt for the event.
+    #[cfg(not(feature = "51"))]
+    pub(crate) fn enable_interrupt(&mut self) {
+        self.rtc.enable_interrupt(RtcInterrupt::Compare0, None);
+    }
+
+    /// Disables the RTC interrupt for the event.
+    #[cfg(not(feature = "51"))]
+    pub(crate) fn disable_interrupt(&mut self) {
+        self.rtc.disable_interrupt(RtcInterrupt::Compare0, None);
+    }
+
+    /// Enables routing of the event to PPI.
+    #[cfg(not(feature = "9160"))]
+    pub(crate) fn enable_event(&mut self) {
+        self.rtc.enable_event(RtcInterrupt::Compare0);
+    }
+
+    /// Disables routing of the event to PPI.
+    #[cfg(not(feature = "9160"))]
+    pub(crate) fn disable_event(&mut self) {
+        self.rtc.disable_event(RtcInterrupt::Compare0);
+    }
+
+    /// Returns reference to the event endpoint for PPI.
+    #[cfg(not(feature = "9160"))]
+    pub(crate) fn event(&self) -> &Reg<u32, _EVENTS_COMPARE> {
+        self.rtc.event_compare(RtcCompareReg::Compare0)
+    }
+
+    /// Stops the counter, and releases the RTC.
+    pub(crate) fn free(mut self) -> T {
+        self.stop();
+        self.rtc.release()
+    }
 }
 
 /// Error types associated with the RTC peripheral interface.
@@ -222,24 +601,55 @@ pub enum Error {
 pub trait Instance: Deref<Target = rtc0::RegisterBlock> + sealed::Sealed {
     /// The interrupt associated with this RTC instance.
     const INTERRUPT: Interrupt;
+
+    /// The alarm using the fourth compare register, or `()` if this RTC only has 3.
+    type Alarm3: sealed::NewAlarm;
+
+    /// Returns a pointer to the register block.
+    fn ptr() -> *const rtc0::RegisterBlock;
 }
 
 mod sealed {
     pub trait Sealed {}
+
+    pub trait NewAlarm {
+        fn new() -> Self;
+    }
+}
+
+impl<T, const N: usize> sealed::NewAlarm for Alarm<T, N> {
+    fn new() -> Self {
+        Alarm { _rtc: PhantomData }
+    }
+}
+
+impl sealed::NewAlarm for () {
+    fn new() -> Self {}
 }
 
 macro_rules! impl_instance {
-    ($($name:ident,)*) => {
+    ($($name:ident => $alarm3:ty,)*) => {
         $(
             impl Instance for $name {
                 const INTERRUPT: Interrupt = Interrupt::$name;
+                type Alarm3 = $alarm3;
+
+                fn ptr() -> *const rtc0::RegisterBlock {
+                    $name::ptr()
+                }
             }
             impl sealed::Sealed for $name {}
         )*
     }
 }
 
-impl_instance!(RTC0, RTC1,);
+#[cfg(not(feature = "9160"))]
+impl_instance!(RTC0 => (),);
+
+#[cfg(feature = "9160")]
+impl_instance!(RTC0 => Alarm<RTC0, 3>,);
+
+impl_instance!(RTC1 => Alarm<RTC1, 3>,);
 
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
-impl_instance!(RTC2,);
+impl_instance!(RTC2 => Alarm<RTC2, 3>,);
@@ -20,7 +20,7 @@
 //! // initialize saadc interface
 //! let saadc_config = SaadcConfig::default();
 //! let mut saadc = Saadc::new(board.SAADC, saadc_config);
-//! let mut saadc_pin = gpios.p0_02; // the pin your analog device is connected to
+//! let mut saadc_pin = gpios.p0_02.into_analog(); // the pin your analog device is connected to
 //!
 //! // blocking read from saadc for `saadc_config.time` microseconds
 //! let _saadc_result = saadc.read(&mut saadc_pin);
@@ -32,27 +32,44 @@ use crate::pac::{saadc_ns as saadc, SAADC_NS as SAADC};
 #[cfg(not(feature = "9160"))]
 use crate::pac::{saadc, SAADC};
 
+use crate::pac::generic::{Reg, Variant};
 use core::{
     hint::unreachable_unchecked,
     sync::atomic::{compiler_fence, Ordering::SeqCst},
 };
 use embedded_hal::adc::{Channel, OneShot};
 
+#[cfg(not(feature = "9160"))]
+use {
+    crate::{ppi::ConfigurablePpi, timer},
+    fugit::MicrosDurationU32,
+};
+
+use saadc::{
+    _EVENTS_DONE, _EVENTS_END, _EVENTS_RESULTDONE, _EVENTS_STARTED, _TASKS_SAMPLE, _TASKS_START,
+    _TASKS_STOP,
+};
+
 pub use saadc::{
-    ch::config::{GAIN_A as Gain, REFSEL_A as Reference, RESP_A as Resistor, TACQ_A as Time},
+    ch::config::{
+        GAIN_A as Gain, REFSEL_A as Reference, RESN_A as NegativeResistor, RESP_A as Resistor,
+        TACQ_A as Time,
+    },
     oversample::OVERSAMPLE_A as Oversample,
     resolution::VAL_A as Resolution,
 };
 
-// Only 1 channel is allowed right now, a discussion needs to be had as to how
-// multiple channels should work (See "scan mode" in the datasheet).
-// Issue: https://github.com/nrf-rs/nrf-hal/issues/82
-
 /// Interface for the SAADC peripheral.
 ///
-/// External analog channels supported by the SAADC implement the `Channel` trait.
-/// Currently, use of only one channel is allowed.
-pub struct Saadc(SAADC);
+/// External analog channels supported by the SAADC implement the `Channel` trait once they have
+/// been converted with `into_analog`. Single channels are sampled with the `OneShot` trait, and
+/// up to 8 channels can be sampled at once with `scan`. Channels can also be sampled
+/// differentially, see `Input::differential`.
+pub struct Saadc(
+    SAADC,
+    // Channel configuration set up by `new`, which inputs with their own settings override.
+    u32,
+);
 
 impl Saadc {
     pub fn new(saadc: SAADC, config: SaadcConfig) -> Self {
@@ -90,7 +107,699 @@ impl Saadc {
         saadc.tasks_calibrateoffset.write(|w| unsafe { w.bits(1) });
         while saadc.events_calibratedone.read().bits() == 0 {}
 
-        Saadc(saadc)
+        let config = saadc.ch[0].config.read().bits();
+        Saadc(saadc, config)
+    }
+
+    /// Samples several inputs in one scan, and stores one result per input in `results`, in the
+    /// order of `inputs`.
+    ///
+    /// All inputs use the configuration the SAADC was created with. The conversions run back to
+    /// back, and the results are transferred to RAM with a single EasyDMA operation. Note that
+    /// this is a blocking operation.
+    pub fn scan(&mut self, inputs: &[Input], results: &mut [i16]) -> Result<(), Error> {
+        if results.len() < inputs.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        self.connect_inputs(inputs)?;
+
+        self.0
+            .result
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(results.as_mut_ptr() as u32) });
+        self.0
+            .result
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(inputs.len() as _) });
+
+        // Conservative compiler fence to prevent starting the ADC before the
+        // pointer and maxcount have been set.
+        compiler_fence(SeqCst);
+
+        self.start_and_sample();
+        self.disconnect_inputs(inputs.len());
+
+        // Second fence to prevent optimizations creating issues with the EasyDMA-modified `results`.
+        compiler_fence(SeqCst);
+
+        Ok(())
+    }
+
+    /// Sets the number of samples averaged into each result.
+    ///
+    /// When more than one input is sampled at once, oversampling requires burst mode on all
+    /// inputs.
+    pub fn set_oversample(&mut self, oversample: Oversample) {
+        self.0
+            .oversample
+            .write(|w| w.oversample().variant(oversample));
+    }
+
+    /// Enables or disables burst mode, for the inputs without their own setting.
+    ///
+    /// In burst mode, which is enabled by default, a single `SAMPLE` task takes all the samples
+    /// averaged into one result as fast as possible. Without it, each `SAMPLE` task takes one of
+    /// these samples, so that a TIMER can spread them over time, e.g. with `ContinuousSaadc`.
+    /// Blocking reads trigger as many `SAMPLE` tasks as needed.
+    pub fn set_burst(&mut self, enabled: bool) {
+        self.0.ch[0].config.modify(|_, w| w.burst().bit(enabled));
+        self.1 = self.0.ch[0].config.read().bits();
+    }
+
+    /// Starts the SAADC, triggers the `SAMPLE` tasks needed for one result per connected
+    /// channel, and waits for the results.
+    fn start_and_sample(&self) {
+        // Without burst mode, each task only takes one of the averaged samples. Oversampling
+        // without burst mode is only allowed with a single channel, so channel 0 decides.
+        let samples = if self.0.ch[0].config.read().burst().is_enabled() {
+            1
+        } else {
+            1 << self.0.oversample.read().bits()
+        };
+
+        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
+        for _ in 1..samples {
+            self.0.events_done.reset();
+            self.0.tasks_sample.write(|w| unsafe { w.bits(1) });
+            while self.0.events_done.read().bits() == 0 {}
+        }
+        // A single SAMPLE task samples all enabled channels.
+        self.0.tasks_sample.write(|w| unsafe { w.bits(1) });
+
+        while self.0.events_end.read().bits() == 0 {}
+        self.0.events_end.reset();
+    }
+
+    /// Connects the inputs to the first channels.
+    fn connect_inputs(&self, inputs: &[Input]) -> Result<(), Error> {
+        if inputs.is_empty() {
+            return Err(Error::NoInputs);
+        }
+        if inputs.len() > self.0.ch.len() {
+            return Err(Error::TooManyInputs);
+        }
+        let burst = self.0.ch[0].config.read().burst().is_enabled();
+        if inputs.len() > 1
+            && self.0.oversample.read().bits() != 0
+            && inputs.iter().any(|input| !input.burst.unwrap_or(burst))
+        {
+            return Err(Error::OversampleWithoutBurst);
+        }
+
+        for (ch, input) in inputs.iter().enumerate() {
+            let ch = &self.0.ch[ch];
+            ch.config.write(|w| {
+                unsafe { w.bits(self.1) };
+                if input.negative.is_some() {
+                    w.mode().diff();
+                }
+                if let Some(gain) = input.gain {
+                    w.gain().variant(gain);
+                }
+                if let Some(reference) = input.reference {
+                    w.refsel().variant(reference);
+                }
+                if let Some(time) = input.time {
+                    w.tacq().variant(time);
+                }
+                if let Some(resistor) = input.resistor {
+                    w.resp().variant(resistor);
+                }
+                if let Some(resistor) = input.negative_resistor {
+                    w.resn().variant(resistor);
+                }
+                if let Some(burst) = input.burst {
+                    w.burst().bit(burst);
+                }
+                w
+            });
+            ch.pselp
+                .write(|w| unsafe { w.pselp().bits(psel(input.positive)) });
+            match input.negative {
+                Some(negative) => ch
+                    .pseln
+                    .write(|w| unsafe { w.pseln().bits(psel(negative)) }),
+                None => ch.pseln.write(|w| w.pseln().nc()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Disconnects the additional channels again, and restores the configuration of channel 0,
+    /// so that `read` only samples channel 0 in single-ended mode.
+    fn disconnect_inputs(&self, count: usize) {
+        for ch in 1..count {
+            self.0.ch[ch].pselp.write(|w| w.pselp().nc());
+            self.0.ch[ch].pseln.write(|w| w.pseln().nc());
+        }
+        self.0.ch[0].config.write(|w| unsafe { w.bits(self.1) });
+        self.0.ch[0].pseln.write(|w| w.pseln().nc());
+    }
+
+    /// Samples the voltage between two pins, in differential mode.
+    ///
+    /// The result is negative when `negative` is at a higher voltage than `positive`. With the
+    /// default configuration, the input range is ±VDD, which is mapped to ±2^13 at 14-bit
+    /// resolution. Note that this is a blocking operation.
+    pub fn read_differential<P, N>(
+        &mut self,
+        positive: &mut P,
+        negative: &mut N,
+    ) -> Result<i16, Error>
+    where
+        P: Channel<Saadc, ID = u8>,
+        N: Channel<Saadc, ID = u8>,
+    {
+        self.read_input(Input::differential(positive, negative))
+    }
+
+    /// Samples a single input, with its own configuration. Note that this is a blocking operation.
+    pub fn read_input(&mut self, input: Input) -> Result<i16, Error> {
+        let mut result = [0];
+        self.scan(&[input], &mut result)?;
+        Ok(result[0])
+    }
+
+    /// Returns the scale of the results of `input`, to convert them to millivolts.
+    ///
+    /// `vdd_mv` is the supply voltage in millivolts, which is only used when the input uses
+    /// `Reference::VDD1_4`. For internal inputs selected with `Input::internal`, the scale
+    /// includes the divider of the input.
+    pub fn scale(&self, input: &Input, vdd_mv: u32) -> Scale {
+        // Channel 0 holds the configuration set up by `new` while no input is connected.
+        let config = self.0.ch[0].config.read();
+        let resolution = self.0.resolution.read().val().variant();
+        let scale = Scale::new(
+            match resolution {
+                Variant::Val(resolution) => resolution,
+                // Reserved values aren't written by this driver.
+                Variant::Res(_) => unreachable!(),
+            },
+            input.gain.unwrap_or_else(|| config.gain().variant()),
+            input.reference.unwrap_or_else(|| config.refsel().variant()),
+            input.negative.is_some(),
+            vdd_mv,
+        );
+        Scale {
+            numerator: scale.numerator * i64::from(input.divider),
+            ..scale
+        }
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    /// Starts the SAADC and prepares the result buffer in RAM.
+    #[inline(always)]
+    pub fn task_start(&self) -> &Reg<u32, _TASKS_START> {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to the `SAMPLE` task endpoint for PPI.
+    /// Takes one SAADC sample.
+    #[inline(always)]
+    pub fn task_sample(&self) -> &Reg<u32, _TASKS_SAMPLE> {
+        &self.0.tasks_sample
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    /// Stops the SAADC and terminates all on-going conversions.
+    #[inline(always)]
+    pub fn task_stop(&self) -> &Reg<u32, _TASKS_STOP> {
+        &self.0.tasks_stop
+    }
+
+    /// Returns reference to the `STARTED` event endpoint for PPI.
+    /// Generated when the SAADC has started.
+    #[inline(always)]
+    pub fn event_started(&self) -> &Reg<u32, _EVENTS_STARTED> {
+        &self.0.events_started
+    }
+
+    /// Returns reference to the `END` event endpoint for PPI.
+    /// Generated when the SAADC has filled up the result buffer.
+    #[inline(always)]
+    pub fn event_end(&self) -> &Reg<u32, _EVENTS_END> {
+        &self.0.events_end
+    }
+
+    /// Returns reference to the `DONE` event endpoint for PPI.
+    /// Generated when a conversion task has been completed.
+    #[inline(always)]
+    pub fn event_done(&self) -> &Reg<u32, _EVENTS_DONE> {
+        &self.0.events_done
+    }
+
+    /// Returns reference to the `RESULTDONE` event endpoint for PPI.
+    /// Generated when a result is ready to get transferred to RAM.
+    #[inline(always)]
+    pub fn event_result_done(&self) -> &Reg<u32, _EVENTS_RESULTDONE> {
+        &self.0.events_resultdone
+    }
+}
+
+/// The two sample buffers used by `ContinuousSaadc`.
+pub type Buffers = (&'static mut [i16], &'static mut [i16]);
+
+/// Continuous sampling at a fixed rate, into two alternating buffers.
+///
+/// A TIMER triggers the `SAMPLE` task through PPI, so the sampling times don't depend on the CPU.
+/// Each `SAMPLE` task samples all the inputs, and the results are interleaved in the buffers: with
+/// inputs `[a, b]`, a buffer holds `[a0, b0, a1, b1, ...]`. When a buffer is full, the `END`
+/// event restarts the SAADC with the other buffer through a second PPI channel, so that no sample
+/// is lost while the CPU processes the full one.
+///
+/// ```ignore
+/// static mut BUF_A: [i16; 256] = [0; 256];
+/// static mut BUF_B: [i16; 256] = [0; 256];
+///
+/// let mut adc = ContinuousSaadc::new(
+///     saadc,
+///     p.TIMER1,
+///     ppi_channels.ppi0,
+///     ppi_channels.ppi1,
+///     &[Input::new(&pin)],
+///     100.micros(),
+///     unsafe { (&mut BUF_A, &mut BUF_B) },
+/// )?;
+/// adc.start();
+///
+/// loop {
+///     let mean = block!(adc.process(|samples| {
+///         samples.iter().map(|&s| i32::from(s)).sum::<i32>() / samples.len() as i32
+///     }))?;
+/// }
+/// ```
+///
+/// Each buffer has to be processed before the other one is full, i.e. within one buffer period.
+#[cfg(not(feature = "9160"))]
+pub struct ContinuousSaadc<T, S, R> {
+    saadc: Saadc,
+    timer: T,
+    sample_ppi: S,
+    restart_ppi: R,
+    inputs: usize,
+    buffers: [&'static mut [i16]; 2],
+    filling: usize,
+}
+
+#[cfg(not(feature = "9160"))]
+impl<T, S, R> ContinuousSaadc<T, S, R>
+where
+    T: timer::Instance,
+    S: ConfigurablePpi,
+    R: ConfigurablePpi,
+{
+    /// Sets up continuous sampling of `inputs` every `interval`, which must be at least the
+    /// total conversion time of the inputs.
+    ///
+    /// The two buffers must have the same length, which must be a multiple of the number of
+    /// inputs. Sampling starts with `start`.
+    ///
+    /// With oversampling, each result is the average of several samples. In burst mode, they are
+    /// all taken on each TIMER event, otherwise one of them is taken per TIMER event.
+    pub fn new(
+        saadc: Saadc,
+        timer: T,
+        mut sample_ppi: S,
+        mut restart_ppi: R,
+        inputs: &[Input],
+        interval: MicrosDurationU32,
+        buffers: Buffers,
+    ) -> Result<Self, Error> {
+        let (buf_a, buf_b) = buffers;
+        let len = buf_a.len();
+        if len != buf_b.len() || len % inputs.len().max(1) != 0 {
+            return Err(Error::BufferLengthMismatch);
+        }
+        if len < inputs.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        if len > 0x7FFF {
+            return Err(Error::BufferTooLong);
+        }
+        saadc.connect_inputs(inputs)?;
+
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        regs.shorts.write(|w| w.compare0_clear().enabled());
+        regs.mode.write(|w| w.mode().timer());
+        regs.prescaler.write(
+            |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
+        );
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.cc[0].write(|w| unsafe { w.bits(interval.ticks().max(1)) });
+
+        sample_ppi.set_event_endpoint(&regs.events_compare[0]);
+        sample_ppi.set_task_endpoint(saadc.task_sample());
+        restart_ppi.set_event_endpoint(saadc.event_end());
+        restart_ppi.set_task_endpoint(saadc.task_start());
+
+        saadc
+            .0
+            .result
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(len as u16) });
+
+        Ok(Self {
+            saadc,
+            timer,
+            sample_ppi,
+            restart_ppi,
+            inputs: inputs.len(),
+            buffers: [buf_a, buf_b],
+            filling: 0,
+        })
+    }
+
+    /// Starts sampling into the first buffer.
+    pub fn start(&mut self) {
+        let first = self.buffers[0].as_mut_ptr() as u32;
+        let second = self.buffers[1].as_mut_ptr() as u32;
+        let saadc = &self.saadc.0;
+        self.filling = 0;
+        saadc.events_end.reset();
+        saadc.events_started.reset();
+        saadc.result.ptr.write(|w| unsafe { w.ptr().bits(first) });
+
+        // Conservative compiler fence to prevent starting the ADC before the
+        // pointer and maxcount have been set.
+        compiler_fence(SeqCst);
+
+        saadc.tasks_start.write(|w| unsafe { w.bits(1) });
+        while saadc.events_started.read().bits() == 0 {}
+        saadc.events_started.reset();
+
+        // The pointer is latched on START, so the next buffer can be set right away.
+        saadc.result.ptr.write(|w| unsafe { w.ptr().bits(second) });
+
+        self.restart_ppi.enable();
+        self.sample_ppi.enable();
+        let timer = self.timer.as_timer0();
+        timer.tasks_clear.write(|w| unsafe { w.bits(1) });
+        timer.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stops sampling. The partially filled buffer is discarded.
+    pub fn stop(&mut self) {
+        self.timer
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+        self.sample_ppi.disable();
+        self.restart_ppi.disable();
+
+        let saadc = &self.saadc.0;
+        saadc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while saadc.events_stopped.read().bits() == 0 {}
+        saadc.events_stopped.reset();
+        saadc.events_end.reset();
+        saadc.events_started.reset();
+
+        compiler_fence(SeqCst);
+    }
+
+    /// Passes the buffer that was filled last to `f`, and returns its result.
+    ///
+    /// Returns `WouldBlock` if no buffer has been filled since the last call. The buffer is
+    /// filled again once the other one is full, so `f` must return before that.
+    pub fn process<F, U>(&mut self, f: F) -> nb::Result<U, Error>
+    where
+        F: FnOnce(&[i16]) -> U,
+    {
+        let saadc = &self.saadc.0;
+        if saadc.events_end.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        saadc.events_end.reset();
+
+        // The `END` event has already restarted the SAADC with the other buffer through PPI.
+        // Once it has started, the full buffer can be queued up after it.
+        while saadc.events_started.read().bits() == 0 {}
+        saadc.events_started.reset();
+
+        let full = self.filling;
+        self.filling = 1 - full;
+        let ptr = self.buffers[full].as_mut_ptr() as u32;
+        saadc.result.ptr.write(|w| unsafe { w.ptr().bits(ptr) });
+
+        // Prevent the buffer from being read before the `END` event has been observed.
+        compiler_fence(SeqCst);
+
+        Ok(f(&*self.buffers[full]))
+    }
+
+    /// Returns reference to the `END` event endpoint, which is generated when a buffer is full.
+    #[inline(always)]
+    pub fn event_end(&self) -> &Reg<u32, _EVENTS_END> {
+        self.saadc.event_end()
+    }
+
+    /// Enables the SAADC interrupt on the `END` event, to be notified of full buffers.
+    pub fn enable_interrupt(&mut self) {
+        self.saadc.0.intenset.write(|w| w.end().set());
+    }
+
+    /// Disables the SAADC interrupt on the `END` event.
+    pub fn disable_interrupt(&mut self) {
+        self.saadc.0.intenclr.write(|w| w.end().clear());
+    }
+
+    /// Stops sampling, and releases the SAADC, the TIMER, the PPI channels and the buffers.
+    pub fn free(mut self) -> (Saadc, T, S, R, Buffers) {
+        self.stop();
+        self.disable_interrupt();
+        self.saadc.disconnect_inputs(self.inputs);
+
+        let Self {
+            saadc,
+            timer,
+            sample_ppi,
+            restart_ppi,
+            buffers: [buf_a, buf_b],
+            ..
+        } = self;
+        (saadc, timer, sample_ppi, restart_ppi, (buf_a, buf_b))
+    }
+}
+
+/// An analog input sampled by `Saadc::scan`, `Saadc::read_input` or `ContinuousSaadc`, along
+/// with the configuration of the SAADC channel sampling it.
+///
+/// By default, inputs use the configuration the SAADC was created with. Each setting can be
+/// overridden per input, so that inputs with different front-ends can be sampled together:
+///
+/// ```ignore
+/// let inputs = [
+///     Input::new(&thermistor),
+///     Input::differential(&shunt_p, &shunt_n)
+///         .gain(Gain::GAIN4)
+///         .reference(Reference::INTERNAL)
+///         .time(Time::_40US),
+/// ];
+/// saadc.scan(&inputs, &mut results)?;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Input {
+    positive: u8,
+    negative: Option<u8>,
+    gain: Option<Gain>,
+    reference: Option<Reference>,
+    time: Option<Time>,
+    resistor: Option<Resistor>,
+    negative_resistor: Option<NegativeResistor>,
+    burst: Option<bool>,
+    // Ratio between the measured voltage and the sampled one, see `InternalInput::DIVIDER`.
+    divider: u32,
+}
+
+impl Input {
+    /// Selects the analog input of the given pin, or an `InternalInput`, in single-ended mode.
+    ///
+    /// Internal inputs should rather be selected with `Input::internal`, so that their divider
+    /// is taken into account by `Saadc::scale`.
+    pub fn new<PIN>(_pin: &PIN) -> Self
+    where
+        PIN: Channel<Saadc, ID = u8>,
+    {
+        Self::with_inputs(PIN::channel(), None)
+    }
+
+    /// Selects an `InternalInput`, in single-ended mode.
+    ///
+    /// The scale returned by `Saadc::scale` for this input converts results to the measured
+    /// voltage, e.g. VDDH instead of VDDH/5 for `InternalVddhDiv5`.
+    pub fn internal<I>(_input: &I) -> Self
+    where
+        I: InternalInput,
+    {
+        Input {
+            divider: I::DIVIDER,
+            ..Self::with_inputs(I::channel(), None)
+        }
+    }
+
+    /// Selects the voltage between two analog inputs, in differential mode.
+    ///
+    /// In differential mode, the input range is ±(reference / gain), and samples are signed.
+    /// This suits bridge sensors, or current measurement across a shunt resistor, where the
+    /// reference voltage of `Reference::INTERNAL` (0.6 V) and a high gain give the best
+    /// resolution for small voltages. Both inputs must stay within the supply range.
+    pub fn differential<P, N>(_positive: &P, _negative: &N) -> Self
+    where
+        P: Channel<Saadc, ID = u8>,
+        N: Channel<Saadc, ID = u8>,
+    {
+        Self::with_inputs(P::channel(), Some(N::channel()))
+    }
+
+    fn with_inputs(positive: u8, negative: Option<u8>) -> Self {
+        Input {
+            positive,
+            negative,
+            gain: None,
+            reference: None,
+            time: None,
+            resistor: None,
+            negative_resistor: None,
+            burst: None,
+            divider: 1,
+        }
+    }
+
+    /// Uses the given gain for this input.
+    pub fn gain(mut self, gain: Gain) -> Self {
+        self.gain = Some(gain);
+        self
+    }
+
+    /// Uses the given reference voltage for this input.
+    pub fn reference(mut self, reference: Reference) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Uses the given acquisition time for this input.
+    ///
+    /// Sources with a high output impedance need a longer acquisition time.
+    pub fn time(mut self, time: Time) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Connects the given resistor ladder to the positive input.
+    pub fn resistor(mut self, resistor: Resistor) -> Self {
+        self.resistor = Some(resistor);
+        self
+    }
+
+    /// Connects the given resistor ladder to the negative input.
+    pub fn negative_resistor(mut self, resistor: NegativeResistor) -> Self {
+        self.negative_resistor = Some(resistor);
+        self
+    }
+
+    /// Enables or disables burst mode for this input. See `Saadc::set_burst`.
+    pub fn burst(mut self, enabled: bool) -> Self {
+        self.burst = Some(enabled);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// `Saadc::scan` was called without any input.
+    NoInputs,
+    /// More inputs than SAADC channels were given to `Saadc::scan`.
+    TooManyInputs,
+    /// The result buffer can't hold one sample per input.
+    BufferTooSmall,
+    /// The result buffer is longer than EasyDMA can transfer at once.
+    BufferTooLong,
+    /// The buffers of `ContinuousSaadc` must have the same length, which must be a multiple of
+    /// the number of inputs.
+    BufferLengthMismatch,
+    /// Oversampling several inputs at once requires burst mode on all of them.
+    OversampleWithoutBurst,
+}
+
+/// Returns the `PSELP` or `PSELN` value selecting the analog input with the given ID.
+fn psel(input: u8) -> u8 {
+    match input {
+        // `AnalogInput0` to `AnalogInput7`
+        0..=7 => input + 1,
+        // `VDD`
+        #[cfg(not(feature = "9160"))]
+        8 => 9,
+        // `VDDHDIV5`
+        #[cfg(any(feature = "52833", feature = "52840"))]
+        9 => 13,
+        // This can never happen the only analog pins have already been defined
+        // PAY CLOSE ATTENTION TO ANY CHANGES TO THIS IMPL OR THE `channel_mappings!` MACRO
+        _ => unsafe { unreachable_unchecked() },
+    }
+}
+
+/// Converts SAADC results to millivolts, and back.
+///
+/// A result is the input voltage multiplied by `gain / reference`, and by 2^resolution in
+/// single-ended mode or 2^(resolution - 1) in differential mode. The scale of an input is best
+/// obtained with `Saadc::scale`, so that it follows configuration changes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Scale {
+    // Millivolts per result, as a fraction.
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Scale {
+    /// Returns the scale for the given configuration.
+    ///
+    /// `vdd_mv` is the supply voltage in millivolts, which is only used with
+    /// `Reference::VDD1_4`.
+    pub fn new(
+        resolution: Resolution,
+        gain: Gain,
+        reference: Reference,
+        differential: bool,
+        vdd_mv: u32,
+    ) -> Self {
+        let bits = match resolution {
+            Resolution::_8BIT => 8,
+            Resolution::_10BIT => 10,
+            Resolution::_12BIT => 12,
+            Resolution::_14BIT => 14,
+        } - differential as u32;
+        let (gain_num, gain_den) = match gain {
+            Gain::GAIN1_6 => (1, 6),
+            Gain::GAIN1_5 => (1, 5),
+            Gain::GAIN1_4 => (1, 4),
+            Gain::GAIN1_3 => (1, 3),
+            Gain::GAIN1_2 => (1, 2),
+            Gain::GAIN1 => (1, 1),
+            Gain::GAIN2 => (2, 1),
+            Gain::GAIN4 => (4, 1),
+        };
+        let reference_mv = match reference {
+            Reference::INTERNAL => 600,
+            Reference::VDD1_4 => i64::from(vdd_mv) / 4,
+        };
+
+        Scale {
+            numerator: reference_mv * gain_den,
+            denominator: gain_num << bits,
+        }
+    }
+
+    /// Converts a result to millivolts.
+    pub fn to_millivolts(&self, result: i16) -> i32 {
+        (i64::from(result) * self.numerator / self.denominator) as i32
+    }
+
+    /// Converts a voltage in millivolts to the result it would be sampled as, saturating at the
+    /// limits of `i16`.
+    pub fn from_millivolts(&self, millivolts: i32) -> i16 {
+        let result = i64::from(millivolts) * self.denominator / self.numerator.max(1);
+        result.max(i64::from(i16::MIN)).min(i64::from(i16::MAX)) as i16
     }
 }
 
@@ -101,6 +810,7 @@ pub struct SaadcConfig {
     /// Output resolution in bits.
     pub resolution: Resolution,
     /// Average 2^`oversample` input samples before transferring the result into memory.
+    /// Sampling more than one input at once with oversampling requires burst mode.
     pub oversample: Oversample,
     /// Reference voltage of the SAADC input.
     pub reference: Reference,
@@ -167,21 +877,9 @@ where
     /// Sample channel `PIN` for the configured ADC acquisition time in differential input mode.
     /// Note that this is a blocking operation.
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<i16, Self::Error> {
-        match PIN::channel() {
-            0 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input0()),
-            1 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input1()),
-            2 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input2()),
-            3 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input3()),
-            4 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input4()),
-            5 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input5()),
-            6 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input6()),
-            7 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input7()),
-            #[cfg(not(feature = "9160"))]
-            8 => self.0.ch[0].pselp.write(|w| w.pselp().vdd()),
-            // This can never happen the only analog pins have already been defined
-            // PAY CLOSE ATTENTION TO ANY CHANGES TO THIS IMPL OR THE `channel_mappings!` MACRO
-            _ => unsafe { unreachable_unchecked() },
-        }
+        self.0.ch[0]
+            .pselp
+            .write(|w| unsafe { w.pselp().bits(psel(PIN::channel())) });
 
         let mut val: i16 = 0;
         self.0
@@ -197,11 +895,7 @@ where
         // pointer and maxcount have been set.
         compiler_fence(SeqCst);
 
-        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
-        self.0.tasks_sample.write(|w| unsafe { w.bits(1) });
-
-        while self.0.events_end.read().bits() == 0 {}
-        self.0.events_end.reset();
+        self.start_and_sample();
 
         // Will only occur if more than one channel has been enabled.
         if self.0.result.amount.read().bits() != 1 {
@@ -218,7 +912,7 @@ where
 macro_rules! channel_mappings {
     ( $($n:expr => $pin:ident,)*) => {
         $(
-            impl<STATE> Channel<Saadc> for crate::gpio::p0::$pin<STATE> {
+            impl Channel<Saadc> for crate::gpio::p0::$pin<crate::gpio::Analog> {
                 type ID = u8;
 
                 fn channel() -> <Self as embedded_hal::adc::Channel<Saadc>>::ID {
@@ -253,6 +947,17 @@ channel_mappings! {
     7 => P0_31,
 }
 
+/// Implemented by the internal inputs of the SAADC, which don't sample a pin.
+///
+/// These are used like analog pins, e.g. `saadc.read(&mut InternalVdd)`.
+pub trait InternalInput: Channel<Saadc, ID = u8> {
+    /// Ratio between the measured voltage and the sampled one.
+    ///
+    /// The measured voltage is the sampled one multiplied by `DIVIDER`, which `Saadc::scale`
+    /// takes into account for inputs selected with `Input::internal`.
+    const DIVIDER: u32;
+}
+
 #[cfg(not(feature = "9160"))]
 impl Channel<Saadc> for InternalVdd {
     type ID = u8;
@@ -262,6 +967,33 @@ impl Channel<Saadc> for InternalVdd {
     }
 }
 
+#[cfg(not(feature = "9160"))]
+impl InternalInput for InternalVdd {
+    const DIVIDER: u32 = 1;
+}
+
 #[cfg(not(feature = "9160"))]
 /// Channel that doesn't sample a pin, but the internal VDD voltage.
 pub struct InternalVdd;
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+impl Channel<Saadc> for InternalVddhDiv5 {
+    type ID = u8;
+
+    fn channel() -> <Self as embedded_hal::adc::Channel<Saadc>>::ID {
+        9
+    }
+}
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+impl InternalInput for InternalVddhDiv5 {
+    const DIVIDER: u32 = 5;
+}
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+/// Channel that doesn't sample a pin, but the VDDH voltage divided by 5.
+///
+/// VDDH is the supply of the high voltage regulator, which can be up to 5.5 V, e.g. a battery
+/// or USB. The divider keeps it within the input range of the SAADC with the default
+/// configuration.
+pub struct InternalVddhDiv5;
@@ -0,0 +1,134 @@
+//! Device-bound secret key storage.
+//!
+//! `Secrets` stores a device-unique 128-bit root key in a KMU key slot, which can only be pushed to
+//! the device root key (K_DR) register of the CryptoCell, and never read back by application
+//! code. Once loaded, the root key is selected as the hardware key of the CryptoCell AES engine,
+//! from which the CryptoCell runtime library derives keys, e.g. a per-device identity or an
+//! attestation signing key, without the root key leaving the hardware.
+//!
+//! ```ignore
+//! let kmu = Kmu::new(p.KMU_S, p.UICR_S);
+//! let mut secrets = Secrets::new(kmu, CryptoCell::new(p.CRYPTOCELL_S), p.CC_HOST_RGF_S);
+//!
+//! // Once, when the device is manufactured:
+//! secrets.provision_root_key(&mut p.NVMC_S, &root_key)?;
+//!
+//! // At each boot:
+//! secrets.load_root_key()?;
+//! // Derive keys with the runtime library, using the hardware root key.
+//! ```
+//!
+//! The root key is stored in the KMU key slot `ROOT_KEY_SLOT`.
+
+use crate::cryptocell::CryptoCell;
+use crate::kmu::{self, KeyDestination, KeyPermissions, Kmu};
+use crate::pac::{CC_HOST_RGF_S, NVMC_S as NVMC};
+
+/// KMU key slot holding the root key.
+pub const ROOT_KEY_SLOT: usize = 0;
+
+/// Secret key storage error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The KMU failed to store or push the key.
+    Kmu(kmu::Error),
+    /// The root key slot is in use with permissions that allow the key to be read or replaced.
+    Insecure,
+    /// The CryptoCell did not retain the root key.
+    NotLoaded,
+}
+
+impl From<kmu::Error> for Error {
+    fn from(error: kmu::Error) -> Self {
+        Error::Kmu(error)
+    }
+}
+
+/// Permissions of the root key slot: push only, and locked once written.
+const ROOT_KEY_PERMISSIONS: KeyPermissions = KeyPermissions {
+    write: false,
+    read: false,
+    push: true,
+};
+
+/// Storage of a device-unique root key, usable by the CryptoCell only.
+pub struct Secrets {
+    kmu: Kmu,
+    cryptocell: CryptoCell,
+    cc_host_rgf: CC_HOST_RGF_S,
+}
+
+impl Secrets {
+    /// Takes ownership of the KMU, and of the CryptoCell, which must be enabled to receive keys.
+    pub fn new(kmu: Kmu, cryptocell: CryptoCell, cc_host_rgf: CC_HOST_RGF_S) -> Self {
+        Self {
+            kmu,
+            cryptocell,
+            cc_host_rgf,
+        }
+    }
+
+    /// Stores `key` as the root key of the device.
+    ///
+    /// This can only be done once, until the next ERASEALL operation. The key should be generated
+    /// for each device, e.g. by the provisioning tool, and erased from memory afterwards.
+    pub fn provision_root_key(&mut self, nvmc: &mut NVMC, key: &[u32; 4]) -> Result<(), Error> {
+        self.kmu.provision(
+            nvmc,
+            ROOT_KEY_SLOT,
+            key,
+            KeyDestination::CryptoCellKdr,
+            ROOT_KEY_PERMISSIONS,
+        )?;
+        Ok(())
+    }
+
+    /// Returns `true` if a root key has been provisioned with secure permissions.
+    ///
+    /// Fails with `Insecure` if the root key slot is in use with other permissions.
+    pub fn is_provisioned(&mut self) -> Result<bool, Error> {
+        match self.kmu.permissions(ROOT_KEY_SLOT)? {
+            None => Ok(false),
+            Some(permissions) if permissions == ROOT_KEY_PERMISSIONS => Ok(true),
+            Some(_) => Err(Error::Insecure),
+        }
+    }
+
+    /// Pushes the root key to the CryptoCell, and selects it as the hardware key of the AES
+    /// engine.
+    ///
+    /// This must be done at each boot, before the runtime library uses the root key. The key is
+    /// retained by the CryptoCell until the next reset.
+    pub fn load_root_key(&mut self) -> Result<(), Error> {
+        if !self.is_provisioned()? {
+            return Err(Error::Kmu(kmu::Error::SlotEmpty));
+        }
+        self.kmu.push(ROOT_KEY_SLOT)?;
+        if !self.is_root_key_loaded() {
+            return Err(Error::NotLoaded);
+        }
+        self.cc_host_rgf
+            .host_cryptokey_sel
+            .write(|w| w.host_cryptokey_sel().k_dr());
+        Ok(())
+    }
+
+    /// Returns `true` if the CryptoCell holds the root key.
+    pub fn is_root_key_loaded(&self) -> bool {
+        // Reading the first K_DR register returns whether K_DR is retained, not the key.
+        self.cc_host_rgf.host_iot_kdr0.read().bits() & 1 != 0
+    }
+
+    /// Locks the provisioning key (K_PRTL) of the CryptoCell until the next reset, so that only
+    /// the root key and session keys can be used.
+    pub fn lock_provisioning_key(&mut self) {
+        self.cc_host_rgf
+            .host_iot_kprtl_lock
+            .write(|w| w.host_iot_kprtl_lock().enabled());
+    }
+
+    /// Releases the KMU, the CryptoCell and its host registers.
+    pub fn free(self) -> (Kmu, CryptoCell, CC_HOST_RGF_S) {
+        (self.kmu, self.cryptocell, self.cc_host_rgf)
+    }
+}
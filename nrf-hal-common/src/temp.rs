@@ -1,6 +1,36 @@
 //! Temperature sensor interface.
+//!
+//! Temperatures are returned in °C as `I30F2` fixed-point numbers, i.e. with a resolution of
+//! 0.25 °C. A measurement takes about 36 µs.
+//!
+//! `TempMonitor` measures the temperature periodically in the background: an RTC compare event
+//! starts the measurements through PPI, and the results are collected in the TEMP interrupt.
+//!
+//! ```ignore
+//! let temp = Temp::new(p.TEMP);
+//! let mut monitor = TempMonitor::new(temp, p.RTC1, ppi_channels.ppi0, 4_000.millis());
+//! monitor.start();
+//!
+//! // After unmasking TEMP in the NVIC, in the `TEMP` interrupt handler:
+//! if let Some(temperature) = monitor.on_interrupt() {
+//!     // ...
+//! }
+//!
+//! // Anywhere else:
+//! let temperature = monitor.temperature();
+//! ```
+//!
+//! The RTC counts at 8 Hz from the low frequency clock, which must be running.
 
-use crate::pac::TEMP;
+use fugit::MillisDurationU32;
+
+use crate::pac::{
+    generic::Reg,
+    temp::{_EVENTS_DATARDY, _TASKS_START},
+    TEMP,
+};
+use crate::ppi::ConfigurablePpi;
+use crate::rtc::{self, PeriodicRtc};
 use fixed::types::I30F2;
 use void::Void;
 
@@ -54,4 +84,112 @@ impl Temp {
             Ok(I30F2::from_bits(raw as i32))
         }
     }
+
+    /// Enables the `TEMP` interrupt, triggered when a measurement is ready.
+    pub fn enable_interrupt(&mut self) {
+        self.0.intenset.write(|w| w.datardy().set());
+    }
+
+    /// Disables the `TEMP` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.0.intenclr.write(|w| w.datardy().clear());
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    #[inline(always)]
+    pub fn task_start(&self) -> &Reg<u32, _TASKS_START> {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to the `DATARDY` event endpoint for PPI.
+    #[inline(always)]
+    pub fn event_data_ready(&self) -> &Reg<u32, _EVENTS_DATARDY> {
+        &self.0.events_datardy
+    }
+
+    /// Cancels any in-progress measurement and releases the temperature sensor.
+    pub fn free(mut self) -> TEMP {
+        self.stop_measurement();
+        self.disable_interrupt();
+        self.0
+    }
+}
+
+/// Periodic temperature measurement, started by an RTC through PPI.
+pub struct TempMonitor<T, P> {
+    temp: Temp,
+    rtc: PeriodicRtc<T>,
+    ppi: P,
+    latest: Option<I30F2>,
+}
+
+impl<T, P> TempMonitor<T, P>
+where
+    T: rtc::Instance,
+    P: ConfigurablePpi,
+{
+    /// Sets up the measurement of the temperature every `period`, in steps of 125 ms.
+    ///
+    /// The RTC compare event 0 is connected to the TEMP `START` task with the PPI channel.
+    /// Measurements start with `start`.
+    pub fn new(mut temp: Temp, rtc: T, mut ppi: P, period: MillisDurationU32) -> Self {
+        temp.stop_measurement();
+        let mut rtc = PeriodicRtc::new(rtc, period);
+        rtc.enable_event();
+
+        ppi.set_event_endpoint(rtc.event());
+        ppi.set_task_endpoint(temp.task_start());
+
+        Self {
+            temp,
+            rtc,
+            ppi,
+            latest: None,
+        }
+    }
+
+    /// Takes a first measurement, and starts the periodic measurements.
+    ///
+    /// The TEMP interrupt must be unmasked in the NVIC, and its handler must call
+    /// `on_interrupt`.
+    pub fn start(&mut self) {
+        self.latest = Some(self.temp.measure());
+
+        self.temp.enable_interrupt();
+        self.ppi.enable();
+        self.rtc.start();
+    }
+
+    /// Stops the periodic measurements.
+    pub fn stop(&mut self) {
+        self.rtc.stop();
+        self.ppi.disable();
+        self.temp.disable_interrupt();
+        self.temp.stop_measurement();
+    }
+
+    /// Handles the TEMP interrupt, collecting the result of the measurement and scheduling the
+    /// next one. Must be called from the TEMP interrupt handler.
+    ///
+    /// Returns the new temperature, if a measurement completed.
+    pub fn on_interrupt(&mut self) -> Option<I30F2> {
+        let temperature = self.temp.read().ok()?;
+        self.latest = Some(temperature);
+
+        self.rtc.schedule_next();
+
+        Some(temperature)
+    }
+
+    /// Returns the last measured temperature in °C, or `None` before `start`.
+    pub fn temperature(&self) -> Option<I30F2> {
+        self.latest
+    }
+
+    /// Stops the measurements, and releases the temperature sensor, the RTC and the PPI channel.
+    pub fn free(mut self) -> (Temp, T, P) {
+        self.stop();
+        self.rtc.disable_event();
+        (self.temp, self.rtc.free(), self.ppi)
+    }
 }
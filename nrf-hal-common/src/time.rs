@@ -1,4 +1,9 @@
 //! Time units.
+//!
+//! The timer, RTC and delay APIs also accept [fugit](https://docs.rs/fugit) durations, which can
+//! be created with the `ExtU32` extension trait re-exported here, e.g. `500.millis()`.
+
+pub use fugit::{ExtU32, HertzU32, MicrosDurationU32, MillisDurationU32, RateExtU32};
 
 /// Bits per second.
 #[derive(Clone, Copy)]
@@ -66,3 +71,9 @@ impl Into<KiloHertz> for MegaHertz {
         KiloHertz(self.0 * 1_000)
     }
 }
+
+impl From<HertzU32> for Hertz {
+    fn from(rate: HertzU32) -> Hertz {
+        Hertz(rate.to_Hz())
+    }
+}
@@ -6,8 +6,8 @@
 use crate::pac::{
     generic::Reg,
     timer0_ns::{
-        RegisterBlock as RegBlock0, _EVENTS_COMPARE, _TASKS_CAPTURE, _TASKS_CLEAR, _TASKS_COUNT,
-        _TASKS_START, _TASKS_STOP,
+        RegisterBlock as RegBlock0, _CC, _EVENTS_COMPARE, _TASKS_CAPTURE, _TASKS_CLEAR,
+        _TASKS_COUNT, _TASKS_START, _TASKS_STOP,
     },
     Interrupt, TIMER0_NS as TIMER0, TIMER1_NS as TIMER1, TIMER2_NS as TIMER2,
 };
@@ -16,8 +16,8 @@ use crate::pac::{
 use crate::pac::{
     generic::Reg,
     timer0::{
-        RegisterBlock as RegBlock0, _EVENTS_COMPARE, _TASKS_CAPTURE, _TASKS_CLEAR, _TASKS_COUNT,
-        _TASKS_START, _TASKS_STOP,
+        RegisterBlock as RegBlock0, _CC, _EVENTS_COMPARE, _TASKS_CAPTURE, _TASKS_CLEAR,
+        _TASKS_COUNT, _TASKS_START, _TASKS_STOP,
     },
     Interrupt, TIMER0, TIMER1, TIMER2,
 };
@@ -47,17 +47,31 @@ use crate::pac::timer0::{
 };
 
 use core::marker::PhantomData;
+use fugit::Duration;
 
 pub struct OneShot;
 pub struct Periodic;
+pub struct Counter;
+
+/// Width of the TIMER counter.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Bitmode {
+    Bits8,
+    Bits16,
+    Bits24,
+    Bits32,
+}
 
 /// Interface to a TIMER instance.
 ///
-/// Right now, this is a very basic interface. The timer will always be
-/// hardcoded to a frequency of 1 MHz and 32 bits accuracy.
+/// The timer will always be hardcoded to a frequency of 1 MHz. The counter is
+/// 32 bits wide by default, which can be changed with `set_bitmode`.
 ///
 /// CC[0] is used for the current/most-recent delay period and CC[1] is used
-/// to grab the current value of the counter at a given instant.
+/// to grab the current value of the counter at a given instant. All CC
+/// channels can also be used directly with `set_compare`, `capture` and the
+/// related methods, e.g. to schedule several independent deadlines on a
+/// free-running counter.
 pub struct Timer<T, U = OneShot>(T, PhantomData<U>);
 
 impl<T> Timer<T, OneShot>
@@ -86,6 +100,28 @@ where
     }
 }
 
+impl<T> Timer<T, Counter>
+where
+    T: Instance,
+{
+    /// Configures the TIMER as a 32-bit counter and starts it.
+    ///
+    /// In counter mode, the counter is incremented by the `COUNT` task instead of the clock. The
+    /// task can be triggered from software with `count`, or from any event through PPI with
+    /// `task_count`, e.g. to count GPIOTE edges without CPU involvement. The number of counts is
+    /// returned by `read`.
+    pub fn counter(timer: T) -> Timer<T, Counter> {
+        timer.set_counter();
+
+        Timer::<T, Counter>(timer, PhantomData)
+    }
+
+    /// Increments the counter by one.
+    pub fn count(&mut self) {
+        self.task_count().write(|w| unsafe { w.bits(1) });
+    }
+}
+
 impl<T, U> Timer<T, U>
 where
     T: Instance,
@@ -93,12 +129,14 @@ where
     pub const TICKS_PER_SECOND: u32 = 1_000_000;
 
     pub fn into_periodic(self) -> Timer<T, Periodic> {
+        self.0.set_timer_mode();
         self.0.set_shorts_periodic();
 
         Timer::<T, Periodic>(self.free(), PhantomData)
     }
 
     pub fn into_oneshot(self) -> Timer<T, OneShot> {
+        self.0.set_timer_mode();
         self.0.set_shorts_oneshot();
 
         Timer::<T, OneShot>(self.free(), PhantomData)
@@ -110,6 +148,8 @@ where
     }
 
     /// Return the current value of the counter, by capturing to CC[1].
+    ///
+    /// In counter mode, this is the number of counts since the counter was cleared.
     pub fn read(&self) -> u32 {
         self.0.read_counter()
     }
@@ -142,6 +182,160 @@ where
         self.0.disable_interrupt();
     }
 
+    /// Starts the counter, without changing its value or any CC register.
+    pub fn start_counter(&mut self) {
+        self.0
+            .as_timer0()
+            .tasks_start
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stops the counter, without changing its value.
+    pub fn stop_counter(&mut self) {
+        self.0
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Sets the counter to 0.
+    pub fn clear_counter(&mut self) {
+        self.0
+            .as_timer0()
+            .tasks_clear
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Disables the shorts set up by the one-shot and periodic modes, which clear (and stop) the
+    /// counter on COMPARE[0].
+    ///
+    /// Use this to let the counter run freely when scheduling several deadlines with the CC
+    /// channels.
+    pub fn disable_shorts(&mut self) {
+        self.0.as_timer0().shorts.reset();
+    }
+
+    /// Sets the width of the counter, stopping the timer.
+    ///
+    /// The counter wraps around to 0 once it exceeds the maximum value for the
+    /// given width, and compare values are truncated to it.
+    ///
+    /// Note that on the nRF51, only TIMER0 supports the 24 and 32 bit modes.
+    pub fn set_bitmode(&mut self, bitmode: Bitmode) {
+        self.0
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+        self.0.as_timer0().bitmode.write(|w| match bitmode {
+            Bitmode::Bits8 => w.bitmode()._08bit(),
+            Bitmode::Bits16 => w.bitmode()._16bit(),
+            Bitmode::Bits24 => w.bitmode()._24bit(),
+            Bitmode::Bits32 => w.bitmode()._32bit(),
+        });
+    }
+
+    /// Returns the number of CC channels of this TIMER instance.
+    pub fn cc_count(&self) -> usize {
+        T::CC_COUNT
+    }
+
+    /// Sets the compare value of CC channel `cc`.
+    ///
+    /// Note that CC[0] is used by `start`, `delay` and the `embedded-hal` traits, and CC[1] is
+    /// used by `read`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn set_compare(&mut self, cc: usize, value: u32) {
+        cc_register::<T, _CC>(&self.0, CC_OFFSET, cc).write(|w| unsafe { w.bits(value) });
+    }
+
+    /// Returns the value of CC channel `cc`, which is either the compare value or the last
+    /// captured counter value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn compare(&self, cc: usize) -> u32 {
+        cc_register::<T, _CC>(&self.0, CC_OFFSET, cc).read().bits()
+    }
+
+    /// Captures the current counter value into CC channel `cc`, and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn capture(&mut self, cc: usize) -> u32 {
+        self.task_capture(cc).write(|w| unsafe { w.bits(1) });
+        self.compare(cc)
+    }
+
+    /// Returns `true` if the COMPARE event of CC channel `cc` has been generated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn is_compare_triggered(&self, cc: usize) -> bool {
+        self.event_compare(cc).read().bits() != 0
+    }
+
+    /// Clears the COMPARE event of CC channel `cc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn reset_compare_event(&mut self, cc: usize) {
+        self.event_compare(cc).reset();
+    }
+
+    /// Enables the interrupt for the COMPARE event of CC channel `cc`.
+    ///
+    /// Note that the interrupt also has to be unmasked in the NVIC, or the
+    /// handler won't get called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn enable_compare_interrupt(&mut self, cc: usize) {
+        assert!(cc < T::CC_COUNT);
+        self.0
+            .as_timer0()
+            .intenset
+            .write(|w| unsafe { w.bits(1 << (16 + cc)) });
+    }
+
+    /// Disables the interrupt for the COMPARE event of CC channel `cc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    pub fn disable_compare_interrupt(&mut self, cc: usize) {
+        assert!(cc < T::CC_COUNT);
+        self.0
+            .as_timer0()
+            .intenclr
+            .write(|w| unsafe { w.bits(1 << (16 + cc)) });
+    }
+
+    /// Starts the timer for the given duration, e.g. `500.millis()`.
+    ///
+    /// Once the duration has elapsed, `wait` returns `Ok(())`.
+    pub fn start_for<const NOM: u32, const DENOM: u32>(
+        &mut self,
+        duration: Duration<u32, NOM, DENOM>,
+    ) {
+        self.0.timer_start(duration.to_micros());
+    }
+
+    /// Blocks for the given duration, e.g. `500.millis()`.
+    pub fn delay_for<const NOM: u32, const DENOM: u32>(
+        &mut self,
+        duration: Duration<u32, NOM, DENOM>,
+    ) {
+        self.delay(duration.to_micros());
+    }
+
     pub fn delay(&mut self, cycles: u32) {
         self.start(cycles);
         match block!(self.wait()) {
@@ -206,6 +400,29 @@ where
         &self.0.as_timer0().tasks_capture[3]
     }
 
+    /// Returns reference to the `CAPTURE` task endpoint of CC channel `cc` for PPI.
+    /// Captures timer value to the CC[`cc`] register.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    #[inline(always)]
+    pub fn task_capture(&self, cc: usize) -> &Reg<u32, _TASKS_CAPTURE> {
+        cc_register(&self.0, TASKS_CAPTURE_OFFSET, cc)
+    }
+
+    /// Returns reference to the `COMPARE` event endpoint of CC channel `cc` for PPI.
+    /// Generated when the counter is incremented and then matches the value
+    /// specified in the CC[`cc`] register.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not lower than `cc_count()`.
+    #[inline(always)]
+    pub fn event_compare(&self, cc: usize) -> &Reg<u32, _EVENTS_COMPARE> {
+        cc_register(&self.0, EVENTS_COMPARE_OFFSET, cc)
+    }
+
     /// Returns reference to the CC[0] `COMPARE` event endpoint for PPI.
     /// Generated when the counter is incremented and then matches the value
     /// specified in the CC[0] register.
@@ -345,11 +562,341 @@ where
     }
 }
 
+impl<T, U> embedded_hal_1::delay::DelayNs for Timer<T, U>
+where
+    T: Instance,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        embedded_hal_1::delay::DelayNs::delay_us(self, ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        // A compare value of 0 would only match after the counter wraps around.
+        if us != 0 {
+            self.delay(us);
+        }
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        // Split the delay so that the number of cycles doesn't overflow.
+        for _ in 0..ms / 1_000_000 {
+            self.delay(1_000_000_000);
+        }
+        embedded_hal_1::delay::DelayNs::delay_us(self, (ms % 1_000_000) * 1_000);
+    }
+}
+
+/// A TIMER-based delay provider that can be shared between execution contexts.
+///
+/// The TIMER counts freely at 1 MHz, and each delay busy-waits until enough time has passed since
+/// it started. Unlike `Timer`, which reprograms the compare register for each delay, this allows
+/// several interrupt handlers to use the same TIMER for delays concurrently, through shared
+/// references: the delay traits are implemented for both `TimerDelay` and `&TimerDelay`.
+///
+/// This is useful when SysTick is already used by an RTOS or a monotonic timer.
+pub struct TimerDelay<T>(T);
+
+// The counter is only read by capturing it to CC[0], and reading the capture register. If the
+// capture is interrupted by another context capturing the counter, the value read is the one
+// captured later, which is still a valid timestamp for the delay.
+unsafe impl<T: Instance> Sync for TimerDelay<T> {}
+
+impl<T> TimerDelay<T>
+where
+    T: Instance,
+{
+    /// Configures the TIMER as a free-running 1 MHz counter and starts it.
+    pub fn new(timer: T) -> Self {
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.shorts.reset();
+        regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        regs.mode.write(|w| w.mode().timer());
+        regs.prescaler.write(
+            |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
+        );
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Self(timer)
+    }
+
+    /// Stops the TIMER and releases it.
+    pub fn free(self) -> T {
+        self.0
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+        self.0
+    }
+
+    /// Returns the current counter value, in microseconds.
+    pub fn now(&self) -> u32 {
+        let regs = self.0.as_timer0();
+        regs.tasks_capture[0].write(|w| unsafe { w.bits(1) });
+        regs.cc[0].read().bits()
+    }
+
+    /// Busy-waits for the given duration, e.g. `500.millis()`.
+    pub fn delay_for<const NOM: u32, const DENOM: u32>(&self, duration: Duration<u32, NOM, DENOM>) {
+        self.delay_us(duration.to_micros());
+    }
+
+    /// Busy-waits for the given number of microseconds.
+    pub fn delay_us(&self, us: u32) {
+        // Waiting in steps of at most 2^31 µs keeps the elapsed time computation unambiguous.
+        const MAX_STEP: u32 = 1 << 31;
+
+        let mut remaining = us;
+        while remaining > 0 {
+            let step = remaining.min(MAX_STEP);
+            let start = self.now();
+            while self.now().wrapping_sub(start) < step {}
+            remaining -= step;
+        }
+    }
+}
+
+impl<T> DelayMs<u32> for TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_ms(&mut self, ms: u32) {
+        (&*self).delay_ms(ms);
+    }
+}
+
+impl<T> DelayUs<u32> for TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_us(&mut self, us: u32) {
+        TimerDelay::delay_us(self, us);
+    }
+}
+
+impl<T> DelayMs<u32> for &TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms / 1_000 {
+            TimerDelay::delay_us(self, 1_000_000);
+        }
+        TimerDelay::delay_us(self, (ms % 1_000) * 1_000);
+    }
+}
+
+impl<T> DelayUs<u32> for &TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_us(&mut self, us: u32) {
+        TimerDelay::delay_us(self, us);
+    }
+}
+
+macro_rules! timer_delay_small {
+    ($($delay:ty),+) => {
+        $(
+            impl<T> DelayMs<u16> for $delay
+            where
+                T: Instance,
+            {
+                fn delay_ms(&mut self, ms: u16) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            impl<T> DelayMs<u8> for $delay
+            where
+                T: Instance,
+            {
+                fn delay_ms(&mut self, ms: u8) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            impl<T> DelayUs<u16> for $delay
+            where
+                T: Instance,
+            {
+                fn delay_us(&mut self, us: u16) {
+                    TimerDelay::delay_us(self, u32(us));
+                }
+            }
+
+            impl<T> DelayUs<u8> for $delay
+            where
+                T: Instance,
+            {
+                fn delay_us(&mut self, us: u8) {
+                    TimerDelay::delay_us(self, u32(us));
+                }
+            }
+        )+
+    };
+}
+
+timer_delay_small!(TimerDelay<T>, &TimerDelay<T>);
+
+impl<T> embedded_hal_1::delay::DelayNs for TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        TimerDelay::delay_us(self, ns.div_ceil(1_000));
+    }
+}
+
+impl<T> embedded_hal_1::delay::DelayNs for &TimerDelay<T>
+where
+    T: Instance,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        TimerDelay::delay_us(self, ns.div_ceil(1_000));
+    }
+}
+
+/// A 64-bit, 16 MHz counter made of two cascaded TIMERs.
+///
+/// The low TIMER counts at 16 MHz, and each time it wraps around, its COMPARE[0] event
+/// increments the high TIMER through PPI, which runs in counter mode. No CPU involvement is
+/// needed to keep the counter running, and it doesn't wrap around in practice.
+///
+/// CC[0] of the low TIMER and CC[1] of both TIMERs are used by this type. On the nRF51, only
+/// TIMER0 supports 32-bit mode, so the low TIMER has to be TIMER0, and the counter is only 48
+/// bits wide.
+#[cfg(not(feature = "9160"))]
+pub struct CascadedTimer<L, H, P> {
+    low: L,
+    high: H,
+    ppi: P,
+}
+
+#[cfg(not(feature = "9160"))]
+impl<L, H, P> CascadedTimer<L, H, P>
+where
+    L: Timer32,
+    H: Instance,
+    P: crate::ppi::ConfigurablePpi,
+{
+    pub const TICKS_PER_SECOND: u32 = 16_000_000;
+
+    /// Configures the TIMERs and the PPI channel, and starts counting from 0.
+    pub fn new(low: L, high: H, mut ppi: P) -> Self {
+        for regs in [low.as_timer0(), high.as_timer0()] {
+            regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+            regs.shorts.reset();
+            regs.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+            regs.bitmode.write(|w| w.bitmode()._32bit());
+            regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        }
+
+        let low_regs = low.as_timer0();
+        low_regs.mode.write(|w| w.mode().timer());
+        low_regs.prescaler.write(
+            |w| unsafe { w.prescaler().bits(0) }, // 16 MHz
+        );
+        // The COMPARE[0] event is generated when the counter wraps around to 0.
+        low_regs.cc[0].write(|w| unsafe { w.bits(0) });
+        high.as_timer0().mode.write(|w| w.mode().counter());
+
+        ppi.set_event_endpoint(&low_regs.events_compare[0]);
+        ppi.set_task_endpoint(&high.as_timer0().tasks_count);
+        ppi.enable();
+
+        let mut timer = Self { low, high, ppi };
+        timer.start();
+        timer
+    }
+
+    /// Starts (or resumes) counting.
+    pub fn start(&mut self) {
+        self.high
+            .as_timer0()
+            .tasks_start
+            .write(|w| unsafe { w.bits(1) });
+        self.low
+            .as_timer0()
+            .tasks_start
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stops counting, keeping the current value.
+    pub fn stop(&mut self) {
+        self.low
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Sets the counter to 0.
+    pub fn clear(&mut self) {
+        self.low
+            .as_timer0()
+            .tasks_clear
+            .write(|w| unsafe { w.bits(1) });
+        self.high
+            .as_timer0()
+            .tasks_clear
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns the current value of the 64-bit counter.
+    pub fn now(&self) -> u64 {
+        loop {
+            let high = self.high.read_counter();
+            let low = self.low.read_counter();
+            // Retry if the low counter wrapped around while it was being read.
+            if high == self.high.read_counter() {
+                return (u64::from(high) << 32) | u64::from(low);
+            }
+        }
+    }
+
+    /// Stops counting, and releases the TIMERs and the PPI channel.
+    pub fn free(self) -> (L, H, P) {
+        let Self { low, high, mut ppi } = self;
+        ppi.disable();
+        low.as_timer0().tasks_stop.write(|w| unsafe { w.bits(1) });
+        high.as_timer0().tasks_stop.write(|w| unsafe { w.bits(1) });
+        (low, high, ppi)
+    }
+}
+
+/// Offset of the `TASKS_CAPTURE[0]` register.
+const TASKS_CAPTURE_OFFSET: usize = 0x040;
+/// Offset of the `EVENTS_COMPARE[0]` register.
+const EVENTS_COMPARE_OFFSET: usize = 0x140;
+/// Offset of the `CC[0]` register.
+const CC_OFFSET: usize = 0x540;
+
+/// Returns the register of CC channel `cc` in the register array at `offset`.
+///
+/// The register arrays of the PACs don't always match the number of CC channels of the instance,
+/// so the register is located from the base address instead.
+#[inline(always)]
+fn cc_register<T: Instance, REG>(timer: &T, offset: usize, cc: usize) -> &Reg<u32, REG> {
+    assert!(cc < T::CC_COUNT);
+    let base = timer.as_timer0() as *const RegBlock0 as *const u8;
+    // SAFETY: `cc` is in range, and all TIMER instances share the same register layout, with
+    // each register array being followed by enough space for `CC_COUNT` registers.
+    unsafe { &*(base.add(offset + 4 * cc) as *const Reg<u32, REG>) }
+}
+
 /// Implemented by all TIMER* instances.
 pub trait Instance: sealed::Sealed {
     /// This interrupt associated with this RTC instance.
     const INTERRUPT: Interrupt;
 
+    /// The number of CC channels of this TIMER instance.
+    const CC_COUNT: usize;
+
+    /// Returns a pointer to the register block.
+    fn ptr() -> *const RegBlock0;
+
     fn as_timer0(&self) -> &RegBlock0;
 
     fn timer_start<Time>(&self, cycles: Time)
@@ -427,6 +974,7 @@ pub trait Instance: sealed::Sealed {
     }
 
     fn set_periodic(&self) {
+        self.set_timer_mode();
         self.set_shorts_periodic();
         self.as_timer0().prescaler.write(
             |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
@@ -434,7 +982,26 @@ pub trait Instance: sealed::Sealed {
         self.as_timer0().bitmode.write(|w| w.bitmode()._32bit());
     }
 
+    fn set_timer_mode(&self) {
+        // Only stop the TIMER when it leaves the counter mode, so that a running timer keeps
+        // running when it's converted to another timer mode.
+        if !self.as_timer0().mode.read().mode().is_timer() {
+            self.as_timer0().tasks_stop.write(|w| unsafe { w.bits(1) });
+            self.as_timer0().mode.write(|w| w.mode().timer());
+        }
+    }
+
+    fn set_counter(&self) {
+        self.as_timer0().tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.as_timer0().shorts.reset();
+        self.as_timer0().mode.write(|w| w.mode().counter());
+        self.as_timer0().bitmode.write(|w| w.bitmode()._32bit());
+        self.as_timer0().tasks_clear.write(|w| unsafe { w.bits(1) });
+        self.as_timer0().tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
     fn set_oneshot(&self) {
+        self.set_timer_mode();
         self.set_shorts_oneshot();
         self.as_timer0().prescaler.write(
             |w| unsafe { w.prescaler().bits(4) }, // 1 MHz
@@ -445,6 +1012,15 @@ pub trait Instance: sealed::Sealed {
 
 impl Instance for TIMER0 {
     const INTERRUPT: Interrupt = Interrupt::TIMER0;
+    #[cfg(feature = "9160")]
+    const CC_COUNT: usize = 6;
+    #[cfg(not(feature = "9160"))]
+    const CC_COUNT: usize = 4;
+
+    #[inline(always)]
+    fn ptr() -> *const RegBlock0 {
+        TIMER0::ptr()
+    }
 
     #[inline(always)]
     fn as_timer0(&self) -> &RegBlock0 {
@@ -454,6 +1030,15 @@ impl Instance for TIMER0 {
 
 impl Instance for TIMER1 {
     const INTERRUPT: Interrupt = Interrupt::TIMER1;
+    #[cfg(feature = "9160")]
+    const CC_COUNT: usize = 6;
+    #[cfg(not(feature = "9160"))]
+    const CC_COUNT: usize = 4;
+
+    #[inline(always)]
+    fn ptr() -> *const RegBlock0 {
+        TIMER1::ptr()
+    }
 
     #[inline(always)]
     fn as_timer0(&self) -> &RegBlock0 {
@@ -463,6 +1048,15 @@ impl Instance for TIMER1 {
 
 impl Instance for TIMER2 {
     const INTERRUPT: Interrupt = Interrupt::TIMER2;
+    #[cfg(feature = "9160")]
+    const CC_COUNT: usize = 6;
+    #[cfg(not(feature = "9160"))]
+    const CC_COUNT: usize = 4;
+
+    #[inline(always)]
+    fn ptr() -> *const RegBlock0 {
+        TIMER2::ptr()
+    }
 
     #[inline(always)]
     fn as_timer0(&self) -> &RegBlock0 {
@@ -473,6 +1067,12 @@ impl Instance for TIMER2 {
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 impl Instance for TIMER3 {
     const INTERRUPT: Interrupt = Interrupt::TIMER3;
+    const CC_COUNT: usize = 6;
+
+    #[inline(always)]
+    fn ptr() -> *const RegBlock0 {
+        TIMER3::ptr() as *const _
+    }
 
     #[inline(always)]
     fn as_timer0(&self) -> &RegBlock0 {
@@ -489,6 +1089,12 @@ impl Instance for TIMER3 {
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 impl Instance for TIMER4 {
     const INTERRUPT: Interrupt = Interrupt::TIMER4;
+    const CC_COUNT: usize = 6;
+
+    #[inline(always)]
+    fn ptr() -> *const RegBlock0 {
+        TIMER4::ptr() as *const _
+    }
 
     #[inline(always)]
     fn as_timer0(&self) -> &RegBlock0 {
@@ -502,6 +1108,25 @@ impl Instance for TIMER4 {
     }
 }
 
+/// Implemented by the TIMER instances supporting the 32-bit mode.
+///
+/// On the nRF51, only TIMER0 is 32 bits wide, TIMER1 and TIMER2 are limited to 16 bits.
+pub trait Timer32: Instance {}
+
+impl Timer32 for TIMER0 {}
+
+#[cfg(not(feature = "51"))]
+impl Timer32 for TIMER1 {}
+
+#[cfg(not(feature = "51"))]
+impl Timer32 for TIMER2 {}
+
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+impl Timer32 for TIMER3 {}
+
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+impl Timer32 for TIMER4 {}
+
 /// Adds task- and event PPI endpoint getters for CC[4] and CC[5] on supported instances.
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 pub trait ExtendedCCTimer {
@@ -6,8 +6,58 @@
 //! - nrf52811: Section 4.5
 //! - nrf52832: Section 14
 //! - nrf52840: Section 4.5
+//!
+//! Besides the customer registers, `Uicr` gives access to the configuration registers that the
+//! device reads at reset, e.g. to use the NFC pins as GPIOs:
+//!
+//! ```ignore
+//! let mut uicr = Uicr::new(p.UICR);
+//! if uicr.set_nfc_pins_as_gpio(&mut p.NVMC)? == UicrWrite::ResetRequired {
+//!     cortex_m::peripheral::SCB::sys_reset();
+//! }
+//! ```
+//!
+//! Bits of the UICR registers can only be changed from `1` to `0`. The configuration setters fail
+//! with `UicrError::EraseRequired` if the new value needs a `0` bit to be set back to `1`.
 use crate::pac::{NVMC, UICR};
 
+/// UICR error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UicrError {
+    /// The UICR must be erased before the new value can be written.
+    EraseRequired,
+}
+
+/// Outcome of a successful write of a UICR configuration register.
+#[must_use = "the UICR configuration only takes effect after a reset"]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UicrWrite {
+    /// The register already held the new value.
+    Unchanged,
+    /// The new value was written, and takes effect after the next reset.
+    ResetRequired,
+}
+
+/// Output voltage of the REG0 regulator stage in high voltage mode.
+#[cfg(any(feature = "52833", feature = "52840"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Regout0 {
+    V1_8 = 0,
+    V2_1 = 1,
+    V2_4 = 2,
+    V2_7 = 3,
+    V3_0 = 4,
+    V3_3 = 5,
+    /// The default voltage of 1.8 V, used while the register is erased.
+    Default = 7,
+}
+
+/// Pin used as the reset pin by pin reset.
+#[cfg(any(feature = "52833", feature = "52840"))]
+const RESET_PIN: u32 = 18;
+#[cfg(any(feature = "52810", feature = "52811", feature = "52832"))]
+const RESET_PIN: u32 = 21;
+
 /// Interface to a UICR instance.
 ///
 /// This is a very basic interface that comes with the following limitations:
@@ -82,4 +132,119 @@ impl Uicr {
 
         values
     }
+
+    /// Returns `true` if the NFC pins are configured as GPIOs.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn nfc_pins_as_gpio(&self) -> bool {
+        self.0.nfcpins.read().protect().is_disabled()
+    }
+
+    /// Configures the NFC pins as GPIOs, which disables the NFC antenna protection.
+    #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+    pub fn set_nfc_pins_as_gpio(&mut self, nvmc: &mut NVMC) -> Result<UicrWrite, UicrError> {
+        let current = self.0.nfcpins.read().bits();
+        self.program(nvmc, current, current & !1, |uicr, value| {
+            uicr.nfcpins.write(|w| unsafe { w.bits(value) })
+        })
+    }
+
+    /// Returns the output voltage of the REG0 regulator stage.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn regout0(&self) -> Regout0 {
+        match self.0.regout0.read().bits() & 7 {
+            0 => Regout0::V1_8,
+            1 => Regout0::V2_1,
+            2 => Regout0::V2_4,
+            3 => Regout0::V2_7,
+            4 => Regout0::V3_0,
+            5 => Regout0::V3_3,
+            _ => Regout0::Default,
+        }
+    }
+
+    /// Sets the output voltage of the REG0 regulator stage, which supplies the GPIOs when the
+    /// device is powered through VDDH.
+    #[cfg(any(feature = "52833", feature = "52840"))]
+    pub fn set_regout0(
+        &mut self,
+        nvmc: &mut NVMC,
+        voltage: Regout0,
+    ) -> Result<UicrWrite, UicrError> {
+        let current = self.0.regout0.read().bits();
+        self.program(
+            nvmc,
+            current,
+            current & !7 | voltage as u32,
+            |uicr, value| uicr.regout0.write(|w| unsafe { w.bits(value) }),
+        )
+    }
+
+    /// Returns `true` if the reset pin is enabled.
+    #[cfg(not(any(feature = "51", feature = "9160")))]
+    pub fn is_pin_reset_enabled(&self) -> bool {
+        self.0.pselreset[0].read().connect().is_connected()
+    }
+
+    /// Enables the reset pin of the device, P0.18 on nRF52833 and nRF52840, and P0.21 on the
+    /// other devices.
+    #[cfg(not(any(feature = "51", feature = "9160")))]
+    pub fn enable_pin_reset(&mut self, nvmc: &mut NVMC) -> Result<UicrWrite, UicrError> {
+        let mut outcome = UicrWrite::Unchanged;
+        // Both registers must hold the same value for pin reset to be enabled.
+        for i in 0..self.0.pselreset.len() {
+            let current = self.0.pselreset[i].read().bits();
+            if self.program(nvmc, current, RESET_PIN, |uicr, value| {
+                uicr.pselreset[i].write(|w| unsafe { w.bits(value) })
+            })? == UicrWrite::ResetRequired
+            {
+                outcome = UicrWrite::ResetRequired;
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Returns `true` if the access port protection is enabled.
+    #[cfg(not(feature = "51"))]
+    pub fn is_approtect_enabled(&self) -> bool {
+        self.0.approtect.read().pall().is_enabled()
+    }
+
+    /// Enables the access port protection, which blocks debugger access to the CPU and memory.
+    ///
+    /// Only an ERASEALL operation through the debug port disables it again, which also erases
+    /// the flash.
+    #[cfg(not(feature = "51"))]
+    pub fn enable_approtect(&mut self, nvmc: &mut NVMC) -> Result<UicrWrite, UicrError> {
+        let current = self.0.approtect.read().bits();
+        self.program(nvmc, current, current & !0xFF, |uicr, value| {
+            uicr.approtect.write(|w| unsafe { w.bits(value) })
+        })
+    }
+
+    /// Programs `value` into a register holding `current` with `write`, unless it is unchanged.
+    #[cfg(not(feature = "51"))]
+    fn program<F>(
+        &mut self,
+        nvmc: &mut NVMC,
+        current: u32,
+        value: u32,
+        write: F,
+    ) -> Result<UicrWrite, UicrError>
+    where
+        F: FnOnce(&UICR, u32),
+    {
+        if value == current {
+            return Ok(UicrWrite::Unchanged);
+        }
+        if value & !current != 0 {
+            return Err(UicrError::EraseRequired);
+        }
+        assert!(!nvmc.config.read().wen().is_een()); // write + erase is forbidden!
+
+        nvmc.config.write(|w| w.wen().wen());
+        write(&self.0, value);
+        while nvmc.ready.read().ready().is_busy() {}
+        nvmc.config.reset();
+        Ok(UicrWrite::ResetRequired)
+    }
 }
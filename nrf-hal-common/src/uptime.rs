@@ -0,0 +1,117 @@
+//! A 64-bit uptime clock.
+//!
+//! `MonotonicClock` extends the 24-bit counter of an RTC to 64 bits by counting its overflows in
+//! the RTC interrupt. At 32.768 kHz, the 24-bit counter overflows every 512 seconds, while the
+//! extended counter never does in practice.
+//!
+//! The clock can be read through a shared reference from any context, including interrupt
+//! handlers of any priority:
+//!
+//! ```ignore
+//! let clock = MonotonicClock::new(p.RTC1);
+//!
+//! // After unmasking RTC1 in the NVIC, in the `RTC1` interrupt handler:
+//! clock.on_interrupt();
+//!
+//! // Anywhere else:
+//! let uptime_us = clock.now_us();
+//! ```
+//!
+//! The low frequency clock has to be started for the RTC to run.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::interrupt;
+
+use crate::clocks::LFCLK_FREQ;
+use crate::rtc::Instance;
+
+/// A 64-bit monotonic clock counting at 32.768 kHz since it was created.
+pub struct MonotonicClock<T> {
+    rtc: T,
+    overflows: AtomicU32,
+}
+
+// The overflow count is only written by `on_interrupt`, and readers detect concurrent updates by
+// reading it before and after the counter. The RTC registers are only read, except for the
+// OVRFLW event, which is only cleared by `on_interrupt`.
+unsafe impl<T: Instance> Sync for MonotonicClock<T> {}
+
+impl<T> MonotonicClock<T>
+where
+    T: Instance,
+{
+    /// Starts the RTC at 32.768 kHz and enables its overflow interrupt.
+    ///
+    /// The RTC interrupt must also be unmasked in the NVIC, and its handler must call
+    /// `on_interrupt`. The handler has to run at least once every 512 seconds.
+    pub fn new(rtc: T) -> Self {
+        rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        rtc.prescaler.write(|w| unsafe { w.bits(0) });
+        rtc.events_ovrflw.reset();
+        rtc.intenset.write(|w| w.ovrflw().set());
+        rtc.tasks_clear.write(|w| unsafe { w.bits(1) });
+        rtc.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Self {
+            rtc,
+            overflows: AtomicU32::new(0),
+        }
+    }
+
+    /// Handles the RTC overflow. Must be called from the RTC interrupt handler.
+    pub fn on_interrupt(&self) {
+        // Readers must not observe the event cleared before the count is incremented.
+        interrupt::free(|_| {
+            if self.rtc.events_ovrflw.read().bits() != 0 {
+                self.rtc.events_ovrflw.reset();
+                let overflows = self.overflows.load(Ordering::Relaxed);
+                self.overflows.store(overflows + 1, Ordering::Release);
+            }
+        });
+    }
+
+    /// Returns the number of ticks since the clock was created.
+    pub fn ticks(&self) -> u64 {
+        loop {
+            let overflows = self.overflows.load(Ordering::Acquire);
+            // An overflow that hasn't been handled by `on_interrupt` yet is still pending in the
+            // OVRFLW event.
+            let pending = self.rtc.events_ovrflw.read().bits() != 0;
+            let counter = self.rtc.counter.read().bits();
+            let pending_after = self.rtc.events_ovrflw.read().bits() != 0;
+
+            if pending == pending_after && overflows == self.overflows.load(Ordering::Acquire) {
+                let overflows = u64::from(overflows) + u64::from(pending);
+                return (overflows << 24) | u64::from(counter);
+            }
+        }
+    }
+
+    /// Returns the tick frequency in Hz.
+    pub fn ticks_per_second(&self) -> u32 {
+        LFCLK_FREQ
+    }
+
+    /// Returns the number of microseconds since the clock was created.
+    pub fn now_us(&self) -> u64 {
+        ticks_to(self.ticks(), 1_000_000)
+    }
+
+    /// Returns the number of nanoseconds since the clock was created.
+    pub fn now_ns(&self) -> u64 {
+        ticks_to(self.ticks(), 1_000_000_000)
+    }
+
+    /// Stops the RTC and releases it.
+    pub fn free(self) -> T {
+        self.rtc.intenclr.write(|w| w.ovrflw().clear());
+        self.rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.rtc
+    }
+}
+
+/// Converts 32.768 kHz ticks to the given unit, without overflowing the intermediate product.
+fn ticks_to(ticks: u64, units_per_second: u64) -> u64 {
+    let freq = u64::from(LFCLK_FREQ);
+    ticks / freq * units_per_second + ticks % freq * units_per_second / freq
+}
@@ -0,0 +1,123 @@
+//! USB DFU runtime interface, to let the host switch the device to its bootloader.
+//!
+//! This module is only available with the `usb` feature.
+//!
+//! `DfuRuntime` is a USB class announcing the DFU runtime interface. When a DFU tool, e.g.
+//! `dfu-util`, sends the DFU_DETACH request, the application stores the request for the
+//! bootloader in GPREGRET and resets:
+//!
+//! ```ignore
+//! let mut dfu = DfuRuntime::new(&usb_bus);
+//! let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd)).build();
+//!
+//! loop {
+//!     usb_dev.poll(&mut [&mut dfu]);
+//!     if dfu.is_detach_requested() {
+//!         power.reset_into_bootloader(BootRequest::Dfu);
+//!     }
+//! }
+//! ```
+//!
+//! Products exposing a USB serial port can use the "1200 baud touch" instead, see
+//! `UsbSerial::is_bootloader_requested`.
+
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, Request, RequestType};
+use usb_device::Result;
+
+/// Application specific interface class.
+const CLASS_APPLICATION_SPECIFIC: u8 = 0xFE;
+/// Device firmware upgrade subclass.
+const SUBCLASS_DFU: u8 = 0x01;
+/// Runtime protocol, used by the application, as opposed to the bootloader.
+const PROTOCOL_RUNTIME: u8 = 0x01;
+
+/// DFU functional descriptor type.
+const DESCRIPTOR_DFU_FUNCTIONAL: u8 = 0x21;
+/// The device detaches itself from the bus after DFU_DETACH, and can download firmware.
+const ATTRIBUTES: u8 = 0x09;
+/// Time allowed to the device to reset after DFU_DETACH, in milliseconds.
+const DETACH_TIMEOUT: u16 = 1000;
+/// Maximum number of bytes per control transfer of the bootloader.
+const TRANSFER_SIZE: u16 = 64;
+/// DFU version 1.1.
+const DFU_VERSION: u16 = 0x0110;
+
+const REQUEST_DETACH: u8 = 0x00;
+const REQUEST_GET_STATUS: u8 = 0x03;
+const REQUEST_GET_STATE: u8 = 0x05;
+
+/// `appIDLE` state of the DFU runtime.
+const STATE_APP_IDLE: u8 = 0x00;
+
+/// DFU runtime USB class.
+pub struct DfuRuntime {
+    interface: InterfaceNumber,
+    detach_requested: bool,
+}
+
+impl DfuRuntime {
+    /// Allocates the DFU runtime interface.
+    pub fn new<B: UsbBus>(alloc: &UsbBusAllocator<B>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            detach_requested: false,
+        }
+    }
+
+    /// Returns `true` if the host has sent the DFU_DETACH request.
+    pub fn is_detach_requested(&self) -> bool {
+        self.detach_requested
+    }
+
+    fn is_for_interface(&self, request: &Request) -> bool {
+        request.request_type == RequestType::Class
+            && request.recipient == Recipient::Interface
+            && request.index == u16::from(u8::from(self.interface))
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for DfuRuntime {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
+        writer.interface(
+            self.interface,
+            CLASS_APPLICATION_SPECIFIC,
+            SUBCLASS_DFU,
+            PROTOCOL_RUNTIME,
+        )?;
+        let [timeout_lo, timeout_hi] = DETACH_TIMEOUT.to_le_bytes();
+        let [size_lo, size_hi] = TRANSFER_SIZE.to_le_bytes();
+        let [version_lo, version_hi] = DFU_VERSION.to_le_bytes();
+        writer.write(
+            DESCRIPTOR_DFU_FUNCTIONAL,
+            &[
+                ATTRIBUTES, timeout_lo, timeout_hi, size_lo, size_hi, version_lo, version_hi,
+            ],
+        )
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        if !self.is_for_interface(xfer.request()) {
+            return;
+        }
+        if xfer.request().request == REQUEST_DETACH {
+            // The reset is left to the application, as the request must be acknowledged first.
+            self.detach_requested = true;
+            xfer.accept().ok();
+        } else {
+            xfer.reject().ok();
+        }
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        if !self.is_for_interface(xfer.request()) {
+            return;
+        }
+        match xfer.request().request {
+            // Status OK, no poll timeout, appIDLE state, no status description.
+            REQUEST_GET_STATUS => xfer.accept_with(&[0, 0, 0, 0, STATE_APP_IDLE, 0]).ok(),
+            REQUEST_GET_STATE => xfer.accept_with(&[STATE_APP_IDLE]).ok(),
+            _ => xfer.reject().ok(),
+        };
+    }
+}
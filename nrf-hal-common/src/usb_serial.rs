@@ -0,0 +1,246 @@
+//! USB serial port, using the CDC-ACM class on top of the USBD driver.
+//!
+//! This module is only available with the `usb` feature.
+//!
+//! `UsbSerial` bundles the USB device and a CDC-ACM serial port with internal read and write ring
+//! buffers, and implements the `embedded_io` `Read` and `Write` traits:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let power = Power::new(p.POWER);
+//! let usb_bus = UsbBusAllocator::new(Usbd::new(p.USBD, &clocks, power.usb_supply()));
+//! let mut serial = UsbSerial::new(
+//!     &usb_bus,
+//!     UsbVidPid(0x16c0, 0x27dd),
+//!     "Manufacturer",
+//!     "Serial console",
+//!     "0001",
+//! );
+//!
+//! loop {
+//!     let mut buf = [0; 64];
+//!     let count = serial.read(&mut buf)?;
+//!     serial.write_all(&buf[..count])?;
+//! }
+//! ```
+//!
+//! The `embedded_io` traits poll the USB device while they wait, so the serial port keeps working
+//! as long as it is read from or written to. Otherwise, `poll` must be called at least every 10 ms,
+//! or from the USBD interrupt handler, in which case the non-blocking `try_read` and `try_write`
+//! should be used.
+//!
+//! Writing fails with `Error::NotConfigured` until the host has configured the device, so that a
+//! console does not block when no host is connected.
+
+use core::borrow::BorrowMut;
+
+use usb_device::bus::UsbBusAllocator;
+use usb_device::device::{UsbDevice, UsbDeviceBuilder, UsbDeviceState, UsbVidPid};
+use usb_device::UsbError;
+use usbd_serial::{DefaultBufferStore, LineCoding, SerialPort, USB_CLASS_CDC};
+
+use crate::usbd::Usbd;
+
+/// USB serial port error.
+#[derive(Debug)]
+pub enum Error {
+    /// The device has not been configured by the host.
+    NotConfigured,
+    /// Error of the USB stack.
+    Usb(UsbError),
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::NotConfigured => embedded_io::ErrorKind::NotConnected,
+            Error::Usb(_) => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// USB serial port, with a read buffer `RS` and a write buffer `WS`.
+pub struct UsbSerial<'a, RS = DefaultBufferStore, WS = DefaultBufferStore>
+where
+    RS: BorrowMut<[u8]>,
+    WS: BorrowMut<[u8]>,
+{
+    device: UsbDevice<'a, Usbd>,
+    port: SerialPort<'a, Usbd, RS, WS>,
+}
+
+impl<'a> UsbSerial<'a> {
+    /// Creates a USB device with a single serial port, using 128 bytes read and write buffers.
+    pub fn new(
+        alloc: &'a UsbBusAllocator<Usbd>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        serial_number: &'a str,
+    ) -> Self {
+        let port = SerialPort::new(alloc);
+        Self::build(alloc, port, vid_pid, manufacturer, product, serial_number)
+    }
+}
+
+impl<'a, RS, WS> UsbSerial<'a, RS, WS>
+where
+    RS: BorrowMut<[u8]>,
+    WS: BorrowMut<[u8]>,
+{
+    /// Creates a USB device with a single serial port, using `read_buffer` and `write_buffer` as
+    /// the ring buffers of the port.
+    pub fn new_with_buffers(
+        alloc: &'a UsbBusAllocator<Usbd>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        serial_number: &'a str,
+        read_buffer: RS,
+        write_buffer: WS,
+    ) -> Self {
+        let port = SerialPort::new_with_store(alloc, read_buffer, write_buffer);
+        Self::build(alloc, port, vid_pid, manufacturer, product, serial_number)
+    }
+
+    fn build(
+        alloc: &'a UsbBusAllocator<Usbd>,
+        port: SerialPort<'a, Usbd, RS, WS>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        serial_number: &'a str,
+    ) -> Self {
+        // The endpoints of the port must be allocated before the device is built.
+        let device = UsbDeviceBuilder::new(alloc, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .serial_number(serial_number)
+            .device_class(USB_CLASS_CDC)
+            .build();
+        Self { device, port }
+    }
+
+    /// Handles the pending USB events. Returns `true` if data may be available for reading.
+    pub fn poll(&mut self) -> bool {
+        self.device.poll(&mut [&mut self.port])
+    }
+
+    /// Returns `true` if the device has been configured by the host.
+    pub fn is_configured(&self) -> bool {
+        self.device.state() == UsbDeviceState::Configured
+    }
+
+    /// Returns the line coding (baud rate, parity, ...) set by the host.
+    pub fn line_coding(&self) -> &LineCoding {
+        self.port.line_coding()
+    }
+
+    /// Returns `true` if the host has set the DTR signal, i.e. a terminal has opened the port.
+    pub fn dtr(&self) -> bool {
+        self.port.dtr()
+    }
+
+    /// Returns `true` if the host has set the RTS signal.
+    pub fn rts(&self) -> bool {
+        self.port.rts()
+    }
+
+    /// Returns `true` if the host has requested the bootloader with a "1200 baud touch", i.e. by
+    /// opening the port at 1200 baud and closing it, as done by the Arduino and Adafruit tools.
+    ///
+    /// The application should then call `Power::reset_into_bootloader`.
+    pub fn is_bootloader_requested(&self) -> bool {
+        self.line_coding().data_rate() == 1200 && !self.dtr()
+    }
+
+    /// Reads the received bytes into `buf`, without blocking. Returns `UsbError::WouldBlock` if no
+    /// byte has been received.
+    pub fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, UsbError> {
+        self.port.read(buf)
+    }
+
+    /// Writes bytes from `buf` into the write buffer, without blocking. Returns
+    /// `UsbError::WouldBlock` if the write buffer is full.
+    pub fn try_write(&mut self, buf: &[u8]) -> Result<usize, UsbError> {
+        self.port.write(buf)
+    }
+
+    /// Returns the USB device.
+    pub fn device(&self) -> &UsbDevice<'a, Usbd> {
+        &self.device
+    }
+
+    /// Releases the USB device and the serial port.
+    pub fn free(self) -> (UsbDevice<'a, Usbd>, SerialPort<'a, Usbd, RS, WS>) {
+        (self.device, self.port)
+    }
+}
+
+impl<RS, WS> embedded_io::ErrorType for UsbSerial<'_, RS, WS>
+where
+    RS: BorrowMut<[u8]>,
+    WS: BorrowMut<[u8]>,
+{
+    type Error = Error;
+}
+
+impl<RS, WS> embedded_io::Read for UsbSerial<'_, RS, WS>
+where
+    RS: BorrowMut<[u8]>,
+    WS: BorrowMut<[u8]>,
+{
+    /// Blocks until at least one byte has been received.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.poll();
+            match self.port.read(buf) {
+                Ok(count) => return Ok(count),
+                Err(UsbError::WouldBlock) => {}
+                Err(error) => return Err(Error::Usb(error)),
+            }
+        }
+    }
+}
+
+impl<RS, WS> embedded_io::Write for UsbSerial<'_, RS, WS>
+where
+    RS: BorrowMut<[u8]>,
+    WS: BorrowMut<[u8]>,
+{
+    /// Blocks until at least one byte has been written into the write buffer.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.poll();
+            if !self.is_configured() {
+                return Err(Error::NotConfigured);
+            }
+            match self.port.write(buf) {
+                Ok(count) => return Ok(count),
+                Err(UsbError::WouldBlock) => {}
+                Err(error) => return Err(Error::Usb(error)),
+            }
+        }
+    }
+
+    /// Blocks until the write buffer has been sent to the host.
+    fn flush(&mut self) -> Result<(), Error> {
+        loop {
+            self.poll();
+            if !self.is_configured() {
+                return Err(Error::NotConfigured);
+            }
+            match self.port.flush() {
+                Ok(()) => return Ok(()),
+                Err(UsbError::WouldBlock) => {}
+                Err(error) => return Err(Error::Usb(error)),
+            }
+        }
+    }
+}
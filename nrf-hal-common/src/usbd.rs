@@ -0,0 +1,874 @@
+//! HAL interface to the USBD peripheral.
+//!
+//! `Usbd` implements the `UsbBus` trait of `usb-device`, so the USB classes of the ecosystem,
+//! e.g. CDC-ACM from `usbd-serial` or HID from `usbd-hid`, can be used with the USBD:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let power = Power::new(p.POWER);
+//! let usb_bus = UsbBusAllocator::new(Usbd::new(p.USBD, &clocks, power.usb_supply()));
+//! let mut serial = SerialPort::new(&usb_bus);
+//! let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+//!     .device_class(USB_CLASS_CDC)
+//!     .build();
+//!
+//! loop {
+//!     if usb_dev.poll(&mut [&mut serial]) {
+//!         // Read from and write to `serial`.
+//!     }
+//! }
+//! ```
+//!
+//! This module is only available with the `usb` feature.
+//!
+//! The USBD needs the external high-frequency oscillator. It is enabled and connected to the bus
+//! once VBUS is present and the USB regulator is ready, and disconnected when VBUS is removed,
+//! which `UsbDevice::poll` reports as a suspend. The supply is checked each time the bus is
+//! polled, so the USB interrupt alone doesn't catch VBUS changes: enabling the `UsbEvent`
+//! interrupts of `Power` makes sure the bus is polled when they happen.
+//!
+//! Control, bulk and interrupt endpoints are supported, with a maximum packet size of 64 bytes.
+//! Endpoint 0 is the control endpoint, and endpoints 1 to 7 can be allocated in each direction.
+//!
+//! Endpoint 8 is an isochronous endpoint in each direction, with a maximum packet size of 1023
+//! bytes, or 512 bytes when both directions are used. Its transfers are synchronized to the start
+//! of frame (SOF), every millisecond: an IN packet written during a frame is sent in the next
+//! one, and is reported as complete at the next SOF, and an OUT packet received during a frame
+//! can be read from the next SOF. The buffers of isochronous transfers must be in RAM.
+//!
+//! The `usbd-errata` feature enables the workarounds of the USBD anomalies of the nRF52833 and
+//! nRF52840, which make the enumeration or the transfers fail on some silicon revisions.
+//!
+//! When the host suspends the bus, the USBD enters its low power mode, and the external
+//! high-frequency oscillator can be stopped until the bus is resumed to meet the suspend current
+//! requirements. If the host has enabled it, the device can wake the host up:
+//!
+//! ```ignore
+//! if usb_dev.state() == UsbDeviceState::Suspend && usb_dev.remote_wakeup_enabled() {
+//!     usb_dev.bus().remote_wakeup()?;
+//! }
+//! ```
+//!
+//! Classes that need to follow the 1 ms USB frame, e.g. audio or MIDI, can enable the SOF
+//! interrupt and check for a new frame after polling:
+//!
+//! ```ignore
+//! usb_dev.bus().set_sof_interrupt(true);
+//! // In the USBD interrupt handler:
+//! usb_dev.poll(&mut [&mut audio]);
+//! if usb_dev.bus().take_sof() {
+//!     audio.on_frame(usb_dev.bus().frame_number());
+//! }
+//! ```
+
+use core::cell::Cell;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use cortex_m::interrupt::{self, Mutex};
+use usb_device::bus::{PollResult, UsbBus};
+use usb_device::endpoint::{EndpointAddress, EndpointType};
+use usb_device::{Result, UsbDirection, UsbError};
+
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::pac::{usbd, USBD};
+use crate::power::UsbSupply;
+
+/// Number of endpoints in each direction, including the control endpoint.
+const ENDPOINTS: usize = 8;
+
+/// Maximum packet size of the control, bulk and interrupt endpoints.
+const MAX_PACKET_SIZE: usize = 64;
+
+/// Index of the isochronous endpoints.
+const ISO_ENDPOINT: usize = 8;
+
+/// Maximum packet size of an isochronous endpoint, when the other direction is not used.
+const MAX_ISO_PACKET_SIZE: usize = 1023;
+
+/// Maximum packet size of the isochronous endpoints, when both directions are used.
+const MAX_ISO_SPLIT_PACKET_SIZE: usize = 512;
+
+/// EasyDMA buffer for one packet.
+#[repr(align(4))]
+struct Buffer([u8; MAX_PACKET_SIZE]);
+
+/// State of the control transfer on endpoint 0.
+#[derive(Copy, Clone)]
+struct Ep0State {
+    /// Direction of the data stage.
+    direction: UsbDirection,
+    /// Number of bytes left in the data stage.
+    remaining: u16,
+    /// A SETUP packet has been received and not read yet.
+    setup: bool,
+    /// The status stage has been started, and not reported as complete yet.
+    status: bool,
+    /// The packet being sent is the last one of the data stage.
+    last_in: bool,
+}
+
+impl Ep0State {
+    const IDLE: Self = Self {
+        direction: UsbDirection::Out,
+        remaining: 0,
+        setup: false,
+        status: false,
+        last_in: false,
+    };
+}
+
+/// USB device bus, implementing `usb_device::bus::UsbBus`.
+pub struct Usbd {
+    usbd: Mutex<USBD>,
+    supply: UsbSupply,
+    /// The USBD is enabled and connected to the bus.
+    connected: Mutex<Cell<bool>>,
+    /// The bus is suspended, and the USBD is in low power mode.
+    suspended: Mutex<Cell<bool>>,
+    /// Allocated IN and OUT endpoints, one bit per endpoint.
+    used_in: u16,
+    used_out: u16,
+    max_packet_size_in: [u16; ENDPOINTS + 1],
+    max_packet_size_out: [u16; ENDPOINTS + 1],
+    ep0: Mutex<Cell<Ep0State>>,
+    /// IN endpoints with a packet waiting to be sent.
+    busy_in: Mutex<Cell<u16>>,
+    /// OUT endpoints with a received packet waiting to be read.
+    ready_out: Mutex<Cell<u16>>,
+    /// A start of frame has been received since the last call to `take_sof`.
+    sof: Mutex<Cell<bool>>,
+    /// The SOF interrupt has been requested with `set_sof_interrupt`.
+    sof_interrupt: Mutex<Cell<bool>>,
+}
+
+impl Usbd {
+    /// Takes ownership of the USBD. The external high-frequency oscillator must be running.
+    pub fn new<L, LSTAT>(
+        usbd: USBD,
+        _clocks: &Clocks<ExternalOscillator, L, LSTAT>,
+        supply: UsbSupply,
+    ) -> Self {
+        Self {
+            usbd: Mutex::new(usbd),
+            supply,
+            connected: Mutex::new(Cell::new(false)),
+            suspended: Mutex::new(Cell::new(false)),
+            used_in: 0,
+            used_out: 0,
+            max_packet_size_in: [0; ENDPOINTS + 1],
+            max_packet_size_out: [0; ENDPOINTS + 1],
+            ep0: Mutex::new(Cell::new(Ep0State::IDLE)),
+            busy_in: Mutex::new(Cell::new(0)),
+            ready_out: Mutex::new(Cell::new(0)),
+            sof: Mutex::new(Cell::new(false)),
+            sof_interrupt: Mutex::new(Cell::new(false)),
+        }
+    }
+
+    /// Returns the number of the current USB frame, incremented on every start of frame (SOF).
+    ///
+    /// The frame number has 11 bits, and wraps around every 2048 ms.
+    pub fn frame_number(&self) -> u16 {
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            usbd.framecntr.read().framecntr().bits()
+        })
+    }
+
+    /// Returns `true` if a start of frame (SOF) has been received since the last call, and clears
+    /// it.
+    ///
+    /// The SOF is only detected by `poll`, so this should be called after `UsbDevice::poll`.
+    pub fn take_sof(&self) -> bool {
+        interrupt::free(|cs| self.sof.borrow(cs).replace(false))
+    }
+
+    /// Enables the USBD interrupt on every start of frame (SOF) if `enabled` is `true`, so that
+    /// `poll` is called once per 1 ms frame.
+    ///
+    /// The SOF interrupt is always enabled while an isochronous endpoint is in use.
+    pub fn set_sof_interrupt(&self, enabled: bool) {
+        interrupt::free(|cs| {
+            self.sof_interrupt.borrow(cs).set(enabled);
+            let usbd = self.usbd.borrow(cs);
+            if enabled {
+                usbd.intenset.write(|w| w.sof().set_bit());
+            } else if (self.used_in | self.used_out) & (1 << ISO_ENDPOINT) == 0 {
+                usbd.intenclr.write(|w| w.sof().set_bit());
+            }
+        })
+    }
+
+    /// Wakes the host up while the bus is suspended, if the host has enabled remote wakeup.
+    ///
+    /// The USBD leaves its low power mode, which needs the external high-frequency oscillator,
+    /// and signals the wakeup to the host, which then resumes the bus.
+    pub fn remote_wakeup(&self) -> Result<()> {
+        interrupt::free(|cs| {
+            if !self.suspended.borrow(cs).get() {
+                return Err(UsbError::InvalidState);
+            }
+            // The wakeup is signaled once the USBD is allowed to, see `poll`.
+            let usbd = self.usbd.borrow(cs);
+            usbd.lowpower.write(|w| w.lowpower().force_normal());
+            Ok(())
+        })
+    }
+
+    /// Enables the USBD and connects to the bus once the USB regulator is ready, and disconnects
+    /// when VBUS is removed. Returns `true` if the USBD is connected.
+    fn update_supply(&self, usbd: &USBD, cs: &interrupt::CriticalSection) -> bool {
+        let connected = self.connected.borrow(cs);
+        if !connected.get() && self.supply.is_power_ready() {
+            #[cfg(feature = "usbd-errata")]
+            errata::pre_enable();
+            usbd.enable.write(|w| w.enable().enabled());
+            while usbd.eventcause.read().ready().is_not_detected() {}
+            usbd.eventcause.write(|w| w.ready().ready());
+            #[cfg(feature = "usbd-errata")]
+            errata::post_enable();
+
+            usbd.epinen
+                .write(|w| unsafe { w.bits(u32::from(self.used_in)) });
+            usbd.epouten
+                .write(|w| unsafe { w.bits(u32::from(self.used_out)) });
+            let iso = 1 << ISO_ENDPOINT;
+            usbd.isosplit.write(|w| {
+                if self.used_in & self.used_out & iso != 0 {
+                    w.split().half_in()
+                } else {
+                    w.split().one_dir()
+                }
+            });
+            usbd.isoinconfig.write(|w| w.response().zero_data());
+            usbd.inten.write(|w| {
+                w.usbreset()
+                    .set_bit()
+                    .usbevent()
+                    .set_bit()
+                    .ep0setup()
+                    .set_bit()
+                    .ep0datadone()
+                    .set_bit()
+                    .epdata()
+                    .set_bit()
+                    .sof()
+                    .bit(
+                        (self.used_in | self.used_out) & iso != 0
+                            || self.sof_interrupt.borrow(cs).get(),
+                    )
+            });
+            usbd.usbpullup.write(|w| w.connect().enabled());
+            connected.set(true);
+        } else if connected.get() && !self.supply.is_vbus_present() {
+            usbd.usbpullup.write(|w| w.connect().disabled());
+            usbd.enable.write(|w| w.enable().disabled());
+            connected.set(false);
+        }
+        connected.get()
+    }
+
+    fn write_ep0(&self, usbd: &USBD, cs: &interrupt::CriticalSection, buf: &[u8]) -> Result<usize> {
+        let ep0 = self.ep0.borrow(cs);
+        let mut state = ep0.get();
+
+        // `usb-device` starts the status stage by sending a zero-length packet, which is done by
+        // the hardware instead. The data stage of OUT transfers is acknowledged by the hardware.
+        if state.direction == UsbDirection::Out || (buf.is_empty() && state.remaining == 0) {
+            usbd.tasks_ep0status.write(|w| unsafe { w.bits(1) });
+            state.status = true;
+            ep0.set(state);
+            return Ok(0);
+        }
+
+        let busy_in = self.busy_in.borrow(cs);
+        if busy_in.get() & 1 != 0 {
+            return Err(UsbError::WouldBlock);
+        }
+        if buf.len() > usize::from(self.max_packet_size_in[0]) {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        start_in(usbd, 0, buf);
+        busy_in.set(busy_in.get() | 1);
+
+        state.remaining = state.remaining.saturating_sub(buf.len() as u16);
+        state.last_in = buf.len() < usize::from(self.max_packet_size_in[0]) || state.remaining == 0;
+        ep0.set(state);
+        Ok(buf.len())
+    }
+
+    fn read_ep0(
+        &self,
+        usbd: &USBD,
+        cs: &interrupt::CriticalSection,
+        buf: &mut [u8],
+    ) -> Result<usize> {
+        let ep0 = self.ep0.borrow(cs);
+        let mut state = ep0.get();
+
+        if state.setup {
+            if buf.len() < 8 {
+                return Err(UsbError::BufferOverflow);
+            }
+            buf[0] = usbd.bmrequesttype.read().bits() as u8;
+            buf[1] = usbd.brequest.read().bits() as u8;
+            buf[2] = usbd.wvaluel.read().bits() as u8;
+            buf[3] = usbd.wvalueh.read().bits() as u8;
+            buf[4] = usbd.windexl.read().bits() as u8;
+            buf[5] = usbd.windexh.read().bits() as u8;
+            buf[6] = usbd.wlengthl.read().bits() as u8;
+            buf[7] = usbd.wlengthh.read().bits() as u8;
+
+            // A new control transfer aborts the previous one.
+            state = Ep0State {
+                direction: if buf[0] & 0x80 != 0 {
+                    UsbDirection::In
+                } else {
+                    UsbDirection::Out
+                },
+                remaining: u16::from_le_bytes([buf[6], buf[7]]),
+                ..Ep0State::IDLE
+            };
+            let busy_in = self.busy_in.borrow(cs);
+            busy_in.set(busy_in.get() & !1);
+            let ready_out = self.ready_out.borrow(cs);
+            ready_out.set(ready_out.get() & !1);
+
+            if state.direction == UsbDirection::Out && state.remaining > 0 {
+                // Accept the first packet of the data stage.
+                usbd.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+            }
+            ep0.set(state);
+            return Ok(8);
+        }
+
+        let ready_out = self.ready_out.borrow(cs);
+        if ready_out.get() & 1 == 0 {
+            return Err(UsbError::WouldBlock);
+        }
+        let size = usize::from(usbd.size.epout[0].read().size().bits());
+        if size > buf.len() {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        start_out(usbd, 0, &mut buf[..size]);
+        ready_out.set(ready_out.get() & !1);
+
+        state.remaining = state.remaining.saturating_sub(size as u16);
+        if state.remaining > 0 {
+            usbd.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+        }
+        ep0.set(state);
+        Ok(size)
+    }
+}
+
+impl UsbBus for Usbd {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        _interval: u8,
+    ) -> Result<EndpointAddress> {
+        let (used, max_packet_sizes, other_used, other_max_packet_sizes) = match ep_dir {
+            UsbDirection::In => (
+                &mut self.used_in,
+                &mut self.max_packet_size_in,
+                self.used_out,
+                &self.max_packet_size_out,
+            ),
+            UsbDirection::Out => (
+                &mut self.used_out,
+                &mut self.max_packet_size_out,
+                self.used_in,
+                &self.max_packet_size_in,
+            ),
+        };
+
+        if ep_type == EndpointType::Isochronous {
+            if *used & (1 << ISO_ENDPOINT) != 0 {
+                return Err(UsbError::EndpointOverflow);
+            }
+            if matches!(ep_addr, Some(addr) if addr.index() != ISO_ENDPOINT) {
+                return Err(UsbError::InvalidEndpoint);
+            }
+            // Both directions share the same buffer, which is split in halves if both are used.
+            let max_iso_packet_size = if other_used & (1 << ISO_ENDPOINT) != 0 {
+                if usize::from(other_max_packet_sizes[ISO_ENDPOINT]) > MAX_ISO_SPLIT_PACKET_SIZE {
+                    return Err(UsbError::EndpointMemoryOverflow);
+                }
+                MAX_ISO_SPLIT_PACKET_SIZE
+            } else {
+                MAX_ISO_PACKET_SIZE
+            };
+            if usize::from(max_packet_size) > max_iso_packet_size {
+                return Err(UsbError::EndpointMemoryOverflow);
+            }
+
+            *used |= 1 << ISO_ENDPOINT;
+            max_packet_sizes[ISO_ENDPOINT] = max_packet_size;
+            return Ok(EndpointAddress::from_parts(ISO_ENDPOINT, ep_dir));
+        }
+
+        if usize::from(max_packet_size) > MAX_PACKET_SIZE {
+            return Err(UsbError::Unsupported);
+        }
+        let index = match ep_addr {
+            Some(addr) if addr.index() < ENDPOINTS && *used & (1 << addr.index()) == 0 => {
+                addr.index()
+            }
+            Some(_) => return Err(UsbError::InvalidEndpoint),
+            None => (1..ENDPOINTS)
+                .find(|index| *used & (1 << index) == 0)
+                .ok_or(UsbError::EndpointOverflow)?,
+        };
+        // Only endpoint 0 is a control endpoint.
+        if (ep_type == EndpointType::Control) != (index == 0) {
+            return Err(UsbError::Unsupported);
+        }
+
+        *used |= 1 << index;
+        max_packet_sizes[index] = max_packet_size;
+        Ok(EndpointAddress::from_parts(index, ep_dir))
+    }
+
+    fn enable(&mut self) {
+        interrupt::free(|cs| {
+            self.update_supply(self.usbd.borrow(cs), cs);
+        });
+    }
+
+    fn reset(&self) {
+        interrupt::free(|cs| {
+            self.ep0.borrow(cs).set(Ep0State::IDLE);
+            self.busy_in.borrow(cs).set(0);
+            self.ready_out.borrow(cs).set(0);
+        });
+    }
+
+    fn set_device_address(&self, _addr: u8) {
+        // The address is set by the hardware when it handles the SET_ADDRESS request.
+    }
+
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            if index == 0 {
+                return self.write_ep0(usbd, cs, buf);
+            }
+            if index == ISO_ENDPOINT && !crate::slice_in_ram(buf) {
+                return Err(UsbError::Unsupported);
+            }
+
+            let busy_in = self.busy_in.borrow(cs);
+            if busy_in.get() & (1 << index) != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            if buf.len() > usize::from(self.max_packet_size_in[index]) {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            if index == ISO_ENDPOINT {
+                start_iso_in(usbd, buf);
+            } else {
+                start_in(usbd, index, buf);
+            }
+            busy_in.set(busy_in.get() | 1 << index);
+            Ok(buf.len())
+        })
+    }
+
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            if index == 0 {
+                return self.read_ep0(usbd, cs, buf);
+            }
+
+            let ready_out = self.ready_out.borrow(cs);
+            if ready_out.get() & (1 << index) == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            if index == ISO_ENDPOINT {
+                return read_iso(usbd, ready_out, buf);
+            }
+            let size = usize::from(usbd.size.epout[index].read().size().bits());
+            if size > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            // Reading the packet also lets the endpoint receive the next one.
+            start_out(usbd, index, &mut buf[..size]);
+            ready_out.set(ready_out.get() & !(1 << index));
+            Ok(size)
+        })
+    }
+
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        let index = ep_addr.index();
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            if index == ISO_ENDPOINT {
+                // Isochronous endpoints can't be stalled.
+                return;
+            }
+            if index == 0 {
+                // The stall of endpoint 0 is cleared by the hardware on the next SETUP packet.
+                if stalled {
+                    usbd.tasks_ep0stall.write(|w| unsafe { w.bits(1) });
+                }
+                return;
+            }
+
+            usbd.epstall.write(|w| {
+                let w = unsafe { w.ep().bits(index as u8) };
+                let w = match ep_addr.direction() {
+                    UsbDirection::In => w.io().in_(),
+                    UsbDirection::Out => w.io().out(),
+                };
+                if stalled {
+                    w.stall().stall()
+                } else {
+                    w.stall().un_stall()
+                }
+            });
+        });
+    }
+
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        let index = ep_addr.index();
+        if index == 0 || index == ISO_ENDPOINT {
+            return false;
+        }
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            match ep_addr.direction() {
+                UsbDirection::In => usbd.halted.epin[index].read().getstatus().is_halted(),
+                UsbDirection::Out => usbd.halted.epout[index].read().getstatus().is_halted(),
+            }
+        })
+    }
+
+    fn suspend(&self) {
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            usbd.lowpower.write(|w| w.lowpower().low_power());
+            self.suspended.borrow(cs).set(true);
+        });
+    }
+
+    fn resume(&self) {
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+            usbd.lowpower.write(|w| w.lowpower().force_normal());
+            self.suspended.borrow(cs).set(false);
+        });
+    }
+
+    fn poll(&self) -> PollResult {
+        interrupt::free(|cs| {
+            let usbd = self.usbd.borrow(cs);
+
+            let was_connected = self.connected.borrow(cs).get();
+            if !self.update_supply(usbd, cs) {
+                if was_connected {
+                    // The device is reset by the host when it is connected again.
+                    self.reset();
+                    return PollResult::Suspend;
+                }
+                return PollResult::None;
+            }
+
+            if usbd.events_usbreset.read().bits() != 0 {
+                usbd.events_usbreset.reset();
+                return PollResult::Reset;
+            }
+
+            if usbd.events_usbevent.read().bits() != 0 {
+                usbd.events_usbevent.reset();
+                let cause = usbd.eventcause.read();
+                // The causes are cleared by writing 1 to them.
+                usbd.eventcause.write(|w| unsafe { w.bits(cause.bits()) });
+                if cause.usbwuallowed().is_allowed() {
+                    // Drive the resume signaling requested by `remote_wakeup`.
+                    usbd.dpdmvalue.write(|w| w.state().resume());
+                    usbd.tasks_dpdmdrive.write(|w| unsafe { w.bits(1) });
+                }
+                if cause.suspend().is_detected() {
+                    return PollResult::Suspend;
+                }
+                if cause.resume().is_detected() {
+                    return PollResult::Resume;
+                }
+            }
+
+            let ep0 = self.ep0.borrow(cs);
+            let busy_in = self.busy_in.borrow(cs);
+            let ready_out = self.ready_out.borrow(cs);
+            let mut state = ep0.get();
+            let mut ep_in_complete = 0;
+            let mut ep_setup = 0;
+
+            if usbd.events_ep0setup.read().bits() != 0 {
+                usbd.events_ep0setup.reset();
+                state.setup = true;
+                ep_setup |= 1;
+            }
+
+            if usbd.events_ep0datadone.read().bits() != 0 {
+                usbd.events_ep0datadone.reset();
+                match state.direction {
+                    UsbDirection::In => {
+                        busy_in.set(busy_in.get() & !1);
+                        ep_in_complete |= 1;
+                        if state.last_in {
+                            usbd.tasks_ep0status.write(|w| unsafe { w.bits(1) });
+                        }
+                    }
+                    UsbDirection::Out => ready_out.set(ready_out.get() | 1),
+                }
+            }
+
+            if state.status {
+                state.status = false;
+                ep_in_complete |= 1;
+            }
+            ep0.set(state);
+
+            if usbd.events_epdata.read().bits() != 0 {
+                usbd.events_epdata.reset();
+                let status = usbd.epdatastatus.read().bits();
+                // The status bits are cleared by writing 1 to them.
+                usbd.epdatastatus.write(|w| unsafe { w.bits(status) });
+
+                let done_in = status as u16 & 0xFE;
+                let done_out = (status >> 16) as u16 & 0xFE;
+                busy_in.set(busy_in.get() & !done_in);
+                ready_out.set(ready_out.get() | done_out);
+                ep_in_complete |= done_in;
+            }
+
+            // Isochronous packets are exchanged once per frame.
+            if usbd.events_sof.read().bits() != 0 {
+                usbd.events_sof.reset();
+                self.sof.borrow(cs).set(true);
+                let iso = 1 << ISO_ENDPOINT;
+                if self.used_in & iso != 0 {
+                    busy_in.set(busy_in.get() & !iso);
+                    ep_in_complete |= iso;
+                }
+                if self.used_out & iso != 0 {
+                    ready_out.set(ready_out.get() | iso);
+                }
+            }
+
+            // Packets that have not been read yet are reported again.
+            let ep_out = ready_out.get();
+            if ep_out | ep_in_complete | ep_setup == 0 {
+                PollResult::None
+            } else {
+                PollResult::Data {
+                    ep_out,
+                    ep_in_complete,
+                    ep_setup,
+                }
+            }
+        })
+    }
+}
+
+/// Copies `buf` to the buffer of the IN endpoint `index`, to be sent to the host.
+fn start_in(usbd: &USBD, index: usize, buf: &[u8]) {
+    let mut buffer = Buffer([0; MAX_PACKET_SIZE]);
+    buffer.0[..buf.len()].copy_from_slice(buf);
+
+    let epin = epin(usbd, index);
+    epin.ptr
+        .write(|w| unsafe { w.ptr().bits(buffer.0.as_ptr() as u32) });
+    epin.maxcnt
+        .write(|w| unsafe { w.maxcnt().bits(buf.len() as u8) });
+
+    compiler_fence(Ordering::SeqCst);
+    #[cfg(feature = "usbd-errata")]
+    errata::start_dma();
+    usbd.events_endepin[index].reset();
+    usbd.tasks_startepin[index].write(|w| unsafe { w.bits(1) });
+    while usbd.events_endepin[index].read().bits() == 0 {}
+    usbd.events_endepin[index].reset();
+    #[cfg(feature = "usbd-errata")]
+    errata::end_dma();
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Copies the packet received by the OUT endpoint `index` to `buf`, which has its size.
+fn start_out(usbd: &USBD, index: usize, buf: &mut [u8]) {
+    let mut buffer = Buffer([0; MAX_PACKET_SIZE]);
+
+    let epout = epout(usbd, index);
+    epout
+        .ptr
+        .write(|w| unsafe { w.ptr().bits(buffer.0.as_mut_ptr() as u32) });
+    epout
+        .maxcnt
+        .write(|w| unsafe { w.maxcnt().bits(buf.len() as u8) });
+
+    compiler_fence(Ordering::SeqCst);
+    #[cfg(feature = "usbd-errata")]
+    errata::start_dma();
+    usbd.events_endepout[index].reset();
+    usbd.tasks_startepout[index].write(|w| unsafe { w.bits(1) });
+    while usbd.events_endepout[index].read().bits() == 0 {}
+    usbd.events_endepout[index].reset();
+    #[cfg(feature = "usbd-errata")]
+    errata::end_dma();
+    compiler_fence(Ordering::SeqCst);
+
+    buf.copy_from_slice(&buffer.0[..buf.len()]);
+}
+
+/// Sends `buf` from the isochronous IN endpoint in the next frame.
+fn start_iso_in(usbd: &USBD, buf: &[u8]) {
+    usbd.isoin
+        .ptr
+        .write(|w| unsafe { w.ptr().bits(buf.as_ptr() as u32) });
+    usbd.isoin
+        .maxcnt
+        .write(|w| unsafe { w.maxcnt().bits(buf.len() as u16) });
+
+    compiler_fence(Ordering::SeqCst);
+    #[cfg(feature = "usbd-errata")]
+    errata::start_dma();
+    usbd.events_endisoin.reset();
+    usbd.tasks_startisoin.write(|w| unsafe { w.bits(1) });
+    while usbd.events_endisoin.read().bits() == 0 {}
+    usbd.events_endisoin.reset();
+    #[cfg(feature = "usbd-errata")]
+    errata::end_dma();
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Reads the packet received by the isochronous OUT endpoint in the last frame, if any.
+fn read_iso(usbd: &USBD, ready_out: &Cell<u16>, buf: &mut [u8]) -> Result<usize> {
+    let size = usbd.size.isoout.read();
+    if size.size().bits() == 0 && size.zero().is_normal() {
+        // No packet was received in the last frame.
+        ready_out.set(ready_out.get() & !(1 << ISO_ENDPOINT));
+        return Err(UsbError::WouldBlock);
+    }
+    let size = usize::from(size.size().bits());
+    if size > buf.len() {
+        return Err(UsbError::BufferOverflow);
+    }
+
+    usbd.isoout
+        .ptr
+        .write(|w| unsafe { w.ptr().bits(buf.as_mut_ptr() as u32) });
+    usbd.isoout
+        .maxcnt
+        .write(|w| unsafe { w.maxcnt().bits(size as u16) });
+
+    compiler_fence(Ordering::SeqCst);
+    #[cfg(feature = "usbd-errata")]
+    errata::start_dma();
+    usbd.events_endisoout.reset();
+    usbd.tasks_startisoout.write(|w| unsafe { w.bits(1) });
+    while usbd.events_endisoout.read().bits() == 0 {}
+    usbd.events_endisoout.reset();
+    #[cfg(feature = "usbd-errata")]
+    errata::end_dma();
+    compiler_fence(Ordering::SeqCst);
+
+    ready_out.set(ready_out.get() & !(1 << ISO_ENDPOINT));
+    Ok(size)
+}
+
+fn epin(usbd: &USBD, index: usize) -> &usbd::EPIN {
+    match index {
+        0 => &usbd.epin0,
+        1 => &usbd.epin1,
+        2 => &usbd.epin2,
+        3 => &usbd.epin3,
+        4 => &usbd.epin4,
+        5 => &usbd.epin5,
+        6 => &usbd.epin6,
+        7 => &usbd.epin7,
+        _ => unreachable!(),
+    }
+}
+
+fn epout(usbd: &USBD, index: usize) -> &usbd::EPOUT {
+    match index {
+        0 => &usbd.epout0,
+        1 => &usbd.epout1,
+        2 => &usbd.epout2,
+        3 => &usbd.epout3,
+        4 => &usbd.epout4,
+        5 => &usbd.epout5,
+        6 => &usbd.epout6,
+        7 => &usbd.epout7,
+        _ => unreachable!(),
+    }
+}
+
+/// Workarounds for the anomalies of the USBD, from the errata of the nRF52833 and nRF52840.
+#[cfg(feature = "usbd-errata")]
+mod errata {
+    use core::ptr;
+
+    /// Writes `value` to the undocumented register at `address`, which must be unlocked.
+    fn write_locked(address: u32, value: u32) {
+        let lock = 0x4006_EC00 as *mut u32;
+        // NOTE(unsafe) The addresses and values are given by the errata
+        unsafe {
+            if ptr::read_volatile(lock) == 0 {
+                ptr::write_volatile(lock, 0x9375);
+                ptr::write_volatile(address as *mut u32, value);
+                ptr::write_volatile(lock, 0x9375);
+            } else {
+                ptr::write_volatile(address as *mut u32, value);
+            }
+        }
+    }
+
+    /// Anomalies 171 and 187: the USBD might not reach its active state. To be called before
+    /// enabling the USBD.
+    pub(super) fn pre_enable() {
+        #[cfg(feature = "52840")]
+        write_locked(0x4006_EC14, 0xC0);
+        write_locked(0x4006_ED14, 0x03);
+    }
+
+    /// Reverts `pre_enable` once the USBD is ready, and applies the workaround for anomaly 166:
+    /// isochronous transfers might not work as expected.
+    pub(super) fn post_enable() {
+        #[cfg(feature = "52840")]
+        write_locked(0x4006_EC14, 0x00);
+        write_locked(0x4006_ED14, 0x00);
+
+        // NOTE(unsafe) The addresses and values are given by the errata
+        unsafe {
+            ptr::write_volatile(0x4002_7800 as *mut u32, 0x7E3);
+            ptr::write_volatile(0x4002_7804 as *mut u32, 0x40);
+        }
+        cortex_m::asm::isb();
+        cortex_m::asm::dsb();
+    }
+
+    /// Anomaly 199: the USBD can't receive tasks during EasyDMA transfers. To be called before
+    /// starting a transfer.
+    pub(super) fn start_dma() {
+        // NOTE(unsafe) The address and value are given by the errata
+        unsafe { ptr::write_volatile(0x4002_7C1C as *mut u32, 0x82) };
+    }
+
+    /// Reverts `start_dma` once the transfer has ended.
+    pub(super) fn end_dma() {
+        // NOTE(unsafe) The address and value are given by the errata
+        unsafe { ptr::write_volatile(0x4002_7C1C as *mut u32, 0x00) };
+    }
+}
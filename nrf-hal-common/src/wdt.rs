@@ -3,6 +3,7 @@
 //! This HAL implements a basic watchdog timer with 1..=8 handles.
 //! Once the watchdog has been started, it cannot be stopped.
 
+use crate::hal::blocking::delay::{DelayMs, DelayUs};
 use crate::pac::WDT;
 use handles::*;
 
@@ -206,6 +207,95 @@ where
     }
 }
 
+/// A delay provider that keeps the watchdog fed during long delays and waits.
+///
+/// The delay is split into steps of at most `feed_interval_us` microseconds, and all the given
+/// handles are pet after each step. The interval should be comfortably shorter than the watchdog
+/// period.
+pub struct WatchdogDelay<'a, D> {
+    delay: D,
+    handles: &'a mut [WatchdogHandle<HdlN>],
+    feed_interval_us: u32,
+}
+
+impl<'a, D> WatchdogDelay<'a, D>
+where
+    D: DelayUs<u32>,
+{
+    /// Wraps a delay provider, feeding the watchdog through `handles` every `feed_interval_us`
+    /// microseconds.
+    ///
+    /// All active handles should be given, after converting them with `degrade`.
+    pub fn new(delay: D, handles: &'a mut [WatchdogHandle<HdlN>], feed_interval_us: u32) -> Self {
+        Self {
+            delay,
+            handles,
+            feed_interval_us: feed_interval_us.max(1),
+        }
+    }
+
+    /// Pets all the handles.
+    pub fn feed(&mut self) {
+        for handle in self.handles.iter_mut() {
+            handle.pet();
+        }
+    }
+
+    /// Busy-waits until `condition` returns `true`, feeding the watchdog every
+    /// `feed_interval_us` microseconds.
+    ///
+    /// `condition` is checked every `poll_interval_us` microseconds.
+    pub fn wait_for<F>(&mut self, poll_interval_us: u32, mut condition: F)
+    where
+        F: FnMut() -> bool,
+    {
+        let poll_interval_us = poll_interval_us.clamp(1, self.feed_interval_us);
+        let mut since_feed = 0;
+        self.feed();
+        while !condition() {
+            if since_feed + poll_interval_us > self.feed_interval_us {
+                self.feed();
+                since_feed = 0;
+            }
+            self.delay.delay_us(poll_interval_us);
+            since_feed += poll_interval_us;
+        }
+    }
+
+    /// Releases the wrapped delay provider.
+    pub fn free(self) -> D {
+        self.delay
+    }
+}
+
+impl<'a, D> DelayUs<u32> for WatchdogDelay<'a, D>
+where
+    D: DelayUs<u32>,
+{
+    fn delay_us(&mut self, us: u32) {
+        let mut remaining = us;
+        while remaining > 0 {
+            let step = remaining.min(self.feed_interval_us);
+            self.feed();
+            self.delay.delay_us(step);
+            remaining -= step;
+        }
+        self.feed();
+    }
+}
+
+impl<'a, D> DelayMs<u32> for WatchdogDelay<'a, D>
+where
+    D: DelayUs<u32>,
+{
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms / 1_000 {
+            self.delay_us(1_000_000);
+        }
+        self.delay_us((ms % 1_000) * 1_000);
+    }
+}
+
 mod sealed {
     pub trait HandleId {
         fn index(&self) -> usize;